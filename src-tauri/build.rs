@@ -1,6 +1,7 @@
 use std::env;
 use std::path::PathBuf;
 use std::process::Command;
+use sha2::{Digest, Sha256};
 
 fn main() {
     // do this first so that later imports don't fail
@@ -35,6 +36,17 @@ fn compile_flatbuffers() {
         return;
     }
 
+    // goes in each session.json, so a recording can be matched to the schemas it was decoded with
+    let mut sorted = fbs_files.clone();
+    sorted.sort();
+    let mut hasher = Sha256::new();
+    for path in &sorted {
+        hasher.update(path.file_name().unwrap().to_string_lossy().as_bytes());
+        hasher.update(std::fs::read(path).expect("Failed to read schema"));
+    }
+    let hash: String = hasher.finalize().iter().map(|b| format!("{b:02x}")).collect();
+    println!("cargo:rustc-env=TELEMETRY_SCHEMA_HASH={hash}");
+
     // Ensure output directory exists
     std::fs::create_dir_all(&out_dir).expect("Failed to create generated directory");
 
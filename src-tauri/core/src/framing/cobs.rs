@@ -0,0 +1,182 @@
+// Consistent Overhead Byte Stuffing, for links that delimit frames with a zero byte
+//
+// Encoding removes every zero from the data at a cost of one byte per 254, so a
+// zero on the wire always means "end of frame". A corrupted or half-received frame
+// costs us that one frame: the decoder throws it away at the next zero and carries on.
+
+// longer than any radio frame, past this without a delimiter we're reading noise
+pub const MAX_FRAME: usize = 1024;
+
+// data -> stuffed bytes with the trailing zero delimiter
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 254 + 2);
+    let mut code_index = 0;
+    let mut code: u8 = 1;
+    out.push(0);
+
+    for &byte in data {
+        if byte != 0 {
+            out.push(byte);
+            code += 1;
+        }
+        if byte == 0 || code == 0xFF {
+            out[code_index] = code;
+            code_index = out.len();
+            out.push(0);
+            code = 1;
+        }
+    }
+    out[code_index] = code;
+    out.push(0);
+    out
+}
+
+// one stuffed block, without its delimiter
+pub fn decode(block: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(block.len());
+    let mut i = 0;
+    while i < block.len() {
+        let code = block[i] as usize;
+        if code == 0 {
+            return Err("zero byte inside a frame".into());
+        }
+        let end = i + code;
+        if end > block.len() {
+            return Err(format!("code byte {code} runs past the end of the frame"));
+        }
+        out.extend_from_slice(&block[i + 1..end]);
+        i = end;
+        // a full 254 byte run has no zero after it
+        if code < 0xFF && i < block.len() {
+            out.push(0);
+        }
+    }
+    Ok(out)
+}
+
+// streaming decoder, feed it whatever the port hands us
+#[derive(Default)]
+pub struct CobsDecoder {
+    block: Vec<u8>,
+    // dropping bytes until the next delimiter after an oversized frame
+    discarding: bool,
+    errors: u64,
+}
+
+impl CobsDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        for &byte in data {
+            if byte != 0 {
+                if self.discarding {
+                    continue;
+                }
+                if self.block.len() >= MAX_FRAME {
+                    self.errors += 1;
+                    self.block.clear();
+                    self.discarding = true;
+                    continue;
+                }
+                self.block.push(byte);
+                continue;
+            }
+
+            // delimiter: whatever we have is a whole frame (or runs of zeros, which are idle)
+            self.discarding = false;
+            if self.block.is_empty() {
+                continue;
+            }
+            match decode(&self.block) {
+                Ok(frame) => frames.push(frame),
+                Err(_) => self.errors += 1,
+            }
+            self.block.clear();
+        }
+        frames
+    }
+
+    // frames thrown away since this decoder was made
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nonzero(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 255) as u8 + 1).collect()
+    }
+
+    fn round_trip(data: &[u8]) -> Vec<u8> {
+        let encoded = encode(data);
+        assert_eq!(encoded.last(), Some(&0));
+        assert!(!encoded[..encoded.len() - 1].contains(&0));
+        decode(&encoded[..encoded.len() - 1]).unwrap()
+    }
+
+    #[test]
+    fn a_full_254_byte_run_has_no_zero_after_it() {
+        let data = nonzero(254);
+        let encoded = encode(&data);
+        assert_eq!(encoded[0], 0xFF);
+        assert_eq!(&encoded[255..], [0x01, 0x00]);
+        assert_eq!(round_trip(&data), data);
+    }
+
+    #[test]
+    fn a_255_byte_run_carries_into_a_second_block() {
+        let data = nonzero(255);
+        let encoded = encode(&data);
+        assert_eq!(encoded[0], 0xFF);
+        assert_eq!(&encoded[255..], [0x02, data[254], 0x00]);
+        assert_eq!(round_trip(&data), data);
+    }
+
+    #[test]
+    fn trailing_and_repeated_zeros_survive() {
+        assert_eq!(encode(&[1, 2, 0]), [3, 1, 2, 1, 0]);
+        for data in [vec![1, 2, 0], vec![0], vec![0, 0], vec![], [nonzero(254), vec![0]].concat()] {
+            assert_eq!(round_trip(&data), data);
+        }
+    }
+
+    #[test]
+    fn bad_blocks_are_errors() {
+        assert!(decode(&[2, 1, 0, 1]).is_err());
+        assert!(decode(&[5, 1, 2]).is_err());
+    }
+
+    #[test]
+    fn decoder_resyncs_after_an_oversized_frame() {
+        let mut decoder = CobsDecoder::new();
+        assert!(decoder.push(&vec![0x55; MAX_FRAME + 10]).is_empty());
+        // the delimiter ends the noise, the next frame comes through whole
+        let frames = decoder.push(&[&[0u8][..], &encode(&[1, 0, 2])].concat());
+        assert_eq!(frames, [vec![1, 0, 2]]);
+        assert_eq!(decoder.errors(), 1);
+    }
+
+    #[test]
+    fn decoder_takes_frames_a_byte_at_a_time() {
+        let messages = [vec![1, 2, 3], vec![0, 0], nonzero(300)];
+        let wire: Vec<u8> = messages.iter().flat_map(|m| encode(m)).collect();
+        let mut decoder = CobsDecoder::new();
+        let frames: Vec<Vec<u8>> = wire.iter().flat_map(|&byte| decoder.push(&[byte])).collect();
+        assert_eq!(frames, messages);
+        assert_eq!(decoder.errors(), 0);
+    }
+
+    #[test]
+    fn idle_zeros_are_not_frames_and_a_bad_frame_costs_only_itself() {
+        let mut decoder = CobsDecoder::new();
+        let wire = [&[0, 0, 0, 5, 1, 0][..], &encode(&[7, 8])].concat();
+        assert_eq!(decoder.push(&wire), [vec![7, 8]]);
+        assert_eq!(decoder.errors(), 1);
+    }
+}
@@ -0,0 +1,91 @@
+// Optional CRC on incoming frames, checked after deframing and before anything decodes
+//
+// The checksum is the last 2 (CRC16) or 4 (CRC32) bytes of the frame, little endian,
+// computed over everything before it, header included. On a signed link it goes
+// after the auth tag, so it's checked (and stripped) first.
+//   Crc16Ccitt  CRC-16/CCITT-FALSE, poly 0x1021, init 0xFFFF
+//   Crc32       the usual zlib/ethernet CRC-32
+use crc::Crc;
+use serde::{Deserialize, Serialize};
+
+const CRC16: Crc<u16> = Crc::<u16>::new(&crc::CRC_16_IBM_3740);
+const CRC32: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum CrcMode {
+    #[default]
+    None,
+    Crc16Ccitt,
+    Crc32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CrcConfig {
+    pub mode: CrcMode,
+    // send a packet_error event with a hexdump for every bad frame
+    #[serde(default)]
+    pub emit_errors: bool,
+}
+
+// bytes the CRC adds to the end of a frame
+pub fn len(mode: CrcMode) -> usize {
+    match mode {
+        CrcMode::None => 0,
+        CrcMode::Crc16Ccitt => 2,
+        CrcMode::Crc32 => 4,
+    }
+}
+
+// the frame without its CRC, or why it failed
+pub fn check(mode: CrcMode, frame: &[u8]) -> Result<&[u8], String> {
+    if mode == CrcMode::None {
+        return Ok(frame);
+    }
+    let len = len(mode);
+    if frame.len() < len {
+        return Err("frame too short for a CRC".into());
+    }
+    let (data, sent) = frame.split_at(frame.len() - len);
+    let (expected, actual) = match mode {
+        CrcMode::Crc16Ccitt => (
+            u16::from_le_bytes([sent[0], sent[1]]) as u32,
+            CRC16.checksum(data) as u32,
+        ),
+        _ => (u32::from_le_bytes([sent[0], sent[1], sent[2], sent[3]]), CRC32.checksum(data)),
+    };
+    if expected != actual {
+        return Err(format!("CRC mismatch, frame says {expected:#x}, computed {actual:#x}"));
+    }
+    Ok(data)
+}
+
+// seal a frame we built ourselves the way the vehicle would have
+pub fn append(mode: CrcMode, frame: &mut Vec<u8>) {
+    match mode {
+        CrcMode::None => {}
+        CrcMode::Crc16Ccitt => {
+            let crc = CRC16.checksum(frame);
+            frame.extend_from_slice(&crc.to_le_bytes());
+        }
+        CrcMode::Crc32 => {
+            let crc = CRC32.checksum(frame);
+            frame.extend_from_slice(&crc.to_le_bytes());
+        }
+    }
+}
+
+// "0000  4b 56 30 52 ...  |KV0R...|", 16 bytes a line
+pub fn hexdump(data: &[u8]) -> String {
+    data.chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect();
+            format!("{:04x}  {:<47}  |{}|", i * 16, hex.join(" "), ascii)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,237 @@
+// Varint length-delimited messages, the framing protobuf's writeDelimitedTo uses
+//
+// Each message goes out as its length in base 128 varint (low 7 bits first, top bit
+// set on every byte but the last) followed by the message. Nothing here cares what
+// the message is, it's the same for protobuf, flatbuffers or anything else.
+//
+// Blocking (Read/Write) and tokio (AsyncRead/AsyncWrite) versions read and write one
+// message at a time; DelimitedDecoder is the push style for data that turns up in
+// arbitrary pieces, like reads off a serial port.
+use std::io::{self, ErrorKind, Read, Write};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+// longer than anything we send, a bigger length is a corrupt prefix
+pub const MAX_MESSAGE: usize = 64 * 1024;
+// a u64 takes at most 10 varint bytes
+const MAX_VARINT_BYTES: usize = 10;
+
+pub fn encode_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// (value, bytes used) from the front of `data`, None if it needs more bytes.
+/// An over-long varint is an error.
+pub fn decode_varint(data: &[u8]) -> Result<Option<(u64, usize)>, String> {
+    let mut value = 0u64;
+    for (i, &byte) in data.iter().enumerate().take(MAX_VARINT_BYTES) {
+        value |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(Some((value, i + 1)));
+        }
+    }
+    if data.len() >= MAX_VARINT_BYTES {
+        return Err("varint longer than 10 bytes".into());
+    }
+    Ok(None)
+}
+
+// message -> length prefix + message
+pub fn encode(message: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(message.len() + 3);
+    encode_varint(message.len() as u64, &mut out);
+    out.extend_from_slice(message);
+    out
+}
+
+fn check_len(len: u64) -> io::Result<usize> {
+    if len as usize > MAX_MESSAGE {
+        return Err(io::Error::new(ErrorKind::InvalidData, format!("message length {len} is past {MAX_MESSAGE}")));
+    }
+    Ok(len as usize)
+}
+
+// ── Blocking ──────────────────────────────────────────────────────────────────
+
+pub fn write_delimited<W: Write>(writer: &mut W, message: &[u8]) -> io::Result<()> {
+    writer.write_all(&encode(message))
+}
+
+/// The next message, None at a clean end of stream (between messages)
+pub fn read_delimited<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut len = 0u64;
+    for i in 0..MAX_VARINT_BYTES {
+        let mut byte = [0u8; 1];
+        match reader.read_exact(&mut byte) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && i == 0 => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        len |= ((byte[0] & 0x7F) as u64) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            let mut message = vec![0u8; check_len(len)?];
+            reader.read_exact(&mut message)?;
+            return Ok(Some(message));
+        }
+    }
+    Err(io::Error::new(ErrorKind::InvalidData, "varint longer than 10 bytes"))
+}
+
+// ── Async ─────────────────────────────────────────────────────────────────────
+
+pub async fn write_delimited_async<W: AsyncWrite + Unpin>(writer: &mut W, message: &[u8]) -> io::Result<()> {
+    writer.write_all(&encode(message)).await
+}
+
+pub async fn read_delimited_async<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut len = 0u64;
+    for i in 0..MAX_VARINT_BYTES {
+        let byte = match reader.read_u8().await {
+            Ok(byte) => byte,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && i == 0 => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        len |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            let mut message = vec![0u8; check_len(len)?];
+            reader.read_exact(&mut message).await?;
+            return Ok(Some(message));
+        }
+    }
+    Err(io::Error::new(ErrorKind::InvalidData, "varint longer than 10 bytes"))
+}
+
+// ── Streaming ─────────────────────────────────────────────────────────────────
+
+// feed it whatever the port hands us, get whole messages back
+#[derive(Default)]
+pub struct DelimitedDecoder {
+    buffer: Vec<u8>,
+    errors: u64,
+}
+
+impl DelimitedDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        self.buffer.extend_from_slice(data);
+        let mut messages = Vec::new();
+        loop {
+            match decode_varint(&self.buffer) {
+                Ok(Some((len, used))) if len as usize <= MAX_MESSAGE => {
+                    let end = used + len as usize;
+                    if self.buffer.len() < end {
+                        break;
+                    }
+                    messages.push(self.buffer[used..end].to_vec());
+                    self.buffer.drain(..end);
+                }
+                Ok(None) => break,
+                // a bad prefix leaves us nowhere to resync from but the next byte
+                Ok(Some(_)) | Err(_) => {
+                    self.errors += 1;
+                    self.buffer.drain(..1);
+                }
+            }
+        }
+        messages
+    }
+
+    // bad length prefixes skipped since this decoder was made
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn varint(value: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_varint(value, &mut out);
+        out
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for (value, len) in [(0, 1), (127, 1), (128, 2), (300, 2), (u32::MAX as u64, 5), (u64::MAX, 10)] {
+            let bytes = varint(value);
+            assert_eq!(bytes.len(), len, "{value}");
+            assert_eq!(decode_varint(&bytes), Ok(Some((value, len))));
+        }
+        assert_eq!(varint(300), [0xAC, 0x02]);
+    }
+
+    #[test]
+    fn a_varint_past_10_bytes_is_an_error() {
+        // u64::MAX is as long as they get
+        assert_eq!(varint(u64::MAX)[9], 0x01);
+        // nine continuation bytes might still end on the tenth
+        assert_eq!(decode_varint(&[0x80; 9]), Ok(None));
+        assert!(decode_varint(&[0x80; 10]).is_err());
+        assert!(decode_varint(&[0xFF; 11]).is_err());
+
+        let err = read_delimited(&mut Cursor::new([0x80; 10])).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let mut decoder = DelimitedDecoder::new();
+        assert!(decoder.push(&[0x80; 10]).is_empty());
+        assert_eq!(decoder.errors(), 1);
+    }
+
+    #[test]
+    fn reads_messages_until_a_clean_end() {
+        let messages = [b"hello".to_vec(), Vec::new(), vec![0xAA; 300]];
+        let mut wire = Vec::new();
+        for message in &messages {
+            write_delimited(&mut wire, message).unwrap();
+        }
+        let mut reader = Cursor::new(wire);
+        for message in &messages {
+            assert_eq!(read_delimited(&mut reader).unwrap().as_ref(), Some(message));
+        }
+        assert!(read_delimited(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn a_cut_off_or_oversized_message_is_an_error() {
+        let mut wire = encode(b"hello");
+        wire.truncate(4);
+        assert_eq!(read_delimited(&mut Cursor::new(wire)).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+
+        let too_long = varint(MAX_MESSAGE as u64 + 1);
+        assert_eq!(read_delimited(&mut Cursor::new(too_long)).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn decoder_takes_messages_a_byte_at_a_time() {
+        let messages = [b"a".to_vec(), Vec::new(), vec![0x42; 200], vec![0; 20_000]];
+        let wire: Vec<u8> = messages.iter().flat_map(|m| encode(m)).collect();
+        let mut decoder = DelimitedDecoder::new();
+        let mut decoded = Vec::new();
+        for &byte in &wire {
+            decoded.extend(decoder.push(&[byte]));
+        }
+        assert_eq!(decoded, messages);
+        assert_eq!(decoder.errors(), 0);
+        assert!(decoder.buffer.is_empty());
+    }
+
+    #[test]
+    fn decoder_drops_a_byte_at_an_oversized_length() {
+        let mut decoder = DelimitedDecoder::new();
+        // 2^21 - 1, past MAX_MESSAGE
+        assert!(decoder.push(&[0xFF, 0xFF, 0x7F]).is_empty());
+        assert_eq!(decoder.errors(), 1);
+        // what's left is read as the next prefix
+        assert_eq!(decoder.buffer, [0xFF, 0x7F]);
+    }
+}
@@ -0,0 +1,69 @@
+// The radio link's framing, shared by everything that reads or writes our frames
+//
+// A frame on air is the KV0R callsign, one length byte and the payload (a flatbuffer
+// Packet, plus its auth tag and CRC on links that use them). Links that need more
+// than the length byte to find frame edges wrap whole frames in COBS or a varint
+// length prefix, see cobs and length_delimited. crc_check is the optional checksum
+// at the end of a frame.
+pub mod cobs;
+pub mod crc_check;
+pub mod length_delimited;
+
+pub const CALLSIGN: &[u8] = b"KV0R";
+pub const HEADER_LEN: usize = CALLSIGN.len() + 1; // magic + length byte
+
+// add the callsign + length header the radios expect
+pub fn frame_payload(payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(HEADER_LEN + payload.len());
+    frame.extend_from_slice(CALLSIGN); // magic header/callsign
+    frame.push(payload.len() as u8); // length
+    frame.extend_from_slice(payload);
+    frame
+}
+
+// Pull the next complete frame (header included) off the front of the accumulator,
+// None means wait for more data
+pub fn next_frame(accumulator: &mut Vec<u8>) -> Option<Vec<u8>> {
+    // Find the magic header
+    let Some(start) = accumulator
+        .windows(CALLSIGN.len())
+        .position(|w| w == CALLSIGN)
+    else {
+        // No magic found — discard everything except the last
+        // (CALLSIGN.len() - 1) bytes in case magic is split across reads
+        if accumulator.len() > CALLSIGN.len() {
+            accumulator.drain(..accumulator.len() - (CALLSIGN.len() - 1));
+        }
+        return None;
+    };
+
+    // Discard anything before the magic
+    if start > 0 {
+        tracing::warn!(
+            "framing: discarding {} bytes before magic",
+            start
+        );
+        accumulator.drain(..start);
+    }
+
+    // Do we have enough bytes to read the length?
+    if accumulator.len() < HEADER_LEN {
+        return None; // wait for more data
+    }
+
+    let payload_len = accumulator[CALLSIGN.len()] as usize;
+    let total_len = HEADER_LEN + payload_len;
+
+    // Do we have the full packet?
+    if accumulator.len() < total_len {
+        return None; // wait for more data
+    }
+
+    // Extract the complete packet
+    Some(accumulator.drain(..total_len).collect::<Vec<u8>>())
+}
+
+// strip the framing header back off
+pub fn frame_body(frame: &[u8]) -> &[u8] {
+    &frame[HEADER_LEN.min(frame.len())..]
+}
@@ -0,0 +1,24 @@
+//! The ground station's core, everything that doesn't need a window
+//!
+//! The Tauri app is one user of this crate; the antenna rig's test harness and the
+//! analysis scripts are others. What's in here:
+//!
+//! - [`middleware`]: the telemetry stores ([`middleware::Middleware`] and
+//!   [`middleware::telemetry_stores`]), alerts, flight state, recording, and
+//!   [`middleware::export`] for CSV, MAT and JSON.
+//! - [`middleware::flatten`]: mapping a decoded packet onto store fields. Generated
+//!   flatbuffers types opt in with [`flatten_fields!`].
+//! - [`framing`]: the KV0R callsign frame, COBS, varint length prefixes and the
+//!   frame CRC.
+//! - [`playback`]: reading a recorded session back in, from its CSVs or packet logs.
+//!
+//! Events the middleware emits go to the sink given to
+//! [`middleware::Middleware::attach_events`], the app hands it its windows. Nothing is
+//! emitted before one is attached.
+//!
+//! Telemetry stores write their CSVs from a tokio task, so a [`middleware::Middleware`]
+//! has to be created and pushed to from inside a tokio runtime.
+
+pub mod framing;
+pub mod middleware;
+pub mod playback;
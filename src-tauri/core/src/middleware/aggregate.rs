@@ -0,0 +1,85 @@
+// Min/max/mean/std over a window of one field, worked out where the data is
+//
+// The window runs back from the field's newest point, not the wall clock, so it means
+// the same thing live and in playback. No window means everything buffered (within
+// the field's buffer_policy limits). NaN and infinite values are left out.
+use serde::{Deserialize, Serialize};
+
+use super::telemetry_stores::TelemetryData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregate {
+    Min,
+    Max,
+    Mean,
+    Std,
+    Sum,
+    Count,
+}
+
+impl Aggregate {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        serde_json::from_value(serde_json::Value::String(name.to_lowercase()))
+            .map_err(|_| format!("Unknown aggregate '{name}', expected min, max, mean, std, sum or count"))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AggregateResult {
+    // None when the window had no finite values (count is still 0 then)
+    pub value: Option<f64>,
+    pub count: u64,
+    // the first and last point that went in, unix ms
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+// one pass, Welford's method for the variance
+#[derive(Default)]
+pub struct Accumulator {
+    count: u64,
+    mean: f64,
+    m2: f64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+impl Accumulator {
+    pub fn add(&mut self, data: &TelemetryData) {
+        let value = data.value.as_f64();
+        if !value.is_finite() {
+            return;
+        }
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        }
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.from = Some(self.from.map_or(data.timestamp, |t| t.min(data.timestamp)));
+        self.to = Some(self.to.map_or(data.timestamp, |t| t.max(data.timestamp)));
+    }
+
+    pub fn finish(&self, aggregate: Aggregate) -> AggregateResult {
+        let value = match aggregate {
+            Aggregate::Count => Some(self.count as f64),
+            _ if self.count == 0 => None,
+            Aggregate::Min => Some(self.min),
+            Aggregate::Max => Some(self.max),
+            Aggregate::Mean => Some(self.mean),
+            Aggregate::Sum => Some(self.sum),
+            // population std, a window is all the samples there are
+            Aggregate::Std => Some((self.m2 / self.count as f64).sqrt()),
+        };
+        AggregateResult { value, count: self.count, from: self.from, to: self.to }
+    }
+}
@@ -0,0 +1,542 @@
+// Alert engine: active alarms plus operator acknowledge/shelve handling
+//
+// Alerts are keyed by a stable string (e.g. "rocket.battery_low") so re-raising the
+// same condition updates the existing alert instead of stacking duplicates.
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use super::config_file::append_json_line;
+use super::launch_sessions::{self, split_store};
+use super::lock_timing::TimedMutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum AlertSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum AlertState {
+    // condition present, nobody has responded
+    Active,
+    // operator has seen it, audio stops but it stays listed
+    Acknowledged,
+    // suppressed entirely until `until` (ms since epoch)
+    Shelved { until: i64 },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub key: String,
+    pub severity: AlertSeverity,
+    pub message: String,
+    pub raised_at: i64,
+    pub last_seen: i64,
+    pub state: AlertState,
+    // the underlying condition is still true
+    pub condition_active: bool,
+}
+
+impl Alert {
+    // frontend plays the alarm sound for these
+    pub fn is_audible(&self) -> bool {
+        self.condition_active && self.state == AlertState::Active
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertFrontend {
+    #[serde(flatten)]
+    pub alert: Alert,
+    pub audible: bool,
+    // raised_at in the operator's time base
+    pub raised_time: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum AlertAction {
+    Acknowledge,
+    Shelve { duration_ms: i64 },
+    Unshelve,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertAuditRecord {
+    pub timestamp: i64,
+    pub key: String,
+    pub action: AlertAction,
+    pub operator_role: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Above,
+    Below,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RatePer {
+    #[default]
+    Second,
+    Minute,
+}
+
+impl RatePer {
+    fn ms(self) -> f64 {
+        match self {
+            RatePer::Second => 1_000.0,
+            RatePer::Minute => 60_000.0,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            RatePer::Second => "s",
+            RatePer::Minute => "min",
+        }
+    }
+}
+
+// compare how fast the field is changing instead of its value: the least squares slope
+// over the last window_ms, so one noisy sample doesn't set it off
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RateConfig {
+    pub window_ms: i64,
+    #[serde(default)]
+    pub per: RatePer,
+}
+
+// a threshold alarm on a single telemetry field, or on its rate of change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    pub store: String,
+    pub field: String,
+    pub comparison: Comparison,
+    pub threshold: f64,
+    pub severity: AlertSeverity,
+    #[serde(default)]
+    pub message: Option<String>,
+    // hysteresis: once raised, the value has to get back past this to clear
+    // (defaults to `threshold`)
+    #[serde(default)]
+    pub clear_threshold: Option<f64>,
+    // the violation must persist this long before the alert is raised
+    #[serde(default)]
+    pub debounce_ms: i64,
+    // threshold is in units per second (or minute) when set, a battery dropping more
+    // than 0.1 V/min is below -0.1 per minute
+    #[serde(default)]
+    pub rate: Option<RateConfig>,
+}
+
+impl AlertRule {
+    fn is_violated(&self, value: f64) -> bool {
+        match self.comparison {
+            Comparison::Above => value > self.threshold,
+            Comparison::Below => value < self.threshold,
+        }
+    }
+
+    fn is_cleared(&self, value: f64) -> bool {
+        let clear = self.clear_threshold.unwrap_or(self.threshold);
+        match self.comparison {
+            Comparison::Above => value <= clear,
+            Comparison::Below => value >= clear,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.debounce_ms < 0 {
+            return Err(format!("Rule '{}' has a negative debounce", self.name));
+        }
+        if self.rate.is_some_and(|rate| rate.window_ms <= 0) {
+            return Err(format!("Rule '{}' needs a rate window of at least 1 ms", self.name));
+        }
+        if let Some(clear) = self.clear_threshold {
+            let ok = match self.comparison {
+                Comparison::Above => clear <= self.threshold,
+                Comparison::Below => clear >= self.threshold,
+            };
+            if !ok {
+                return Err(format!(
+                    "Rule '{}' clear threshold {} is on the wrong side of {}",
+                    self.name, clear, self.threshold
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn describe(&self, value: f64) -> String {
+        self.message.clone().unwrap_or_else(|| {
+            let dir = match self.comparison {
+                Comparison::Above => "above",
+                Comparison::Below => "below",
+            };
+            match self.rate {
+                Some(rate) => format!(
+                    "{}.{} changing at {:.3}/{} is {} {}",
+                    self.store, self.field, value, rate.per.label(), dir, self.threshold
+                ),
+                None => format!("{}.{} = {} is {} {}", self.store, self.field, value, dir, self.threshold),
+            }
+        })
+    }
+}
+
+// debounce/hysteresis bookkeeping for one rule
+#[derive(Debug, Clone, Default)]
+struct RuleState {
+    // timestamp of the first sample in the current run of violations
+    violating_since: Option<i64>,
+    // the rule has raised its alert and is waiting for the clear threshold
+    latched: bool,
+    // (timestamp, value) inside a rate rule's window, oldest first
+    samples: VecDeque<(i64, f64)>,
+}
+
+impl RuleState {
+    // the window's slope in units per `rate.per`. None until the samples span half
+    // the window, the first two points of a stream say nothing about its trend.
+    fn rate(&mut self, rate: RateConfig, value: f64, timestamp: i64) -> Option<f64> {
+        if !value.is_finite() {
+            return None;
+        }
+        // a timestamp going backwards is a new stream (playback restarted), start over
+        if self.samples.back().is_some_and(|&(t, _)| timestamp < t) {
+            self.samples.clear();
+        }
+        self.samples.push_back((timestamp, value));
+        while self.samples.front().is_some_and(|&(t, _)| t < timestamp - rate.window_ms) {
+            self.samples.pop_front();
+        }
+        let (first, _) = *self.samples.front()?;
+        if self.samples.len() < 2 || timestamp - first < rate.window_ms / 2 {
+            return None;
+        }
+
+        // relative to the first sample so big unix ms timestamps don't eat the precision
+        let n = self.samples.len() as f64;
+        let (mut sum_t, mut sum_v, mut sum_tt, mut sum_tv) = (0.0, 0.0, 0.0, 0.0);
+        for &(t, v) in &self.samples {
+            let t = (t - first) as f64;
+            sum_t += t;
+            sum_v += v;
+            sum_tt += t * t;
+            sum_tv += t * v;
+        }
+        let denominator = n * sum_tt - sum_t * sum_t;
+        if denominator == 0.0 {
+            return None;
+        }
+        Some((n * sum_tv - sum_t * sum_v) / denominator * rate.per.ms())
+    }
+}
+
+// a named, vetted alarm configuration (one per vehicle/flight type)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRuleSet {
+    pub name: String,
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+}
+
+pub struct AlertEngine {
+    alerts: DashMap<String, Alert>,
+    audit: TimedMutex<Vec<AlertAuditRecord>>,
+    audit_path: PathBuf,
+
+    // every imported rule set, by name
+    rule_sets: DashMap<String, AlertRuleSet>,
+    // the rule set evaluated on ingest
+    active_rules: TimedMutex<Option<AlertRuleSet>>,
+    rule_state: DashMap<String, RuleState>,
+}
+
+impl AlertEngine {
+    pub fn new(audit_path: PathBuf) -> Self {
+        Self {
+            alerts: DashMap::new(),
+            audit: TimedMutex::new("alerts.audit", Vec::new()),
+            audit_path,
+            rule_sets: DashMap::new(),
+            active_rules: TimedMutex::new("alerts.rules", None),
+            rule_state: DashMap::new(),
+        }
+    }
+
+// ---------------------------------------------  Rules  --------------------------------------------- //
+
+    /// Add (or replace) a rule set in the library, returning its name
+    pub fn add_rule_set(&self, rule_set: AlertRuleSet) -> Result<String, String> {
+        if rule_set.name.is_empty() {
+            return Err("Rule set has no name".into());
+        }
+        for rule in &rule_set.rules {
+            rule.validate()?;
+        }
+        let name = rule_set.name.clone();
+
+        // keep the live copy in sync if someone re-imports the active set
+        let mut active = self.active_rules.lock();
+        if active.as_ref().is_some_and(|a| a.name == name) {
+            *active = Some(rule_set.clone());
+        }
+        self.rule_sets.insert(name.clone(), rule_set);
+        Ok(name)
+    }
+
+    pub fn get_rule_set(&self, name: &str) -> Result<AlertRuleSet, String> {
+        self.rule_sets
+            .get(name)
+            .map(|r| r.clone())
+            .ok_or_else(|| format!("No alert rule set named '{}'", name))
+    }
+
+    pub fn list_rule_sets(&self) -> Vec<String> {
+        self.rule_sets.iter().map(|r| r.key().clone()).collect()
+    }
+
+    pub fn active_rule_set(&self) -> Option<String> {
+        self.active_rules.lock().as_ref().map(|r| r.name.clone())
+    }
+
+    /// Switch the active rule set (None disables rule evaluation)
+    pub fn activate_rule_set(&self, name: Option<&str>) -> Result<(), String> {
+        let next = name.map(|n| self.get_rule_set(n)).transpose()?;
+        let previous = std::mem::replace(&mut *self.active_rules.lock(), next);
+
+        // alerts raised by the old rules no longer have anything maintaining them
+        if let Some(previous) = previous {
+            for rule in previous.rules {
+                self.clear(&rule.name);
+            }
+            // nor do the launch sessions' copies of them
+            for state in self.rule_state.iter() {
+                self.clear(state.key());
+            }
+        }
+        self.rule_state.clear();
+        Ok(())
+    }
+
+    /// Check an incoming datapoint against the active rules. `timestamp` is the
+    /// datapoint's own time so debounce works the same live and in playback. A launch
+    /// session's stores run the rules on their own, see launch_sessions.
+    pub fn evaluate(&self, store: &str, field: &str, value: f64, timestamp: i64) {
+        let active = self.active_rules.lock();
+        let Some(rule_set) = active.as_ref() else {
+            return;
+        };
+        let (session, store) = split_store(store);
+
+        for rule in rule_set.rules.iter().filter(|r| r.store == store && r.field == field) {
+            let key = match session {
+                Some(id) => launch_sessions::session_key(id, &rule.name),
+                None => rule.name.clone(),
+            };
+            let mut state = self.rule_state.entry(key.clone()).or_default();
+            // from here on `value` is whatever the rule compares, the rate for a rate rule
+            let value = match rule.rate {
+                Some(rate) => match state.rate(rate, value, timestamp) {
+                    Some(rate) => rate,
+                    None => continue,
+                },
+                None => value,
+            };
+
+            if state.latched {
+                if rule.is_cleared(value) {
+                    state.latched = false;
+                    state.violating_since = None;
+                    self.clear(&key);
+                } else {
+                    // keep the message/value current while it's still up
+                    self.raise(&key, rule.severity, rule.describe(value));
+                }
+                continue;
+            }
+
+            if !rule.is_violated(value) {
+                state.violating_since = None;
+                continue;
+            }
+
+            let since = *state.violating_since.get_or_insert(timestamp);
+            if timestamp - since >= rule.debounce_ms {
+                state.latched = true;
+                self.raise(&key, rule.severity, rule.describe(value));
+            }
+        }
+    }
+
+    /// Raise (or refresh) an alert. Acknowledged/shelved alerts keep their state
+    /// while the condition persists.
+    pub fn raise(&self, key: &str, severity: AlertSeverity, message: impl Into<String>) {
+        let now = now_ms();
+        let message = message.into();
+
+        let mut alert = self.alerts.entry(key.to_string()).or_insert_with(|| Alert {
+            key: key.to_string(),
+            severity,
+            message: message.clone(),
+            raised_at: now,
+            last_seen: now,
+            state: AlertState::Active,
+            condition_active: true,
+        });
+
+        // a condition that had cleared and came back is a fresh alarm
+        if !alert.condition_active && !matches!(alert.state, AlertState::Shelved { .. }) {
+            alert.state = AlertState::Active;
+            alert.raised_at = now;
+        }
+        // escalation always needs a new acknowledgment
+        if severity > alert.severity && alert.state == AlertState::Acknowledged {
+            alert.state = AlertState::Active;
+        }
+
+        alert.severity = severity;
+        alert.message = message;
+        alert.last_seen = now;
+        alert.condition_active = true;
+    }
+
+    /// Clears every alert under `prefix` and forgets the rule state behind them, for a
+    /// launch session that ended
+    pub fn clear_prefixed(&self, prefix: &str) {
+        self.rule_state.retain(|key, _| !key.starts_with(prefix));
+        let keys: Vec<String> = self.alerts.iter().filter(|a| a.key().starts_with(prefix)).map(|a| a.key().clone()).collect();
+        for key in keys {
+            self.clear(&key);
+        }
+    }
+
+    /// Mark the condition behind an alert as gone. Acknowledged alerts are removed,
+    /// unacknowledged ones stay listed so the operator still sees them.
+    pub fn clear(&self, key: &str) {
+        let remove = match self.alerts.get_mut(key) {
+            Some(mut alert) => {
+                alert.condition_active = false;
+                alert.state == AlertState::Acknowledged
+            }
+            None => false,
+        };
+        if remove {
+            self.alerts.remove(key);
+        }
+    }
+
+    pub fn acknowledge(&self, key: &str, operator_role: &str) -> Result<(), String> {
+        let remove = {
+            let mut alert = self.get_alert_mut(key)?;
+            alert.state = AlertState::Acknowledged;
+            !alert.condition_active
+        };
+        if remove {
+            self.alerts.remove(key);
+        }
+        self.record(key, AlertAction::Acknowledge, operator_role);
+        Ok(())
+    }
+
+    pub fn acknowledge_all(&self, operator_role: &str) {
+        let keys: Vec<String> = self.alerts
+            .iter()
+            .filter(|a| a.state == AlertState::Active)
+            .map(|a| a.key.clone())
+            .collect();
+        for key in keys {
+            let _ = self.acknowledge(&key, operator_role);
+        }
+    }
+
+    pub fn shelve(&self, key: &str, duration_ms: i64, operator_role: &str) -> Result<(), String> {
+        if duration_ms <= 0 {
+            return Err(format!("Shelve duration must be positive, got {}", duration_ms));
+        }
+        self.get_alert_mut(key)?.state = AlertState::Shelved { until: now_ms() + duration_ms };
+        self.record(key, AlertAction::Shelve { duration_ms }, operator_role);
+        Ok(())
+    }
+
+    pub fn unshelve(&self, key: &str, operator_role: &str) -> Result<(), String> {
+        self.get_alert_mut(key)?.state = AlertState::Active;
+        self.record(key, AlertAction::Unshelve, operator_role);
+        Ok(())
+    }
+
+    /// All alerts that are not currently shelved, most severe first
+    pub fn list(&self) -> Vec<Alert> {
+        self.expire_shelves();
+
+        let mut alerts: Vec<Alert> = self.alerts
+            .iter()
+            .filter(|a| !matches!(a.state, AlertState::Shelved { .. }))
+            .map(|a| a.value().clone())
+            .collect();
+        alerts.sort_by(|a, b| b.severity.cmp(&a.severity).then(a.raised_at.cmp(&b.raised_at)));
+        alerts
+    }
+
+    pub fn list_shelved(&self) -> Vec<Alert> {
+        self.expire_shelves();
+
+        self.alerts
+            .iter()
+            .filter(|a| matches!(a.state, AlertState::Shelved { .. }))
+            .map(|a| a.value().clone())
+            .collect()
+    }
+
+    pub fn audit_log(&self) -> Vec<AlertAuditRecord> {
+        self.audit.lock().clone()
+    }
+
+    // shelves that ran out either come back or drop off if the condition is gone
+    fn expire_shelves(&self) {
+        let now = now_ms();
+        self.alerts.retain(|_, alert| match alert.state {
+            AlertState::Shelved { until } if until <= now => {
+                alert.state = AlertState::Active;
+                alert.condition_active
+            }
+            _ => true,
+        });
+    }
+
+    fn get_alert_mut(
+        &self,
+        key: &str,
+    ) -> Result<dashmap::mapref::one::RefMut<'_, String, Alert>, String> {
+        self.alerts
+            .get_mut(key)
+            .ok_or_else(|| format!("No alert named '{}'", key))
+    }
+
+    // keep the audit trail in memory and append it to disk as JSON lines
+    fn record(&self, key: &str, action: AlertAction, operator_role: &str) {
+        let record = AlertAuditRecord {
+            timestamp: now_ms(),
+            key: key.to_string(),
+            action,
+            operator_role: operator_role.to_string(),
+        };
+
+        if let Err(e) = append_json_line(&self.audit_path, &record) {
+            eprintln!("[alerts] Failed to write audit record: {e}");
+        }
+        self.audit.lock().push(record);
+    }
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
@@ -0,0 +1,203 @@
+// Rolling "black box" of every telemetry point, written whether or not we're recording
+//
+// So an operator who forgot to press record still has the flight. Points go to a set
+// of CSV segments (store,field,timestamp,value) on a background thread; once there
+// are SEGMENTS of them the oldest is deleted, leaving roughly the last
+// SEGMENTS * SEGMENT_SECS of data on disk.
+use std::collections::VecDeque;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+
+use super::drops::SharedDrops;
+use super::encryption::{SessionEncryption, SessionWriter};
+use super::telemetry_stores::TelemetryData;
+
+const SEGMENT_SECS: u64 = 60;
+const SEGMENTS: usize = 10;
+// bounded so a stuck disk can't eat all our memory, points past this are dropped
+const QUEUE_DEPTH: usize = 16_384;
+// worst case we lose this much on a crash
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+enum BlackBoxCommand {
+    Point { store: String, field: String, timestamp: i64, value: String },
+    Flush,
+    Stop,
+}
+
+pub struct BlackBox {
+    tx: SyncSender<BlackBoxCommand>,
+    dir: PathBuf,
+    // points sent but not yet written, std channels can't tell us
+    queued: Arc<AtomicUsize>,
+    drops: SharedDrops,
+}
+
+impl BlackBox {
+    pub fn new(dir: PathBuf, drops: SharedDrops, encryption: SessionEncryption) -> Self {
+        let (tx, rx) = mpsc::sync_channel(QUEUE_DEPTH);
+        let writer_dir = dir.clone();
+        let queued = Arc::new(AtomicUsize::new(0));
+        let writer_queued = queued.clone();
+        std::thread::spawn(move || {
+            let mut segments = Segments::new(writer_dir, encryption);
+            loop {
+                match rx.recv_timeout(FLUSH_INTERVAL) {
+                    Ok(BlackBoxCommand::Point { store, field, timestamp, value }) => {
+                        segments.write(&store, &field, timestamp, &value);
+                        writer_queued.fetch_sub(1, Ordering::Relaxed);
+                    }
+                    Ok(BlackBoxCommand::Flush) => segments.flush(),
+                    Ok(BlackBoxCommand::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+                    Err(RecvTimeoutError::Timeout) => {}
+                }
+                segments.flush_if_due();
+            }
+            segments.flush();
+        });
+        Self { tx, dir, queued, drops }
+    }
+
+    pub fn record(&self, store: &str, field: &str, data: &TelemetryData) {
+        let cmd = BlackBoxCommand::Point {
+            store: store.to_string(),
+            field: field.to_string(),
+            timestamp: data.timestamp,
+            value: data.value.to_string(),
+        };
+        // never hold up the telemetry path for the black box
+        match self.tx.try_send(cmd) {
+            Ok(()) => {
+                self.queued.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Full(_)) => self.drops.note("black_box", 1),
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    // oldest first
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| p.extension().is_some_and(|ext| ext == "csv"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+        files
+    }
+
+    pub fn flush(&self) {
+        let _ = self.tx.try_send(BlackBoxCommand::Flush);
+    }
+
+    pub fn shutdown(&self) {
+        let _ = self.tx.try_send(BlackBoxCommand::Stop);
+    }
+}
+
+// ── Writer side ───────────────────────────────────────────────────────────────
+
+struct Segments {
+    dir: PathBuf,
+    // oldest first, the last one is being written
+    files: VecDeque<PathBuf>,
+    current: Option<(BufWriter<SessionWriter>, Instant)>,
+    last_flush: Instant,
+    encryption: SessionEncryption,
+}
+
+impl Segments {
+    fn new(dir: PathBuf, encryption: SessionEncryption) -> Self {
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            eprintln!("[black_box] Failed to create {}: {e}", dir.display());
+        }
+        // segments left by earlier runs count towards SEGMENTS too, or they'd never go
+        let files = existing_segments(&dir).into();
+        Self { dir, files, current: None, last_flush: Instant::now(), encryption }
+    }
+
+    fn write(&mut self, store: &str, field: &str, timestamp: i64, value: &str) {
+        let expired = self
+            .current
+            .as_ref()
+            .is_some_and(|(_, opened)| opened.elapsed() >= Duration::from_secs(SEGMENT_SECS));
+        if self.current.is_none() || expired {
+            self.rotate();
+        }
+        if let Some((file, _)) = self.current.as_mut() {
+            let _ = writeln!(file, "{},{},{},{}", store, field, timestamp, value);
+        }
+    }
+
+    fn rotate(&mut self) {
+        self.flush();
+        self.current = None;
+
+        while self.files.len() >= SEGMENTS {
+            if let Some(oldest) = self.files.pop_front() {
+                let _ = std::fs::remove_file(oldest);
+            }
+        }
+
+        let path = self
+            .dir
+            .join(format!("black_box_{}.csv", Local::now().format("%Y-%m-%d_%H-%M-%S")));
+        match open_segment(&path, &self.encryption) {
+            Ok(file) => {
+                self.current = Some((file, Instant::now()));
+                self.files.push_back(path);
+            }
+            Err(e) => eprintln!("[black_box] {e}"),
+        }
+    }
+
+    fn flush_if_due(&mut self) {
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some((file, _)) = self.current.as_mut() {
+            let _ = file.flush();
+        }
+        self.last_flush = Instant::now();
+    }
+}
+
+// black_box_<time>.csv names sort oldest first
+fn existing_segments(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| {
+                    p.file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| name.starts_with("black_box_") && name.ends_with(".csv"))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+fn open_segment(path: &Path, encryption: &SessionEncryption) -> Result<BufWriter<SessionWriter>, String> {
+    let mut writer = BufWriter::new(encryption.create(path)?);
+    writeln!(writer, "store,field,timestamp,value").map_err(|e| e.to_string())?;
+    Ok(writer)
+}
@@ -0,0 +1,81 @@
+// How much of each stream the in-memory stores keep
+//
+// Every (store, field) takes the limits of the first matching rule, the defaults if
+// none does. A buffer can be capped by count, by age or both, e.g. an hour of GPS but
+// only ten seconds of a 1 kHz IMU:
+//   { "store": "rocket", "field": "gps_*", "max_age_ms": 3600000 }
+//   { "store": "rocket", "field": "imu_*", "max_age_ms": 10000 }
+// Age is measured back from the newest point in that field rather than the wall clock,
+// so a replay or a stream that went quiet keeps its tail. Only memory is trimmed, the
+// CSVs, journal and sinks still get everything.
+use serde::{Deserialize, Serialize};
+
+use super::load_shedding::wildcard;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BufferLimit {
+    #[serde(default)]
+    pub max_points: Option<usize>,
+    #[serde(default)]
+    pub max_age_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BufferRule {
+    // "*" matches anything, like the load shedding rules
+    pub store: String,
+    pub field: String,
+    #[serde(flatten)]
+    pub limit: BufferLimit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BufferConfig {
+    #[serde(default)]
+    pub rules: Vec<BufferRule>,
+    // for fields no rule matches
+    #[serde(default = "default_limit")]
+    pub default: BufferLimit,
+}
+
+fn default_limit() -> BufferLimit {
+    BufferLimit { max_points: Some(10_000), max_age_ms: None }
+}
+
+impl Default for BufferConfig {
+    fn default() -> Self {
+        Self { rules: Vec::new(), default: default_limit() }
+    }
+}
+
+impl BufferLimit {
+    fn validate(&self, what: &str) -> Result<(), String> {
+        if self.max_points.is_none() && self.max_age_ms.is_none() {
+            return Err(format!("{what} needs a max_points or a max_age_ms, or it would grow forever"));
+        }
+        if self.max_points == Some(0) || self.max_age_ms.is_some_and(|ms| ms <= 0) {
+            return Err(format!("{what} has to keep at least one point and one ms"));
+        }
+        Ok(())
+    }
+}
+
+impl BufferConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        self.default.validate("The default buffer")?;
+        for rule in &self.rules {
+            if rule.store.is_empty() || rule.field.is_empty() {
+                return Err(format!("Rule for '{}'.'{}' needs both a store and a field pattern", rule.store, rule.field));
+            }
+            rule.limit.validate(&format!("The buffer for '{}'.'{}'", rule.store, rule.field))?;
+        }
+        Ok(())
+    }
+
+    pub fn limit(&self, store: &str, field: &str) -> BufferLimit {
+        self.rules
+            .iter()
+            .find(|rule| wildcard(&rule.store, store) && wildcard(&rule.field, field))
+            .map_or(self.default, |rule| rule.limit)
+    }
+}
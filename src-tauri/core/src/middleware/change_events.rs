@@ -0,0 +1,94 @@
+// One event per change of a discrete field, instead of the UI polling for it
+//
+// Watch a field (the rocket's state, gps_lock, a latch servo) and push_data emits
+// field_changed with the old and new value whenever it's different from the last point,
+// nothing while it holds. The first point of a watched field is a change from nothing.
+// Meant for bools and enums, a float that moves every sample would flood the event log.
+// The ground side flight state has its own flight_state event already.
+// Points flagged suspect or out_of_range don't count, a glitch isn't a deployment.
+// The last MAX_RECENT changes are kept so a window opened late can fill in its log.
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+use super::data_quality::DataQuality;
+use super::load_shedding::wildcard;
+use super::telemetry_stores::{TelemetryData, TelemetryValue};
+
+pub const FIELD_CHANGED_EVENT: &str = "field_changed";
+const MAX_RECENT: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchedField {
+    // "*" matches anything, like the load shedding rules
+    pub store: String,
+    pub field: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    pub store: String,
+    pub field: String,
+    pub old: Option<TelemetryValue>,
+    pub new: TelemetryValue,
+    // the point's own time, unix ms
+    pub timestamp: i64,
+}
+
+#[derive(Default)]
+pub struct ChangeWatcher {
+    watched: Vec<WatchedField>,
+    // (store, field) -> the last value seen
+    last: HashMap<(String, String), TelemetryValue>,
+    recent: VecDeque<FieldChange>,
+}
+
+impl ChangeWatcher {
+    pub fn watch(&mut self, watched: WatchedField) -> Result<(), String> {
+        if watched.store.is_empty() || watched.field.is_empty() {
+            return Err("A watched field needs both a store and a field pattern".into());
+        }
+        if !self.watched.contains(&watched) {
+            self.watched.push(watched);
+        }
+        Ok(())
+    }
+
+    pub fn unwatch(&mut self, watched: &WatchedField) {
+        self.watched.retain(|w| w != watched);
+        let still_watched: Vec<(String, String)> =
+            self.last.keys().filter(|(store, field)| self.is_watched(store, field)).cloned().collect();
+        self.last.retain(|key, _| still_watched.contains(key));
+    }
+
+    pub fn watched(&self) -> Vec<WatchedField> {
+        self.watched.clone()
+    }
+
+    pub fn recent(&self) -> Vec<FieldChange> {
+        self.recent.iter().cloned().collect()
+    }
+
+    fn is_watched(&self, store: &str, field: &str) -> bool {
+        self.watched.iter().any(|w| wildcard(&w.store, store) && wildcard(&w.field, field))
+    }
+
+    /// The change `data` makes to a watched field, if it makes one
+    pub fn observe(&mut self, store: &str, field: &str, data: &TelemetryData) -> Option<FieldChange> {
+        if self.watched.is_empty() || matches!(data.quality, DataQuality::Suspect | DataQuality::OutOfRange) {
+            return None;
+        }
+        if !self.is_watched(store, field) {
+            return None;
+        }
+        let old = self.last.insert((store.to_string(), field.to_string()), data.value);
+        if old == Some(data.value) {
+            return None;
+        }
+        let change = FieldChange { store: store.to_string(), field: field.to_string(), old, new: data.value, timestamp: data.timestamp };
+        if self.recent.len() == MAX_RECENT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(change.clone());
+        Some(change)
+    }
+}
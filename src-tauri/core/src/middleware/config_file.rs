@@ -0,0 +1,54 @@
+// Read/write small config documents (rule sets, mission profiles) as JSON or TOML,
+// picked by file extension
+use serde::{de::DeserializeOwned, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+enum Format {
+    Json,
+    Toml,
+}
+
+fn format_for(path: &Path) -> Result<Format, String> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => Ok(Format::Json),
+        Some("toml") => Ok(Format::Toml),
+        _ => Err(format!("Unsupported config file type: {}", path.display())),
+    }
+}
+
+pub fn read_config_file<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+
+    match format_for(path)? {
+        Format::Json => serde_json::from_str(&text).map_err(|e| format!("Invalid JSON in {}: {e}", path.display())),
+        Format::Toml => toml::from_str(&text).map_err(|e| format!("Invalid TOML in {}: {e}", path.display())),
+    }
+}
+
+pub fn write_config_file<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let text = match format_for(path)? {
+        Format::Json => serde_json::to_string_pretty(value).map_err(|e| e.to_string())?,
+        Format::Toml => toml::to_string_pretty(value).map_err(|e| e.to_string())?,
+    };
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(path, text).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+// for audit trails, one JSON document per line so a crash only loses the last one
+pub fn append_json_line<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    let line = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())
+}
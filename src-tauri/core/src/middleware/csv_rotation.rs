@@ -0,0 +1,56 @@
+// Splitting a long recording into segments, so a pad hold isn't one multi-gigabyte CSV
+//
+// Off by default. With max_mb or max_minutes set, a store's CSV rolls over to the next
+// segment once the current one reaches either limit: rocket.csv, then rocket_0002.csv,
+// rocket_0003.csv and so on next to it. Every segment starts with the header, so each
+// one opens on its own. The size is counted as rows are handed to the file, before
+// encryption, and the time from a segment's first row.
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RotationConfig {
+    #[serde(default)]
+    pub max_mb: Option<u64>,
+    #[serde(default)]
+    pub max_minutes: Option<u64>,
+}
+
+impl RotationConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_mb == Some(0) || self.max_minutes == Some(0) {
+            return Err("Rotation limits have to be at least 1, leave one out to not rotate on it".into());
+        }
+        Ok(())
+    }
+
+    pub fn due(&self, bytes: u64, started_at: i64, now: i64) -> bool {
+        self.max_mb.is_some_and(|mb| bytes >= mb * 1024 * 1024)
+            || self.max_minutes.is_some_and(|minutes| now - started_at >= minutes as i64 * 60_000)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingSegment {
+    // from 1
+    pub index: u32,
+    pub path: PathBuf,
+    // unix ms of the first row, and of the last once the segment is closed
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    pub rows: u64,
+    pub bytes: u64,
+}
+
+// the first segment is the store's own path, the ones after it are numbered
+pub fn segment_path(base: &Path, index: u32) -> PathBuf {
+    if index <= 1 {
+        return base.to_path_buf();
+    }
+    let stem = base.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = match base.extension() {
+        Some(ext) => format!("{stem}_{index:04}.{}", ext.to_string_lossy()),
+        None => format!("{stem}_{index:04}"),
+    };
+    base.with_file_name(name)
+}
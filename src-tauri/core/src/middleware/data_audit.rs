@@ -0,0 +1,57 @@
+// Audit trail for operations that throw away flight data
+//
+// Nothing here is undoable by itself, so every record points at the recovery file
+// that was written before the data went away.
+use serde::Serialize;
+use std::path::PathBuf;
+
+use super::config_file::append_json_line;
+use super::lock_timing::TimedMutex;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind")]
+pub enum DataAction {
+    ClearAllTelemetry {
+        // cleared while recording was still running
+        forced: bool,
+        stores: usize,
+        points: usize,
+        recovery_file: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DataAuditRecord {
+    pub timestamp: i64,
+    pub action: DataAction,
+    pub operator_role: String,
+}
+
+pub struct DataAuditLog {
+    records: TimedMutex<Vec<DataAuditRecord>>,
+    path: PathBuf,
+}
+
+impl DataAuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self { records: TimedMutex::new("data_audit", Vec::new()), path }
+    }
+
+    pub fn record(&self, action: DataAction, operator_role: &str) -> DataAuditRecord {
+        let record = DataAuditRecord {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            action,
+            operator_role: operator_role.to_string(),
+        };
+
+        if let Err(e) = append_json_line(&self.path, &record) {
+            eprintln!("[data_audit] Failed to write audit record: {e}");
+        }
+        self.records.lock().push(record.clone());
+        record
+    }
+
+    pub fn records(&self) -> Vec<DataAuditRecord> {
+        self.records.lock().clone()
+    }
+}
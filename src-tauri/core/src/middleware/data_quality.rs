@@ -0,0 +1,157 @@
+// How far to trust each datapoint
+//
+// Every point carries a DataQuality. Whoever produces it can mark it (playback filling
+// a gap would say interpolated), then push_data runs it past the validator, which can
+// only make it worse:
+//   duplicate     the field already has a point at this timestamp
+//   out_of_range  outside the min/max of the first matching rule, or with no rule the
+//                 field's expected range from field_metadata
+//   suspect       not finite, or moved faster than the rule's max_rate allows
+// The tiers are ordered, so a point that is both suspect and out of range is out of
+// range. The quality follows the point into queries and the exports; a flagged point
+// is still stored, it's up to the analyst to filter.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::load_shedding::wildcard;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataQuality {
+    #[default]
+    Valid,
+    Interpolated,
+    Suspect,
+    Duplicate,
+    OutOfRange,
+}
+
+impl DataQuality {
+    pub fn is_valid(&self) -> bool {
+        *self == DataQuality::Valid
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataQuality::Valid => "valid",
+            DataQuality::Interpolated => "interpolated",
+            DataQuality::Suspect => "suspect",
+            DataQuality::Duplicate => "duplicate",
+            DataQuality::OutOfRange => "out_of_range",
+        }
+    }
+
+    // numeric tier for formats without strings, 0 is valid
+    pub fn code(&self) -> u8 {
+        *self as u8
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityRule {
+    // "*" matches anything, like the load shedding rules
+    pub store: String,
+    pub field: String,
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    // per second, between consecutive points
+    #[serde(default)]
+    pub max_rate: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QualityConfig {
+    #[serde(default)]
+    pub rules: Vec<QualityRule>,
+}
+
+impl QualityConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in &self.rules {
+            if rule.store.is_empty() || rule.field.is_empty() {
+                return Err(format!("Rule for '{}'.'{}' needs both a store and a field pattern", rule.store, rule.field));
+            }
+            if let (Some(min), Some(max)) = (rule.min, rule.max) {
+                if min > max {
+                    return Err(format!("Rule for '{}'.'{}' has min above max", rule.store, rule.field));
+                }
+            }
+            if rule.max_rate.is_some_and(|rate| rate <= 0.0) {
+                return Err(format!("Rule for '{}'.'{}' needs a max_rate above 0", rule.store, rule.field));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct QualityValidator {
+    config: QualityConfig,
+    // the last (timestamp, value) of every field, store -> field
+    last: HashMap<String, HashMap<String, (i64, f64)>>,
+}
+
+impl Default for QualityValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QualityValidator {
+    pub fn new() -> Self {
+        Self { config: QualityConfig::default(), last: HashMap::new() }
+    }
+
+    pub fn config(&self) -> QualityConfig {
+        self.config.clone()
+    }
+
+    pub fn set_config(&mut self, config: QualityConfig) -> Result<(), String> {
+        config.validate()?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// The quality a point with `claimed` quality ends up with. `expected` is the field's
+    /// (min, max) from its metadata, used when no rule matches.
+    pub fn check(
+        &mut self,
+        store: &str,
+        field: &str,
+        value: f64,
+        timestamp: i64,
+        claimed: DataQuality,
+        expected: Option<(Option<f64>, Option<f64>)>,
+    ) -> DataQuality {
+        let previous = self
+            .last
+            .entry(store.to_string())
+            .or_default()
+            .insert(field.to_string(), (timestamp, value));
+        let mut quality = claimed;
+        if !value.is_finite() {
+            quality = quality.max(DataQuality::Suspect);
+        }
+        if previous.is_some_and(|(t, _)| t == timestamp) {
+            quality = quality.max(DataQuality::Duplicate);
+        }
+        let Some(rule) = self.config.rules.iter().find(|r| wildcard(&r.store, store) && wildcard(&r.field, field)) else {
+            if let Some((min, max)) = expected {
+                if min.is_some_and(|min| value < min) || max.is_some_and(|max| value > max) {
+                    quality = quality.max(DataQuality::OutOfRange);
+                }
+            }
+            return quality;
+        };
+        if rule.min.is_some_and(|min| value < min) || rule.max.is_some_and(|max| value > max) {
+            quality = quality.max(DataQuality::OutOfRange);
+        }
+        if let (Some(max_rate), Some((t, v))) = (rule.max_rate, previous) {
+            if timestamp > t && ((value - v) / ((timestamp - t) as f64 / 1000.0)).abs() > max_rate {
+                quality = quality.max(DataQuality::Suspect);
+            }
+        }
+        quality
+    }
+}
@@ -0,0 +1,65 @@
+// Data we got but couldn't decode, so the operator hears about it instead of stderr
+//
+// Every decode failure (a radio frame that isn't a packet under either schema, a CSV
+// row the simulator can't read) becomes a decode_error event with the offending bytes
+// or row, and bumps decode_errors in that stream's store so it can be plotted next to
+// the data it's missing from.
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::packet_log::to_hex;
+
+pub const DECODE_ERROR_EVENT: &str = "decode_error";
+// longest row text or hex we put in an event
+const MAX_CONTENT: usize = 512;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodeError {
+    // the store the data was headed for
+    pub stream: String,
+    pub timestamp: i64,
+    pub error: String,
+    // 1-based line in the CSV, None for radio frames
+    pub row: Option<u64>,
+    // the row as text, or the frame as hex
+    pub content: String,
+    pub length: usize,
+    // errors on this stream so far, this one included
+    pub count: u64,
+}
+
+impl DecodeError {
+    pub fn frame(stream: &str, timestamp: i64, frame: &[u8], error: String) -> Self {
+        Self::new(stream, timestamp, error, None, to_hex(frame), frame.len())
+    }
+
+    pub fn row(stream: &str, row: u64, text: &str, error: String) -> Self {
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        Self::new(stream, timestamp, error, Some(row), text.to_string(), text.len())
+    }
+
+    fn new(stream: &str, timestamp: i64, error: String, row: Option<u64>, mut content: String, length: usize) -> Self {
+        if content.len() > MAX_CONTENT {
+            let mut end = MAX_CONTENT;
+            while !content.is_char_boundary(end) {
+                end -= 1;
+            }
+            content.truncate(end);
+            content.push('…');
+        }
+        Self { stream: stream.to_string(), timestamp, error, row, content, length, count: 0 }
+    }
+}
+
+#[derive(Default)]
+pub struct DecodeErrorCounts {
+    counts: HashMap<String, u64>,
+}
+
+impl DecodeErrorCounts {
+    pub fn note(&mut self, stream: &str) -> u64 {
+        let count = self.counts.entry(stream.to_string()).or_default();
+        *count += 1;
+        *count
+    }
+}
@@ -0,0 +1,241 @@
+// Computed fields, worked out on ingest and pushed like anything the vehicle sent
+//
+// A derived field lives in the same store as its inputs and is pushed through
+// push_data with its inputs' timestamp, so the CSVs, charts, alerts and sinks can't
+// tell it apart from a real one. Definitions come from the mission profile or the
+// operator, e.g.
+//   { "store": "rocket", "name": "vertical_velocity", "kind": "derivative", "input": "alt", "window_ms": 500 }
+//   { "store": "rocket", "name": "delta_v", "kind": "integral", "input": "accel_z" }
+//   { "store": "rocket", "name": "total_accel", "kind": "magnitude", "inputs": ["accel_x", "accel_y", "accel_z"] }
+//   { "store": "rocket", "name": "alt_ft", "kind": "linear", "input": "alt", "scale": 3.28084 }
+// A derived field can be the input of another one, as long as nothing loops back.
+//
+// A derivative without a window is the rate between consecutive samples, which on a
+// noisy baro is mostly noise. With window_ms it's the least squares slope of the
+// samples in that window, once half of it has filled. An integral is the running
+// trapezoid sum per second since the definition was set: accel_z gives the delta v,
+// chain a linear field with the mass for the impulse. Both start over when the
+// input's timestamps go backwards, which is a playback restarting.
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DerivedKind {
+    // per second, between consecutive samples of the input or smoothed over window_ms
+    Derivative {
+        input: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        window_ms: Option<i64>,
+    },
+    // per second, the area under the input since the definition was set
+    Integral { input: String },
+    // sqrt of the sum of squares, once every input has a value at the same timestamp
+    Magnitude { inputs: Vec<String> },
+    Linear {
+        input: String,
+        #[serde(default = "default_scale")]
+        scale: f64,
+        #[serde(default)]
+        offset: f64,
+    },
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DerivedField {
+    pub store: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: DerivedKind,
+}
+
+impl DerivedField {
+    fn inputs(&self) -> Vec<&str> {
+        match &self.kind {
+            DerivedKind::Derivative { input, .. } | DerivedKind::Integral { input } | DerivedKind::Linear { input, .. } => {
+                vec![input.as_str()]
+            }
+            DerivedKind::Magnitude { inputs } => inputs.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+// per derived field, what it has seen of its inputs
+#[derive(Default)]
+struct FieldState {
+    // derivative and integral: the previous (timestamp, value)
+    previous: Option<(i64, f64)>,
+    // windowed derivative: the (timestamp, value) samples inside the window
+    window: VecDeque<(i64, f64)>,
+    // integral: the sum so far
+    total: f64,
+    // magnitude: the latest (timestamp, value) of each input
+    latest: HashMap<String, (i64, f64)>,
+}
+
+#[derive(Default)]
+pub struct DerivedFields {
+    fields: Vec<DerivedField>,
+    state: Vec<FieldState>,
+    // (store, field, value, timestamp) waiting to be pushed by the middleware
+    derived: Vec<(String, String, f64, i64)>,
+}
+
+pub fn validate(fields: &[DerivedField]) -> Result<(), String> {
+    for (i, field) in fields.iter().enumerate() {
+        if field.name.is_empty() || field.store.is_empty() {
+            return Err("A derived field needs a store and a name".into());
+        }
+        if field.inputs().is_empty() || field.inputs().iter().any(|input| input.is_empty()) {
+            return Err(format!("Derived field '{}' needs at least one input", field.name));
+        }
+        if fields[..i].iter().any(|f| f.store == field.store && f.name == field.name) {
+            return Err(format!("Derived field '{}.{}' is defined twice", field.store, field.name));
+        }
+        if let DerivedKind::Derivative { window_ms: Some(window_ms), .. } = field.kind {
+            if window_ms <= 0 {
+                return Err(format!("Derived field '{}' needs a window_ms above 0", field.name));
+            }
+        }
+        if let DerivedKind::Linear { scale, offset, .. } = field.kind {
+            if !scale.is_finite() || !offset.is_finite() {
+                return Err(format!("Derived field '{}' needs a finite scale and offset", field.name));
+            }
+        }
+    }
+    // each definition only feeds the ones after it in some order, or it loops
+    for field in fields {
+        let mut path = vec![field.name.as_str()];
+        if let Some(cycle) = find_cycle(fields, &field.store, &mut path) {
+            return Err(format!("Derived fields loop back on themselves: {cycle}"));
+        }
+    }
+    Ok(())
+}
+
+// depth first from the end of `path`, through the fields that take it as an input
+fn find_cycle<'a>(fields: &'a [DerivedField], store: &str, path: &mut Vec<&'a str>) -> Option<String> {
+    let current = *path.last()?;
+    for field in fields.iter().filter(|f| f.store == store && f.inputs().contains(&current)) {
+        if path.contains(&field.name.as_str()) {
+            path.push(&field.name);
+            return Some(path.join(" -> "));
+        }
+        path.push(&field.name);
+        if let Some(cycle) = find_cycle(fields, store, path) {
+            return Some(cycle);
+        }
+        path.pop();
+    }
+    None
+}
+
+impl DerivedFields {
+    pub fn fields(&self) -> Vec<DerivedField> {
+        self.fields.clone()
+    }
+
+    /// Replaces every definition, starting them all from scratch
+    pub fn set_fields(&mut self, fields: Vec<DerivedField>) -> Result<(), String> {
+        validate(&fields)?;
+        self.state = fields.iter().map(|_| FieldState::default()).collect();
+        self.fields = fields;
+        Ok(())
+    }
+
+    pub fn observe(&mut self, store: &str, field: &str, value: f64, timestamp: i64) {
+        if self.fields.is_empty() || !value.is_finite() {
+            return;
+        }
+        for (definition, state) in self.fields.iter().zip(self.state.iter_mut()) {
+            if definition.store != store {
+                continue;
+            }
+            let derived = match &definition.kind {
+                DerivedKind::Derivative { input, window_ms: Some(window_ms) } if input == field => {
+                    windowed_slope(&mut state.window, *window_ms, value, timestamp)
+                }
+                DerivedKind::Derivative { input, window_ms: None } if input == field => {
+                    let rate = state.previous.and_then(|(t, v)| {
+                        // a repeat of the same timestamp has no rate, wait for the next one
+                        (timestamp > t).then(|| (value - v) / ((timestamp - t) as f64 / 1000.0))
+                    });
+                    if state.previous.is_none_or(|(t, _)| timestamp > t) {
+                        state.previous = Some((timestamp, value));
+                    }
+                    rate
+                }
+                DerivedKind::Integral { input } if input == field => match state.previous {
+                    Some((t, _)) if timestamp < t => {
+                        state.previous = Some((timestamp, value));
+                        state.total = 0.0;
+                        Some(0.0)
+                    }
+                    // same timestamp again, nothing to add
+                    Some((t, _)) if timestamp == t => None,
+                    Some((t, v)) => {
+                        state.total += (value + v) / 2.0 * ((timestamp - t) as f64 / 1000.0);
+                        state.previous = Some((timestamp, value));
+                        Some(state.total)
+                    }
+                    None => {
+                        state.previous = Some((timestamp, value));
+                        Some(0.0)
+                    }
+                },
+                DerivedKind::Magnitude { inputs } if inputs.iter().any(|i| i == field) => {
+                    state.latest.insert(field.to_string(), (timestamp, value));
+                    let complete = inputs.iter().all(|i| state.latest.get(i).is_some_and(|&(t, _)| t == timestamp));
+                    complete.then(|| inputs.iter().map(|i| state.latest[i].1.powi(2)).sum::<f64>().sqrt())
+                }
+                DerivedKind::Linear { input, scale, offset } if input == field => Some(value * scale + offset),
+                _ => None,
+            };
+            if let Some(derived) = derived {
+                self.derived.push((store.to_string(), definition.name.clone(), derived, timestamp));
+            }
+        }
+    }
+
+    pub fn take_derived(&mut self) -> Vec<(String, String, f64, i64)> {
+        std::mem::take(&mut self.derived)
+    }
+}
+
+// least squares slope of the samples within window_ms of the newest, per second
+fn windowed_slope(window: &mut VecDeque<(i64, f64)>, window_ms: i64, value: f64, timestamp: i64) -> Option<f64> {
+    if window.back().is_some_and(|&(t, _)| timestamp < t) {
+        window.clear();
+    }
+    if window.back().is_some_and(|&(t, _)| t == timestamp) {
+        return None;
+    }
+    window.push_back((timestamp, value));
+    while window.front().is_some_and(|&(t, _)| t < timestamp - window_ms) {
+        window.pop_front();
+    }
+    let (first, _) = *window.front()?;
+    if window.len() < 2 || timestamp - first < window_ms / 2 {
+        return None;
+    }
+
+    // relative to the first sample so big unix ms timestamps don't eat the precision
+    let n = window.len() as f64;
+    let (mut sum_t, mut sum_v, mut sum_tt, mut sum_tv) = (0.0, 0.0, 0.0, 0.0);
+    for &(t, v) in window.iter() {
+        let t = (t - first) as f64;
+        sum_t += t;
+        sum_v += v;
+        sum_tt += t * t;
+        sum_tv += t * v;
+    }
+    let denominator = n * sum_tt - sum_t * sum_t;
+    if denominator == 0.0 {
+        return None;
+    }
+    Some((n * sum_tv - sum_t * sum_v) / denominator * 1000.0)
+}
@@ -0,0 +1,57 @@
+// Counts every place we knowingly throw data away
+//
+// So "did we lose data or did the rocket stop sending" has an answer: if the
+// counters are flat and the stream went quiet, it was the rocket. Sites are named
+// "<where>.<what>", e.g. "csv.rocket" or "video.encoder".
+use dashmap::DashMap;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DropSite {
+    pub site: String,
+    pub dropped: u64,
+    // unix ms of the most recent drop
+    pub last_drop: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DropReport {
+    pub total: u64,
+    // most recently dropping first
+    pub sites: Vec<DropSite>,
+}
+
+#[derive(Debug, Default)]
+pub struct DropCounters {
+    sites: DashMap<String, (u64, i64)>,
+}
+
+// handed to everything that can drop, the middleware keeps the original
+pub type SharedDrops = Arc<DropCounters>;
+
+impl DropCounters {
+    pub fn new() -> SharedDrops {
+        Arc::new(Self::default())
+    }
+
+    pub fn note(&self, site: &str, count: u64) {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut entry = self.sites.entry(site.to_string()).or_insert((0, now));
+        entry.0 += count;
+        entry.1 = now;
+    }
+
+    /// Counters owned elsewhere (the relay keeps its own) can be folded in with `extra`
+    pub fn report(&self, extra: impl IntoIterator<Item = DropSite>) -> DropReport {
+        let mut sites: Vec<DropSite> = self
+            .sites
+            .iter()
+            .map(|s| DropSite { site: s.key().clone(), dropped: s.0, last_drop: s.1 })
+            .chain(extra.into_iter().filter(|s| s.dropped > 0))
+            .collect();
+        sites.sort_by_key(|s| Reverse(s.last_drop));
+        DropReport { total: sites.iter().map(|s| s.dropped).sum(), sites }
+    }
+}
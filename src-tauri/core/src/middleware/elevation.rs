@@ -0,0 +1,136 @@
+// Offline terrain elevation from SRTM .hgt tiles
+//
+// Tiles are the standard 1x1 degree files named by their south-west corner
+// (e.g. N42W072.hgt), either 1201x1201 (3 arc-second) or 3601x3601 (1 arc-second)
+// big-endian i16 samples in meters. Drop the tiles for the launch site into the
+// DEM directory before heading out.
+use dashmap::DashMap;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// SRTM marks voids with this value
+const VOID: i16 = -32768;
+
+struct Tile {
+    size: usize,
+    samples: Vec<i16>,
+}
+
+impl Tile {
+    fn load(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let count = bytes.len() / 2;
+        let size = (count as f64).sqrt() as usize;
+        if size * size != count || size < 2 {
+            return Err(format!("{} is not a square SRTM tile", path.display()));
+        }
+
+        let samples = bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_be_bytes([b[0], b[1]]))
+            .collect();
+        Ok(Self { size, samples })
+    }
+
+    fn sample(&self, row: usize, col: usize) -> Option<f64> {
+        let v = self.samples[row * self.size + col];
+        (v != VOID).then_some(v as f64)
+    }
+
+    // bilinear interpolation, (frac_lat, frac_lon) are offsets in [0, 1] from the SW corner
+    fn elevation(&self, frac_lat: f64, frac_lon: f64) -> Option<f64> {
+        let last = (self.size - 1) as f64;
+        // rows run north to south
+        let y = (1.0 - frac_lat) * last;
+        let x = frac_lon * last;
+
+        let (r0, c0) = (y.floor() as usize, x.floor() as usize);
+        let (r1, c1) = ((r0 + 1).min(self.size - 1), (c0 + 1).min(self.size - 1));
+        let (dy, dx) = (y - r0 as f64, x - c0 as f64);
+
+        let top = self.sample(r0, c0)? * (1.0 - dx) + self.sample(r0, c1)? * dx;
+        let bottom = self.sample(r1, c0)? * (1.0 - dx) + self.sample(r1, c1)? * dx;
+        Some(top * (1.0 - dy) + bottom * dy)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TerrainPoint {
+    pub timestamp: i64,
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+    pub terrain: Option<f64>,
+    // altitude above the terrain under this point, not above the pad
+    pub agl: Option<f64>,
+}
+
+pub struct ElevationService {
+    dem_dir: PathBuf,
+    // None caches "no tile here" so we don't hit the disk every lookup
+    tiles: DashMap<(i32, i32), Option<Arc<Tile>>>,
+}
+
+impl ElevationService {
+    pub fn new(dem_dir: PathBuf) -> Self {
+        Self { dem_dir, tiles: DashMap::new() }
+    }
+
+    pub fn set_dem_dir(&mut self, dem_dir: PathBuf) {
+        self.dem_dir = dem_dir;
+        self.tiles.clear();
+    }
+
+    pub fn dem_dir(&self) -> &PathBuf {
+        &self.dem_dir
+    }
+
+    /// Terrain height in meters MSL, None if there's no tile or the sample is void
+    pub fn elevation(&self, lat: f64, lon: f64) -> Option<f64> {
+        if !(-90.0..90.0).contains(&lat) || !(-180.0..180.0).contains(&lon) {
+            return None;
+        }
+        let (tile_lat, tile_lon) = (lat.floor() as i32, lon.floor() as i32);
+        let tile = self.tile(tile_lat, tile_lon)?;
+        tile.elevation(lat - tile_lat as f64, lon - tile_lon as f64)
+    }
+
+    pub fn terrain_point(&self, timestamp: i64, lat: f64, lon: f64, alt: f64) -> TerrainPoint {
+        let terrain = self.elevation(lat, lon);
+        TerrainPoint {
+            timestamp,
+            lat,
+            lon,
+            alt,
+            terrain,
+            agl: terrain.map(|t| alt - t),
+        }
+    }
+
+    fn tile(&self, lat: i32, lon: i32) -> Option<Arc<Tile>> {
+        self.tiles
+            .entry((lat, lon))
+            .or_insert_with(|| {
+                let path = self.dem_dir.join(tile_name(lat, lon));
+                match Tile::load(&path) {
+                    Ok(t) => Some(Arc::new(t)),
+                    Err(e) => {
+                        eprintln!("[elevation] no tile for {lat},{lon}: {e}");
+                        None
+                    }
+                }
+            })
+            .clone()
+    }
+}
+
+fn tile_name(lat: i32, lon: i32) -> String {
+    format!(
+        "{}{:02}{}{:03}.hgt",
+        if lat >= 0 { 'N' } else { 'S' },
+        lat.abs(),
+        if lon >= 0 { 'E' } else { 'W' },
+        lon.abs()
+    )
+}
@@ -0,0 +1,304 @@
+// Optional at-rest encryption for session files (telemetry CSVs, black box, raw captures)
+//
+// Some payload data can't sit in plaintext on a laptop that might get left at the
+// launch site. With encryption on, new files go through an AES-256-GCM writer using a
+// key kept in the OS keychain, never on disk next to the data. Files are sealed in
+// chunks so a crash only loses what hadn't been flushed yet:
+//   "GSENC\x01\0\0" then per chunk [u32 LE length][12 byte nonce][ciphertext + tag]
+// with the chunk index as associated data, so chunks can't be reordered. Readers sniff
+// the magic and decrypt transparently, plaintext files from older sessions still open.
+//
+// Turning it on affects files opened afterwards, a store's CSV keeps whatever it was
+// created with.
+//
+// Built without the encryption feature, files are only ever written in plaintext:
+// enable() refuses, and opening an encrypted file says why it can't be read.
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+#[cfg(feature = "encryption")]
+use std::sync::Arc;
+
+#[cfg(feature = "encryption")]
+use parking_lot::RwLock;
+#[cfg(feature = "encryption")]
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+#[cfg(feature = "encryption")]
+use aes_gcm::{Aes256Gcm, Nonce};
+
+const MAGIC: &[u8; 8] = b"GSENC\x01\0\0";
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 12;
+// plaintext per chunk, a flush seals whatever is buffered early
+#[cfg(feature = "encryption")]
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[cfg(feature = "encryption")]
+const KEYCHAIN_SERVICE: &str = "groundstation-2026";
+#[cfg(feature = "encryption")]
+const KEYCHAIN_USER: &str = "session-encryption-key";
+
+pub type SessionWriter = Box<dyn Write + Send>;
+pub type SessionReader = Box<dyn Read + Send>;
+
+#[cfg(feature = "encryption")]
+pub struct SessionCipher {
+    aead: Aes256Gcm,
+}
+
+#[cfg(feature = "encryption")]
+impl SessionCipher {
+    // the key in the keychain, made (and stored) on first use
+    pub fn from_keychain(create: bool) -> Result<Self, String> {
+        let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_USER).map_err(|e| e.to_string())?;
+        let key = match entry.get_password() {
+            Ok(hex_key) => hex::decode(hex_key.trim()).map_err(|e| format!("Keychain key is not valid hex: {e}"))?,
+            Err(keyring::Error::NoEntry) if create => {
+                let key = Aes256Gcm::generate_key(OsRng);
+                entry
+                    .set_password(&hex::encode(key))
+                    .map_err(|e| format!("Failed to store key in the keychain: {e}"))?;
+                key.to_vec()
+            }
+            Err(keyring::Error::NoEntry) => {
+                return Err("No session encryption key in this machine's keychain".into())
+            }
+            Err(e) => return Err(format!("Failed to read the keychain: {e}")),
+        };
+        let aead = Aes256Gcm::new_from_slice(&key).map_err(|_| "Session key must be 32 bytes".to_string())?;
+        Ok(Self { aead })
+    }
+
+    fn seal(&self, index: u64, plaintext: &[u8]) -> io::Result<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let aad = index.to_le_bytes();
+        let ciphertext = self
+            .aead
+            .encrypt(&nonce, Payload { msg: plaintext, aad: &aad })
+            .map_err(|_| io::Error::other("encryption failed"))?;
+        let mut chunk = Vec::with_capacity(4 + NONCE_LEN + ciphertext.len());
+        chunk.extend((ciphertext.len() as u32).to_le_bytes());
+        chunk.extend(nonce.as_slice());
+        chunk.extend(ciphertext);
+        Ok(chunk)
+    }
+
+    fn open(&self, index: u64, nonce: &[u8], ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+        let aad = index.to_le_bytes();
+        self.aead
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "chunk failed to decrypt (wrong key or corrupted file)"))
+    }
+}
+
+// shared with every writer, the cipher is only Some while encryption is on
+#[derive(Clone, Default)]
+pub struct SessionEncryption {
+    #[cfg(feature = "encryption")]
+    cipher: Arc<RwLock<Option<Arc<SessionCipher>>>>,
+}
+
+#[cfg(feature = "encryption")]
+impl SessionEncryption {
+    pub fn enable(&self) -> Result<(), String> {
+        let cipher = SessionCipher::from_keychain(true)?;
+        *self.cipher.write() = Some(Arc::new(cipher));
+        Ok(())
+    }
+
+    pub fn disable(&self) {
+        *self.cipher.write() = None;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.cipher.read().is_some()
+    }
+
+    fn wrap(&self, path: &Path, file: File) -> Result<SessionWriter, String> {
+        match self.cipher.read().clone() {
+            Some(cipher) => {
+                let writer = EncryptingWriter::new(file, cipher).map_err(|e| format!("{}: {e}", path.display()))?;
+                Ok(Box::new(writer))
+            }
+            None => Ok(Box::new(file)),
+        }
+    }
+}
+
+#[cfg(not(feature = "encryption"))]
+impl SessionEncryption {
+    pub fn enable(&self) -> Result<(), String> {
+        Err("This build has no session encryption (the encryption feature is off)".into())
+    }
+
+    pub fn disable(&self) {}
+
+    pub fn is_enabled(&self) -> bool {
+        false
+    }
+
+    fn wrap(&self, _path: &Path, file: File) -> Result<SessionWriter, String> {
+        Ok(Box::new(file))
+    }
+}
+
+impl SessionEncryption {
+    // a new file, encrypted if encryption is on right now
+    pub fn create(&self, path: &Path) -> Result<SessionWriter, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        self.wrap(path, file)
+    }
+
+    /// As create, plus a second handle on the file for fsync. Flush the writer first,
+    /// what's still in its buffers isn't in the file yet.
+    pub fn create_synced(&self, path: &Path) -> Result<(SessionWriter, File), String> {
+        let file = File::create(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let sync = file.try_clone().map_err(|e| format!("{}: {e}", path.display()))?;
+        Ok((self.wrap(path, file)?, sync))
+    }
+}
+
+// whether the file starts with the encrypted magic, an empty file isn't
+pub fn is_encrypted(path: &Path) -> Result<bool, String> {
+    let mut file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut magic = [0u8; MAGIC.len()];
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(format!("{}: {e}", path.display())),
+    }
+}
+
+// opens plaintext and encrypted files alike, the key comes from the keychain when needed
+pub fn open(path: &Path) -> Result<SessionReader, String> {
+    let mut file = BufReader::new(File::open(path).map_err(|e| format!("{}: {e}", path.display()))?);
+    let mut magic = [0u8; MAGIC.len()];
+    let encrypted = match file.read_exact(&mut magic) {
+        Ok(()) => &magic == MAGIC,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => false,
+        Err(e) => return Err(format!("{}: {e}", path.display())),
+    };
+
+    if !encrypted {
+        // start over, those bytes were data
+        let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        return Ok(Box::new(BufReader::new(file)));
+    }
+    decrypting(path, file)
+}
+
+#[cfg(feature = "encryption")]
+fn decrypting(path: &Path, file: BufReader<File>) -> Result<SessionReader, String> {
+    let cipher = SessionCipher::from_keychain(false).map_err(|e| format!("{} is encrypted: {e}", path.display()))?;
+    Ok(Box::new(DecryptingReader { inner: file, cipher, index: 0, plaintext: Vec::new(), pos: 0 }))
+}
+
+#[cfg(not(feature = "encryption"))]
+fn decrypting(path: &Path, _file: BufReader<File>) -> Result<SessionReader, String> {
+    Err(format!("{} is encrypted and this build has no session encryption", path.display()))
+}
+
+// ── Writer ────────────────────────────────────────────────────────────────────
+
+#[cfg(feature = "encryption")]
+pub struct EncryptingWriter<W: Write> {
+    inner: W,
+    cipher: Arc<SessionCipher>,
+    index: u64,
+    buffer: Vec<u8>,
+}
+
+#[cfg(feature = "encryption")]
+impl<W: Write> EncryptingWriter<W> {
+    pub fn new(mut inner: W, cipher: Arc<SessionCipher>) -> io::Result<Self> {
+        inner.write_all(MAGIC)?;
+        Ok(Self { inner, cipher, index: 0, buffer: Vec::with_capacity(CHUNK_SIZE) })
+    }
+
+    fn seal_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = self.cipher.seal(self.index, &self.buffer)?;
+        self.inner.write_all(&chunk)?;
+        self.index += 1;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<W: Write> Write for EncryptingWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let take = data.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&data[..take]);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.seal_buffer()?;
+        }
+        Ok(take)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.seal_buffer()?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<W: Write> Drop for EncryptingWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+// ── Reader ────────────────────────────────────────────────────────────────────
+
+#[cfg(feature = "encryption")]
+struct DecryptingReader<R: Read> {
+    inner: R,
+    cipher: SessionCipher,
+    index: u64,
+    plaintext: Vec<u8>,
+    pos: usize,
+}
+
+#[cfg(feature = "encryption")]
+impl<R: Read> DecryptingReader<R> {
+    // false at a clean end of file
+    fn next_chunk(&mut self) -> io::Result<bool> {
+        let mut len = [0u8; 4];
+        match self.inner.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e),
+        }
+        let mut nonce = [0u8; NONCE_LEN];
+        self.inner.read_exact(&mut nonce)?;
+        let mut ciphertext = vec![0u8; u32::from_le_bytes(len) as usize];
+        match self.inner.read_exact(&mut ciphertext) {
+            Ok(()) => {}
+            // a chunk cut off by a crash, everything before it was fine
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e),
+        }
+        self.plaintext = self.cipher.open(self.index, &nonce, &ciphertext)?;
+        self.pos = 0;
+        self.index += 1;
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<R: Read> Read for DecryptingReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.plaintext.len() {
+            if !self.next_chunk()? {
+                return Ok(0);
+            }
+        }
+        let n = out.len().min(self.plaintext.len() - self.pos);
+        out[..n].copy_from_slice(&self.plaintext[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
@@ -0,0 +1,155 @@
+// Push events to every window, so the frontend doesn't have to poll for everything
+//
+// Backends emit through the middleware, which holds the event sink once setup has
+// attached it: the app's windows, or whatever another user of the crate wants them
+// sent to. Before that (or with no windows, like the self test) emits go nowhere.
+//
+// A trace writes down every emit for a while, one JSON line each:
+//   {"t": 1718000000123, "event": "telemetry_packet", "bytes": 412}
+// so a test can drive ingest at a known rate and check the windows aren't being
+// flooded. Emits are traced whether or not a sink is attached.
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::lock_timing::TimedMutex;
+
+// a trace is for a test run, not something to leave on
+pub const MAX_TRACE_MS: u64 = 10 * 60 * 1000;
+
+// event name and payload, Err if it couldn't be delivered
+pub type EventSink = Box<dyn Fn(&str, Value) -> Result<(), String> + Send + Sync>;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EventCount {
+    pub count: u64,
+    // serialized payload bytes
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventTraceSummary {
+    pub path: String,
+    pub duration_ms: i64,
+    pub events: u64,
+    pub bytes: u64,
+    // the most events in any one second of the trace
+    pub peak_per_second: u64,
+    pub by_event: BTreeMap<String, EventCount>,
+}
+
+struct EventTrace {
+    path: PathBuf,
+    file: BufWriter<File>,
+    started: i64,
+    total: EventCount,
+    by_event: BTreeMap<String, EventCount>,
+    // events per whole second since `started`
+    per_second: BTreeMap<i64, u64>,
+}
+
+#[derive(Serialize)]
+struct TraceLine<'a> {
+    t: i64,
+    event: &'a str,
+    bytes: usize,
+}
+
+pub struct EventEmitter {
+    sink: Option<EventSink>,
+    // so an emit only looks at the trace while one is running
+    tracing: AtomicBool,
+    trace: TimedMutex<Option<EventTrace>>,
+}
+
+impl Default for EventEmitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventEmitter {
+    pub fn new() -> Self {
+        Self { sink: None, tracing: AtomicBool::new(false), trace: TimedMutex::new("events.trace", None) }
+    }
+
+    pub fn start_trace(&self, path: &Path) -> Result<(), String> {
+        let mut trace = self.trace.lock();
+        if trace.is_some() {
+            return Err("An event trace is already running".into());
+        }
+        let file = File::create(path).map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+        *trace = Some(EventTrace {
+            path: path.to_path_buf(),
+            file: BufWriter::new(file),
+            started: chrono::Utc::now().timestamp_millis(),
+            total: EventCount::default(),
+            by_event: BTreeMap::new(),
+            per_second: BTreeMap::new(),
+        });
+        self.tracing.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    pub fn stop_trace(&self) -> Result<EventTraceSummary, String> {
+        self.tracing.store(false, Ordering::Release);
+        let mut trace = self.trace.lock().take().ok_or("No event trace is running")?;
+        trace.file.flush().map_err(|e| format!("Failed to write {}: {e}", trace.path.display()))?;
+        println!("[events] Traced {} events to {}", trace.total.count, trace.path.display());
+        Ok(EventTraceSummary {
+            path: trace.path.display().to_string(),
+            duration_ms: chrono::Utc::now().timestamp_millis() - trace.started,
+            events: trace.total.count,
+            bytes: trace.total.bytes,
+            peak_per_second: trace.per_second.values().copied().max().unwrap_or(0),
+            by_event: trace.by_event,
+        })
+    }
+
+    fn record(&self, event: &str, payload: &Value) {
+        let mut trace = self.trace.lock();
+        let Some(trace) = trace.as_mut() else {
+            return;
+        };
+        let t = chrono::Utc::now().timestamp_millis();
+        let bytes = serde_json::to_vec(payload).map_or(0, |b| b.len());
+        for count in [&mut trace.total, trace.by_event.entry(event.to_string()).or_default()] {
+            count.count += 1;
+            count.bytes += bytes as u64;
+        }
+        *trace.per_second.entry((t - trace.started) / 1000).or_default() += 1;
+        let line = serde_json::to_string(&TraceLine { t, event, bytes }).unwrap_or_default();
+        if let Err(e) = writeln!(trace.file, "{line}") {
+            eprintln!("[events] Failed to write the trace: {e}");
+        }
+    }
+
+    pub fn attach(&mut self, sink: EventSink) {
+        self.sink = Some(sink);
+    }
+
+    pub fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        let tracing = self.tracing.load(Ordering::Acquire);
+        if self.sink.is_none() && !tracing {
+            return;
+        }
+        let result = serde_json::to_value(payload)
+            .map_err(|e| e.to_string())
+            .and_then(|payload| {
+                if tracing {
+                    self.record(event, &payload);
+                }
+                match &self.sink {
+                    Some(sink) => sink(event, payload),
+                    None => Ok(()),
+                }
+            });
+        if let Err(e) = result {
+            eprintln!("[events] Failed to emit '{event}': {e}");
+        }
+    }
+}
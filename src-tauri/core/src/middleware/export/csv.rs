@@ -0,0 +1,43 @@
+// Aligned CSV, unlike the recording CSVs every row here is one timestamp on the shared axis.
+// Each field is followed by <field>_quality, empty with the value when there's no sample.
+use std::path::Path;
+
+use super::{ExportFormat, ExportTable};
+
+pub struct CsvExport;
+
+impl ExportFormat for CsvExport {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn write(&self, table: &ExportTable, path: &Path) -> Result<(), String> {
+        let mut writer = ::csv::Writer::from_path(path).map_err(|e| format!("{}: {e}", path.display()))?;
+
+        let mut header = vec!["timestamp".to_string()];
+        for (name, _) in &table.columns {
+            header.push(name.clone());
+            header.push(format!("{name}_quality"));
+        }
+        writer.write_record(&header).map_err(|e| e.to_string())?;
+
+        for (row, timestamp) in table.timestamps.iter().enumerate() {
+            let mut record = vec![timestamp.to_string()];
+            // NaN means no sample, leave the cells empty
+            for ((_, values), quality) in table.columns.iter().zip(&table.quality) {
+                let v = values[row];
+                if v.is_nan() {
+                    record.extend([String::new(), String::new()]);
+                } else {
+                    record.extend([v.to_string(), quality[row].as_str().to_string()]);
+                }
+            }
+            writer.write_record(&record).map_err(|e| e.to_string())?;
+        }
+        writer.flush().map_err(|e| e.to_string())
+    }
+}
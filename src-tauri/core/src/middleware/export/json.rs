@@ -0,0 +1,54 @@
+// One object per timestamp on the shared axis (for radio telemetry, one decoded packet),
+// fields with no sample left out. A flagged sample also gets "<field>_quality". "json" is
+// a pretty printed array, "ndjson" one object a line for pandas.read_json(lines=True)
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use super::{ExportFormat, ExportTable};
+
+pub struct JsonExport {
+    pub lines: bool,
+}
+
+fn object(table: &ExportTable, row: usize) -> Value {
+    let mut object = Map::new();
+    object.insert("timestamp".into(), table.timestamps[row].into());
+    for ((name, values), quality) in table.columns.iter().zip(&table.quality) {
+        let v = values[row];
+        if !v.is_nan() {
+            object.insert(name.clone(), Value::from(v));
+            if !quality[row].is_valid() {
+                object.insert(format!("{name}_quality"), quality[row].as_str().into());
+            }
+        }
+    }
+    Value::Object(object)
+}
+
+impl ExportFormat for JsonExport {
+    fn name(&self) -> &'static str {
+        if self.lines { "ndjson" } else { "json" }
+    }
+
+    fn extension(&self) -> &'static str {
+        self.name()
+    }
+
+    fn write(&self, table: &ExportTable, path: &Path) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        if self.lines {
+            for i in 0..table.timestamps.len() {
+                serde_json::to_writer(&mut writer, &object(table, i)).map_err(|e| e.to_string())?;
+                writer.write_all(b"\n").map_err(|e| e.to_string())?;
+            }
+        } else {
+            let rows: Vec<Value> = (0..table.timestamps.len()).map(|i| object(table, i)).collect();
+            serde_json::to_writer_pretty(&mut writer, &rows).map_err(|e| e.to_string())?;
+        }
+        writer.flush().map_err(|e| e.to_string())
+    }
+}
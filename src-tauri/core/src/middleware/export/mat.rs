@@ -0,0 +1,176 @@
+// MATLAB Level 5 .mat writer, for the half of the aero team that lives in MATLAB
+//
+// The file holds one struct named after the store:
+//   rocket.t             seconds since the first sample, the common axis
+//   rocket.timestamp_ms  the same axis as unix milliseconds
+//   rocket.<field>       one column vector per field, NaN where there was no sample
+//   rocket.<field>_quality  its data_quality tier, 0 valid up to 4 out of range
+// so `load("flight.mat"); plot(rocket.t, rocket.altitude)` just works.
+//
+// Layout follows the "MAT-File Format" reference: a 128 byte header, then tagged
+// data elements, each padded out to 8 bytes. Everything is written little endian.
+use std::path::Path;
+
+use super::{ExportFormat, ExportTable};
+
+const MI_INT8: u32 = 1;
+const MI_INT32: u32 = 5;
+const MI_UINT32: u32 = 6;
+const MI_DOUBLE: u32 = 9;
+const MI_MATRIX: u32 = 14;
+
+const MX_STRUCT_CLASS: u32 = 2;
+const MX_DOUBLE_CLASS: u32 = 6;
+
+// MATLAB's limit for struct field names is 63, but v5 files store them in fixed
+// slots and older readers choke past 31
+const FIELD_NAME_SLOT: usize = 32;
+
+pub struct MatExport;
+
+impl ExportFormat for MatExport {
+    fn name(&self) -> &'static str {
+        "mat"
+    }
+
+    fn extension(&self) -> &'static str {
+        "mat"
+    }
+
+    fn write(&self, table: &ExportTable, path: &Path) -> Result<(), String> {
+        let first = table.timestamps.first().copied().unwrap_or(0);
+        let mut fields: Vec<(String, Vec<f64>)> = vec![
+            (
+                "t".to_string(),
+                table.timestamps.iter().map(|t| (t - first) as f64 / 1000.0).collect(),
+            ),
+            (
+                "timestamp_ms".to_string(),
+                table.timestamps.iter().map(|t| *t as f64).collect(),
+            ),
+        ];
+        for ((name, values), quality) in table.columns.iter().zip(&table.quality) {
+            fields.push((unique_name(&identifier(name), &fields), values.clone()));
+            let codes = quality.iter().map(|q| q.code() as f64).collect();
+            fields.push((unique_name(&identifier(&format!("{name}_quality")), &fields), codes));
+        }
+
+        let mut out = header();
+        out.extend(struct_element(&identifier(&table.store), &fields));
+        std::fs::write(path, out).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+fn header() -> Vec<u8> {
+    let text = format!(
+        "MATLAB 5.0 MAT-file, Platform: groundstation-2026, Created on: {}",
+        chrono::Utc::now().format("%a %b %e %H:%M:%S %Y")
+    );
+    let mut out = text.into_bytes();
+    out.resize(116, b' ');
+    // no subsystem data
+    out.extend([0u8; 8]);
+    out.extend(0x0100u16.to_le_bytes());
+    // reads back as "MI" on a big endian machine, telling it to swap
+    out.extend(b"IM");
+    out
+}
+
+// ── Data elements ─────────────────────────────────────────────────────────────
+
+fn element(data_type: u32, data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(8 + data.len() + 7);
+    if !data.is_empty() && data.len() <= 4 {
+        // small element format, tag and data share 8 bytes
+        out.extend(((data.len() as u32) << 16 | data_type).to_le_bytes());
+        out.extend(data);
+        out.resize(8, 0);
+        return out;
+    }
+    out.extend(data_type.to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    out.extend(data);
+    pad(&mut out);
+    out
+}
+
+fn pad(out: &mut Vec<u8>) {
+    out.resize(out.len().div_ceil(8) * 8, 0);
+}
+
+fn array_flags(class: u32) -> Vec<u8> {
+    let mut data = class.to_le_bytes().to_vec();
+    data.extend(0u32.to_le_bytes());
+    element(MI_UINT32, &data)
+}
+
+fn dimensions(rows: usize, cols: usize) -> Vec<u8> {
+    let mut data = (rows as i32).to_le_bytes().to_vec();
+    data.extend((cols as i32).to_le_bytes());
+    element(MI_INT32, &data)
+}
+
+fn matrix(body: Vec<u8>) -> Vec<u8> {
+    element(MI_MATRIX, &body)
+}
+
+// N x 1 double column
+fn double_column(name: &str, values: &[f64]) -> Vec<u8> {
+    let mut body = array_flags(MX_DOUBLE_CLASS);
+    body.extend(dimensions(values.len(), 1));
+    body.extend(element(MI_INT8, name.as_bytes()));
+    let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    body.extend(element(MI_DOUBLE, &data));
+    matrix(body)
+}
+
+// 1 x 1 struct holding each column as a field
+fn struct_element(name: &str, fields: &[(String, Vec<f64>)]) -> Vec<u8> {
+    let mut body = array_flags(MX_STRUCT_CLASS);
+    body.extend(dimensions(1, 1));
+    body.extend(element(MI_INT8, name.as_bytes()));
+    body.extend(element(MI_INT32, &(FIELD_NAME_SLOT as i32).to_le_bytes()));
+
+    let mut names = Vec::with_capacity(fields.len() * FIELD_NAME_SLOT);
+    for (field, _) in fields {
+        let mut slot = field.as_bytes().to_vec();
+        slot.resize(FIELD_NAME_SLOT, 0);
+        names.extend(slot);
+    }
+    body.extend(element(MI_INT8, &names));
+
+    for (_, values) in fields {
+        // struct members are unnamed, the name list above covers them
+        body.extend(double_column("", values));
+    }
+    matrix(body)
+}
+
+// ── Names ─────────────────────────────────────────────────────────────────────
+
+// "gps.lat-deg" -> "gps_lat_deg", MATLAB identifiers are [A-Za-z][A-Za-z0-9_]*
+fn identifier(name: &str) -> String {
+    let mut id: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !id.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        id.insert(0, 'f');
+    }
+    id.truncate(FIELD_NAME_SLOT - 1);
+    id
+}
+
+// sanitizing can make two fields collide, e.g. "a.b" and "a_b"
+fn unique_name(id: &str, taken: &[(String, Vec<f64>)]) -> String {
+    let mut candidate = id.to_string();
+    let mut n = 2;
+    while taken.iter().any(|(name, _)| *name == candidate) {
+        let suffix = format!("_{n}");
+        let mut base = id.to_string();
+        base.truncate(FIELD_NAME_SLOT - 1 - suffix.len());
+        candidate = base + &suffix;
+        n += 1;
+    }
+    candidate
+}
@@ -0,0 +1,110 @@
+// Export a store's buffered telemetry to analysis formats
+//
+// Every format sees the same table: one shared timestamp axis (every timestamp any
+// field has a point at) and one column per field lined up against it, NaN where a
+// field had nothing at that time. Each column has a quality alongside it (see
+// data_quality), valid where there was no sample. New formats implement ExportFormat
+// and get added to ExportRegistry::new.
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::data_quality::DataQuality;
+use super::telemetry_stores::TelemetryData;
+
+mod csv;
+mod json;
+mod mat;
+
+pub struct ExportTable {
+    pub store: String,
+    // unix ms, ascending
+    pub timestamps: Vec<i64>,
+    pub columns: Vec<(String, Vec<f64>)>,
+    // same shape as columns
+    pub quality: Vec<Vec<DataQuality>>,
+}
+
+impl ExportTable {
+    pub fn from_fields(store: &str, fields: &BTreeMap<String, Vec<TelemetryData>>) -> Self {
+        let mut timestamps: Vec<i64> = fields
+            .values()
+            .flat_map(|data| data.iter().map(|d| d.timestamp))
+            .collect();
+        timestamps.sort_unstable();
+        timestamps.dedup();
+
+        let (columns, quality) = fields
+            .iter()
+            .map(|(name, data)| {
+                let mut column = vec![f64::NAN; timestamps.len()];
+                let mut quality = vec![DataQuality::Valid; timestamps.len()];
+                for d in data {
+                    // every timestamp is on the axis by construction
+                    if let Ok(i) = timestamps.binary_search(&d.timestamp) {
+                        column[i] = d.value.as_f64();
+                        quality[i] = d.quality;
+                    }
+                }
+                ((name.clone(), column), quality)
+            })
+            .unzip();
+
+        Self { store: store.to_string(), timestamps, columns, quality }
+    }
+
+    /// Only the rows with start <= timestamp <= end
+    pub fn within(mut self, start: i64, end: i64) -> Self {
+        let from = self.timestamps.partition_point(|&t| t < start);
+        let to = self.timestamps.partition_point(|&t| t <= end).max(from);
+        self.timestamps = self.timestamps[from..to].to_vec();
+        for (_, column) in self.columns.iter_mut() {
+            *column = column[from..to].to_vec();
+        }
+        for quality in self.quality.iter_mut() {
+            *quality = quality[from..to].to_vec();
+        }
+        self
+    }
+}
+
+pub trait ExportFormat: Send + Sync {
+    /// What the frontend asks for, e.g. "csv"
+    fn name(&self) -> &'static str;
+    fn extension(&self) -> &'static str;
+    fn write(&self, table: &ExportTable, path: &Path) -> Result<(), String>;
+}
+
+pub struct ExportRegistry {
+    formats: Vec<Box<dyn ExportFormat>>,
+}
+
+impl Default for ExportRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExportRegistry {
+    pub fn new() -> Self {
+        Self {
+            formats: vec![
+                Box::new(csv::CsvExport),
+                Box::new(mat::MatExport),
+                Box::new(json::JsonExport { lines: false }),
+                Box::new(json::JsonExport { lines: true }),
+            ],
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.formats.iter().map(|f| f.name().to_string()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Result<&dyn ExportFormat, String> {
+        self.formats
+            .iter()
+            .find(|f| f.name() == name)
+            .map(|f| f.as_ref())
+            .ok_or_else(|| format!("Unknown export format '{}'", name))
+    }
+}
@@ -0,0 +1,100 @@
+// What each telemetry field is: its unit, a name fit for an axis label and the range
+// it should stay in
+//
+// Keys work like the field formats: "store.field" first, then the bare field name, so
+// one "battery_voltage" entry covers every vehicle. Backends register what they know
+// about the fields they push, a mission profile can add to or override it, and the
+// frontend reads it with get_field_metadata. The expected range also feeds the data
+// quality check, a point outside it is out_of_range unless a quality rule says
+// otherwise.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FieldMetadata {
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+}
+
+impl FieldMetadata {
+    pub fn unit(unit: &str) -> Self {
+        Self { unit: Some(unit.into()), ..Self::default() }
+    }
+
+    pub fn named(mut self, display_name: &str) -> Self {
+        self.display_name = Some(display_name.into());
+        self
+    }
+
+    pub fn range(mut self, min: f64, max: f64) -> Self {
+        self.min = Some(min);
+        self.max = Some(max);
+        self
+    }
+
+    pub fn validate(&self, key: &str) -> Result<(), String> {
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            if min > max {
+                return Err(format!("Metadata for '{key}' has min above max"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct FieldMetadataRegistry {
+    fields: BTreeMap<String, FieldMetadata>,
+}
+
+impl FieldMetadataRegistry {
+    /// None removes the entry
+    pub fn set(&mut self, key: &str, metadata: Option<FieldMetadata>) -> Result<(), String> {
+        match metadata {
+            Some(metadata) => {
+                metadata.validate(key)?;
+                self.fields.insert(key.to_string(), metadata);
+            }
+            None => {
+                self.fields.remove(key);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn extend(&mut self, fields: BTreeMap<String, FieldMetadata>) -> Result<(), String> {
+        for (key, metadata) in &fields {
+            metadata.validate(key)?;
+        }
+        self.fields.extend(fields);
+        Ok(())
+    }
+
+    pub fn all(&self) -> BTreeMap<String, FieldMetadata> {
+        self.fields.clone()
+    }
+
+    pub fn lookup(&self, store: &str, field: &str) -> Option<&FieldMetadata> {
+        if self.fields.is_empty() {
+            return None;
+        }
+        self.fields
+            .get(&format!("{store}.{field}"))
+            .or_else(|| self.fields.get(field))
+    }
+
+    // (min, max) when either end is set
+    pub fn range(&self, store: &str, field: &str) -> Option<(Option<f64>, Option<f64>)> {
+        self.lookup(store, field)
+            .filter(|m| m.min.is_some() || m.max.is_some())
+            .map(|m| (m.min, m.max))
+    }
+}
@@ -0,0 +1,156 @@
+// Decoded packets -> one telemetry field per leaf value
+//
+// The packets are flatbuffers, not prost messages, and flatbuffers has no runtime
+// reflection, so a type opts in with one flatten_fields! line listing its accessors
+// instead of a push_data call per field. Scalars become a field named after the
+// accessor, nested structs and tables are flattened under a dotted prefix
+// (sensors.lps22.pressure), vectors get their index (covariance.0), absent optionals
+// push nothing.
+//
+// field_names() gives the same keys from the type alone, for the recording CSV headers.
+// A vector's length is only known from a packet, so vectors have no names there.
+use super::telemetry_stores::TelemetryValue;
+
+pub trait Flatten {
+    fn flatten_into(&self, key: &str, out: &mut Vec<(String, TelemetryValue)>);
+
+    /// Every key flatten_into could push, in the same order
+    fn field_names(key: &str, out: &mut Vec<String>);
+}
+
+/// The keys of `T`, relative to `prefix`
+pub fn field_names<T: Flatten + ?Sized>(prefix: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    T::field_names(prefix, &mut out);
+    out
+}
+
+// what the accessor returns is the only place its type is written down
+#[doc(hidden)]
+pub fn accessor_field_names<S, R: Flatten>(_accessor: impl Fn(&S) -> R, key: &str, out: &mut Vec<String>) {
+    R::field_names(key, out);
+}
+
+/// Every leaf of `message` as (dotted key, value), keys relative to `prefix`
+pub fn flatten<T: Flatten + ?Sized>(message: &T, prefix: &str) -> Vec<(String, TelemetryValue)> {
+    let mut out = Vec::new();
+    message.flatten_into(prefix, &mut out);
+    out
+}
+
+// "" + "lat" = "lat", "gps" + "lat" = "gps.lat"
+pub fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{prefix}.{key}")
+    }
+}
+
+macro_rules! flatten_leaf {
+    ($($ty:ty => $variant:ident as $as:ty),* $(,)?) => {
+        $(
+            impl Flatten for $ty {
+                fn flatten_into(&self, key: &str, out: &mut Vec<(String, TelemetryValue)>) {
+                    out.push((key.to_string(), TelemetryValue::$variant(*self as $as)));
+                }
+
+                fn field_names(key: &str, out: &mut Vec<String>) {
+                    out.push(key.to_string());
+                }
+            }
+        )*
+    };
+}
+
+flatten_leaf! {
+    u8 => U64 as u64, u16 => U64 as u64, u32 => U64 as u64, u64 => U64 as u64,
+    i8 => I64 as i64, i16 => I64 as i64, i32 => I64 as i64, i64 => I64 as i64,
+    f32 => F64 as f64, f64 => F64 as f64,
+}
+
+impl Flatten for bool {
+    fn flatten_into(&self, key: &str, out: &mut Vec<(String, TelemetryValue)>) {
+        out.push((key.to_string(), TelemetryValue::Bool(*self)));
+    }
+
+    fn field_names(key: &str, out: &mut Vec<String>) {
+        out.push(key.to_string());
+    }
+}
+
+impl<T: Flatten + ?Sized> Flatten for &T {
+    fn flatten_into(&self, key: &str, out: &mut Vec<(String, TelemetryValue)>) {
+        (**self).flatten_into(key, out)
+    }
+
+    fn field_names(key: &str, out: &mut Vec<String>) {
+        T::field_names(key, out)
+    }
+}
+
+impl<T: Flatten> Flatten for Option<T> {
+    fn flatten_into(&self, key: &str, out: &mut Vec<(String, TelemetryValue)>) {
+        if let Some(value) = self {
+            value.flatten_into(key, out);
+        }
+    }
+
+    fn field_names(key: &str, out: &mut Vec<String>) {
+        T::field_names(key, out)
+    }
+}
+
+impl<'a, T: flatbuffers::Follow<'a> + 'a> Flatten for flatbuffers::Vector<'a, T>
+where
+    T::Inner: Flatten,
+{
+    fn flatten_into(&self, key: &str, out: &mut Vec<(String, TelemetryValue)>) {
+        for (index, value) in self.iter().enumerate() {
+            value.flatten_into(&join(key, &index.to_string()), out);
+        }
+    }
+
+    fn field_names(_key: &str, _out: &mut Vec<String>) {}
+}
+
+/// `flatten_fields!(hprc::EKF { w, i, j, k })` flattens a generated type through the
+/// listed accessors. `enum Type` flattens a generated enum newtype as its number.
+#[macro_export]
+macro_rules! flatten_fields {
+    (enum $ty:ty) => {
+        impl $crate::middleware::flatten::Flatten for $ty {
+            fn flatten_into(&self, key: &str, out: &mut Vec<(String, $crate::middleware::telemetry_stores::TelemetryValue)>) {
+                $crate::middleware::flatten::Flatten::flatten_into(&self.0, key, out)
+            }
+
+            fn field_names(key: &str, out: &mut Vec<String>) {
+                out.push(key.to_string());
+            }
+        }
+    };
+    ($ty:ty { $($field:ident),* $(,)? }) => {
+        impl $crate::middleware::flatten::Flatten for $ty {
+            fn flatten_into(&self, key: &str, out: &mut Vec<(String, $crate::middleware::telemetry_stores::TelemetryValue)>) {
+                $(
+                    $crate::middleware::flatten::Flatten::flatten_into(
+                        &self.$field(),
+                        &$crate::middleware::flatten::join(key, stringify!($field)),
+                        out,
+                    );
+                )*
+            }
+
+            fn field_names(key: &str, out: &mut Vec<String>) {
+                $(
+                    $crate::middleware::flatten::accessor_field_names(
+                        <$ty>::$field,
+                        &$crate::middleware::flatten::join(key, stringify!($field)),
+                        out,
+                    );
+                )*
+            }
+        }
+    };
+}
+pub use crate::flatten_fields;
@@ -0,0 +1,766 @@
+// Ground side flight state, worked out from the vehicle's altitude
+//
+// The flight computer reports its own state, but that's one more thing that can be
+// wrong on the day, so we keep our own. How a flight goes depends on the vehicle, so
+// the detection logic is a strategy picked (with its parameters) in the mission profile:
+//   single_deploy  pad, boost, coast, descent, landed
+//   dual_deploy    as above, with descent split into drogue and main at main_altitude_m
+//   two_stage      dual deploy with stage separation and a sustainer burn (and coast)
+// Every strategy sees the same smoothed kinematics (AGL altitude, vertical speed and
+// acceleration). An operator can force a state when the sensors mislead us, and
+// optionally hold it there.
+//
+// After separation each stage gets its own context: the configured store follows the
+// sustainer, the booster follows booster_store if it has a tracker of its own and is
+// dead reckoned from the separation point otherwise. Both get a landing prediction, and
+// the per-stage numbers go out as derived channels in the "flight" store.
+use serde::{Deserialize, Serialize};
+
+use super::landing::{self, Fix, LandingPrediction, LandingSummary, Track};
+
+// vertical speed and acceleration smoothing, higher follows the data more closely
+const SMOOTHING: f64 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlightState {
+    Pad,
+    Boost,
+    Coast,
+    // booster dropped, sustainer not lit yet
+    Separated,
+    SustainerBoost,
+    SustainerCoast,
+    // after apogee, for vehicles with a single deployment
+    Descent,
+    Drogue,
+    Main,
+    Landed,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Kinematics {
+    pub timestamp: i64,
+    pub altitude_agl: f64,
+    pub velocity: f64,
+    pub acceleration: f64,
+}
+
+// what every strategy needs to know about the vehicle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommonParams {
+    #[serde(default = "default_store")]
+    pub store: String,
+    // barometric or GPS altitude in metres, anything that goes up
+    #[serde(default = "default_altitude_field")]
+    pub altitude_field: String,
+    // climb above the pad that counts as liftoff
+    #[serde(default = "default_launch_altitude")]
+    pub launch_altitude_m: f64,
+    // slower than this for landed_secs and we're on the ground
+    #[serde(default = "default_landed_speed")]
+    pub landed_speed_mps: f64,
+    #[serde(default = "default_landed_secs")]
+    pub landed_secs: f64,
+}
+
+fn default_store() -> String {
+    "rocket".into()
+}
+
+fn default_altitude_field() -> String {
+    "alt".into()
+}
+
+fn default_launch_altitude() -> f64 {
+    30.0
+}
+
+fn default_landed_speed() -> f64 {
+    1.0
+}
+
+fn default_landed_secs() -> f64 {
+    5.0
+}
+
+impl Default for CommonParams {
+    fn default() -> Self {
+        Self {
+            store: default_store(),
+            altitude_field: default_altitude_field(),
+            launch_altitude_m: default_launch_altitude(),
+            landed_speed_mps: default_landed_speed(),
+            landed_secs: default_landed_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum DetectorConfig {
+    SingleDeploy {
+        #[serde(flatten)]
+        common: CommonParams,
+    },
+    DualDeploy {
+        #[serde(flatten)]
+        common: CommonParams,
+        main_altitude_m: f64,
+    },
+    TwoStage {
+        #[serde(flatten)]
+        common: CommonParams,
+        main_altitude_m: f64,
+        // upward acceleration during coast that means the sustainer lit
+        #[serde(default = "default_sustainer_accel")]
+        sustainer_accel_mps2: f64,
+        // coast this long after booster burnout and we call it separated, if the
+        // sustainer lighting doesn't tell us first
+        #[serde(default = "default_separation_delay")]
+        separation_delay_secs: f64,
+        // where the booster's own tracker reports lat, lon and altitude, if it has one
+        #[serde(default)]
+        booster_store: Option<String>,
+        // for dead reckoning a booster without telemetry
+        #[serde(default = "default_booster_descent")]
+        booster_descent_rate_mps: f64,
+    },
+}
+
+fn default_sustainer_accel() -> f64 {
+    20.0
+}
+
+fn default_separation_delay() -> f64 {
+    1.0
+}
+
+fn default_booster_descent() -> f64 {
+    25.0
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        DetectorConfig::SingleDeploy { common: CommonParams::default() }
+    }
+}
+
+impl DetectorConfig {
+    pub fn common(&self) -> &CommonParams {
+        match self {
+            DetectorConfig::SingleDeploy { common }
+            | DetectorConfig::DualDeploy { common, .. }
+            | DetectorConfig::TwoStage { common, .. } => common,
+        }
+    }
+
+    fn build(&self) -> Box<dyn FlightDetector> {
+        let ascent = Ascent::new(self.common());
+        match self {
+            DetectorConfig::SingleDeploy { .. } => Box::new(SingleDeploy { ascent }),
+            DetectorConfig::DualDeploy { main_altitude_m, .. } => {
+                Box::new(DualDeploy { ascent, main_altitude_m: *main_altitude_m })
+            }
+            DetectorConfig::TwoStage {
+                main_altitude_m,
+                sustainer_accel_mps2,
+                separation_delay_secs,
+                ..
+            } => Box::new(TwoStage {
+                dual: DualDeploy { ascent, main_altitude_m: *main_altitude_m },
+                sustainer_accel_mps2: *sustainer_accel_mps2,
+                separation_delay_ms: (*separation_delay_secs * 1000.0) as i64,
+                coast_since: None,
+            }),
+        }
+    }
+
+    fn booster(&self) -> Option<BoosterContext> {
+        match self {
+            DetectorConfig::TwoStage { booster_store, booster_descent_rate_mps, .. } => Some(BoosterContext {
+                store: booster_store.clone(),
+                descent_rate_mps: *booster_descent_rate_mps,
+                altitude_field: self.common().altitude_field.clone(),
+                track: Track::default(),
+                altitude: None,
+                separation: None,
+            }),
+            _ => None,
+        }
+    }
+}
+
+// ── Strategies ────────────────────────────────────────────────────────────────
+
+pub trait FlightDetector: Send {
+    fn name(&self) -> &'static str;
+    // the state to move to, None to stay put
+    fn update(&mut self, state: FlightState, k: &Kinematics) -> Option<FlightState>;
+}
+
+// the parts every vehicle shares: liftoff, burnout and landing
+struct Ascent {
+    launch_altitude_m: f64,
+    landed_speed_mps: f64,
+    landed_ms: i64,
+    still_since: Option<i64>,
+}
+
+impl Ascent {
+    fn new(common: &CommonParams) -> Self {
+        Self {
+            launch_altitude_m: common.launch_altitude_m,
+            landed_speed_mps: common.landed_speed_mps,
+            landed_ms: (common.landed_secs * 1000.0) as i64,
+            still_since: None,
+        }
+    }
+
+    fn liftoff(&self, k: &Kinematics) -> bool {
+        k.altitude_agl > self.launch_altitude_m && k.velocity > 0.0
+    }
+
+    fn burnout(&self, k: &Kinematics) -> bool {
+        k.acceleration < 0.0
+    }
+
+    fn apogee(&self, k: &Kinematics) -> bool {
+        k.velocity < 0.0
+    }
+
+    fn landed(&mut self, k: &Kinematics) -> bool {
+        if k.velocity.abs() > self.landed_speed_mps {
+            self.still_since = None;
+            return false;
+        }
+        let since = *self.still_since.get_or_insert(k.timestamp);
+        k.timestamp - since >= self.landed_ms
+    }
+
+    // pad to apogee, then `descent` until landing
+    fn step(&mut self, state: FlightState, k: &Kinematics, descent: FlightState) -> Option<FlightState> {
+        match state {
+            FlightState::Pad if self.liftoff(k) => Some(FlightState::Boost),
+            FlightState::Boost if self.burnout(k) => Some(FlightState::Coast),
+            FlightState::Coast if self.apogee(k) => Some(descent),
+            FlightState::Descent | FlightState::Drogue | FlightState::Main if self.landed(k) => {
+                Some(FlightState::Landed)
+            }
+            _ => None,
+        }
+    }
+}
+
+struct SingleDeploy {
+    ascent: Ascent,
+}
+
+impl FlightDetector for SingleDeploy {
+    fn name(&self) -> &'static str {
+        "single_deploy"
+    }
+
+    fn update(&mut self, state: FlightState, k: &Kinematics) -> Option<FlightState> {
+        self.ascent.step(state, k, FlightState::Descent)
+    }
+}
+
+struct DualDeploy {
+    ascent: Ascent,
+    main_altitude_m: f64,
+}
+
+impl FlightDetector for DualDeploy {
+    fn name(&self) -> &'static str {
+        "dual_deploy"
+    }
+
+    fn update(&mut self, state: FlightState, k: &Kinematics) -> Option<FlightState> {
+        if state == FlightState::Drogue && k.altitude_agl < self.main_altitude_m {
+            return Some(FlightState::Main);
+        }
+        self.ascent.step(state, k, FlightState::Drogue)
+    }
+}
+
+struct TwoStage {
+    dual: DualDeploy,
+    sustainer_accel_mps2: f64,
+    separation_delay_ms: i64,
+    coast_since: Option<i64>,
+}
+
+impl FlightDetector for TwoStage {
+    fn name(&self) -> &'static str {
+        "two_stage"
+    }
+
+    fn update(&mut self, state: FlightState, k: &Kinematics) -> Option<FlightState> {
+        if state != FlightState::Coast {
+            self.coast_since = None;
+        }
+        let lit = k.acceleration > self.sustainer_accel_mps2;
+        match state {
+            // lighting the sustainer means we separated, whether or not we noticed
+            FlightState::Coast | FlightState::Separated if lit => Some(FlightState::SustainerBoost),
+            FlightState::Coast | FlightState::Separated if self.dual.ascent.apogee(k) => Some(FlightState::Drogue),
+            FlightState::Coast => {
+                let since = *self.coast_since.get_or_insert(k.timestamp);
+                (k.timestamp - since >= self.separation_delay_ms).then_some(FlightState::Separated)
+            }
+            FlightState::SustainerBoost if self.dual.ascent.burnout(k) => Some(FlightState::SustainerCoast),
+            FlightState::SustainerCoast if self.dual.ascent.apogee(k) => Some(FlightState::Drogue),
+            _ => self.dual.update(state, k),
+        }
+    }
+}
+
+// ── State machine ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlightTransition {
+    pub from: FlightState,
+    pub to: FlightState,
+    pub timestamp: i64,
+    pub forced: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlightStatus {
+    pub strategy: &'static str,
+    pub state: FlightState,
+    pub since: Option<i64>,
+    // detection is paused until the operator lets go
+    pub held: bool,
+    pub kinematics: Option<Kinematics>,
+    pub separated_at: Option<i64>,
+    pub landing: Vec<LandingPrediction>,
+}
+
+// the booster once it's on its own
+struct BoosterContext {
+    store: Option<String>,
+    altitude_field: String,
+    descent_rate_mps: f64,
+    track: Track,
+    // (timestamp, AGL altitude) from its own tracker
+    altitude: Option<(i64, f64)>,
+    // what the stack looked like when it came apart: time, AGL altitude and track
+    separation: Option<(i64, f64, Track)>,
+}
+
+impl BoosterContext {
+    fn observe(&mut self, field: &str, value: f64, timestamp: i64, pad_altitude: f64) {
+        if field == self.altitude_field {
+            let agl = value - pad_altitude;
+            if let Some((prev_ts, prev_agl)) = self.altitude {
+                let dt = (timestamp - prev_ts) as f64 / 1000.0;
+                if dt > 0.0 && agl < prev_agl {
+                    self.descent_rate_mps += 0.3 * ((prev_agl - agl) / dt - self.descent_rate_mps);
+                }
+            }
+            self.altitude = Some((timestamp, agl));
+        } else {
+            self.track.observe(field, value, timestamp);
+        }
+    }
+
+    fn predict(&self, now: i64) -> Option<LandingPrediction> {
+        let (lat, lon, eta_secs, from_telemetry) = match (self.altitude, self.track.fix()) {
+            (Some((ts, agl)), Some(_)) => {
+                let (lat, lon, eta) = self.track.predict(agl, self.descent_rate_mps, (now - ts) as f64 / 1000.0)?;
+                (lat, lon, eta, true)
+            }
+            _ => {
+                let (ts, agl, track) = self.separation.as_ref()?;
+                let (lat, lon, eta) = track.predict(*agl, self.descent_rate_mps, (now - ts) as f64 / 1000.0)?;
+                (lat, lon, eta, false)
+            }
+        };
+        Some(LandingPrediction { stage: "booster".into(), lat, lon, eta_secs, from_telemetry })
+    }
+}
+
+pub struct FlightStateMachine {
+    config: DetectorConfig,
+    detector: Box<dyn FlightDetector>,
+    state: FlightState,
+    since: Option<i64>,
+    held: bool,
+    pad_altitude: Option<f64>,
+    kinematics: Option<Kinematics>,
+    // the configured store: the whole stack, then the sustainer after separation
+    track: Track,
+    booster: Option<BoosterContext>,
+    separated_at: Option<i64>,
+    // for the landing summary: the last fix before liftoff, when it left and how high it got
+    pad_fix: Option<Fix>,
+    launched_at: Option<i64>,
+    max_altitude_agl: f64,
+    // derived channels waiting to be pushed, see take_derived
+    derived: Vec<(String, f64, i64)>,
+}
+
+impl Default for FlightStateMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlightStateMachine {
+    pub fn new() -> Self {
+        Self::with_config(DetectorConfig::default())
+    }
+
+    fn with_config(config: DetectorConfig) -> Self {
+        Self {
+            detector: config.build(),
+            state: FlightState::Pad,
+            since: None,
+            held: false,
+            pad_altitude: None,
+            kinematics: None,
+            track: Track::default(),
+            booster: config.booster(),
+            separated_at: None,
+            pad_fix: None,
+            launched_at: None,
+            max_altitude_agl: 0.0,
+            derived: Vec::new(),
+            config,
+        }
+    }
+
+    // a new strategy starts over on the pad
+    pub fn configure(&mut self, config: DetectorConfig) {
+        *self = Self::with_config(config);
+    }
+
+    pub fn status(&self) -> FlightStatus {
+        FlightStatus {
+            strategy: self.detector.name(),
+            state: self.state,
+            since: self.since,
+            held: self.held,
+            kinematics: self.kinematics,
+            separated_at: self.separated_at,
+            landing: self.landing_predictions(chrono::Utc::now().timestamp_millis()),
+        }
+    }
+
+    // the primary stage once it's coming down, and the booster once it's on its own
+    pub fn landing_predictions(&self, now: i64) -> Vec<LandingPrediction> {
+        let mut predictions = Vec::new();
+        if let Some(k) = self.kinematics.filter(|k| k.velocity < 0.0 && self.state != FlightState::Landed) {
+            let stage = self.primary_stage();
+            if let Some((lat, lon, eta_secs)) =
+                self.track.predict(k.altitude_agl, -k.velocity, (now - k.timestamp) as f64 / 1000.0)
+            {
+                predictions.push(LandingPrediction { stage: stage.into(), lat, lon, eta_secs, from_telemetry: true });
+            }
+        }
+        if let Some(booster) = self.booster.as_ref().filter(|_| self.separated_at.is_some()) {
+            predictions.extend(booster.predict(now));
+        }
+        predictions
+    }
+
+    /// How the flight went, for when it's just landed at `landed_at`
+    pub fn landing_summary(&self, landed_at: i64) -> LandingSummary {
+        let fix = self.track.fix();
+        let from_pad = self.pad_fix.zip(fix).map(|(pad, fix)| landing::distance_bearing(&pad, &fix));
+        let mut summary = LandingSummary {
+            stage: self.primary_stage().to_string(),
+            landed_at,
+            lat: fix.map(|f| f.lat),
+            lon: fix.map(|f| f.lon),
+            distance_m: from_pad.map(|(distance, _)| distance),
+            bearing_deg: from_pad.map(|(_, bearing)| bearing),
+            max_altitude_agl_m: self.max_altitude_agl,
+            flight_time_secs: self.launched_at.map(|t| (landed_at - t) as f64 / 1000.0),
+            callout: String::new(),
+        };
+        summary.callout = summary.describe();
+        summary
+    }
+
+    fn primary_stage(&self) -> &str {
+        if self.separated_at.is_some() { "sustainer" } else { &self.config.common().store }
+    }
+
+    /// Per-stage channels worked out since the last call, as (field, value, timestamp)
+    pub fn take_derived(&mut self) -> Vec<(String, f64, i64)> {
+        std::mem::take(&mut self.derived)
+    }
+
+    fn derive(&mut self, timestamp: i64) {
+        let mut derived = Vec::new();
+        if let Some(k) = self.kinematics {
+            let stage = self.primary_stage();
+            derived.push((format!("{stage}_altitude_agl"), k.altitude_agl, timestamp));
+            derived.push((format!("{stage}_velocity"), k.velocity, timestamp));
+        }
+        if let Some((_, agl)) = self.booster.as_ref().and_then(|b| b.altitude) {
+            derived.push(("booster_altitude_agl".into(), agl, timestamp));
+        }
+        for prediction in self.landing_predictions(timestamp) {
+            let stage = &prediction.stage;
+            derived.push((format!("{stage}_landing_lat"), prediction.lat, timestamp));
+            derived.push((format!("{stage}_landing_lon"), prediction.lon, timestamp));
+            derived.push((format!("{stage}_landing_eta"), prediction.eta_secs, timestamp));
+        }
+        self.derived.extend(derived);
+    }
+
+    pub fn force(&mut self, state: FlightState, hold: bool, timestamp: i64) -> FlightTransition {
+        let transition = FlightTransition { from: self.state, to: state, timestamp, forced: true };
+        self.enter(state, timestamp);
+        self.held = hold;
+        transition
+    }
+
+    fn enter(&mut self, state: FlightState, timestamp: i64) {
+        let separating = matches!(state, FlightState::Separated | FlightState::SustainerBoost)
+            && self.separated_at.is_none()
+            && self.booster.is_some();
+        if separating {
+            self.separated_at = Some(timestamp);
+            let agl = self.kinematics.map(|k| k.altitude_agl).unwrap_or(0.0);
+            if let Some(booster) = &mut self.booster {
+                booster.separation = Some((timestamp, agl, self.track.clone()));
+            }
+        }
+        if self.state == FlightState::Pad && state != FlightState::Pad && self.launched_at.is_none() {
+            self.launched_at = Some(timestamp);
+        }
+        self.state = state;
+        self.since = Some(timestamp);
+    }
+
+    pub fn evaluate(&mut self, store: &str, field: &str, value: f64, timestamp: i64) -> Option<FlightTransition> {
+        if !value.is_finite() {
+            return None;
+        }
+        if let Some(booster) = &mut self.booster {
+            if booster.store.as_deref() == Some(store) && self.separated_at.is_some() {
+                booster.observe(field, value, timestamp, self.pad_altitude.unwrap_or(0.0));
+            }
+        }
+        let common = self.config.common();
+        if store != common.store {
+            return None;
+        }
+        if field != common.altitude_field {
+            self.track.observe(field, value, timestamp);
+            if self.state == FlightState::Pad {
+                self.pad_fix = self.track.fix().or(self.pad_fix);
+            }
+            return None;
+        }
+        let altitude = value;
+        // the pad follows the sensor's drift until we leave it
+        if self.state == FlightState::Pad {
+            let pad = self.pad_altitude.get_or_insert(altitude);
+            *pad = pad.min(altitude);
+        }
+        let altitude_agl = altitude - self.pad_altitude.unwrap_or(altitude);
+
+        let k = match self.kinematics {
+            Some(prev) if timestamp > prev.timestamp => {
+                let dt = (timestamp - prev.timestamp) as f64 / 1000.0;
+                let velocity = prev.velocity + SMOOTHING * ((altitude_agl - prev.altitude_agl) / dt - prev.velocity);
+                let acceleration =
+                    prev.acceleration + SMOOTHING * ((velocity - prev.velocity) / dt - prev.acceleration);
+                Kinematics { timestamp, altitude_agl, velocity, acceleration }
+            }
+            // duplicate or out of order sample
+            Some(_) => return None,
+            None => Kinematics { timestamp, altitude_agl, velocity: 0.0, acceleration: 0.0 },
+        };
+        self.kinematics = Some(k);
+        self.max_altitude_agl = self.max_altitude_agl.max(altitude_agl);
+        self.derive(timestamp);
+
+        if self.held {
+            return None;
+        }
+        let next = self.detector.update(self.state, &k)?;
+        let transition = FlightTransition { from: self.state, to: next, timestamp, forced: false };
+        self.enter(next, timestamp);
+        Some(transition)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const G: f64 = 9.81;
+    // metres above sea level, so the tests see AGL worked out
+    const PAD: f64 = 250.0;
+    const DT_MS: i64 = 100;
+
+    // altitude samples of a made up flight at 10 Hz
+    #[derive(Default)]
+    struct Flight {
+        t: i64,
+        agl: f64,
+        velocity: f64,
+        samples: Vec<(i64, f64)>,
+    }
+
+    impl Flight {
+        fn step(&mut self, acceleration: f64) {
+            let dt = DT_MS as f64 / 1000.0;
+            self.velocity += acceleration * dt;
+            self.agl = (self.agl + self.velocity * dt).max(0.0);
+            self.t += DT_MS;
+            self.samples.push((self.t, PAD + self.agl));
+        }
+
+        fn rest(mut self, secs: f64) -> Self {
+            self.velocity = 0.0;
+            for _ in 0..(secs * 10.0) as usize {
+                self.step(0.0);
+            }
+            self
+        }
+
+        // net upward acceleration
+        fn burn(mut self, acceleration: f64, secs: f64) -> Self {
+            for _ in 0..(secs * 10.0) as usize {
+                self.step(acceleration);
+            }
+            self
+        }
+
+        fn coast_secs(mut self, secs: f64) -> Self {
+            for _ in 0..(secs * 10.0) as usize {
+                self.step(-G);
+            }
+            self
+        }
+
+        fn coast_to_apogee(mut self) -> Self {
+            while self.velocity > 0.0 {
+                self.step(-G);
+            }
+            self
+        }
+
+        // falls until it's coming down at `rate`, then holds it, down to `agl`
+        fn descend(mut self, rate: f64, agl: f64) -> Self {
+            while self.agl > agl {
+                let acceleration = if self.velocity > -rate { -G } else { 0.0 };
+                self.step(acceleration);
+                self.velocity = self.velocity.max(-rate);
+            }
+            self
+        }
+    }
+
+    fn fly(config: DetectorConfig, flight: &Flight) -> (FlightStateMachine, Vec<FlightState>) {
+        let mut machine = FlightStateMachine::with_config(config);
+        let states = flight
+            .samples
+            .iter()
+            .filter_map(|&(t, altitude)| machine.evaluate("rocket", "alt", altitude, t))
+            .map(|transition| transition.to)
+            .collect();
+        (machine, states)
+    }
+
+    fn single_stage() -> Flight {
+        Flight::default().rest(3.0).burn(60.0, 3.0).coast_to_apogee().descend(30.0, 150.0).descend(6.0, 0.0).rest(8.0)
+    }
+
+    #[test]
+    fn single_deploy_goes_from_pad_to_landed() {
+        let (machine, states) = fly(DetectorConfig::default(), &single_stage());
+        use FlightState::*;
+        assert_eq!(states, [Boost, Coast, Descent, Landed]);
+        assert!(machine.max_altitude_agl > 1500.0);
+        assert!(machine.launched_at.is_some());
+    }
+
+    #[test]
+    fn dual_deploy_splits_descent_at_the_main_altitude() {
+        let config = DetectorConfig::DualDeploy { common: CommonParams::default(), main_altitude_m: 150.0 };
+        let (machine, states) = fly(config, &single_stage());
+        use FlightState::*;
+        assert_eq!(states, [Boost, Coast, Drogue, Main, Landed]);
+        assert_eq!(machine.status().state, Landed);
+    }
+
+    #[test]
+    fn two_stage_separates_before_the_sustainer_lights() {
+        let config = DetectorConfig::TwoStage {
+            common: CommonParams::default(),
+            main_altitude_m: 150.0,
+            sustainer_accel_mps2: default_sustainer_accel(),
+            separation_delay_secs: default_separation_delay(),
+            booster_store: None,
+            booster_descent_rate_mps: default_booster_descent(),
+        };
+        let flight = Flight::default()
+            .rest(3.0)
+            .burn(60.0, 3.0)
+            .coast_secs(3.0)
+            .burn(80.0, 2.0)
+            .coast_to_apogee()
+            .descend(30.0, 150.0)
+            .descend(6.0, 0.0)
+            .rest(8.0);
+        let (machine, states) = fly(config, &flight);
+        use FlightState::*;
+        assert_eq!(states, [Boost, Coast, Separated, SustainerBoost, SustainerCoast, Drogue, Main, Landed]);
+        assert!(machine.separated_at.is_some());
+        assert_eq!(machine.primary_stage(), "sustainer");
+    }
+
+    #[test]
+    fn pad_noise_and_drift_stay_on_the_pad() {
+        let mut flight = Flight::default();
+        for i in 0..600 {
+            // a few metres of noise on a slowly drifting baro
+            let noise = if i % 2 == 0 { 4.0 } else { -4.0 };
+            flight.samples.push((i * DT_MS, PAD - i as f64 * 0.02 + noise));
+        }
+        let (machine, states) = fly(DetectorConfig::default(), &flight);
+        assert!(states.is_empty());
+        assert_eq!(machine.status().state, FlightState::Pad);
+        assert!(machine.launched_at.is_none());
+    }
+
+    #[test]
+    fn a_held_state_stops_detection_until_released() {
+        let flight = single_stage();
+        let mut machine = FlightStateMachine::new();
+        let forced = machine.force(FlightState::Main, true, 0);
+        assert!(forced.forced);
+        assert_eq!(forced.from, FlightState::Pad);
+        for &(t, altitude) in &flight.samples {
+            assert!(machine.evaluate("rocket", "alt", altitude, t).is_none());
+        }
+        assert_eq!(machine.status().state, FlightState::Main);
+        assert!(machine.status().held);
+
+        // letting go picks detection back up from where it was forced to
+        machine.force(FlightState::Main, false, flight.t);
+        let transition = (1..=80)
+            .find_map(|i| machine.evaluate("rocket", "alt", PAD, flight.t + i * DT_MS))
+            .unwrap();
+        assert_eq!(transition.to, FlightState::Landed);
+    }
+
+    #[test]
+    fn other_stores_fields_and_bad_samples_are_ignored() {
+        let mut machine = FlightStateMachine::new();
+        assert!(machine.evaluate("rocket", "alt", PAD, 0).is_none());
+        assert!(machine.evaluate("payload", "alt", PAD + 500.0, 100).is_none());
+        assert!(machine.evaluate("rocket", "alt", f64::NAN, 200).is_none());
+        assert!(machine.evaluate("rocket", "alt", PAD + 500.0, 0).is_none());
+        assert!(machine.status().kinematics.is_some_and(|k| k.altitude_agl == 0.0));
+    }
+}
@@ -0,0 +1,50 @@
+// How often a recording's writer pushes its rows out to the file
+//
+// Rows are written into a buffer and the buffer goes to the file on a flush. Not
+// flushing until the recording stops loses everything on a crash (an encrypted CSV
+// only seals a chunk on a flush). Flushing every row at 100 Hz hammers the disk. The
+// policy is picked when a recording starts: every N rows, every T ms or both, checked
+// as rows come in. With sync_on_events the flight state changes (liftoff, apogee,
+// landing) also fsync every recording, so those moments survive a power cut and not
+// just a crash. Stopping a recording and shutting down always flush.
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlushPolicy {
+    #[serde(default)]
+    pub every_rows: Option<u64>,
+    #[serde(default = "default_every_ms")]
+    pub every_ms: Option<u64>,
+    #[serde(default = "default_sync_on_events")]
+    pub sync_on_events: bool,
+}
+
+fn default_every_ms() -> Option<u64> {
+    Some(1000)
+}
+
+fn default_sync_on_events() -> bool {
+    true
+}
+
+impl Default for FlushPolicy {
+    fn default() -> Self {
+        Self { every_rows: None, every_ms: default_every_ms(), sync_on_events: default_sync_on_events() }
+    }
+}
+
+impl FlushPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.every_rows == Some(0) || self.every_ms == Some(0) {
+            return Err("Flush intervals have to be at least 1, leave one out to not flush on it".into());
+        }
+        Ok(())
+    }
+
+    pub fn due(&self, rows: u64, since_flush: Duration) -> bool {
+        rows > 0
+            && (self.every_rows.is_some_and(|n| rows >= n)
+                || self.every_ms.is_some_and(|ms| since_flush >= Duration::from_millis(ms)))
+    }
+}
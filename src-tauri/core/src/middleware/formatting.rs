@@ -0,0 +1,122 @@
+// Per-field display formatting, so every panel shows e.g. voltage to 3 places and
+// altitude in whole feet without each one reimplementing it
+//
+// Formats are looked up by "store.field" first, then by bare field name, so a
+// profile can say "every `voltage` is 3 decimals" and still override one store.
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use super::telemetry_stores::TelemetryValue;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormatStyle {
+    #[default]
+    Number,
+    // hemisphere suffix instead of a sign, 42.27° N
+    Latitude,
+    Longitude,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FieldFormat {
+    // None leaves the value as it came in
+    #[serde(default)]
+    pub decimals: Option<usize>,
+    // applied before rounding, e.g. 3.28084 to show meters as feet
+    #[serde(default)]
+    pub scale: Option<f64>,
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub style: FormatStyle,
+}
+
+impl FieldFormat {
+    pub fn apply(&self, value: &TelemetryValue) -> String {
+        // bools have nothing to format
+        if let TelemetryValue::Bool(b) = value {
+            return b.to_string();
+        }
+
+        let scaled = value.as_f64() * self.scale.unwrap_or(1.0);
+        let (number, suffix) = match self.style {
+            FormatStyle::Number => (scaled, None),
+            FormatStyle::Latitude => (scaled.abs(), Some(if scaled < 0.0 { "S" } else { "N" })),
+            FormatStyle::Longitude => (scaled.abs(), Some(if scaled < 0.0 { "W" } else { "E" })),
+        };
+
+        let mut text = match self.decimals {
+            Some(places) => format!("{:.*}", places, number),
+            // untouched integers shouldn't pick up a ".0"
+            None if self.scale.is_none() && suffix.is_none() => value.to_string(),
+            None => number.to_string(),
+        };
+        if let Some(unit) = &self.unit {
+            text.push_str(unit);
+        }
+        if let Some(suffix) = suffix {
+            text.push(' ');
+            text.push_str(suffix);
+        }
+        text
+    }
+}
+
+// clones share the same formats, so the telemetry reader formats like the middleware
+#[derive(Clone)]
+pub struct ValueFormatter {
+    formats: Arc<RwLock<BTreeMap<String, FieldFormat>>>,
+}
+
+impl Default for ValueFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ValueFormatter {
+    pub fn new() -> Self {
+        Self { formats: Arc::new(RwLock::new(BTreeMap::new())) }
+    }
+
+    pub fn set_formats(&self, formats: BTreeMap<String, FieldFormat>) {
+        *self.formats.write() = formats;
+    }
+
+    pub fn set_format(&self, key: &str, format: Option<FieldFormat>) {
+        let mut formats = self.formats.write();
+        match format {
+            Some(format) => {
+                formats.insert(key.to_string(), format);
+            }
+            None => {
+                formats.remove(key);
+            }
+        }
+    }
+
+    pub fn formats(&self) -> BTreeMap<String, FieldFormat> {
+        self.formats.read().clone()
+    }
+
+    pub fn lookup(&self, store: &str, field: &str) -> Option<FieldFormat> {
+        lookup(&self.formats.read(), store, field).cloned()
+    }
+
+    /// Display string for a value, plain to_string() if nothing is configured
+    pub fn format(&self, store: &str, field: &str, value: &TelemetryValue) -> String {
+        match lookup(&self.formats.read(), store, field) {
+            Some(format) => format.apply(value),
+            None => value.to_string(),
+        }
+    }
+}
+
+fn lookup<'a>(formats: &'a BTreeMap<String, FieldFormat>, store: &str, field: &str) -> Option<&'a FieldFormat> {
+    formats
+        .get(&format!("{store}.{field}"))
+        .or_else(|| formats.get(field))
+}
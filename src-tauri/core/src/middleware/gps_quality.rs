@@ -0,0 +1,80 @@
+// One number for how much to believe a vehicle's GPS
+//
+// The pinned telemetry-2026 liv3f only reports satellites in view, so that is what the
+// fix is scored on: gps_quality, 0 to 1, pushed into the same store as the fix, plus
+// gps_trustworthy for whether it's good enough to point the tracker at. Fewer than four
+// satellites is no 3D fix at all and scores nothing.
+use serde::{Deserialize, Serialize};
+
+pub const QUALITY_FIELD: &str = "gps_quality";
+pub const TRUSTWORTHY_FIELD: &str = "gps_trustworthy";
+// the fewest satellites a 3D fix needs
+const FIX_SATELLITES: f64 = 4.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpsQualityConfig {
+    // satellites for a full score, and the fewest a trustworthy fix can have
+    pub good_satellites: u32,
+    pub min_satellites: u32,
+    pub trust_threshold: f64,
+}
+
+impl Default for GpsQualityConfig {
+    fn default() -> Self {
+        Self { good_satellites: 10, min_satellites: 5, trust_threshold: 0.6 }
+    }
+}
+
+impl GpsQualityConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_satellites > self.good_satellites || self.good_satellites == 0 {
+            return Err("min_satellites must be at most good_satellites, which must be positive".into());
+        }
+        if !(0.0..=1.0).contains(&self.trust_threshold) {
+            return Err("trust_threshold must be in [0, 1]".into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct GpsQuality {
+    config: GpsQualityConfig,
+    // (store, field, value, timestamp) waiting to be pushed by the middleware
+    derived: Vec<(String, &'static str, f64, i64)>,
+}
+
+impl GpsQuality {
+    pub fn config(&self) -> GpsQualityConfig {
+        self.config.clone()
+    }
+
+    pub fn set_config(&mut self, config: GpsQualityConfig) -> Result<(), String> {
+        config.validate()?;
+        self.config = config;
+        Ok(())
+    }
+
+    pub fn observe(&mut self, store: &str, field: &str, value: f64, timestamp: i64) {
+        if field != "satellites" || !value.is_finite() {
+            return;
+        }
+        let (quality, trustworthy) = self.score(value);
+        self.derived.push((store.to_string(), QUALITY_FIELD, quality, timestamp));
+        self.derived.push((store.to_string(), TRUSTWORTHY_FIELD, trustworthy as u8 as f64, timestamp));
+    }
+
+    pub fn take_derived(&mut self) -> Vec<(String, &'static str, f64, i64)> {
+        std::mem::take(&mut self.derived)
+    }
+
+    fn score(&self, satellites: f64) -> (f64, bool) {
+        let c = &self.config;
+        if satellites < FIX_SATELLITES {
+            return (0.0, false);
+        }
+        let quality = (satellites / c.good_satellites as f64).clamp(0.0, 1.0);
+        let trustworthy = quality >= c.trust_threshold && satellites >= c.min_satellites as f64;
+        (quality, trustworthy)
+    }
+}
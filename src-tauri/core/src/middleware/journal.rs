@@ -0,0 +1,310 @@
+// Every change to the telemetry stores, in order, as a replayable log
+//
+// With the journal on, each push, store created or removed, clear and recording
+// start/stop is appended to journal_<time>.jsonl, one JSON object a line:
+//   {"seq":12,"at":1760000000125,"op":"set","store":"rocket","field":"alt","timestamp":1760000000120,"value":{"f64":812.4}}
+//   {"seq":13,"at":1760000000130,"op":"stop_recording","store":"rocket"}
+// Turning it on first writes what's already in memory, so rebuild() gives back the
+// stores exactly as they were at any seq and "how did rocket end up in that state"
+// can be stepped through after the flight. Values keep their type, unlike the CSVs.
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use super::data_quality::DataQuality;
+use super::encryption::{self, SessionEncryption, SessionWriter};
+use super::lock_timing::TimedMutex;
+use super::telemetry_stores::{TelemetryData, TelemetryValue};
+
+// pushes between flushes, everything else is flushed straight away
+const FLUSH_EVERY: u64 = 256;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalValue {
+    F64(f64),
+    // NaN and inf have no JSON, written as "NaN", "inf" or "-inf"
+    NonFinite(String),
+    I64(i64),
+    U64(u64),
+    Bool(bool),
+}
+
+impl From<TelemetryValue> for JournalValue {
+    fn from(value: TelemetryValue) -> Self {
+        match value {
+            TelemetryValue::F64(v) if v.is_finite() => JournalValue::F64(v),
+            TelemetryValue::F64(v) => JournalValue::NonFinite(v.to_string()),
+            TelemetryValue::I64(v) => JournalValue::I64(v),
+            TelemetryValue::U64(v) => JournalValue::U64(v),
+            TelemetryValue::Bool(v) => JournalValue::Bool(v),
+        }
+    }
+}
+
+impl From<JournalValue> for TelemetryValue {
+    fn from(value: JournalValue) -> Self {
+        match value {
+            JournalValue::F64(v) => TelemetryValue::F64(v),
+            JournalValue::NonFinite(v) => TelemetryValue::F64(v.parse().unwrap_or(f64::NAN)),
+            JournalValue::I64(v) => TelemetryValue::I64(v),
+            JournalValue::U64(v) => TelemetryValue::U64(v),
+            JournalValue::Bool(v) => TelemetryValue::Bool(v),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JournalOp {
+    Set {
+        store: String,
+        field: String,
+        timestamp: i64,
+        value: JournalValue,
+        #[serde(default, skip_serializing_if = "DataQuality::is_valid")]
+        quality: DataQuality,
+    },
+    CreateStore { store: String },
+    RemoveStore { store: String },
+    ClearAll,
+    StartRecording { store: String },
+    StopRecording { store: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub seq: u64,
+    // unix ms it happened, not the point's own timestamp
+    pub at: i64,
+    #[serde(flatten)]
+    pub op: JournalOp,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalStatus {
+    pub enabled: bool,
+    pub path: Option<String>,
+    pub entries: u64,
+}
+
+struct JournalWriter {
+    path: PathBuf,
+    file: BufWriter<SessionWriter>,
+    unflushed: u64,
+}
+
+#[derive(Default)]
+struct JournalInner {
+    writer: Option<JournalWriter>,
+    seq: u64,
+}
+
+// &self everywhere, stores are created and recording toggled from &self methods
+pub struct Journal {
+    inner: TimedMutex<JournalInner>,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self { inner: TimedMutex::new("journal", JournalInner::default()) }
+    }
+}
+
+impl Journal {
+    /// Opens a new journal in `dir`, starting with `baseline`: whatever the stores
+    /// already hold, so a rebuild doesn't start from nothing
+    pub fn start(&self, dir: &Path, encryption: &SessionEncryption, baseline: Vec<JournalOp>) -> Result<(), String> {
+        let mut inner = self.inner.lock();
+        if inner.writer.is_some() {
+            return Ok(());
+        }
+        let path = dir.join(format!("journal_{}.jsonl", Local::now().format("%Y-%m-%d_%H-%M-%S")));
+        let file = encryption.create(&path)?;
+        println!("[journal] Journaling store changes to {}", path.display());
+        inner.writer = Some(JournalWriter { path, file: BufWriter::new(file), unflushed: 0 });
+        inner.seq = 0;
+        for op in baseline {
+            Self::append(&mut inner, op);
+        }
+        Self::flush(&mut inner);
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        let mut inner = self.inner.lock();
+        Self::flush(&mut inner);
+        if let Some(writer) = inner.writer.take() {
+            println!("[journal] Closed {} after {} entries", writer.path.display(), inner.seq);
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.lock().writer.is_some()
+    }
+
+    pub fn record(&self, op: JournalOp) {
+        let mut inner = self.inner.lock();
+        if inner.writer.is_none() {
+            return;
+        }
+        let flush_now = !matches!(op, JournalOp::Set { .. });
+        Self::append(&mut inner, op);
+        if flush_now || inner.writer.as_ref().is_some_and(|w| w.unflushed >= FLUSH_EVERY) {
+            Self::flush(&mut inner);
+        }
+    }
+
+    pub fn status(&self) -> JournalStatus {
+        let inner = self.inner.lock();
+        JournalStatus {
+            enabled: inner.writer.is_some(),
+            path: inner.writer.as_ref().map(|w| w.path.display().to_string()),
+            entries: inner.seq,
+        }
+    }
+
+    fn append(inner: &mut JournalInner, op: JournalOp) {
+        let entry = JournalEntry { seq: inner.seq, at: chrono::Utc::now().timestamp_millis(), op };
+        let Some(writer) = inner.writer.as_mut() else {
+            return;
+        };
+        let written = serde_json::to_writer(&mut writer.file, &entry)
+            .map_err(|e| e.to_string())
+            .and_then(|_| writer.file.write_all(b"\n").map_err(|e| e.to_string()));
+        match written {
+            Ok(()) => {
+                writer.unflushed += 1;
+                inner.seq += 1;
+            }
+            Err(e) => eprintln!("[journal] Failed to write to {}: {e}", writer.path.display()),
+        }
+    }
+
+    fn flush(inner: &mut JournalInner) {
+        if let Some(writer) = inner.writer.as_mut() {
+            if let Err(e) = writer.file.flush() {
+                eprintln!("[journal] Failed to flush {}: {e}", writer.path.display());
+            }
+            writer.unflushed = 0;
+        }
+    }
+}
+
+// ── Rebuild ───────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Default)]
+pub struct RebuiltStore {
+    pub recording: bool,
+    pub fields: BTreeMap<String, Vec<TelemetryData>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RebuiltState {
+    pub stores: BTreeMap<String, RebuiltStore>,
+    // entries applied, and the last one's seq and time
+    pub entries: u64,
+    pub last_seq: Option<u64>,
+    pub last_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RebuiltField {
+    pub points: usize,
+    pub last: Option<TelemetryData>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RebuiltStoreSummary {
+    pub recording: bool,
+    pub fields: BTreeMap<String, RebuiltField>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RebuildSummary {
+    pub entries: u64,
+    pub last_seq: Option<u64>,
+    pub last_at: Option<i64>,
+    pub stores: BTreeMap<String, RebuiltStoreSummary>,
+}
+
+impl RebuiltState {
+    pub fn apply(&mut self, entry: JournalEntry) {
+        match entry.op {
+            JournalOp::Set { store, field, timestamp, value, quality } => {
+                self.stores
+                    .entry(store)
+                    .or_default()
+                    .fields
+                    .entry(field)
+                    .or_default()
+                    .push(
+                        TelemetryData::new()
+                            .with_timestamp(timestamp)
+                            .with_value(TelemetryValue::from(value))
+                            .with_quality(quality),
+                    );
+            }
+            JournalOp::CreateStore { store } => {
+                self.stores.entry(store).or_default();
+            }
+            JournalOp::RemoveStore { store } => {
+                self.stores.remove(&store);
+            }
+            // the stores stay, their buffers go
+            JournalOp::ClearAll => {
+                for store in self.stores.values_mut() {
+                    store.fields.clear();
+                }
+            }
+            JournalOp::StartRecording { store } => self.stores.entry(store).or_default().recording = true,
+            JournalOp::StopRecording { store } => self.stores.entry(store).or_default().recording = false,
+        }
+        self.entries += 1;
+        self.last_seq = Some(entry.seq);
+        self.last_at = Some(entry.at);
+    }
+
+    pub fn summary(&self) -> RebuildSummary {
+        RebuildSummary {
+            entries: self.entries,
+            last_seq: self.last_seq,
+            last_at: self.last_at,
+            stores: self
+                .stores
+                .iter()
+                .map(|(name, store)| {
+                    let fields = store
+                        .fields
+                        .iter()
+                        .map(|(field, data)| (field.clone(), RebuiltField { points: data.len(), last: data.last().cloned() }))
+                        .collect();
+                    (name.clone(), RebuiltStoreSummary { recording: store.recording, fields })
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The stores as the journal at `path` left them, or as of entry `until_seq` if given.
+/// A line that doesn't parse fails the rebuild, since everything after it would be off.
+pub fn rebuild(path: &Path, until_seq: Option<u64>) -> Result<RebuiltState, String> {
+    let reader = BufReader::new(encryption::open(path)?);
+    let mut state = RebuiltState::default();
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| format!("{}: {e}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: JournalEntry = serde_json::from_str(&line)
+            .map_err(|e| format!("{} line {}: {e}", path.display(), line_number + 1))?;
+        if until_seq.is_some_and(|until| entry.seq > until) {
+            break;
+        }
+        state.apply(entry);
+    }
+    Ok(state)
+}
@@ -0,0 +1,152 @@
+// Where a descending stage is going to come down
+//
+// Deliberately simple: the stage keeps drifting with the horizontal velocity its last
+// GPS fixes showed (under a chute that's mostly the wind) and keeps descending at its
+// current rate until it reaches the pad's altitude. Good enough to point a recovery
+// team at the right field.
+use serde::{Deserialize, Serialize};
+
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+// horizontal velocity smoothing, GPS fixes are noisy
+const DRIFT_SMOOTHING: f64 = 0.2;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Fix {
+    pub timestamp: i64,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LandingPrediction {
+    pub stage: String,
+    pub lat: f64,
+    pub lon: f64,
+    // seconds until touchdown from now, 0 once it should be down
+    pub eta_secs: f64,
+    // false when the stage has no telemetry of its own and this is dead reckoning
+    // from the separation point
+    pub from_telemetry: bool,
+}
+
+// what gets announced once the vehicle is down, and kept in session.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LandingSummary {
+    pub stage: String,
+    pub landed_at: i64,
+    // the last fix, None if GPS never locked
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    // from the last fix on the pad to the landing fix
+    pub distance_m: Option<f64>,
+    pub bearing_deg: Option<f64>,
+    pub max_altitude_agl_m: f64,
+    // liftoff to landing, None if we never saw it leave the pad
+    pub flight_time_secs: Option<f64>,
+    // all of the above as one sentence, for the alert and for reading out loud
+    #[serde(default)]
+    pub callout: String,
+}
+
+impl LandingSummary {
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("{} landed", self.stage)];
+        if let (Some(distance), Some(bearing)) = (self.distance_m, self.bearing_deg) {
+            parts.push(format!("{:.0} metres from the pad bearing {:03.0}", distance, bearing));
+        }
+        parts.push(format!("apogee {:.0} metres", self.max_altitude_agl_m));
+        if let Some(secs) = self.flight_time_secs {
+            parts.push(format!("flight time {:.0} seconds", secs));
+        }
+        if let (Some(lat), Some(lon)) = (self.lat, self.lon) {
+            parts.push(format!("last fix {lat:.5}, {lon:.5}"));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Great circle distance in metres and initial bearing in degrees (0 is north)
+pub fn distance_bearing(from: &Fix, to: &Fix) -> (f64, f64) {
+    let (lat1, lat2) = (from.lat.to_radians(), to.lat.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (to.lon - from.lon).to_radians();
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    let distance = 2.0 * EARTH_RADIUS_M * a.sqrt().atan2((1.0 - a).sqrt());
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    (distance, y.atan2(x).to_degrees().rem_euclid(360.0))
+}
+
+// lat and lon arrive as separate points with the same timestamp, pair them up
+#[derive(Debug, Clone, Default)]
+pub struct Track {
+    pending: Option<(i64, Option<f64>, Option<f64>)>,
+    fix: Option<Fix>,
+    // m/s
+    east: f64,
+    north: f64,
+}
+
+impl Track {
+    pub fn observe(&mut self, field: &str, value: f64, timestamp: i64) {
+        if !value.is_finite() || (field != "lat" && field != "lon") {
+            return;
+        }
+        let pending = match &mut self.pending {
+            Some(p) if p.0 == timestamp => p,
+            _ => self.pending.insert((timestamp, None, None)),
+        };
+        if field == "lat" {
+            pending.1 = Some(value);
+        } else {
+            pending.2 = Some(value);
+        }
+        if let (ts, Some(lat), Some(lon)) = *pending {
+            self.pending = None;
+            self.add_fix(Fix { timestamp: ts, lat, lon });
+        }
+    }
+
+    fn add_fix(&mut self, fix: Fix) {
+        // 0,0 is a GPS without lock, not a rocket off the coast of Africa
+        if fix.lat == 0.0 && fix.lon == 0.0 {
+            return;
+        }
+        if let Some(prev) = self.fix {
+            let dt = (fix.timestamp - prev.timestamp) as f64 / 1000.0;
+            if dt <= 0.0 {
+                return;
+            }
+            let (east, north) = offset_m(&prev, &fix);
+            self.east += DRIFT_SMOOTHING * (east / dt - self.east);
+            self.north += DRIFT_SMOOTHING * (north / dt - self.north);
+        }
+        self.fix = Some(fix);
+    }
+
+    pub fn fix(&self) -> Option<Fix> {
+        self.fix
+    }
+
+    /// Touchdown point and seconds to get there, from `altitude_agl` metres up
+    /// descending at `descent_mps`. `elapsed_secs` is how long ago that altitude was.
+    pub fn predict(&self, altitude_agl: f64, descent_mps: f64, elapsed_secs: f64) -> Option<(f64, f64, f64)> {
+        let fix = self.fix?;
+        let total = (altitude_agl.max(0.0) / descent_mps.max(0.1)).max(0.0);
+        let (lat, lon) = moved(&fix, self.east * total, self.north * total);
+        Some((lat, lon, (total - elapsed_secs).max(0.0)))
+    }
+}
+
+// flat earth is fine over a few km
+fn offset_m(from: &Fix, to: &Fix) -> (f64, f64) {
+    let north = (to.lat - from.lat).to_radians() * EARTH_RADIUS_M;
+    let east = (to.lon - from.lon).to_radians() * EARTH_RADIUS_M * from.lat.to_radians().cos();
+    (east, north)
+}
+
+fn moved(fix: &Fix, east_m: f64, north_m: f64) -> (f64, f64) {
+    let lat = fix.lat + (north_m / EARTH_RADIUS_M).to_degrees();
+    let lon = fix.lon + (east_m / (EARTH_RADIUS_M * fix.lat.to_radians().cos())).to_degrees();
+    (lat, lon)
+}
@@ -0,0 +1,131 @@
+// Launch sessions: two rockets on two radios at the same club launch, kept apart
+//
+// The session folder is still one per run of the app. A launch session is one flight
+// inside it that owns some data sources, by the name they report under (a radio's
+// "telemetry_radio_backup"). Whatever a bound source pushes into "rocket" lands in
+// "<session>/rocket" instead, so each flight gets its own stores and CSVs (under a
+// <session>/ folder), is recorded and stopped on its own, and runs the active alert
+// rules separately, raised as "<session>/<rule>". Anything that takes a store name
+// already works per session. Quality, buffer and derived field patterns see the full
+// name, "*/rocket" covers every session's rocket. Sources nobody bound keep writing
+// to the plain stores.
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+pub const SEPARATOR: char = '/';
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LaunchSessionSpec {
+    // short, it's in every store name: "l2_alpha"
+    pub id: String,
+    // for the operators, defaults to the id
+    #[serde(default)]
+    pub name: Option<String>,
+    pub sources: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchSession {
+    pub id: String,
+    pub name: String,
+    pub sources: Vec<String>,
+    pub started_at: i64,
+    pub recording: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchSessionStatus {
+    #[serde(flatten)]
+    pub session: LaunchSession,
+    pub stores: Vec<String>,
+    // raised under this session and not cleared
+    pub alerts: usize,
+}
+
+/// (session, store) for a store name, None for a store outside any session
+pub fn split_store(store: &str) -> (Option<&str>, &str) {
+    match store.split_once(SEPARATOR) {
+        Some((session, store)) => (Some(session), store),
+        None => (None, store),
+    }
+}
+
+pub fn session_key(id: &str, key: &str) -> String {
+    format!("{id}{SEPARATOR}{key}")
+}
+
+#[derive(Default)]
+pub struct LaunchSessions {
+    sessions: BTreeMap<String, LaunchSession>,
+    // source -> the session it's bound to
+    sources: HashMap<String, String>,
+}
+
+impl LaunchSessions {
+    pub fn start(&mut self, spec: LaunchSessionSpec) -> Result<LaunchSession, String> {
+        if spec.id.is_empty() || !spec.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!("'{}' can't be a session id, use letters, digits, _ and -", spec.id));
+        }
+        if self.sessions.contains_key(&spec.id) {
+            return Err(format!("Session '{}' is already active", spec.id));
+        }
+        if spec.sources.is_empty() {
+            return Err(format!("Session '{}' needs at least one source", spec.id));
+        }
+        for source in &spec.sources {
+            if let Some(owner) = self.sources.get(source) {
+                return Err(format!("'{source}' is already bound to session '{owner}'"));
+            }
+        }
+
+        let session = LaunchSession {
+            name: spec.name.unwrap_or_else(|| spec.id.clone()),
+            id: spec.id,
+            sources: spec.sources,
+            started_at: chrono::Utc::now().timestamp_millis(),
+            recording: false,
+        };
+        for source in &session.sources {
+            self.sources.insert(source.clone(), session.id.clone());
+        }
+        self.sessions.insert(session.id.clone(), session.clone());
+        Ok(session)
+    }
+
+    // the bindings go, the stores stay with their data
+    pub fn end(&mut self, id: &str) -> Result<LaunchSession, String> {
+        let session = self.sessions.remove(id).ok_or_else(|| format!("No active session '{id}'"))?;
+        self.sources.retain(|_, owner| owner != id);
+        Ok(session)
+    }
+
+    pub fn get(&self, id: &str) -> Result<&LaunchSession, String> {
+        self.sessions.get(id).ok_or_else(|| format!("No active session '{id}'"))
+    }
+
+    pub fn list(&self) -> Vec<LaunchSession> {
+        self.sessions.values().cloned().collect()
+    }
+
+    pub fn set_recording(&mut self, id: &str, recording: bool) -> Result<(), String> {
+        self.sessions
+            .get_mut(id)
+            .ok_or_else(|| format!("No active session '{id}'"))?
+            .recording = recording;
+        Ok(())
+    }
+
+    /// Where `source` pushing into `store` should go
+    pub fn store_for(&self, source: &str, store: &str) -> String {
+        match self.sources.get(source) {
+            Some(id) => session_key(id, store),
+            None => store.to_string(),
+        }
+    }
+
+    // a store a recording session owns, started as soon as it's created
+    pub fn records(&self, store: &str) -> bool {
+        let (session, _) = split_store(store);
+        session.and_then(|id| self.sessions.get(id)).is_some_and(|s| s.recording)
+    }
+}
@@ -0,0 +1,266 @@
+// Shedding telemetry by priority when ingest can't keep up
+//
+// Every (store, field) gets a priority from the first matching rule: critical fields
+// (position, altitude, flight events) are never touched, normal ones are thinned out
+// under heavy load and debug ones are the first to go. The resource monitor reports
+// CPU and writer queue depth once a sample; each overloaded sample steps the level up,
+// CALM_SAMPLES quiet ones in a row step it back down:
+//   0  everything kept
+//   1  debug fields dropped
+//   2  debug dropped, normal fields kept 1 in NORMAL_DECIMATION
+// What was shed is counted per priority and as shed.<store> drops.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const LOAD_SHEDDING_EVENT: &str = "load_shedding";
+const MAX_LEVEL: u8 = 2;
+const CALM_SAMPLES: u32 = 5;
+const NORMAL_DECIMATION: u64 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamPriority {
+    Critical,
+    Normal,
+    Debug,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriorityRule {
+    // "*" matches anything, e.g. "gps_*" or "*_debug"
+    pub store: String,
+    pub field: String,
+    pub priority: StreamPriority,
+}
+
+impl PriorityRule {
+    fn new(store: &str, field: &str, priority: StreamPriority) -> Self {
+        Self { store: store.into(), field: field.into(), priority }
+    }
+
+    fn matches(&self, store: &str, field: &str) -> bool {
+        wildcard(&self.store, store) && wildcard(&self.field, field)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadSheddingConfig {
+    pub rules: Vec<PriorityRule>,
+    // for fields no rule matches
+    #[serde(default = "default_priority")]
+    pub default_priority: StreamPriority,
+    // process CPU as a share of the whole machine
+    #[serde(default = "default_cpu_percent")]
+    pub cpu_percent: f64,
+    // rows waiting in the fullest CSV writer
+    #[serde(default = "default_queue_depth")]
+    pub queue_depth: usize,
+}
+
+fn default_priority() -> StreamPriority {
+    StreamPriority::Normal
+}
+
+fn default_cpu_percent() -> f64 {
+    85.0
+}
+
+fn default_queue_depth() -> usize {
+    512
+}
+
+impl Default for LoadSheddingConfig {
+    fn default() -> Self {
+        use StreamPriority::*;
+        Self {
+            rules: vec![
+                PriorityRule::new("flight", "*", Critical),
+                PriorityRule::new("*", "lat", Critical),
+                PriorityRule::new("*", "lon", Critical),
+                PriorityRule::new("*", "alt*", Critical),
+                PriorityRule::new("*", "gps*", Critical),
+                PriorityRule::new("*", "*state*", Critical),
+                // so shedding itself stays on the plots
+                PriorityRule::new("ground_station", "shed*", Critical),
+                PriorityRule::new("ground_station", "*", Debug),
+                PriorityRule::new("*", "*debug*", Debug),
+            ],
+            default_priority: default_priority(),
+            cpu_percent: default_cpu_percent(),
+            queue_depth: default_queue_depth(),
+        }
+    }
+}
+
+impl LoadSheddingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.cpu_percent > 0.0 && self.cpu_percent <= 100.0) {
+            return Err("The CPU limit must be a percentage above 0".into());
+        }
+        if self.queue_depth == 0 {
+            return Err("The queue depth limit must be at least 1".into());
+        }
+        if let Some(rule) = self.rules.iter().find(|r| r.store.is_empty() || r.field.is_empty()) {
+            return Err(format!("Rule for '{}'.'{}' needs both a store and a field pattern", rule.store, rule.field));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LoadSheddingStatus {
+    pub level: u8,
+    // unix ms the current shedding started, None when nothing is shed
+    pub since: Option<i64>,
+    pub shed_normal: u64,
+    pub shed_debug: u64,
+    // the last sample's view of the load
+    pub cpu_percent: Option<f64>,
+    pub queue_depth: usize,
+}
+
+struct FieldState {
+    priority: StreamPriority,
+    seen: u64,
+}
+
+pub struct LoadShedder {
+    config: LoadSheddingConfig,
+    level: u8,
+    calm: u32,
+    since: Option<i64>,
+    shed_normal: u64,
+    shed_debug: u64,
+    cpu_percent: Option<f64>,
+    queue_depth: usize,
+    // the rules resolved once per field
+    fields: HashMap<String, HashMap<String, FieldState>>,
+}
+
+// '*' matches any run of characters, everything else literally
+pub(crate) fn wildcard(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // no '*' at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+impl Default for LoadShedder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoadShedder {
+    pub fn new() -> Self {
+        Self {
+            config: LoadSheddingConfig::default(),
+            level: 0,
+            calm: 0,
+            since: None,
+            shed_normal: 0,
+            shed_debug: 0,
+            cpu_percent: None,
+            queue_depth: 0,
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> LoadSheddingConfig {
+        self.config.clone()
+    }
+
+    pub fn set_config(&mut self, config: LoadSheddingConfig) -> Result<(), String> {
+        config.validate()?;
+        self.config = config;
+        self.fields.clear();
+        Ok(())
+    }
+
+    pub fn priority(&self, store: &str, field: &str) -> StreamPriority {
+        self.config
+            .rules
+            .iter()
+            .find(|rule| rule.matches(store, field))
+            .map_or(self.config.default_priority, |rule| rule.priority)
+    }
+
+    /// Whether a point should go in, false means it was shed
+    pub fn admit(&mut self, store: &str, field: &str) -> bool {
+        if self.level == 0 {
+            return true;
+        }
+        if !self.fields.get(store).is_some_and(|f| f.contains_key(field)) {
+            let priority = self.priority(store, field);
+            self.fields
+                .entry(store.to_string())
+                .or_default()
+                .insert(field.to_string(), FieldState { priority, seen: 0 });
+        }
+        let state = self.fields.get_mut(store).and_then(|f| f.get_mut(field)).expect("inserted above");
+        state.seen += 1;
+        let keep = match state.priority {
+            StreamPriority::Critical => true,
+            StreamPriority::Normal => self.level < 2 || state.seen % NORMAL_DECIMATION == 1,
+            StreamPriority::Debug => false,
+        };
+        if !keep {
+            match state.priority {
+                StreamPriority::Debug => self.shed_debug += 1,
+                _ => self.shed_normal += 1,
+            }
+        }
+        keep
+    }
+
+    /// One resource sample. Returns the new level if it changed.
+    pub fn observe(&mut self, cpu_percent: Option<f64>, queue_depth: usize, now: i64) -> Option<u8> {
+        self.cpu_percent = cpu_percent;
+        self.queue_depth = queue_depth;
+        let overloaded = cpu_percent.is_some_and(|cpu| cpu >= self.config.cpu_percent) || queue_depth >= self.config.queue_depth;
+        let previous = self.level;
+        if overloaded {
+            self.calm = 0;
+            self.level = (self.level + 1).min(MAX_LEVEL);
+        } else if self.level > 0 {
+            self.calm += 1;
+            if self.calm >= CALM_SAMPLES {
+                self.calm = 0;
+                self.level -= 1;
+            }
+        }
+        if self.level == previous {
+            return None;
+        }
+        match (previous, self.level) {
+            (0, _) => self.since = Some(now),
+            (_, 0) => self.since = None,
+            _ => {}
+        }
+        Some(self.level)
+    }
+
+    pub fn status(&self) -> LoadSheddingStatus {
+        LoadSheddingStatus {
+            level: self.level,
+            since: self.since,
+            shed_normal: self.shed_normal,
+            shed_debug: self.shed_debug,
+            cpu_percent: self.cpu_percent,
+            queue_depth: self.queue_depth,
+        }
+    }
+}
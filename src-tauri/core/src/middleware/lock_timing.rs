@@ -0,0 +1,187 @@
+// How long the shared sync locks are held, and which ones go over budget
+//
+// A TimedMutex is a parking_lot mutex with a name. When its guard drops we add the
+// hold time to that name's stats in one process-wide registry; the locks are created
+// all over the app, mostly without a middleware to hand. A hold longer than the budget
+// (LockBudgetConfig, one default and optional per-lock overrides) is kept as an
+// overrun until the middleware collects them, which turns them into alerts.
+//
+// Only the sync locks go through here. The middleware itself sits behind a tokio
+// mutex, which can't poison and is already watched through the queue depths.
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use parking_lot::{Mutex, MutexGuard, RwLock};
+use serde::{Deserialize, Serialize};
+
+// overruns waiting to be collected, the oldest are dropped past this
+const MAX_PENDING_OVERRUNS: usize = 256;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockBudgetConfig {
+    #[serde(default = "default_budget_ms")]
+    pub default_ms: u64,
+    // by lock name, e.g. "journal" = 100
+    #[serde(default)]
+    pub per_lock: BTreeMap<String, u64>,
+}
+
+fn default_budget_ms() -> u64 {
+    50
+}
+
+impl Default for LockBudgetConfig {
+    fn default() -> Self {
+        Self { default_ms: default_budget_ms(), per_lock: BTreeMap::new() }
+    }
+}
+
+impl LockBudgetConfig {
+    fn budget(&self, name: &str) -> Duration {
+        Duration::from_millis(*self.per_lock.get(name).unwrap_or(&self.default_ms))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LockStats {
+    pub name: String,
+    pub acquisitions: u64,
+    pub mean_hold_us: f64,
+    pub max_hold_us: u64,
+    pub overruns: u64,
+    pub budget_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LockOverrun {
+    pub name: &'static str,
+    pub held_ms: f64,
+    pub budget_ms: u64,
+    // unix ms the lock was let go
+    pub at: i64,
+}
+
+#[derive(Default)]
+struct Stat {
+    acquisitions: u64,
+    total_hold: Duration,
+    max_hold: Duration,
+    overruns: u64,
+}
+
+#[derive(Default)]
+struct Registry {
+    stats: DashMap<&'static str, Stat>,
+    config: RwLock<LockBudgetConfig>,
+    overruns: Mutex<Vec<LockOverrun>>,
+}
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::default);
+
+fn record(name: &'static str, held: Duration) {
+    let budget = REGISTRY.config.read().budget(name);
+    let mut stat = REGISTRY.stats.entry(name).or_default();
+    stat.acquisitions += 1;
+    stat.total_hold += held;
+    stat.max_hold = stat.max_hold.max(held);
+    if held > budget {
+        stat.overruns += 1;
+        drop(stat);
+        let mut overruns = REGISTRY.overruns.lock();
+        if overruns.len() >= MAX_PENDING_OVERRUNS {
+            overruns.remove(0);
+        }
+        overruns.push(LockOverrun {
+            name,
+            held_ms: held.as_secs_f64() * 1000.0,
+            budget_ms: budget.as_millis() as u64,
+            at: chrono::Utc::now().timestamp_millis(),
+        });
+    }
+}
+
+pub fn set_budget(config: LockBudgetConfig) -> Result<(), String> {
+    if config.default_ms == 0 || config.per_lock.values().any(|&ms| ms == 0) {
+        return Err("A lock budget has to be at least 1 ms".into());
+    }
+    *REGISTRY.config.write() = config;
+    Ok(())
+}
+
+pub fn budget() -> LockBudgetConfig {
+    REGISTRY.config.read().clone()
+}
+
+/// Every named lock that has been taken at least once, by name
+pub fn stats() -> Vec<LockStats> {
+    let config = REGISTRY.config.read().clone();
+    let mut stats: Vec<LockStats> = REGISTRY
+        .stats
+        .iter()
+        .map(|entry| {
+            let stat = entry.value();
+            LockStats {
+                name: entry.key().to_string(),
+                acquisitions: stat.acquisitions,
+                mean_hold_us: stat.total_hold.as_secs_f64() * 1e6 / stat.acquisitions.max(1) as f64,
+                max_hold_us: stat.max_hold.as_micros() as u64,
+                overruns: stat.overruns,
+                budget_ms: config.budget(entry.key()).as_millis() as u64,
+            }
+        })
+        .collect();
+    stats.sort_by(|a, b| a.name.cmp(&b.name));
+    stats
+}
+
+/// The overruns since the last call, oldest first
+pub fn take_overruns() -> Vec<LockOverrun> {
+    std::mem::take(&mut *REGISTRY.overruns.lock())
+}
+
+// ── TimedMutex ────────────────────────────────────────────────────────────────
+
+pub struct TimedMutex<T> {
+    name: &'static str,
+    inner: Mutex<T>,
+}
+
+impl<T> TimedMutex<T> {
+    pub const fn new(name: &'static str, value: T) -> Self {
+        Self { name, inner: Mutex::new(value) }
+    }
+
+    pub fn lock(&self) -> TimedGuard<'_, T> {
+        let guard = self.inner.lock();
+        TimedGuard { guard, name: self.name, acquired: Instant::now() }
+    }
+}
+
+pub struct TimedGuard<'a, T> {
+    guard: MutexGuard<'a, T>,
+    name: &'static str,
+    acquired: Instant,
+}
+
+impl<T> Deref for TimedGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for TimedGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for TimedGuard<'_, T> {
+    fn drop(&mut self) {
+        record(self.name, self.acquired.elapsed());
+    }
+}
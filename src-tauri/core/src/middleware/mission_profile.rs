@@ -0,0 +1,138 @@
+// Per-flight mission profile: which vehicle we're flying and how the ground station
+// should be configured for it. Loaded from a JSON/TOML file before the flight.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::flight_state::DetectorConfig;
+use super::formatting::FieldFormat;
+use super::derived_fields::DerivedField;
+use super::buffer_policy::BufferConfig;
+use super::data_quality::QualityConfig;
+use super::field_metadata::FieldMetadata;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MissionProfile {
+    pub name: String,
+    #[serde(default)]
+    pub vehicle: String,
+    // name of a rule set already imported into the alert engine
+    #[serde(default)]
+    pub alert_rule_set: Option<String>,
+    // store name -> the fields the vehicle should be sending into it
+    #[serde(default)]
+    pub expected_schema: BTreeMap<String, Vec<String>>,
+    // "store.field" or bare field name -> how to display it
+    #[serde(default)]
+    pub field_formats: BTreeMap<String, FieldFormat>,
+    // flight state strategy and its parameters, single deploy off "rocket.alt" if unset
+    #[serde(default)]
+    pub flight_detector: Option<DetectorConfig>,
+    // uplink command sequences the operator can run by name
+    #[serde(default)]
+    pub command_macros: Vec<CommandMacro>,
+    // fields computed from the vehicle's on ingest, see derived_fields
+    #[serde(default)]
+    pub derived_fields: Vec<DerivedField>,
+    // how much of each stream to keep in memory, the station's current limits if unset
+    #[serde(default)]
+    pub buffers: Option<BufferConfig>,
+    // the ranges the vehicle's fields are flagged against, see data_quality
+    #[serde(default)]
+    pub data_quality: Option<QualityConfig>,
+    // "store.field" or bare field name -> unit, display name and expected range
+    #[serde(default)]
+    pub field_metadata: BTreeMap<String, FieldMetadata>,
+    // store name -> the fields every single packet has to carry, see schema_health.
+    // Narrower than expected_schema, a beacon leaves most of those out.
+    #[serde(default)]
+    pub packet_schemas: BTreeMap<String, Vec<String>>,
+}
+
+// one step of a command macro, the app's command_macros sends them and waits for acks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MacroStep {
+    pub command: u8,
+    #[serde(default)]
+    pub label: Option<String>,
+    // wait this long after the step before the next one
+    #[serde(default)]
+    pub delay_ms: u64,
+    #[serde(default)]
+    pub expect_ack: bool,
+    #[serde(default = "default_ack_timeout")]
+    pub ack_timeout_ms: u64,
+    // resends after the first try when no ack comes
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+}
+
+fn default_ack_timeout() -> u64 {
+    3000
+}
+
+fn default_retries() -> u32 {
+    2
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandMacro {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub steps: Vec<MacroStep>,
+    // the store whose last_command_received acknowledges our commands
+    #[serde(default = "default_ack_store")]
+    pub ack_store: String,
+    // the operator has to type the macro's name to run it
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+fn default_ack_store() -> String {
+    "rocket".into()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaDiff {
+    pub store: String,
+    pub missing: Vec<String>,
+    pub unexpected: Vec<String>,
+}
+
+impl SchemaDiff {
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.missing.is_empty() {
+            parts.push(format!("missing {}", self.missing.join(", ")));
+        }
+        if !self.unexpected.is_empty() {
+            parts.push(format!("unexpected {}", self.unexpected.join(", ")));
+        }
+        format!("'{}' schema mismatch: {}", self.store, parts.join("; "))
+    }
+}
+
+impl MissionProfile {
+    /// Compare what a store actually received with what the profile expects.
+    /// None if the profile says nothing about this store.
+    pub fn schema_diff(&self, store: &str, received: &[String]) -> Option<SchemaDiff> {
+        let expected = self.expected_schema.get(store)?;
+        let mut missing: Vec<String> = expected
+            .iter()
+            .filter(|f| !received.contains(f))
+            .cloned()
+            .collect();
+        let mut unexpected: Vec<String> = received
+            .iter()
+            .filter(|f| !expected.contains(f))
+            .cloned()
+            .collect();
+        missing.sort();
+        unexpected.sort();
+        Some(SchemaDiff { store: store.to_string(), missing, unexpected })
+    }
+}
@@ -0,0 +1,1758 @@
+// Main middleware module
+
+use std::{collections::{BTreeMap, HashSet}, path::{Path, PathBuf}, sync::Arc};
+use std::io::Write;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::broadcast;
+
+use chrono::Local;
+
+pub mod video_streams;
+pub mod telemetry_stores;
+pub mod video_encoder_manager;
+pub mod alerts;
+pub mod config_file;
+pub mod mission_profile;
+pub mod elevation;
+pub mod recovery;
+pub mod packet_log;
+pub mod data_audit;
+pub mod black_box;
+pub mod formatting;
+pub mod time_base;
+pub mod drops;
+pub mod export;
+pub mod events;
+pub mod encryption;
+pub mod session_manifest;
+pub mod flight_state;
+pub mod landing;
+pub mod gps_quality;
+pub mod flatten;
+pub mod session_template;
+pub mod packet_stats;
+pub mod packet_recorder;
+pub mod decode_errors;
+pub mod load_shedding;
+pub mod journal;
+pub mod derived_fields;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+pub mod lock_timing;
+pub mod buffer_policy;
+pub mod csv_rotation;
+pub mod recording_arm;
+pub mod recording_format;
+pub mod recording_compression;
+pub mod flush_policy;
+pub mod recording_resume;
+pub mod recording_library;
+pub mod session_naming;
+pub mod telemetry_reader;
+pub mod data_quality;
+pub mod aggregate;
+pub mod telemetry_search;
+pub mod field_metadata;
+pub mod store_snapshot;
+pub mod launch_sessions;
+pub mod schema_health;
+pub mod change_events;
+
+use video_streams::
+    {VideoFrame, VideoStreams};
+use video_encoder_manager::EncoderManager;
+use telemetry_stores::
+    {TelemetryData, TelemetryStores};
+use alerts::{AlertAuditRecord, AlertEngine, AlertFrontend, AlertRuleSet, AlertSeverity};
+use mission_profile::{MissionProfile, SchemaDiff};
+use elevation::{ElevationService, TerrainPoint};
+use recovery::RecoveryBundle;
+use packet_log::{InspectedFrame, PacketLog};
+use data_audit::{DataAction, DataAuditLog, DataAuditRecord};
+use black_box::BlackBox;
+use formatting::{FieldFormat, ValueFormatter};
+use telemetry_stores::TelemetryValue;
+use time_base::{TimeBase, TimeBaseClock, TimeBaseConfig};
+use drops::{DropCounters, DropReport, DropSite, SharedDrops};
+use export::{ExportRegistry, ExportTable};
+use events::{EventEmitter, EventSink, EventTraceSummary};
+use encryption::SessionEncryption;
+use session_manifest::{SessionManifest, SessionManifestFile, StationLocation, TimeSyncRecord};
+use flight_state::{FlightState, FlightStateMachine, FlightStatus, FlightTransition};
+use landing::LandingPrediction;
+use gps_quality::{GpsQuality, GpsQualityConfig};
+use session_template::{Checklist, ChecklistItem, PreparedSession};
+use packet_stats::{PacketStats, PacketStreamStats};
+use packet_recorder::{PacketLogStatus, PacketRecorder};
+use decode_errors::{DecodeError, DecodeErrorCounts, DECODE_ERROR_EVENT};
+use load_shedding::{LoadShedder, LoadSheddingConfig, LoadSheddingStatus, LOAD_SHEDDING_EVENT};
+use journal::{Journal, JournalOp, JournalStatus};
+use derived_fields::{DerivedField, DerivedFields};
+#[cfg(feature = "sqlite")]
+use sqlite_store::{SqliteStatus, SqliteStore};
+use store_snapshot::{RestoreSummary, SnapshotWriter};
+use launch_sessions::{LaunchSession, LaunchSessionSpec, LaunchSessionStatus, LaunchSessions};
+use schema_health::{SchemaHealth, SchemaRegistry};
+use change_events::{ChangeWatcher, FieldChange, WatchedField, FIELD_CHANGED_EVENT};
+use lock_timing::{LockBudgetConfig, LockOverrun, LockStats};
+use buffer_policy::BufferConfig;
+use csv_rotation::{RecordingSegment, RotationConfig};
+use recording_arm::{ArmConfig, ArmStatus, RecordingArm};
+use recording_format::RecordingFormat;
+use recording_compression::RecordingCompression;
+use flush_policy::FlushPolicy;
+use recording_resume::ResumeSummary;
+use session_naming::SessionPaths;
+use telemetry_reader::TelemetryReader;
+use data_quality::{DataQuality, QualityConfig, QualityValidator};
+use field_metadata::{FieldMetadata, FieldMetadataRegistry};
+
+#[derive(Serialize, Deserialize)]
+pub struct VideoFrameFrontend {
+    pub timestamp: i64,
+    pub data_base64: String,
+    pub width: u32,
+    pub height: u32,
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct DownlinkedImage {
+    pub stream: String,
+    pub image_id: u16,
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+// one pushed point, as seen by live sinks
+#[derive(Debug, Clone)]
+pub struct TelemetryPoint {
+    pub store: String,
+    pub field: String,
+    pub data: TelemetryData,
+}
+
+// slow sinks past this get Lagged and lose points
+const LIVE_POINT_BACKLOG: usize = 8192;
+// emitted to every window on each flight state change
+const FLIGHT_STATE_EVENT: &str = "flight_state";
+// emitted once per landing with the LandingSummary, its callout is ready to be read out
+const LANDING_SUMMARY_EVENT: &str = "landing_summary";
+// per-stage altitude, velocity and landing predictions worked out by the state machine
+const FLIGHT_STORE: &str = "flight";
+// emitted for each photo the payload finishes sending
+const IMAGE_DOWNLINK_EVENT: &str = "image_downlinked";
+
+#[derive(Serialize, Deserialize)]
+pub struct TelemetryDataFrontend {
+    pub timestamp: i64,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "DataQuality::is_valid")]
+    pub quality: DataQuality,
+}
+
+// the latest value of several fields, all read at the same instant (get_latest_consistent)
+#[derive(Serialize)]
+pub struct ConsistentSnapshot {
+    pub as_of: i64,
+    // keyed "store.field" as requested, None if it has no data yet
+    pub values: BTreeMap<String, Option<TelemetryDataFrontend>>,
+}
+
+pub struct Middleware {
+    telemetry: Arc<TelemetryStores>,
+    clock: TimeBaseClock,
+    drops: SharedDrops,
+    exporters: ExportRegistry,
+    live_points: broadcast::Sender<TelemetryPoint>,
+    events: EventEmitter,
+    encryption: SessionEncryption,
+    manifest: SessionManifestFile,
+    video_streams: Arc<VideoStreams>,
+    alerts: AlertEngine,
+    mission_profile: Option<MissionProfile>,
+    flight: FlightStateMachine,
+    gps_quality: GpsQuality,
+    derived: DerivedFields,
+    quality: QualityValidator,
+    // store -> the fields its producer declared, see csv_header
+    store_schemas: BTreeMap<String, Vec<String>>,
+    metadata: FieldMetadataRegistry,
+    schemas: SchemaRegistry,
+    changes: ChangeWatcher,
+    arm: RecordingArm,
+    formatter: ValueFormatter,
+    elevation: ElevationService,
+    packet_log: PacketLog,
+    packet_stats: PacketStats,
+    packet_recorder: PacketRecorder,
+    decode_errors: DecodeErrorCounts,
+    shedder: LoadShedder,
+    journal: Journal,
+    #[cfg(feature = "sqlite")]
+    sqlite: SqliteStore,
+    // locks with an overrun alert up, cleared once a check finds them back in budget
+    lock_alerts: HashSet<&'static str>,
+    data_audit: DataAuditLog,
+    black_box: BlackBox,
+    checklist: Option<Checklist>,
+    launch_sessions: LaunchSessions,
+    base_path: PathBuf,
+    recording: AtomicBool,
+    // what start_recording writes, CSV unless the operator picked otherwise
+    recording_format: RecordingFormat,
+    recording_compression: RecordingCompression,
+    flush_policy: FlushPolicy,
+}
+
+impl Middleware {
+    pub fn new(base_path: PathBuf) -> Self {
+        let clock = time_base::new_clock();
+        let drops = DropCounters::new();
+        let encryption = SessionEncryption::default();
+        Middleware { 
+            telemetry: Arc::new(TelemetryStores::new(clock.clone(), drops.clone(), encryption.clone())),
+            clock,
+            exporters: ExportRegistry::new(),
+            live_points: broadcast::channel(LIVE_POINT_BACKLOG).0,
+            events: EventEmitter::new(),
+            video_streams: Arc::new(
+                VideoStreams::new(
+                    Arc::new(EncoderManager::new(drops.clone()))
+                )
+            ),
+            alerts: AlertEngine::new(base_path.join("alert_audit.jsonl")),
+            mission_profile: None,
+            flight: FlightStateMachine::new(),
+            gps_quality: GpsQuality::default(),
+            derived: DerivedFields::default(),
+            quality: QualityValidator::new(),
+            store_schemas: BTreeMap::new(),
+            metadata: FieldMetadataRegistry::default(),
+            schemas: SchemaRegistry::default(),
+            changes: ChangeWatcher::default(),
+            arm: RecordingArm::default(),
+            formatter: ValueFormatter::new(),
+            // DEM tiles live next to the session folders, shared between sessions
+            elevation: ElevationService::new(
+                base_path.parent().unwrap_or(&base_path).join("dem")
+            ),
+            packet_log: PacketLog::new(),
+            packet_stats: PacketStats::default(),
+            packet_recorder: PacketRecorder::new(),
+            decode_errors: DecodeErrorCounts::default(),
+            shedder: LoadShedder::new(),
+            journal: Journal::default(),
+            #[cfg(feature = "sqlite")]
+            sqlite: SqliteStore::new(drops.clone()),
+            lock_alerts: HashSet::new(),
+            data_audit: DataAuditLog::new(base_path.join("data_audit.jsonl")),
+            black_box: BlackBox::new(base_path.join("black_box"), drops.clone(), encryption.clone()),
+            checklist: None,
+            launch_sessions: LaunchSessions::default(),
+            encryption,
+            manifest: SessionManifestFile::new(base_path.join("session.json")),
+            base_path,
+            recording: AtomicBool::new(false),
+            recording_format: RecordingFormat::default(),
+            recording_compression: RecordingCompression::default(),
+            flush_policy: FlushPolicy::default(),
+            drops,
+        }
+    }
+
+    pub fn shutdown(&mut self) {
+        self.manifest.record_recording_stop();
+        self.telemetry.shutdown();
+        self.video_streams.shutdown();
+        self.black_box.shutdown();
+        self.journal.stop();
+        #[cfg(feature = "sqlite")]
+        self.sqlite.stop();
+    }
+
+// ------------------------------------------------  Events  ------------------------------------------------ //
+    pub fn attach_events(&mut self, sink: EventSink) {
+        self.events.attach(sink);
+    }
+
+    pub fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        self.events.emit(event, payload);
+    }
+
+    /// Starts writing every emitted event to a trace in the session folder, until
+    /// stop_event_trace. Returns where it's going.
+    pub fn start_event_trace(&self) -> Result<PathBuf, String> {
+        let path = self.base_path.join(format!("event_trace_{}.jsonl", Local::now().format("%H-%M-%S")));
+        self.events.start_trace(&path)?;
+        Ok(path)
+    }
+
+    pub fn stop_event_trace(&self) -> Result<EventTraceSummary, String> {
+        self.events.stop_trace()
+    }
+
+// ------------------------------------------------  GPS quality  ------------------------------------------------ //
+
+    pub fn set_gps_quality_config(&mut self, config: GpsQualityConfig) -> Result<(), String> {
+        self.gps_quality.set_config(config)
+    }
+
+    pub fn get_gps_quality_config(&self) -> GpsQualityConfig {
+        self.gps_quality.config()
+    }
+
+// ------------------------------------------------  Derived fields  ------------------------------------------------ //
+
+    pub fn set_derived_fields(&mut self, fields: Vec<DerivedField>) -> Result<(), String> {
+        self.derived.set_fields(fields)
+    }
+
+    pub fn get_derived_fields(&self) -> Vec<DerivedField> {
+        self.derived.fields()
+    }
+
+// ------------------------------------------------  Data quality  ------------------------------------------------ //
+
+    /// The ranges and rates points are flagged against (see data_quality). Only points
+    /// pushed from now on are checked with the new rules.
+    pub fn set_quality_config(&mut self, config: QualityConfig) -> Result<(), String> {
+        self.quality.set_config(config)
+    }
+
+    pub fn get_quality_config(&self) -> QualityConfig {
+        self.quality.config()
+    }
+
+// ------------------------------------------------  Flight state  ------------------------------------------------ //
+
+    pub fn get_flight_status(&self) -> FlightStatus {
+        self.flight.status()
+    }
+
+    pub fn get_landing_predictions(&self) -> Vec<LandingPrediction> {
+        self.flight.landing_predictions(chrono::Utc::now().timestamp_millis())
+    }
+
+    /// Put the state machine in `state` by hand. With `hold` it stays there until the
+    /// next override, otherwise detection carries on from the new state.
+    pub fn force_flight_state(&mut self, state: FlightState, hold: bool) -> FlightTransition {
+        let transition = self.flight.force(state, hold, chrono::Utc::now().timestamp_millis());
+        self.on_flight_transition(transition.clone());
+        transition
+    }
+
+    fn on_flight_transition(&mut self, transition: FlightTransition) {
+        println!(
+            "[flight] {:?} -> {:?}{}",
+            transition.from,
+            transition.to,
+            if transition.forced { " (forced)" } else { "" }
+        );
+        if self.flush_policy.sync_on_events {
+            self.telemetry.sync_all();
+        }
+        self.alerts.raise(
+            "flight.state",
+            AlertSeverity::Info,
+            format!("Flight state {:?}{}", transition.to, if transition.forced { " (set by operator)" } else { "" }),
+        );
+        if transition.from == FlightState::Pad && transition.to != FlightState::Pad {
+            self.on_liftoff(transition.timestamp);
+        }
+        if transition.to == FlightState::Landed && transition.from != FlightState::Landed {
+            self.on_landing(transition.timestamp);
+        }
+        self.emit(FLIGHT_STATE_EVENT, transition);
+    }
+
+    // the summary goes to every window, the alert list and session.json
+    fn on_landing(&mut self, landed_at: i64) {
+        let summary = self.flight.landing_summary(landed_at);
+        println!("[flight] {}", summary.callout);
+        self.alerts.clear("landing.summary");
+        self.alerts.raise("landing.summary", AlertSeverity::Info, summary.callout.clone());
+        self.manifest.record_landing(summary.clone());
+        self.emit(LANDING_SUMMARY_EVENT, summary);
+    }
+
+// ------------------------------------------------  Session  ------------------------------------------------ //
+
+    pub fn get_session_manifest(&self) -> SessionManifest {
+        self.manifest.get().clone()
+    }
+
+    pub fn record_time_sync(&mut self, record: TimeSyncRecord) {
+        self.manifest.record_time_sync(record);
+    }
+
+    /// What built this session's files, for session.json
+    pub fn set_build_info(&mut self, app_version: &str, schema_hash: Option<&str>) {
+        self.manifest.record_build(app_version, schema_hash);
+    }
+
+    pub fn set_station_location(&mut self, station: StationLocation) {
+        self.manifest.record_station(station);
+    }
+
+    // blank clears it
+    pub fn set_session_operator(&mut self, operator: &str) {
+        let operator = operator.trim();
+        self.manifest.record_operator((!operator.is_empty()).then(|| operator.to_string()));
+    }
+
+    // templates are a station setting, shared by every session
+    fn templates_dir(&self) -> PathBuf {
+        self.base_path.parent().unwrap_or(&self.base_path).join("templates")
+    }
+
+    pub fn list_session_templates(&self) -> Vec<String> {
+        session_template::list(&self.templates_dir())
+    }
+
+    /// Lay out this session from a template: folders, empty logs, checklist, mission
+    /// profile and manifest, and start recording if the template says to
+    pub fn create_session_from_template(&mut self, template: &str) -> Result<PreparedSession, String> {
+        let (template, template_path) = session_template::load(&self.templates_dir(), template)?;
+        // load the profile first, a template pointing at a bad one shouldn't half apply
+        let mission_profile = match &template.mission_profile {
+            Some(profile) => {
+                let path = template_path.parent().unwrap_or(Path::new(".")).join(profile);
+                Some(self.load_mission_profile(&path)?.name)
+            }
+            None => None,
+        };
+        let checklist = session_template::apply(&template, &self.base_path)?;
+        self.manifest.record_template(&template.name, template.mission.clone());
+        if template.start_recording {
+            self.start_recording_all()?;
+        }
+        println!("[session] prepared {} from template {}", self.base_path.display(), template.name);
+        self.checklist = Some(checklist.clone());
+        Ok(PreparedSession {
+            session: self.base_path.display().to_string(),
+            manifest: self.manifest.get().clone(),
+            checklist,
+            mission_profile,
+            recording: template.start_recording,
+        })
+    }
+
+    pub fn get_checklist(&self) -> Option<Checklist> {
+        self.checklist.clone()
+    }
+
+    pub fn set_checklist_item(&mut self, id: &str, done: bool, operator_role: &str) -> Result<ChecklistItem, String> {
+        let checklist = self.checklist.as_mut().ok_or("This session has no checklist, create it from a template")?;
+        let item = checklist.set_done(id, done, operator_role)?;
+        session_template::save_checklist(&self.base_path, checklist)?;
+        Ok(item)
+    }
+
+// ------------------------------------------------  Launch sessions  ------------------------------------------------ //
+    /// Starts a flight that owns `spec.sources`, their data goes to its own stores from
+    /// here on (see launch_sessions)
+    pub fn start_launch_session(&mut self, spec: LaunchSessionSpec) -> Result<LaunchSession, String> {
+        let session = self.launch_sessions.start(spec)?;
+        println!("[session] launch session {} started with {}", session.id, session.sources.join(", "));
+        Ok(session)
+    }
+
+    /// Stops recording the session's stores and clears its alerts, nothing raises them
+    /// any more. The stores and their data stay.
+    pub fn end_launch_session(&mut self, id: &str) -> Result<LaunchSession, String> {
+        self.stop_session_recording(id)?;
+        self.alerts.clear_prefixed(&launch_sessions::session_key(id, ""));
+        let session = self.launch_sessions.end(id)?;
+        println!("[session] launch session {} ended", session.id);
+        Ok(session)
+    }
+
+    pub fn list_active_sessions(&self) -> Vec<LaunchSessionStatus> {
+        let alerts = self.alerts.list();
+        self.launch_sessions
+            .list()
+            .into_iter()
+            .map(|session| {
+                let prefix = launch_sessions::session_key(&session.id, "");
+                LaunchSessionStatus {
+                    stores: self.session_stores(&session.id),
+                    alerts: alerts.iter().filter(|a| a.condition_active && a.key.starts_with(&prefix)).count(),
+                    session,
+                }
+            })
+            .collect()
+    }
+
+    /// The store a source's push into `store_name` belongs in, for producers that can
+    /// be bound to a launch session
+    pub fn session_store(&self, source: &str, store_name: &str) -> String {
+        self.launch_sessions.store_for(source, store_name)
+    }
+
+    // its stores now, and any it gets later as they're created
+    pub fn start_session_recording(&mut self, id: &str) -> Result<(), String> {
+        self.launch_sessions.set_recording(id, true)?;
+        for store_name in self.session_stores(id) {
+            self.start_recording(&store_name)?;
+        }
+        Ok(())
+    }
+
+    pub fn stop_session_recording(&mut self, id: &str) -> Result<(), String> {
+        self.launch_sessions.set_recording(id, false)?;
+        for store_name in self.session_stores(id) {
+            self.stop_recording(&store_name)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_session_alerts(&self, id: &str) -> Result<Vec<AlertFrontend>, String> {
+        self.launch_sessions.get(id)?;
+        let prefix = launch_sessions::session_key(id, "");
+        Ok(self.get_alerts().into_iter().filter(|a| a.alert.key.starts_with(&prefix)).collect())
+    }
+
+    fn session_stores(&self, id: &str) -> Vec<String> {
+        self.get_store_names()
+            .into_iter()
+            .filter(|store| launch_sessions::split_store(store).0 == Some(id))
+            .collect()
+    }
+
+// ------------------------------------------------  Encryption  ------------------------------------------------ //
+
+    // only files opened from here on are affected
+    pub fn set_session_encryption(&self, enabled: bool) -> Result<(), String> {
+        if enabled {
+            #[cfg(feature = "sqlite")]
+            if self.sqlite.is_enabled() {
+                return Err("The SQLite store isn't encrypted, turn it off before encrypting the session".into());
+            }
+            self.encryption.enable()?;
+        } else {
+            self.encryption.disable();
+        }
+        Ok(())
+    }
+
+    pub fn get_session_encryption(&self) -> bool {
+        self.encryption.is_enabled()
+    }
+
+    // for writers outside the middleware, like raw serial captures
+    pub fn session_encryption(&self) -> SessionEncryption {
+        self.encryption.clone()
+    }
+
+    pub fn session_dir(&self) -> PathBuf {
+        self.base_path.clone()
+    }
+
+    /// The session folder and what's being written in it (see session_naming). The raw
+    /// capture belongs to the radio, it's left for the caller to fill in.
+    pub fn session_paths(&self) -> SessionPaths {
+        let telemetry = self
+            .get_store_names()
+            .into_iter()
+            .map(|store| {
+                let path = session_naming::telemetry_file(&self.base_path, &store);
+                (store, path)
+            })
+            .collect();
+        let video = self
+            .get_video_keys()
+            .into_iter()
+            .filter(|key| self.video_streams.is_recording(key))
+            .filter_map(|key| Some((key.clone(), self.video_streams.video_path(&key)?)))
+            .collect();
+        SessionPaths { dir: self.base_path.clone(), telemetry, raw: None, video }
+    }
+
+// ------------------------------------------------  Recording  ------------------------------------------------ //
+
+
+    pub fn start_recording_all(&mut self) -> Result<(), String> {
+        self.recording.store(true, Ordering::Release);
+        let store_names = self.get_store_names();
+        self.manifest.record_recording_start(store_names.clone());
+        for store_name in store_names {
+            self.start_recording(&store_name)?;
+        }
+        let stream_names = self.get_video_keys();
+        for key in stream_names {
+            self.start_recording_video(&key, 60)?;
+        }
+        Ok(())
+    }
+
+    /// Start recording every store, appending to its CSV in an earlier session (the newest
+    /// if `session` is None) where it has one (see recording_resume). Every CSV is
+    /// checked before any store starts, one that doesn't fit fails the lot.
+    pub fn resume_recording_all(&mut self, session: Option<&Path>) -> Result<ResumeSummary, String> {
+        if self.get_recording_status() {
+            return Err("Stop the current recording before resuming an earlier one".into());
+        }
+        if self.encryption.is_enabled() {
+            return Err("Encryption is on, an earlier plaintext CSV can't be carried on".into());
+        }
+        let session = match session {
+            Some(session) => session.to_path_buf(),
+            None => recording_resume::latest_previous(&self.base_path)?,
+        };
+        let store_names = self.get_store_names();
+        let mut points = Vec::new();
+        for store_name in &store_names {
+            let point = recording_resume::find(&session, store_name, &self.csv_header(store_name))?;
+            points.push((store_name.clone(), point));
+        }
+
+        self.recording.store(true, Ordering::Release);
+        self.manifest.record_recording_start(store_names);
+        let mut summary = ResumeSummary { session, resumed: BTreeMap::new(), started: Vec::new() };
+        for (store_name, point) in points {
+            match point {
+                Some(point) => {
+                    summary.resumed.insert(store_name.clone(), point.path.clone());
+                    self.telemetry.resume_recording(&store_name, point, self.flush_policy)?;
+                    self.journal.record(JournalOp::StartRecording { store: store_name });
+                }
+                None => {
+                    self.start_recording(&store_name)?;
+                    summary.started.push(store_name);
+                }
+            }
+        }
+        for key in self.get_video_keys() {
+            self.start_recording_video(&key, 60)?;
+        }
+        println!(
+            "[recording] resumed {} stores from {}, {} started new",
+            summary.resumed.len(),
+            summary.session.display(),
+            summary.started.len()
+        );
+        Ok(summary)
+    }
+
+    /// A whole earlier session folder (see recording_library)
+    pub fn delete_recording(&self, path: &Path) -> Result<u64, String> {
+        recording_library::delete(path, &self.base_path)
+    }
+
+    pub fn stop_recording_all(&mut self) -> Result<(), String> {
+        self.recording.store(false, Ordering::Release);
+        self.packet_recorder.close();
+        self.manifest.record_recording_stop();
+        let store_names = self.get_store_names();
+        for store_name in store_names {
+            self.stop_recording(&store_name)?;
+        }
+        let stream_names = self.get_video_keys();
+        for key in stream_names {
+            self.stop_recording_video(&key)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_recording_status(&self) -> bool {
+        self.recording.load(Ordering::Acquire)
+    }
+
+    /// For the recordings started from now on, the ones running keep theirs (see recording_format)
+    pub fn set_recording_format(&mut self, format: RecordingFormat) {
+        self.recording_format = format;
+    }
+
+    pub fn get_recording_format(&self) -> RecordingFormat {
+        self.recording_format
+    }
+
+    /// For the files recordings open from now on (see recording_compression)
+    pub fn set_recording_compression(&mut self, compression: RecordingCompression) -> Result<(), String> {
+        compression.available()?;
+        self.recording_compression = compression;
+        Ok(())
+    }
+
+    pub fn get_recording_compression(&self) -> RecordingCompression {
+        self.recording_compression
+    }
+
+    /// Also for the recordings started from now on (see flush_policy)
+    pub fn set_flush_policy(&mut self, policy: FlushPolicy) -> Result<(), String> {
+        policy.validate()?;
+        self.flush_policy = policy;
+        Ok(())
+    }
+
+    pub fn get_flush_policy(&self) -> FlushPolicy {
+        self.flush_policy
+    }
+
+    /// Start recording at liftoff, with the seconds before it (see recording_arm)
+    pub fn arm_recording(&mut self, config: ArmConfig) -> Result<(), String> {
+        if self.get_recording_status() {
+            return Err("Already recording, there's nothing to arm".into());
+        }
+        self.arm.arm(config)?;
+        println!("[recording] armed for liftoff");
+        Ok(())
+    }
+
+    pub fn disarm_recording(&mut self) {
+        self.arm.disarm();
+    }
+
+    pub fn get_recording_arm(&self) -> ArmStatus {
+        self.arm.status()
+    }
+
+    // a no-op unless armed, so every way of detecting liftoff can call it
+    fn on_liftoff(&mut self, timestamp: i64) {
+        let Some(config) = self.arm.fire(timestamp) else {
+            return;
+        };
+        self.recording.store(true, Ordering::Release);
+        let since = timestamp - (config.pre_trigger_secs * 1000.0) as i64;
+        let mut rows = 0;
+        let store_names = self.get_store_names();
+        self.manifest.record_recording_start(store_names.clone());
+        for store_name in store_names {
+            let started = self.start_recording(&store_name).and_then(|_| self.telemetry.backfill(&store_name, since));
+            match started {
+                Ok(count) => rows += count,
+                Err(e) => eprintln!("[recording] {store_name} didn't start at liftoff: {e}"),
+            }
+        }
+        if config.video {
+            for key in self.get_video_keys() {
+                if let Err(e) = self.start_recording_video(&key, config.video_fps) {
+                    eprintln!("[recording] video {key} didn't start at liftoff: {e}");
+                }
+            }
+        }
+        println!("[recording] started at liftoff, {rows} rows from before it");
+        self.alerts.raise(
+            "recording.liftoff",
+            AlertSeverity::Info,
+            format!("Recording started at liftoff with {} s before it", config.pre_trigger_secs),
+        );
+    }
+
+    // also log every decoded frame to a .frames file while recording
+    pub fn set_packet_logging(&self, enabled: bool) {
+        self.packet_recorder.set_enabled(enabled);
+    }
+
+    pub fn get_packet_log_status(&self) -> PacketLogStatus {
+        self.packet_recorder.status()
+    }
+
+    pub fn record_packet(&self, source: &str, frame: &[u8]) {
+        if !self.get_recording_status() {
+            return;
+        }
+        if let Err(e) = self.packet_recorder.record(&self.base_path, source, frame, &self.encryption) {
+            eprintln!("[packet_recorder] Failed to log {source} packet: {e}");
+        }
+    }
+
+
+// ------------------------------------------------  Telemetry  ------------------------------------------------ //
+    pub fn push_data(&mut self, store_name: &str, field: &str, mut data: TelemetryData) -> Result<(), String> {
+        if !self.telemetry.has_store(store_name) {
+            self.create_new_store(store_name)?;
+            if self.launch_sessions.records(store_name) {
+                self.start_recording(store_name)?;
+            }
+        }
+        // println!("{} {} {:#?}", store_name, field, data); // holy prints
+        // counts as arrived even if it's shed below, the vehicle did send it
+        self.schemas.note(store_name, field);
+        if !self.shedder.admit(store_name, field) {
+            self.drops.note(&format!("shed.{store_name}"), 1);
+            return Ok(());
+        }
+        let (value, timestamp) = (data.value.as_f64(), data.timestamp);
+        let expected = self.metadata.range(store_name, field);
+        data.quality = self.quality.check(store_name, field, value, timestamp, data.quality, expected);
+        let change = self.changes.observe(store_name, field, &data);
+        self.black_box.record(store_name, field, &data);
+        // only pay for the clone if someone is listening
+        if self.live_points.receiver_count() > 0 {
+            let _ = self.live_points.send(TelemetryPoint {
+                store: store_name.to_string(),
+                field: field.to_string(),
+                data: data.clone(),
+            });
+        }
+        if self.journal.is_enabled() {
+            self.journal.record(JournalOp::Set {
+                store: store_name.to_string(),
+                field: field.to_string(),
+                timestamp: data.timestamp,
+                value: data.value.into(),
+                quality: data.quality,
+            });
+        }
+        #[cfg(feature = "sqlite")]
+        self.sqlite.record(store_name, field, &data);
+        self.telemetry.push(store_name, field, data)?;
+        self.alerts.evaluate(store_name, field, value, timestamp);
+        if self.arm.observe(store_name, field, value) {
+            self.on_liftoff(timestamp);
+        }
+        // once the point is in, so a listener that reads the field gets it
+        if let Some(change) = change {
+            self.emit(FIELD_CHANGED_EVENT, change);
+        }
+        if let Some(transition) = self.flight.evaluate(store_name, field, value, timestamp) {
+            self.on_flight_transition(transition);
+        }
+        for (derived, value, timestamp) in self.flight.take_derived() {
+            self.push_data(FLIGHT_STORE, &derived, TelemetryData::new().with_value(value).with_timestamp(timestamp))?;
+        }
+        self.gps_quality.observe(store_name, field, value, timestamp);
+        for (store, derived, value, timestamp) in self.gps_quality.take_derived() {
+            self.push_data(&store, derived, TelemetryData::new().with_value(value).with_timestamp(timestamp))?;
+        }
+        self.derived.observe(store_name, field, value, timestamp);
+        for (store, derived, value, timestamp) in self.derived.take_derived() {
+            self.push_data(&store, &derived, TelemetryData::new().with_value(value).with_timestamp(timestamp))?;
+        }
+        Ok(())
+    }
+
+    /// One field per leaf of a decoded packet (see flatten), all with the same timestamp
+    pub fn push_flattened<T: flatten::Flatten + ?Sized>(&mut self, store_name: &str, message: &T) -> Result<(), String> {
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        for (field, value) in flatten::flatten(message, "") {
+            self.push_data(store_name, &field, TelemetryData::new().with_value(value).with_timestamp(timestamp))?;
+        }
+        Ok(())
+    }
+
+    /// Every point pushed from now on, for sinks that forward telemetry live
+    pub fn subscribe_telemetry(&self) -> broadcast::Receiver<TelemetryPoint> {
+        self.live_points.subscribe()
+    }
+
+    pub fn get_last(&self, store_name: &str, field: &str
+    ) -> Result<Option<TelemetryData>, String> {
+        self.telemetry.get_last(store_name, field)
+    }
+
+    pub fn get_last_n(&self, store_name: &str, field: &str, n: usize
+    ) -> Result<Option<Vec<TelemetryData>>, String> {
+        self.telemetry.get_last_n(store_name, field, n)
+    }
+
+    /// The latest value of every "store.field" key in one go. Nothing can push between
+    /// the reads while we hold &self, so they're all as of the same instant; reading them
+    /// one command at a time could mix an altitude from one packet with a velocity from
+    /// the next.
+    pub fn get_latest_consistent(&self, keys: &[String], formatted: bool) -> Result<ConsistentSnapshot, String> {
+        let as_of = self.convert_timestamp(chrono::Utc::now().timestamp_millis());
+        let mut values = BTreeMap::new();
+        for key in keys {
+            // store names have no dots, field names can
+            let (store, field) = key
+                .split_once('.')
+                .ok_or_else(|| format!("'{key}' isn't a store.field key"))?;
+            let last = if self.telemetry.has_store(store) { self.get_last(store, field)? } else { None };
+            let value = last.map(|d| TelemetryDataFrontend {
+                timestamp: self.convert_timestamp(d.timestamp),
+                value: if formatted { self.format_value(store, field, &d.value) } else { d.value.to_string() },
+                quality: d.quality,
+            });
+            values.insert(key.clone(), value);
+        }
+        Ok(ConsistentSnapshot { as_of, values })
+    }
+
+    pub fn get_all(&self, store_name: &str, field: &str
+    ) -> Result<Vec<TelemetryData>, String> {
+        self.telemetry.get_all(store_name, field)
+    }
+
+    /// A handle onto the stores for reads that shouldn't wait on the middleware lock
+    pub fn telemetry_reader(&self) -> TelemetryReader {
+        TelemetryReader::new(self.telemetry.clone(), self.clock.clone(), self.formatter.clone())
+    }
+
+    pub fn get_store_names(&self) -> Vec<String> {
+        self.telemetry.list_stores()
+    }
+
+    pub fn get_field_names(&self, store_name: &str) -> Result<Vec<String>, String> {
+        self.telemetry.get_field_names(store_name)
+    }
+
+    pub fn start_recording(&self, store_name: &str) -> Result<(), String> {
+        self.telemetry.start_recording(
+            store_name,
+            &self.csv_header(store_name),
+            self.recording_format,
+            self.recording_compression,
+            self.flush_policy,
+        )?;
+        self.journal.record(JournalOp::StartRecording { store: store_name.to_string() });
+        Ok(())
+    }
+
+    /// The fields a store's recording CSV starts with, in order: what its producer
+    /// registered, then the mission profile's expected schema, then derived fields.
+    /// Fields that turn up without being in any of them are added after these.
+    pub fn csv_header(&self, store_name: &str) -> Vec<String> {
+        // a launch session's "l2/rocket" has the same packets as "rocket"
+        let (_, base_store) = launch_sessions::split_store(store_name);
+        let registered = self.store_schemas.get(base_store).into_iter().flatten();
+        let expected = self
+            .mission_profile
+            .as_ref()
+            .and_then(|p| p.expected_schema.get(base_store))
+            .into_iter()
+            .flatten();
+        let derived: Vec<String> = self
+            .derived
+            .fields()
+            .into_iter()
+            .filter(|f| f.store == store_name)
+            .map(|f| f.name)
+            .collect();
+        let mut header: Vec<String> = Vec::new();
+        for field in registered.chain(expected).chain(derived.iter()) {
+            if !header.contains(field) {
+                header.push(field.clone());
+            }
+        }
+        header
+    }
+
+    /// Declares the fields a producer pushes into `store_name`, for the CSV header
+    pub fn register_store_schema(&mut self, store_name: &str, fields: Vec<String>) {
+        self.store_schemas.insert(store_name.to_string(), fields);
+    }
+
+    pub fn stop_recording(&self, store_name: &str) -> Result<(), String> {
+        self.telemetry.stop_recording(store_name)?;
+        self.journal.record(JournalOp::StopRecording { store: store_name.to_string() });
+        Ok(())
+    }
+
+    pub fn get_store_path(&self, store_name: &str) -> Result<PathBuf, String> {
+        self.telemetry.store_path(store_name)
+    }
+
+    pub fn remove_store(&self, store_name: &str) -> Result<(), String> {
+        self.telemetry.remove_store(store_name)?;
+        self.journal.record(JournalOp::RemoveStore { store: store_name.to_string() });
+        Ok(())
+    }
+
+    // wipes every in-memory buffer, but only after the data is safely on disk
+    pub fn clear_all_telemetry(&self, force: bool, operator_role: &str) -> Result<DataAuditRecord, String> {
+        let recording = self.get_recording_status();
+        if recording && !force {
+            return Err("Stop recording before clearing telemetry, or force it".into());
+        }
+
+        let snapshot = self.telemetry.snapshot();
+        let points = snapshot.values().flat_map(|fields| fields.values()).map(Vec::len).sum();
+        // no recovery file, no clear
+        let recovery_file = self.write_snapshot("cleared_telemetry", &snapshot)?;
+
+        self.telemetry.clear_all();
+        self.journal.record(JournalOp::ClearAll);
+
+        let record = self.data_audit.record(
+            DataAction::ClearAllTelemetry {
+                forced: recording,
+                stores: snapshot.len(),
+                points,
+                recovery_file: recovery_file.display().to_string(),
+            },
+            operator_role,
+        );
+        self.clear_alert("data.cleared");
+        self.raise_alert(
+            "data.cleared",
+            AlertSeverity::Info,
+            format!("{} cleared {} telemetry points, saved to {}", operator_role, points, recovery_file.display()),
+        );
+        Ok(record)
+    }
+
+    /// Get everything onto disk right now, for when the laptop is about to die.
+    /// Queued CSV rows and black box points are flushed, recording video is cut into
+    /// a finished segment and a new one started, and the in-memory state is snapshotted.
+    pub fn emergency_flush(&self, reason: &str) -> Result<PathBuf, String> {
+        self.telemetry.sync_all();
+        self.black_box.flush();
+
+        // one stream failing to restart mustn't cost us the snapshot
+        let mut errors = Vec::new();
+        for key in self.get_video_keys() {
+            let Some(fps) = self.video_streams.recording_fps(&key) else {
+                continue;
+            };
+            // ffmpeg only writes a playable file once it's been stopped
+            if let Err(e) = self.stop_recording_video(&key).and_then(|_| self.start_recording_video(&key, fps)) {
+                eprintln!("[emergency_flush] video {key}: {e}");
+                errors.push(format!("video {key}: {e}"));
+            }
+        }
+
+        let snapshot_file = self.write_snapshot("emergency_snapshot", &self.telemetry.snapshot())?;
+
+        let mut message = format!("{}: data flushed, snapshot saved to {}", reason, snapshot_file.display());
+        if !errors.is_empty() {
+            message.push_str(&format!(" ({})", errors.join("; ")));
+        }
+        self.clear_alert("data.emergency_flush");
+        self.raise_alert("data.emergency_flush", AlertSeverity::Critical, message);
+        Ok(snapshot_file)
+    }
+
+    pub fn get_data_audit_log(&self) -> Vec<DataAuditRecord> {
+        self.data_audit.records()
+    }
+
+    // for backends to count their own drops against
+    pub fn drops(&self) -> SharedDrops {
+        self.drops.clone()
+    }
+
+    pub fn get_drop_report(&self, extra: Vec<DropSite>) -> DropReport {
+        self.drops.report(extra)
+    }
+
+    // backlog of every writer queue we own, for the resource monitor
+    pub fn get_queue_depths(&self) -> Vec<(String, usize)> {
+        let mut depths: Vec<(String, usize)> = self
+            .telemetry
+            .writer_queue_depths()
+            .into_iter()
+            .map(|(store, depth)| (format!("csv.{store}"), depth))
+            .collect();
+        depths.push(("black_box".to_string(), self.black_box.queue_depth()));
+        #[cfg(feature = "sqlite")]
+        depths.push(("sqlite".to_string(), self.sqlite.queue_depth()));
+        depths
+    }
+
+    pub fn get_black_box_files(&self) -> Vec<PathBuf> {
+        self.black_box.files()
+    }
+
+// ------------------------------------------------  VIDEO  ------------------------------------------------ //
+    pub fn process_video_frame(&self, name: &str, frame: Arc<VideoFrame>) -> Result<(), String> {
+        if !self.video_streams.has_stream(name) {
+            self.video_streams.create_stream(name);
+        }
+
+        self.video_streams.push_frame(name, frame)
+    }
+
+    pub fn get_latest_video_frame(
+    &self,
+    name: &str,
+) -> Option<VideoFrameFrontend> {
+    let frame = self.video_streams.latest_frame(name)?;
+
+    Some(VideoFrameFrontend {
+        timestamp: frame.timestamp,
+        data_base64: frame.to_frontend_base64(),
+        width: frame.width,
+        height: frame.height,
+    })
+}
+
+    pub fn get_video_keys(&self) -> Vec<String> {
+        self.video_streams.list_streams()
+    }
+
+    pub fn start_recording_video(&self, name: &str, fps: i32,) -> Result<(), String> {
+        let frame = self
+            .video_streams
+            .latest_frame(name)
+            .ok_or_else(|| "No video input! Cannot start recording".to_string())?;
+        self.video_streams.start_recording(name, self.create_video_path(name), frame.width, frame.height, fps)
+    }
+
+    pub fn stop_recording_video(&self, name: &str) -> Result<(), String> {
+        self.video_streams.stop_recording(name)
+    }
+
+    /// A photo reassembled off the radio: shown under `stream` like any video frame, and
+    /// the original JPEG kept in the session folder
+    pub fn publish_downlinked_image(&self, stream: &str, image_id: u16, jpeg: &[u8], frame: Arc<VideoFrame>) -> Result<PathBuf, String> {
+        let path = self.base_path.join("images").join(format!(
+            "{}_{}_{}.jpg",
+            stream,
+            image_id,
+            Local::now().format("%Y-%m-%d_%H-%M-%S")
+        ));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut file = self.encryption.create(&path)?;
+        file.write_all(jpeg).and_then(|_| file.flush()).map_err(|e| e.to_string())?;
+
+        self.emit(IMAGE_DOWNLINK_EVENT, DownlinkedImage {
+            stream: stream.to_string(),
+            image_id,
+            path: path.display().to_string(),
+            width: frame.width,
+            height: frame.height,
+        });
+        self.process_video_frame(stream, frame)?;
+        Ok(path)
+    }
+
+    pub fn get_video_path(&self, name: &str) -> Option<PathBuf> {
+        self.video_streams.video_path(name)
+    }
+
+    pub fn remove_video_stream(&self, name: &str) -> Result<(), String> {
+        self.video_streams.remove_stream(name)
+    }
+
+    // timelapses are generated in the background when the session is stopped
+    pub fn set_video_timelapse(&self, name: &str, speedup: Option<f64>) -> Result<(), String> {
+        self.video_streams.set_timelapse(name, speedup)
+    }
+
+// ------------------------------------------------  Alerts  ------------------------------------------------ //
+    pub fn raise_alert(&self, key: &str, severity: AlertSeverity, message: impl Into<String>) {
+        self.alerts.raise(key, severity, message)
+    }
+
+    pub fn clear_alert(&self, key: &str) {
+        self.alerts.clear(key)
+    }
+
+    pub fn get_alerts(&self) -> Vec<AlertFrontend> {
+        self.alerts
+            .list()
+            .into_iter()
+            .map(|alert| AlertFrontend {
+                audible: alert.is_audible(),
+                raised_time: self.timestamp_label(alert.raised_at),
+                alert,
+            })
+            .collect()
+    }
+
+    pub fn get_shelved_alerts(&self) -> Vec<AlertFrontend> {
+        self.alerts
+            .list_shelved()
+            .into_iter()
+            .map(|alert| AlertFrontend {
+                audible: false,
+                raised_time: self.timestamp_label(alert.raised_at),
+                alert,
+            })
+            .collect()
+    }
+
+    pub fn acknowledge_alert(&self, key: &str, operator_role: &str) -> Result<(), String> {
+        self.alerts.acknowledge(key, operator_role)
+    }
+
+    pub fn acknowledge_all_alerts(&self, operator_role: &str) {
+        self.alerts.acknowledge_all(operator_role)
+    }
+
+    pub fn shelve_alert(&self, key: &str, duration_ms: i64, operator_role: &str) -> Result<(), String> {
+        self.alerts.shelve(key, duration_ms, operator_role)
+    }
+
+    pub fn unshelve_alert(&self, key: &str, operator_role: &str) -> Result<(), String> {
+        self.alerts.unshelve(key, operator_role)
+    }
+
+    pub fn get_alert_audit_log(&self) -> Vec<AlertAuditRecord> {
+        self.alerts.audit_log()
+    }
+
+    pub fn import_alert_rules(&self, path: &Path) -> Result<String, String> {
+        let rule_set: AlertRuleSet = config_file::read_config_file(path)?;
+        self.alerts.add_rule_set(rule_set)
+    }
+
+    pub fn export_alert_rules(&self, name: &str, path: &Path) -> Result<(), String> {
+        let rule_set = self.alerts.get_rule_set(name)?;
+        config_file::write_config_file(path, &rule_set)
+    }
+
+    pub fn list_alert_rule_sets(&self) -> Vec<String> {
+        self.alerts.list_rule_sets()
+    }
+
+    pub fn get_active_alert_rule_set(&self) -> Option<String> {
+        self.alerts.active_rule_set()
+    }
+
+    pub fn activate_alert_rule_set(&self, name: Option<&str>) -> Result<(), String> {
+        self.alerts.activate_rule_set(name)
+    }
+
+// ------------------------------------------------  Mission Profile  ------------------------------------------------ //
+    pub fn load_mission_profile(&mut self, path: &Path) -> Result<MissionProfile, String> {
+        let profile: MissionProfile = config_file::read_config_file(path)?;
+        self.set_mission_profile(profile.clone())?;
+        Ok(profile)
+    }
+
+    pub fn set_mission_profile(&mut self, profile: MissionProfile) -> Result<(), String> {
+        derived_fields::validate(&profile.derived_fields)?;
+        if let Some(quality) = &profile.data_quality {
+            quality.validate()?;
+        }
+        for (key, metadata) in &profile.field_metadata {
+            metadata.validate(key)?;
+        }
+        if let Some((store, _)) = profile.packet_schemas.iter().find(|(_, fields)| fields.is_empty()) {
+            return Err(format!("The packet schema for '{store}' has no fields"));
+        }
+        if let Some(buffers) = &profile.buffers {
+            buffers.validate()?;
+        }
+        // bind the profile's vetted alarms, failing before anything changes if it's missing
+        self.alerts.activate_rule_set(profile.alert_rule_set.as_deref())?;
+        self.formatter.set_formats(profile.field_formats.clone());
+        self.flight.configure(profile.flight_detector.clone().unwrap_or_default());
+        self.derived.set_fields(profile.derived_fields.clone())?;
+        if let Some(quality) = &profile.data_quality {
+            self.quality.set_config(quality.clone())?;
+        }
+        if let Some(buffers) = &profile.buffers {
+            self.telemetry.set_buffer_config(buffers.clone())?;
+        }
+        // the profile's metadata goes over what the backends registered
+        self.metadata.extend(profile.field_metadata.clone())?;
+        for (store, fields) in &profile.packet_schemas {
+            self.set_expected_schema(store, Some(fields.clone()))?;
+        }
+        self.mission_profile = Some(profile);
+        Ok(())
+    }
+
+    pub fn get_mission_profile(&self) -> Option<MissionProfile> {
+        self.mission_profile.clone()
+    }
+
+    /// Compare the fields a store has received against the profile's expected schema,
+    /// raising a warning on any mismatch. None if there's no schema for this store.
+    pub fn check_schema(&self, store_name: &str) -> Result<Option<SchemaDiff>, String> {
+        let Some(profile) = &self.mission_profile else {
+            return Ok(None);
+        };
+        let received = self.get_field_names(store_name)?;
+        let Some(diff) = profile.schema_diff(store_name, &received) else {
+            return Ok(None);
+        };
+
+        let key = format!("schema.{store_name}");
+        if diff.is_clean() {
+            self.alerts.clear(&key);
+        } else {
+            self.alerts.raise(&key, AlertSeverity::Warning, diff.describe());
+        }
+        Ok(Some(diff))
+    }
+
+// ------------------------------------------------  Change events  ------------------------------------------------ //
+    /// Emit field_changed whenever a field matching `watched` changes (see change_events)
+    pub fn watch_field_changes(&mut self, watched: WatchedField) -> Result<(), String> {
+        self.changes.watch(watched)
+    }
+
+    pub fn unwatch_field_changes(&mut self, watched: &WatchedField) {
+        self.changes.unwatch(watched);
+    }
+
+    pub fn get_watched_fields(&self) -> Vec<WatchedField> {
+        self.changes.watched()
+    }
+
+    pub fn get_recent_changes(&self) -> Vec<FieldChange> {
+        self.changes.recent()
+    }
+
+// ------------------------------------------------  Packet schemas  ------------------------------------------------ //
+    /// The fields every packet into `store_name` needs (see schema_health), None stops
+    /// checking it
+    pub fn set_expected_schema(&mut self, store_name: &str, fields: Option<Vec<String>>) -> Result<(), String> {
+        self.schemas.set(store_name, fields)?;
+        self.clear_alert(&format!("schema.{store_name}.missing"));
+        Ok(())
+    }
+
+    pub fn get_expected_schemas(&self) -> BTreeMap<String, Vec<String>> {
+        self.schemas.all()
+    }
+
+    /// For producers, once a packet's fields are all pushed. A packet short of its
+    /// schema raises a warning, the next complete one clears it.
+    pub fn end_packet(&mut self, store_name: &str) {
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        let Some(missing) = self.schemas.end_packet(store_name, timestamp) else {
+            return;
+        };
+        let key = format!("schema.{store_name}.missing");
+        if missing.is_empty() {
+            self.clear_alert(&key);
+        } else {
+            self.raise_alert(&key, AlertSeverity::Warning, format!("'{}' packet missing {}", store_name, missing.join(", ")));
+        }
+    }
+
+    pub fn get_schema_health(&self) -> Vec<SchemaHealth> {
+        self.schemas.health()
+    }
+
+// ------------------------------------------------  Export  ------------------------------------------------ //
+    /// Write one store's buffered data out in `format`. The format's extension is
+    /// added if the path doesn't already have it. Returns where it went.
+    pub fn export_store(&self, store_name: &str, format: &str, path: &Path) -> Result<PathBuf, String> {
+        self.export_store_range(store_name, format, None, path)
+    }
+
+    // `range` is (start, end) unix ms, both ends included
+    pub fn export_store_range(&self, store_name: &str, format: &str, range: Option<(i64, i64)>, path: &Path) -> Result<PathBuf, String> {
+        let exporter = self.exporters.get(format)?;
+        let path = if path.extension().is_some_and(|e| e == exporter.extension()) {
+            path.to_path_buf()
+        } else {
+            path.with_extension(exporter.extension())
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let mut table = ExportTable::from_fields(store_name, &self.telemetry.store_snapshot(store_name)?);
+        if let Some((start, end)) = range {
+            table = table.within(start, end);
+        }
+        exporter.write(&table, &path)?;
+        Ok(path)
+    }
+
+    pub fn get_export_formats(&self) -> Vec<String> {
+        self.exporters.names()
+    }
+
+// ------------------------------------------------  Time base  ------------------------------------------------ //
+    pub fn set_time_base(&self, base: TimeBase) {
+        self.clock.send_modify(|c| c.base = base);
+    }
+
+    // None marks T-0 as right now
+    pub fn set_mission_t0(&self, t0: Option<i64>) {
+        let t0 = t0.unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+        self.clock.send_modify(|c| c.t0 = t0);
+    }
+
+    pub fn get_time_base(&self) -> TimeBaseConfig {
+        *self.clock.borrow()
+    }
+
+    // every timestamp going out to a window should come through here
+    pub fn convert_timestamp(&self, timestamp: i64) -> i64 {
+        self.clock.borrow().convert(timestamp)
+    }
+
+    pub fn timestamp_label(&self, timestamp: i64) -> String {
+        self.clock.borrow().label(timestamp)
+    }
+
+// ------------------------------------------------  Formatting  ------------------------------------------------ //
+    pub fn format_value(&self, store_name: &str, field: &str, value: &TelemetryValue) -> String {
+        self.formatter.format(store_name, field, value)
+    }
+
+    // None removes the format, back to the raw value
+    pub fn set_field_format(&mut self, key: &str, format: Option<FieldFormat>) {
+        self.formatter.set_format(key, format);
+    }
+
+    pub fn get_field_formats(&self) -> BTreeMap<String, FieldFormat> {
+        self.formatter.formats()
+    }
+
+// ------------------------------------------------  Field metadata  ------------------------------------------------ //
+    /// Units, display names and expected ranges by "store.field" or bare field name
+    /// (see field_metadata). Backends fill it in as they're set up.
+    pub fn register_field_metadata(&mut self, fields: BTreeMap<String, FieldMetadata>) -> Result<(), String> {
+        self.metadata.extend(fields)
+    }
+
+    // None removes it
+    pub fn set_field_metadata(&mut self, key: &str, metadata: Option<FieldMetadata>) -> Result<(), String> {
+        self.metadata.set(key, metadata)
+    }
+
+    pub fn get_field_metadata(&self) -> BTreeMap<String, FieldMetadata> {
+        self.metadata.all()
+    }
+
+    pub fn field_metadata(&self, store_name: &str, field: &str) -> Option<FieldMetadata> {
+        self.metadata.lookup(store_name, field).cloned()
+    }
+
+// ------------------------------------------------  Terrain  ------------------------------------------------ //
+    pub fn set_dem_directory(&mut self, path: PathBuf) {
+        self.elevation.set_dem_dir(path)
+    }
+
+    pub fn get_dem_directory(&self) -> PathBuf {
+        self.elevation.dem_dir().clone()
+    }
+
+    pub fn get_terrain_elevation(&self, lat: f64, lon: f64) -> Option<f64> {
+        self.elevation.elevation(lat, lon)
+    }
+
+    /// Latest position of a vehicle with the terrain height under it
+    pub fn get_height_above_terrain(&self, store_name: &str) -> Result<Option<TerrainPoint>, String> {
+        let lat = self.get_last(store_name, "lat")?;
+        let lon = self.get_last(store_name, "lon")?;
+        let alt = self.get_last(store_name, "alt")?;
+
+        Ok(match (lat, lon, alt) {
+            (Some(lat), Some(lon), Some(alt)) => Some(self.elevation.terrain_point(
+                lat.timestamp,
+                lat.value.as_f64(),
+                lon.value.as_f64(),
+                alt.value.as_f64(),
+            )),
+            _ => None,
+        })
+    }
+
+    /// Terrain profile under the last `count` GPS fixes (all of them if None)
+    pub fn get_track_terrain(&self, store_name: &str, count: Option<usize>) -> Result<Vec<TerrainPoint>, String> {
+        let fetch = |field: &str| -> Result<Vec<TelemetryData>, String> {
+            match count {
+                Some(n) => Ok(self.get_last_n(store_name, field, n)?.unwrap_or_default()),
+                None => self.get_all(store_name, field),
+            }
+        };
+        let (lat, lon, alt) = (fetch("lat")?, fetch("lon")?, fetch("alt")?);
+
+        // lat/lon/alt are pushed together for every fix, so they line up by index
+        Ok(lat
+            .iter()
+            .zip(lon.iter())
+            .zip(alt.iter())
+            .map(|((lat, lon), alt)| self.elevation.terrain_point(
+                lat.timestamp,
+                lat.value.as_f64(),
+                lon.value.as_f64(),
+                alt.value.as_f64(),
+            ))
+            .collect())
+    }
+
+// ------------------------------------------------  Recovery  ------------------------------------------------ //
+    /// One-shot snapshot for the recovery crew, recomputed from the latest data every call
+    pub fn get_recovery_bundle(&self, store_name: &str) -> Result<RecoveryBundle, String> {
+        let now = chrono::Utc::now().timestamp_millis();
+        // missing fields just mean that part of the bundle is unknown
+        let history = |field: &str| self.get_all(store_name, field).unwrap_or_default();
+        let latest = |field: &str| self.get_last(store_name, field).ok().flatten();
+
+        let fixes = recovery::gps_fixes(&history("lat"), &history("lon"), &history("alt"));
+        let landed_at = recovery::landing_time(&history("state"));
+
+        Ok(RecoveryBundle {
+            store: store_name.to_string(),
+            generated_at: now,
+            last_fix: fixes.last().copied(),
+            satellites: latest("satellites").map(|d| d.value.as_f64() as u32),
+            predicted_landing: recovery::predict_landing(&fixes, landed_at.is_some(), &self.elevation),
+            // where the tracker was last jogged to, i.e. peaked on the signal
+            rssi_bearing: self.get_last("tracker", "commanded_azimuth").ok().flatten().map(|d| d.value.as_f64()),
+            battery_voltage: latest("battery_voltage").map(|d| d.value.as_f64()),
+            landed_at,
+            elapsed_since_landing_ms: landed_at.map(|t| now - t),
+        })
+    }
+
+// ------------------------------------------------  Packet inspector  ------------------------------------------------ //
+    pub fn record_frame(&self, source: &str, frame: InspectedFrame) {
+        self.packet_log.record(source, frame)
+    }
+
+    pub fn inspect_last_packets(&self, source: &str, n: usize) -> Result<Vec<InspectedFrame>, String> {
+        self.packet_log.last(source, n)
+    }
+
+    pub fn get_packet_sources(&self) -> Vec<String> {
+        self.packet_log.sources()
+    }
+
+// ------------------------------------------------  Link stats  ------------------------------------------------ //
+    pub fn record_packet_stats(&mut self, stream: &str, timestamp: i64) {
+        self.packet_stats.record_packet(stream, timestamp)
+    }
+
+    pub fn record_crc_failure(&mut self, stream: &str) {
+        self.packet_stats.record_crc_failure(stream)
+    }
+
+    pub fn get_link_stats(&self) -> Vec<PacketStreamStats> {
+        self.packet_stats.streams(chrono::Utc::now().timestamp_millis())
+    }
+
+    pub fn get_stream_link_stats(&self, stream: &str) -> Option<PacketStreamStats> {
+        self.packet_stats.stream(stream, chrono::Utc::now().timestamp_millis())
+    }
+
+// ------------------------------------------------  Decode errors  ------------------------------------------------ //
+    /// Data for `error.stream` that didn't decode and was dropped: counted in that store
+    /// as decode_errors and as a drop, and sent to the operator as a decode_error event
+    pub fn report_decode_error(&mut self, mut error: DecodeError) {
+        error.count = self.decode_errors.note(&error.stream);
+        let _ = self.push_data(
+            &error.stream,
+            "decode_errors",
+            TelemetryData::new().with_timestamp(error.timestamp).with_value(error.count),
+        );
+        self.drops.note(&format!("decode.{}", error.stream), 1);
+        self.emit(DECODE_ERROR_EVENT, error);
+    }
+
+// ------------------------------------------------  Load shedding  ------------------------------------------------ //
+    /// One sample from the resource monitor. Emits LOAD_SHEDDING_EVENT when the level moves.
+    pub fn observe_load(&mut self, cpu_percent: Option<f64>, queue_depth: usize) {
+        let now = chrono::Utc::now().timestamp_millis();
+        if let Some(level) = self.shedder.observe(cpu_percent, queue_depth, now) {
+            println!("[load_shedding] Level {level} (cpu {cpu_percent:?}%, queue {queue_depth})");
+            self.emit(LOAD_SHEDDING_EVENT, self.shedder.status());
+        }
+    }
+
+    pub fn load_shedding_status(&self) -> LoadSheddingStatus {
+        self.shedder.status()
+    }
+
+    pub fn stream_priorities(&self) -> LoadSheddingConfig {
+        self.shedder.config()
+    }
+
+    pub fn set_stream_priorities(&mut self, config: LoadSheddingConfig) -> Result<(), String> {
+        self.shedder.set_config(config)
+    }
+
+// ------------------------------------------------  Buffers  ------------------------------------------------ //
+    /// How many points, or how long, each stream keeps in memory (see buffer_policy)
+    pub fn get_buffer_config(&self) -> BufferConfig {
+        self.telemetry.buffer_config()
+    }
+
+    pub fn set_buffer_config(&self, config: BufferConfig) -> Result<(), String> {
+        self.telemetry.set_buffer_config(config)
+    }
+
+// ------------------------------------------------  Recording rotation  ------------------------------------------------ //
+    /// When a store's CSV rolls over to a new segment (see csv_rotation)
+    pub fn get_recording_rotation(&self) -> RotationConfig {
+        self.telemetry.rotation()
+    }
+
+    pub fn set_recording_rotation(&self, config: RotationConfig) -> Result<(), String> {
+        self.telemetry.set_rotation(config)
+    }
+
+    pub fn get_recording_segments(&self, store_name: &str) -> Result<Vec<RecordingSegment>, String> {
+        self.telemetry.segments(store_name)
+    }
+
+// ------------------------------------------------  Lock timing  ------------------------------------------------ //
+    /// Turns the lock overruns since the last check into alerts, one per lock, and
+    /// clears the alerts of locks that stayed in budget. Called by the resource monitor.
+    pub fn check_lock_budgets(&mut self) -> Vec<LockOverrun> {
+        let overruns = lock_timing::take_overruns();
+        let mut worst: BTreeMap<&'static str, &LockOverrun> = BTreeMap::new();
+        for overrun in &overruns {
+            let entry = worst.entry(overrun.name).or_insert(overrun);
+            if overrun.held_ms > entry.held_ms {
+                *entry = overrun;
+            }
+        }
+        for (name, overrun) in &worst {
+            self.alerts.raise(
+                &format!("lock.{name}"),
+                AlertSeverity::Warning,
+                format!(
+                    "Lock '{name}' was held for {:.1} ms, over its {} ms budget",
+                    overrun.held_ms, overrun.budget_ms
+                ),
+            );
+            self.lock_alerts.insert(name);
+        }
+        let calm: Vec<&'static str> = self.lock_alerts.iter().copied().filter(|name| !worst.contains_key(name)).collect();
+        for name in calm {
+            self.alerts.clear(&format!("lock.{name}"));
+            self.lock_alerts.remove(name);
+        }
+        overruns
+    }
+
+    pub fn get_lock_stats(&self) -> Vec<LockStats> {
+        lock_timing::stats()
+    }
+
+    pub fn set_lock_budget(&self, config: LockBudgetConfig) -> Result<(), String> {
+        lock_timing::set_budget(config)
+    }
+
+    pub fn get_lock_budget(&self) -> LockBudgetConfig {
+        lock_timing::budget()
+    }
+
+// ------------------------------------------------  Journal  ------------------------------------------------ //
+    /// Starts or stops journaling every store change (see journal). Starting writes
+    /// what's in memory first, so the journal can rebuild the stores on its own.
+    pub fn set_journal_enabled(&self, enabled: bool) -> Result<(), String> {
+        if !enabled {
+            self.journal.stop();
+            return Ok(());
+        }
+        if self.journal.is_enabled() {
+            return Ok(());
+        }
+        let mut baseline = Vec::new();
+        for (store, fields) in self.telemetry.snapshot() {
+            baseline.push(JournalOp::CreateStore { store: store.clone() });
+            if self.telemetry.is_recording(&store)? {
+                baseline.push(JournalOp::StartRecording { store: store.clone() });
+            }
+            for (field, data) in fields {
+                baseline.extend(data.into_iter().map(|d| JournalOp::Set {
+                    store: store.clone(),
+                    field: field.clone(),
+                    timestamp: d.timestamp,
+                    value: d.value.into(),
+                    quality: d.quality,
+                }));
+            }
+        }
+        self.journal.start(&self.base_path, &self.encryption, baseline)
+    }
+
+    pub fn get_journal_status(&self) -> JournalStatus {
+        self.journal.status()
+    }
+
+// ------------------------------------------------  Snapshots  ------------------------------------------------ //
+    /// What the snapshot task writes with (see store_snapshot), it doesn't need us locked
+    pub fn snapshot_writer(&self) -> SnapshotWriter {
+        SnapshotWriter::new(self.telemetry.clone(), &self.base_path, self.encryption.clone())
+    }
+
+    /// Puts a snapshot's points back into the buffers, from the newest earlier session
+    /// if `path` is None. Stores it has that aren't here yet are created.
+    pub fn restore_snapshot(&self, path: Option<&Path>) -> Result<RestoreSummary, String> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => store_snapshot::latest_previous(&self.base_path)?,
+        };
+        let snapshot = store_snapshot::read(&path)?;
+
+        let mut restored = BTreeMap::new();
+        let mut skipped = 0;
+        for (store, fields) in snapshot.stores {
+            if !self.telemetry.has_store(&store) {
+                self.create_new_store(&store)?;
+            }
+            let mut points = 0;
+            for (field, data) in fields {
+                let total = data.len();
+                let kept = self.telemetry.restore(&store, &field, store_snapshot::into_data(data))?;
+                points += kept;
+                skipped += total.saturating_sub(kept);
+            }
+            restored.insert(store, points);
+        }
+
+        let points: usize = restored.values().sum();
+        println!("[snapshot] Restored {} points from {} ({} skipped)", points, path.display(), skipped);
+        self.clear_alert("data.snapshot_restored");
+        self.raise_alert(
+            "data.snapshot_restored",
+            AlertSeverity::Info,
+            format!("Restored {} telemetry points from {}", points, path.display()),
+        );
+        Ok(RestoreSummary { path: path.display().to_string(), taken_at: snapshot.taken_at, restored, skipped })
+    }
+
+// ------------------------------------------------  SQLite  ------------------------------------------------ //
+    /// Starts or stops writing every point to the session's SQLite database (see
+    /// sqlite_store). Starting writes what's in memory first.
+    #[cfg(feature = "sqlite")]
+    pub fn set_sqlite_enabled(&self, enabled: bool) -> Result<(), String> {
+        if !enabled {
+            self.sqlite.stop();
+            return Ok(());
+        }
+        if self.sqlite.is_enabled() {
+            return Ok(());
+        }
+        // the database is plain SQLite, it can't keep an encrypted session's promise
+        if self.encryption.is_enabled() {
+            return Err("The SQLite store isn't encrypted, turn session encryption off to use it".into());
+        }
+        let mut baseline = Vec::new();
+        for (store, fields) in self.telemetry.snapshot() {
+            for (field, data) in fields {
+                baseline.extend(data.into_iter().map(|d| (store.clone(), field.clone(), d)));
+            }
+        }
+        self.sqlite.start(&self.base_path.join(sqlite_store::DATABASE_NAME), baseline)
+    }
+
+    #[cfg(feature = "sqlite")]
+    pub fn get_sqlite_status(&self) -> SqliteStatus {
+        self.sqlite.status()
+    }
+
+// ------------------------------------------------  Utility  ------------------------------------------------ //
+
+    // one CSV per store, named after it (rocket.csv, payload.csv), in the session folder.
+    // A launch session's stores ("l2/rocket") go in a folder of their own.
+    fn create_new_store(&self, store_name: &str) -> Result<(), String> {
+        let path = session_naming::telemetry_file(&self.base_path, store_name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("{}: {e}", parent.display()))?;
+        }
+        self.telemetry.create_new_store(store_name, path)?;
+        self.journal.record(JournalOp::CreateStore { store: store_name.to_string() });
+        Ok(())
+    }
+
+    /// Where a raw byte capture from `source` started now goes, next to the session's CSVs
+    pub fn raw_capture_path(&self, source: &str) -> PathBuf {
+        session_naming::raw_file(&self.base_path, source)
+    }
+
+    fn write_snapshot<T: Serialize>(&self, prefix: &str, snapshot: &T) -> Result<PathBuf, String> {
+        let path = self.base_path.join(format!(
+            "{}_{}.json",
+            prefix,
+            Local::now().format("%Y-%m-%d_%H-%M-%S")
+        ));
+        config_file::write_config_file(&path, snapshot)?;
+        Ok(path)
+    }
+
+    fn create_video_path(&self, name: &str) -> PathBuf {
+        session_naming::video_file(&self.base_path, name)
+    }
+
+
+}
\ No newline at end of file
@@ -0,0 +1,95 @@
+// Recent raw frames per source, for debugging framing problems in the field
+use dashmap::DashMap;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+// per source, older frames fall off the back
+const MAX_FRAMES: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InspectedFrame {
+    pub timestamp: i64,
+    pub length: usize,
+    // space separated, e.g. "4b 56 30 52 1c ..."
+    pub hex: String,
+    pub decoded: bool,
+    pub packet_type: Option<String>,
+    pub error: Option<String>,
+    // the decoder's view of every field
+    pub fields: Option<String>,
+}
+
+impl InspectedFrame {
+    pub fn decoded(timestamp: i64, raw: &[u8], packet_type: String, fields: String) -> Self {
+        Self {
+            timestamp,
+            length: raw.len(),
+            hex: to_hex(raw),
+            decoded: true,
+            packet_type: Some(packet_type),
+            error: None,
+            fields: Some(fields),
+        }
+    }
+
+    pub fn failed(timestamp: i64, raw: &[u8], error: String) -> Self {
+        Self {
+            timestamp,
+            length: raw.len(),
+            hex: to_hex(raw),
+            decoded: false,
+            packet_type: None,
+            error: Some(error),
+            fields: None,
+        }
+    }
+}
+
+pub struct PacketLog {
+    sources: DashMap<String, VecDeque<InspectedFrame>>,
+}
+
+impl Default for PacketLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PacketLog {
+    pub fn new() -> Self {
+        Self { sources: DashMap::new() }
+    }
+
+    pub fn record(&self, source: &str, frame: InspectedFrame) {
+        let mut frames = self
+            .sources
+            .entry(source.to_string())
+            .or_insert_with(|| VecDeque::with_capacity(MAX_FRAMES));
+        if frames.len() == MAX_FRAMES {
+            frames.pop_front();
+        }
+        frames.push_back(frame);
+    }
+
+    /// Newest last
+    pub fn last(&self, source: &str, n: usize) -> Result<Vec<InspectedFrame>, String> {
+        let frames = self
+            .sources
+            .get(source)
+            .ok_or_else(|| format!("No frames from '{}'", source))?;
+        let skip = frames.len().saturating_sub(n);
+        Ok(frames.iter().skip(skip).cloned().collect())
+    }
+
+    pub fn sources(&self) -> Vec<String> {
+        self.sources.iter().map(|s| s.key().clone()).collect()
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
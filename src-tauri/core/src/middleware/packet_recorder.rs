@@ -0,0 +1,109 @@
+// Decoded packets, byte for byte, next to the CSVs
+//
+// The CSVs round values and flatten the packets into columns, which is fine to plot
+// but can't reproduce a flight exactly. With packet logging on, every frame that
+// decoded is also appended to <source>_packets_<time>.frames while recording runs:
+// the whole frame as it came off the link framing, as a varint length-delimited
+// record. That's the .frames format the radio already replays, so loading the file
+// as a replay capture puts the same bytes through the same decoder again.
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::Local;
+use serde::Serialize;
+
+use super::encryption::{SessionEncryption, SessionWriter};
+use super::lock_timing::TimedMutex;
+use crate::framing::length_delimited;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PacketLogStatus {
+    pub enabled: bool,
+    pub files: Vec<PacketLogFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PacketLogFile {
+    pub source: String,
+    pub path: String,
+    pub packets: u64,
+}
+
+struct PacketLogWriter {
+    path: PathBuf,
+    file: BufWriter<SessionWriter>,
+    packets: u64,
+}
+
+pub struct PacketRecorder {
+    enabled: AtomicBool,
+    // one open log per source while recording, closed when recording stops
+    writers: TimedMutex<HashMap<String, PacketLogWriter>>,
+}
+
+impl Default for PacketRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PacketRecorder {
+    pub fn new() -> Self {
+        Self { enabled: AtomicBool::new(false), writers: TimedMutex::new("packet_recorder", HashMap::new()) }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Release);
+        if !enabled {
+            self.close();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    pub fn record(&self, dir: &Path, source: &str, frame: &[u8], encryption: &SessionEncryption) -> Result<(), String> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let mut writers = self.writers.lock();
+        if !writers.contains_key(source) {
+            let path = dir.join(format!("{source}_packets_{}.frames", Local::now().format("%Y-%m-%d_%H-%M-%S")));
+            let file = encryption.create(&path)?;
+            println!("[packet_recorder] Logging {source} packets to {}", path.display());
+            writers.insert(source.to_string(), PacketLogWriter { path, file: BufWriter::new(file), packets: 0 });
+        }
+        let writer = writers.get_mut(source).expect("inserted above");
+        length_delimited::write_delimited(&mut writer.file, frame).map_err(|e| e.to_string())?;
+        // flush every packet so a crash mid-flight still leaves a usable log
+        writer.file.flush().map_err(|e| e.to_string())?;
+        writer.packets += 1;
+        Ok(())
+    }
+
+    // the next recording starts fresh files
+    pub fn close(&self) {
+        for (_, mut writer) in self.writers.lock().drain() {
+            if let Err(e) = writer.file.flush() {
+                eprintln!("[packet_recorder] Failed to flush {}: {e}", writer.path.display());
+            }
+        }
+    }
+
+    pub fn status(&self) -> PacketLogStatus {
+        let writers = self.writers.lock();
+        let mut files: Vec<_> = writers
+            .iter()
+            .map(|(source, writer)| PacketLogFile {
+                source: source.clone(),
+                path: writer.path.display().to_string(),
+                packets: writer.packets,
+            })
+            .collect();
+        files.sort_by(|a, b| a.source.cmp(&b.source));
+        PacketLogStatus { enabled: self.is_enabled(), files }
+    }
+}
@@ -0,0 +1,91 @@
+// Link health per stream (one radio's packets), for seeing at a glance how the link is doing
+//
+// Per stream: packets per second over the last RATE_WINDOW_MS, the gap between packets
+// (last and longest) and CRC failures. The pinned telemetry-2026 Packet carries no
+// sequence number, so packets that never arrived can't be counted, only the silences.
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+
+pub const LINK_STATS_EVENT: &str = "link_stats";
+const RATE_WINDOW_MS: i64 = 5000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PacketStreamStats {
+    pub stream: String,
+    pub packets: u64,
+    pub packets_per_sec: f64,
+    pub last_packet_at: Option<i64>,
+    // between the last two packets, and the longest seen
+    pub last_gap_ms: Option<i64>,
+    pub max_gap_ms: Option<i64>,
+    // since the last packet, growing while the link is quiet
+    pub silence_ms: Option<i64>,
+    pub crc_failures: u64,
+}
+
+#[derive(Default)]
+struct StreamStats {
+    packets: u64,
+    recent: VecDeque<i64>,
+    last_packet_at: Option<i64>,
+    last_gap_ms: Option<i64>,
+    max_gap_ms: Option<i64>,
+    crc_failures: u64,
+}
+
+impl StreamStats {
+    fn prune(&mut self, now: i64) {
+        while self.recent.front().is_some_and(|&t| now - t > RATE_WINDOW_MS) {
+            self.recent.pop_front();
+        }
+    }
+
+    fn report(&self, stream: &str, now: i64) -> PacketStreamStats {
+        let in_window = self.recent.iter().filter(|&&t| now - t <= RATE_WINDOW_MS).count();
+        PacketStreamStats {
+            stream: stream.to_string(),
+            packets: self.packets,
+            packets_per_sec: in_window as f64 * 1000.0 / RATE_WINDOW_MS as f64,
+            last_packet_at: self.last_packet_at,
+            last_gap_ms: self.last_gap_ms,
+            max_gap_ms: self.max_gap_ms,
+            silence_ms: self.last_packet_at.map(|t| (now - t).max(0)),
+            crc_failures: self.crc_failures,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PacketStats {
+    streams: HashMap<String, StreamStats>,
+}
+
+impl PacketStats {
+    /// A packet that decoded, our own uplink heard back included
+    pub fn record_packet(&mut self, stream: &str, timestamp: i64) {
+        let stats = self.streams.entry(stream.to_string()).or_default();
+        stats.packets += 1;
+        if let Some(last) = stats.last_packet_at {
+            let gap = (timestamp - last).max(0);
+            stats.last_gap_ms = Some(gap);
+            stats.max_gap_ms = Some(stats.max_gap_ms.map_or(gap, |max| max.max(gap)));
+        }
+        stats.last_packet_at = Some(timestamp);
+        stats.recent.push_back(timestamp);
+        stats.prune(timestamp);
+    }
+
+    pub fn record_crc_failure(&mut self, stream: &str) {
+        self.streams.entry(stream.to_string()).or_default().crc_failures += 1;
+    }
+
+    pub fn stream(&self, stream: &str, now: i64) -> Option<PacketStreamStats> {
+        self.streams.get(stream).map(|s| s.report(stream, now))
+    }
+
+    pub fn streams(&self, now: i64) -> Vec<PacketStreamStats> {
+        let mut streams: Vec<_> = self.streams.iter().map(|(name, s)| s.report(name, now)).collect();
+        streams.sort_by(|a, b| a.stream.cmp(&b.stream));
+        streams
+    }
+}
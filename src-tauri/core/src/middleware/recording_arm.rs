@@ -0,0 +1,103 @@
+// Arm the recording on the pad and let liftoff start it
+//
+// Armed, nothing is written until the ground side flight state leaves the pad (see
+// flight_state, launch_altitude_m), or sooner if an acceleration trigger is set and a
+// point on that field reaches it:
+//   { "pre_trigger_secs": 10, "video": true, "accel": { "store": "rocket", "field": "accel_z", "threshold_mps2": 30 } }
+// Then every store starts recording (in the format last used) and the buffered rows
+// from pre_trigger_secs before the trigger are written first, so the seconds before
+// liftoff aren't lost. That's only as far back as the buffer_policy keeps. Video has
+// no buffer, with video set the cameras start at the trigger. It fires once and disarms.
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccelTrigger {
+    pub store: String,
+    pub field: String,
+    pub threshold_mps2: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArmConfig {
+    #[serde(default = "default_pre_trigger")]
+    pub pre_trigger_secs: f64,
+    #[serde(default)]
+    pub video: bool,
+    #[serde(default = "default_video_fps")]
+    pub video_fps: i32,
+    #[serde(default)]
+    pub accel: Option<AccelTrigger>,
+}
+
+fn default_pre_trigger() -> f64 {
+    10.0
+}
+
+fn default_video_fps() -> i32 {
+    60
+}
+
+impl ArmConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.pre_trigger_secs.is_finite() || self.pre_trigger_secs < 0.0 {
+            return Err("pre_trigger_secs can't be negative".into());
+        }
+        if self.video && self.video_fps <= 0 {
+            return Err("video_fps has to be above 0".into());
+        }
+        if let Some(accel) = &self.accel {
+            if accel.store.is_empty() || accel.field.is_empty() {
+                return Err("The acceleration trigger needs a store and a field".into());
+            }
+            if !accel.threshold_mps2.is_finite() || accel.threshold_mps2 <= 0.0 {
+                return Err("The acceleration trigger needs a threshold above 0".into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArmStatus {
+    pub armed: bool,
+    pub config: Option<ArmConfig>,
+    // when the last trigger fired, unix ms
+    pub triggered_at: Option<i64>,
+}
+
+#[derive(Default)]
+pub struct RecordingArm {
+    config: Option<ArmConfig>,
+    triggered_at: Option<i64>,
+}
+
+impl RecordingArm {
+    pub fn arm(&mut self, config: ArmConfig) -> Result<(), String> {
+        config.validate()?;
+        self.config = Some(config);
+        Ok(())
+    }
+
+    pub fn disarm(&mut self) {
+        self.config = None;
+    }
+
+    pub fn status(&self) -> ArmStatus {
+        ArmStatus { armed: self.config.is_some(), config: self.config.clone(), triggered_at: self.triggered_at }
+    }
+
+    /// True when `value` sets off the acceleration trigger
+    pub fn observe(&self, store: &str, field: &str, value: f64) -> bool {
+        let Some(accel) = self.config.as_ref().and_then(|c| c.accel.as_ref()) else {
+            return false;
+        };
+        accel.store == store && accel.field == field && value >= accel.threshold_mps2
+    }
+
+    /// Disarms and hands back what to start, None if it wasn't armed
+    pub fn fire(&mut self, timestamp: i64) -> Option<ArmConfig> {
+        let config = self.config.take()?;
+        self.triggered_at = Some(timestamp);
+        Some(config)
+    }
+}
@@ -0,0 +1,96 @@
+// Gzipping recordings as they're written, so a range day of pad holds fits on the laptop
+//
+// Off by default. With gzip on, a recording's files are written as rocket.csv.gz,
+// rocket_0002.csv.gz and rocket.jsonl.gz, compressed in the store's writer task so it
+// costs push_data nothing. Each flush is a deflate sync flush, so what's been flushed
+// decompresses even if the app dies before the file is finished. With encryption on
+// as well the file is compressed first, then encrypted. open reads both back, and
+// playback loads .csv.gz like .csv. A compressed recording can't be resumed, and
+// rotation's max_mb counts the size before compression.
+//
+// Like encryption, it applies to files opened afterwards: a CSV that has written its
+// header keeps going as it is and the change takes effect at the next segment.
+//
+// Gzip needs the compression feature. Without it only None can be chosen and a .gz
+// recording can't be opened.
+#[cfg(feature = "compression")]
+use flate2::read::MultiGzDecoder;
+#[cfg(feature = "compression")]
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::encryption::{self, SessionReader, SessionWriter};
+
+const EXTENSION: &str = "gz";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingCompression {
+    #[default]
+    None,
+    Gzip,
+}
+
+impl RecordingCompression {
+    // whether this build can write it
+    pub fn available(&self) -> Result<(), String> {
+        match self {
+            RecordingCompression::Gzip if !cfg!(feature = "compression") => {
+                Err("This build has no gzip recording (the compression feature is off)".into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // where a file at `path` actually goes, rocket.csv -> rocket.csv.gz
+    pub fn file_path(&self, path: &Path) -> PathBuf {
+        match self {
+            RecordingCompression::None => path.to_path_buf(),
+            RecordingCompression::Gzip => {
+                let mut name = path.as_os_str().to_owned();
+                name.push(".");
+                name.push(EXTENSION);
+                PathBuf::from(name)
+            }
+        }
+    }
+
+    pub fn wrap(&self, writer: SessionWriter) -> SessionWriter {
+        match self {
+            RecordingCompression::None => writer,
+            #[cfg(feature = "compression")]
+            RecordingCompression::Gzip => Box::new(GzEncoder::new(writer, flate2::Compression::default())),
+            // set_recording_compression checks available() first
+            #[cfg(not(feature = "compression"))]
+            RecordingCompression::Gzip => unreachable!("gzip recording without the compression feature"),
+        }
+    }
+}
+
+/// As encryption::open, gunzipping a .gz file on the way
+pub fn open(path: &Path) -> Result<SessionReader, String> {
+    let reader = encryption::open(path)?;
+    if path.extension().is_some_and(|ext| ext == EXTENSION) {
+        return gunzip(path, reader);
+    }
+    Ok(reader)
+}
+
+#[cfg(feature = "compression")]
+fn gunzip(_path: &Path, reader: SessionReader) -> Result<SessionReader, String> {
+    Ok(Box::new(MultiGzDecoder::new(reader)))
+}
+
+#[cfg(not(feature = "compression"))]
+fn gunzip(path: &Path, _reader: SessionReader) -> Result<SessionReader, String> {
+    Err(format!("{} is gzipped and this build has no compression", path.display()))
+}
+
+// "rocket.csv.gz" or "rocket.csv" -> "rocket"
+pub fn recording_stem(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy();
+    let name = name.strip_suffix(".gz").unwrap_or(&name);
+    let stem = Path::new(name).file_stem()?;
+    Some(stem.to_string_lossy().into_owned())
+}
@@ -0,0 +1,48 @@
+// What a store's recording is written as
+//
+// CSV is the default and what everything downstream reads. It turns every value into
+// a string and every nested field into a dotted column. JSON Lines writes the same
+// rows to <store>.jsonl next to where the CSV would be, one object per timestamp, with
+// numbers and bools kept as their types and the dotted fields nested back up:
+//   {"timestamp":1760000000000,"time":"...","fields":{"alt":1203.5,"gps_lock":true,"sensors":{"lps22":{"pressure":88213.0}}}}
+// A vector's elements stay keyed by index ("covariance":{"0":...}). Non-finite numbers
+// come out as null, JSON has no NaN. Rotation applies to the CSVs only.
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::telemetry_stores::TelemetryValue;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingFormat {
+    #[default]
+    Csv,
+    Jsonl,
+}
+
+/// One row as a JSON line, without the newline. Fields with no value yet are left out.
+pub fn json_line(timestamp: i64, time: &str, values: Vec<(String, Option<TelemetryValue>)>) -> String {
+    let mut fields = Map::new();
+    for (field, value) in values {
+        if let Some(value) = value {
+            insert_nested(&mut fields, &field, serde_json::to_value(value).unwrap_or(Value::Null));
+        }
+    }
+    let mut row = Map::new();
+    row.insert("timestamp".into(), timestamp.into());
+    row.insert("time".into(), time.into());
+    row.insert("fields".into(), Value::Object(fields));
+    Value::Object(row).to_string()
+}
+
+// "a.b.c" goes in as {"a":{"b":{"c":..}}}, unless "a" is already a plain value
+fn insert_nested(map: &mut Map<String, Value>, key: &str, value: Value) {
+    if let Some((head, rest)) = key.split_once('.') {
+        let slot = map.entry(head.to_string()).or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(inner) = slot {
+            insert_nested(inner, rest, value);
+            return;
+        }
+    }
+    map.insert(key.to_string(), value);
+}
@@ -0,0 +1,224 @@
+// The station's earlier recordings, for the flight library
+//
+// A recording is a session folder with store CSVs in it: rocket.csv, its rotated
+// segments, the .gz versions and a launch session's <id>/rocket.csv. Each one is
+// listed with its size on disk (video and all), the stores it has and how long it
+// spans, first to last timestamp across every store. Plain CSVs are read from both
+// ends, compressed and encrypted ones have to be read through, so listing a folder of
+// long gzipped pad holds takes a while.
+//
+// Deleting takes the whole folder. Only a session folder straight under the station's
+// recordings folder goes, once symlinks and ..s are resolved, and only if it looks like
+// a session (a session.json or a store CSV). Never the station's folder itself, or
+// anything holding the session this run is writing.
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use super::encryption;
+use super::recording_compression;
+
+const MANIFEST: &str = "session.json";
+// the black box keeps CSVs of its own, they aren't a store's
+const SKIP_DIRS: &[&str] = &["black_box"];
+// plenty for one row
+const TAIL_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingFile {
+    // "rocket", or "l2/rocket" for a launch session's
+    pub store: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingInfo {
+    // the folder's name, when the session started
+    pub name: String,
+    pub path: PathBuf,
+    // everything in the folder, video included
+    pub bytes: u64,
+    pub stores: Vec<String>,
+    pub files: Vec<RecordingFile>,
+    // unix ms, None if no CSV has a row with a timestamp
+    pub first_timestamp: Option<i64>,
+    pub last_timestamp: Option<i64>,
+    pub duration_ms: Option<i64>,
+    // the session this run is writing
+    pub current: bool,
+}
+
+/// Every recording in `dir`, newest first
+pub fn list(dir: &Path, current: &Path) -> Result<Vec<RecordingInfo>, String> {
+    let mut sessions: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to list {}: {e}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    sessions.sort();
+    sessions.reverse();
+
+    Ok(sessions
+        .into_iter()
+        .filter_map(|path| {
+            let files = store_files(&path);
+            (!files.is_empty()).then(|| info(path, files, current))
+        })
+        .collect())
+}
+
+/// Removes a recording's folder, returns how many bytes it freed
+pub fn delete(path: &Path, current: &Path) -> Result<u64, String> {
+    let path = &path.canonicalize().map_err(|e| format!("{}: {e}", path.display()))?;
+    let current = &current.canonicalize().map_err(|e| format!("{}: {e}", current.display()))?;
+    if current.starts_with(path) {
+        return Err("That's the session being recorded now, or holds it".into());
+    }
+    let station = current.parent().ok_or("The session being recorded has no station folder")?;
+    if path.parent() != Some(station) {
+        return Err(format!("{} isn't one of the station's recordings", path.display()));
+    }
+    if !path.is_dir() {
+        return Err(format!("{} isn't a recording folder", path.display()));
+    }
+    if !path.join(MANIFEST).is_file() && store_files(path).is_empty() {
+        return Err(format!("{} doesn't look like a recording, it has no session.json or CSVs", path.display()));
+    }
+    let bytes = folder_bytes(path);
+    std::fs::remove_dir_all(path).map_err(|e| format!("Failed to delete {}: {e}", path.display()))?;
+    println!("[recordings] deleted {} ({bytes} bytes)", path.display());
+    Ok(bytes)
+}
+
+/// Fails unless `path` is a recording folder, checked before revealing it
+pub fn check(path: &Path) -> Result<(), String> {
+    if !path.is_dir() || store_files(path).is_empty() {
+        return Err(format!("{} isn't a recording folder", path.display()));
+    }
+    Ok(())
+}
+
+fn info(path: PathBuf, files: Vec<RecordingFile>, current: &Path) -> RecordingInfo {
+    let stores: BTreeSet<String> = files.iter().map(|f| f.store.clone()).collect();
+    let spans: Vec<(i64, i64)> = files.iter().filter_map(|f| span(&f.path)).collect();
+    let first_timestamp = spans.iter().map(|s| s.0).min();
+    let last_timestamp = spans.iter().map(|s| s.1).max();
+    RecordingInfo {
+        name: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+        bytes: folder_bytes(&path),
+        current: same_path(&path, current),
+        path,
+        stores: stores.into_iter().collect(),
+        files,
+        first_timestamp,
+        last_timestamp,
+        duration_ms: first_timestamp.zip(last_timestamp).map(|(first, last)| last - first),
+    }
+}
+
+// the store CSVs at the top of the folder and one folder down (launch sessions)
+fn store_files(session: &Path) -> Vec<RecordingFile> {
+    let mut files = Vec::new();
+    collect_csvs(session, None, &mut files);
+    let Ok(entries) = std::fs::read_dir(session) else {
+        return files;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if path.is_dir() && !SKIP_DIRS.contains(&name.as_str()) {
+            collect_csvs(&path, Some(&name), &mut files);
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+fn collect_csvs(dir: &Path, prefix: Option<&str>, files: &mut Vec<RecordingFile>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            let name = path.to_string_lossy();
+            path.is_file() && (name.ends_with(".csv") || name.ends_with(".csv.gz"))
+        })
+        .collect();
+    let stems: BTreeMap<String, &PathBuf> = paths
+        .iter()
+        .filter_map(|path| recording_compression::recording_stem(path).map(|stem| (stem, path)))
+        .collect();
+
+    for (stem, path) in &stems {
+        let store = segment_of(stem, &stems).unwrap_or(stem.as_str());
+        files.push(RecordingFile {
+            store: match prefix {
+                Some(prefix) => format!("{prefix}/{store}"),
+                None => store.to_string(),
+            },
+            path: path.to_path_buf(),
+            bytes: path.metadata().map(|m| m.len()).unwrap_or(0),
+        });
+    }
+}
+
+// "rocket_0002" is a segment of rocket when rocket.csv is there too (see csv_rotation)
+fn segment_of<'a>(stem: &'a str, stems: &BTreeMap<String, &PathBuf>) -> Option<&'a str> {
+    let (base, index) = stem.rsplit_once('_')?;
+    (index.len() == 4 && index.bytes().all(|b| b.is_ascii_digit()) && stems.contains_key(base)).then_some(base)
+}
+
+// first and last timestamp in a store CSV
+fn span(path: &Path) -> Option<(i64, i64)> {
+    let plain = !path.to_string_lossy().ends_with(".gz") && !encryption::is_encrypted(path).ok()?;
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(recording_compression::open(path).ok()?);
+    let column = reader.headers().ok()?.iter().position(|h| h == "timestamp")?;
+    let mut timestamps = reader
+        .records()
+        .filter_map(|record| record.ok()?.get(column)?.parse::<i64>().ok());
+    let first = timestamps.next()?;
+    let last = if plain { last_timestamp(path, column) } else { timestamps.last() };
+    Some((first, last.unwrap_or(first).max(first)))
+}
+
+// the last full row's timestamp, read from the end of a plain file
+fn last_timestamp(path: &Path, column: usize) -> Option<i64> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES))).ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    let tail = String::from_utf8_lossy(&tail);
+    // a row cut off by a crash has no newline yet, it's skipped with the rest
+    tail.lines()
+        .rev()
+        .skip(usize::from(!tail.ends_with('\n')))
+        .find_map(|line| line.split(',').nth(column)?.parse::<i64>().ok())
+}
+
+fn folder_bytes(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => folder_bytes(&entry.path()),
+            Ok(kind) if kind.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
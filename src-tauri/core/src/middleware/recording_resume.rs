@@ -0,0 +1,127 @@
+// Carrying on an earlier session's CSVs after a restart, so a pad hold stays one file
+//
+// Every run of the app is a new session folder, so a restart mid pad hold splits the
+// recording in two. Resuming points each store's writer back at its CSV in the earlier
+// session (the newest one by default) and appends to it. The last segment is the one
+// carried on, rotation picks up from its number. The file's header has to start with
+// timestamp and time and have every column the store has now, rows are written in the
+// file's column order. Columns the file has that the store doesn't any more stay empty.
+// A row cut off by the crash is trimmed back to the last full line first.
+//
+// Encrypted CSVs can't be resumed, the chunks are numbered from the start of the file.
+// JSON Lines recordings aren't carried on either, a resumed store writes CSV.
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::csv_rotation;
+use super::encryption;
+
+// how far back from the end to look for the last full row
+const TAIL_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct ResumePoint {
+    // the store's first segment, what rotation numbers from
+    pub base: PathBuf,
+    // the last segment, the one appended to
+    pub path: PathBuf,
+    pub segment: u32,
+    pub headers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResumeSummary {
+    pub session: PathBuf,
+    // store -> the file it carries on
+    pub resumed: BTreeMap<String, PathBuf>,
+    // stores the earlier session had no CSV for, these start new ones here
+    pub started: Vec<String>,
+}
+
+/// The newest session other than `session_dir` with a CSV in it. Session folders are
+/// named by their start time, so the last one by name is the most recent.
+pub fn latest_previous(session_dir: &Path) -> Result<PathBuf, String> {
+    let station_dir = session_dir.parent().ok_or("The session folder has no parent")?;
+    let mut sessions: Vec<PathBuf> = std::fs::read_dir(station_dir)
+        .map_err(|e| format!("Failed to list {}: {e}", station_dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path != session_dir && path.is_dir() && has_csv(path))
+        .collect();
+    sessions.sort();
+    sessions.pop().ok_or_else(|| "No earlier session has a recording".into())
+}
+
+fn has_csv(dir: &Path) -> bool {
+    std::fs::read_dir(dir)
+        .map(|entries| entries.filter_map(Result::ok).any(|e| e.path().extension().is_some_and(|ext| ext == "csv")))
+        .unwrap_or(false)
+}
+
+/// Where `store` would carry on in `session`, None if the session has no CSV for it.
+/// `columns` is what the store has now, every one of them has to be in the file.
+pub fn find(session: &Path, store: &str, columns: &[String]) -> Result<Option<ResumePoint>, String> {
+    let base = session.join(format!("{store}.csv"));
+    // an empty one never got as far as its header, there's nothing to carry on
+    if !base.metadata().is_ok_and(|m| m.is_file() && m.len() > 0) {
+        return Ok(None);
+    }
+    let mut segment = 1;
+    while csv_rotation::segment_path(&base, segment + 1).is_file() {
+        segment += 1;
+    }
+    let path = csv_rotation::segment_path(&base, segment);
+
+    if encryption::is_encrypted(&path)? {
+        return Err(format!("{} is encrypted, it can't be resumed", path.display()));
+    }
+    let file = File::open(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let headers: Vec<String> = csv::Reader::from_reader(file)
+        .headers()
+        .map_err(|e| format!("{}: {e}", path.display()))?
+        .iter()
+        .map(str::to_string)
+        .collect();
+
+    if headers.len() < 2 || headers[0] != "timestamp" || headers[1] != "time" {
+        return Err(format!("{} doesn't start with a timestamp and time header", path.display()));
+    }
+    let missing: Vec<&str> = columns
+        .iter()
+        .filter(|c| !headers.contains(c))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("{} has no column for {}", path.display(), missing.join(", ")));
+    }
+    Ok(Some(ResumePoint { base, path, segment, headers }))
+}
+
+/// The file opened for appending, with a partly written last row cut off
+pub fn open_for_append(path: &Path) -> Result<File, String> {
+    let err = |e: std::io::Error| format!("{}: {e}", path.display());
+    let mut file = OpenOptions::new().read(true).write(true).open(path).map_err(err)?;
+    let len = file.metadata().map_err(err)?.len();
+    let start = len.saturating_sub(TAIL_BYTES);
+    file.seek(SeekFrom::Start(start)).map_err(err)?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).map_err(err)?;
+
+    if tail.last().is_some_and(|&b| b != b'\n') {
+        match tail.iter().rposition(|&b| b == b'\n') {
+            Some(end) => {
+                eprintln!("[recording] trimmed a partial row off {}", path.display());
+                file.set_len(start + end as u64 + 1).map_err(err)?;
+            }
+            // one row past TAIL_BYTES isn't a CSV row, leave it be and start a new line
+            None => {
+                file.seek(SeekFrom::End(0)).map_err(err)?;
+                file.write_all(b"\n").map_err(err)?;
+            }
+        }
+    }
+    file.seek(SeekFrom::End(0)).map_err(err)?;
+    Ok(file)
+}
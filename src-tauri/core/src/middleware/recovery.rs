@@ -0,0 +1,150 @@
+// Everything the recovery team needs in one payload, plus a simple landing predictor
+use serde::Serialize;
+
+use crate::middleware::elevation::ElevationService;
+use crate::middleware::telemetry_stores::TelemetryData;
+
+// hprc::States::Recovery, the flight computer's "on the ground" state
+pub const RECOVERY_STATE: u32 = 6;
+
+// only look at recent fixes when estimating drift
+const PREDICTION_WINDOW_MS: i64 = 10_000;
+// slower than this we call it not descending
+const MIN_DESCENT_RATE: f64 = 0.5;
+// rough horizontal accuracy of a single GPS fix
+const GPS_ACCURACY_M: f64 = 10.0;
+const METERS_PER_DEG_LAT: f64 = 111_320.0;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct GpsFix {
+    pub timestamp: i64,
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LandingPrediction {
+    pub lat: f64,
+    pub lon: f64,
+    // 1-sigma-ish radius around the point
+    pub uncertainty_m: f64,
+    // 0 once landed
+    pub time_to_impact_s: f64,
+    pub ground_alt: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryBundle {
+    pub store: String,
+    pub generated_at: i64,
+    pub last_fix: Option<GpsFix>,
+    pub satellites: Option<u32>,
+    pub predicted_landing: Option<LandingPrediction>,
+    // azimuth the antenna tracker was last steered to, if one is running
+    pub rssi_bearing: Option<f64>,
+    pub battery_voltage: Option<f64>,
+    pub landed_at: Option<i64>,
+    pub elapsed_since_landing_ms: Option<i64>,
+}
+
+/// Zip the lat/lon/alt histories into fixes (they are pushed together per packet)
+pub fn gps_fixes(lat: &[TelemetryData], lon: &[TelemetryData], alt: &[TelemetryData]) -> Vec<GpsFix> {
+    lat.iter()
+        .zip(lon.iter())
+        .zip(alt.iter())
+        .map(|((lat, lon), alt)| GpsFix {
+            timestamp: lat.timestamp,
+            lat: lat.value.as_f64(),
+            lon: lon.value.as_f64(),
+            alt: alt.value.as_f64(),
+        })
+        .collect()
+}
+
+/// Timestamp of the most recent transition into the recovery state
+pub fn landing_time(states: &[TelemetryData]) -> Option<i64> {
+    let mut landed_at = None;
+    let mut previous: Option<u32> = None;
+    for s in states {
+        let state = s.value.as_f64() as u32;
+        if state == RECOVERY_STATE && previous != Some(RECOVERY_STATE) {
+            landed_at = Some(s.timestamp);
+        }
+        previous = Some(state);
+    }
+    // a later state change means we left recovery again (reset, false trigger)
+    match previous {
+        Some(RECOVERY_STATE) => landed_at,
+        _ => None,
+    }
+}
+
+/// Extrapolate the recent GPS track down to the terrain (or the lowest altitude seen
+/// if we have no DEM tile).
+pub fn predict_landing(
+    fixes: &[GpsFix],
+    landed: bool,
+    elevation: &ElevationService,
+) -> Option<LandingPrediction> {
+    let last = *fixes.last()?;
+    let ground_alt = elevation
+        .elevation(last.lat, last.lon)
+        .unwrap_or_else(|| fixes.iter().map(|f| f.alt).fold(f64::INFINITY, f64::min));
+
+    if landed {
+        return Some(LandingPrediction {
+            lat: last.lat,
+            lon: last.lon,
+            uncertainty_m: GPS_ACCURACY_M,
+            time_to_impact_s: 0.0,
+            ground_alt,
+        });
+    }
+
+    let recent: Vec<GpsFix> = fixes
+        .iter()
+        .filter(|f| last.timestamp - f.timestamp <= PREDICTION_WINDOW_MS)
+        .copied()
+        .collect();
+    let first = *recent.first()?;
+    let dt = (last.timestamp - first.timestamp) as f64 / 1000.0;
+    if dt <= 0.0 {
+        return None;
+    }
+
+    let m_per_deg_lon = METERS_PER_DEG_LAT * last.lat.to_radians().cos();
+    let v_north = (last.lat - first.lat) * METERS_PER_DEG_LAT / dt;
+    let v_east = (last.lon - first.lon) * m_per_deg_lon / dt;
+    let v_down = (first.alt - last.alt) / dt;
+    if v_down < MIN_DESCENT_RATE {
+        return None; // still climbing or hovering, nothing sensible to say
+    }
+
+    let time_to_impact = ((last.alt - ground_alt) / v_down).max(0.0);
+
+    // spread of segment-to-segment drift is our uncertainty on the wind
+    let segment_speeds: Vec<(f64, f64)> = recent
+        .windows(2)
+        .filter_map(|w| {
+            let dt = (w[1].timestamp - w[0].timestamp) as f64 / 1000.0;
+            (dt > 0.0).then(|| (
+                (w[1].lat - w[0].lat) * METERS_PER_DEG_LAT / dt,
+                (w[1].lon - w[0].lon) * m_per_deg_lon / dt,
+            ))
+        })
+        .collect();
+    let n = segment_speeds.len().max(1) as f64;
+    let var = segment_speeds
+        .iter()
+        .map(|(vn, ve)| (vn - v_north).powi(2) + (ve - v_east).powi(2))
+        .sum::<f64>() / n;
+
+    Some(LandingPrediction {
+        lat: last.lat + v_north * time_to_impact / METERS_PER_DEG_LAT,
+        lon: last.lon + v_east * time_to_impact / m_per_deg_lon,
+        uncertainty_m: GPS_ACCURACY_M + var.sqrt() * time_to_impact,
+        time_to_impact_s: time_to_impact,
+        ground_alt,
+    })
+}
@@ -0,0 +1,136 @@
+// Which packets came in without the fields their stream has to have
+//
+// Register the fields a stream can't do without ("rocket" needs alt, gps_lock and
+// state) and every packet is checked against them. A producer pushes a packet's
+// fields and then calls end_packet, anything expected that didn't turn up in between
+// marks the packet incomplete. Each stream keeps a health status: how many packets
+// were short, which fields go missing most and what the last one lacked. A launch
+// session's "l2/rocket" is held to the schema registered for "rocket".
+//
+// This is per packet. check_schema and the mission profile's expected_schema compare
+// everything a store has ever received, which an occasional short packet never trips.
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::launch_sessions::split_store;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaHealthState {
+    // nothing checked yet
+    NoData,
+    Healthy,
+    // the last packet was missing something
+    Degraded,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaHealth {
+    pub store: String,
+    pub state: SchemaHealthState,
+    pub expected: Vec<String>,
+    pub packets: u64,
+    pub incomplete: u64,
+    // field -> packets that came without it
+    pub missing_counts: BTreeMap<String, u64>,
+    pub last_missing: Vec<String>,
+    pub last_checked_at: Option<i64>,
+}
+
+#[derive(Default)]
+struct StreamHealth {
+    // fields pushed since the last end_packet
+    seen: BTreeSet<String>,
+    packets: u64,
+    incomplete: u64,
+    missing_counts: BTreeMap<String, u64>,
+    last_missing: Vec<String>,
+    last_checked_at: Option<i64>,
+}
+
+#[derive(Default)]
+pub struct SchemaRegistry {
+    // base store -> the fields every packet needs
+    expected: BTreeMap<String, Vec<String>>,
+    streams: HashMap<String, StreamHealth>,
+}
+
+impl SchemaRegistry {
+    // None stops checking the store, its counts go with it
+    pub fn set(&mut self, store: &str, fields: Option<Vec<String>>) -> Result<(), String> {
+        match fields {
+            Some(fields) if fields.is_empty() => return Err(format!("The schema for '{store}' has no fields")),
+            Some(fields) => {
+                self.expected.insert(store.to_string(), fields);
+            }
+            None => {
+                self.expected.remove(store);
+            }
+        }
+        self.streams.retain(|name, _| split_store(name).1 != store);
+        Ok(())
+    }
+
+    pub fn all(&self) -> BTreeMap<String, Vec<String>> {
+        self.expected.clone()
+    }
+
+    fn expected(&self, store: &str) -> Option<&Vec<String>> {
+        self.expected.get(split_store(store).1)
+    }
+
+    // every push goes through here, so it's a lookup and nothing else for unchecked stores
+    pub fn note(&mut self, store: &str, field: &str) {
+        if self.expected(store).is_none() {
+            return;
+        }
+        self.streams.entry(store.to_string()).or_default().seen.insert(field.to_string());
+    }
+
+    /// Closes the packet `store` has been getting fields for. Returns what it was
+    /// missing, None for a store nobody registered a schema for.
+    pub fn end_packet(&mut self, store: &str, timestamp: i64) -> Option<Vec<String>> {
+        let expected = self.expected.get(split_store(store).1)?;
+        let stream = self.streams.entry(store.to_string()).or_default();
+        let missing: Vec<String> = expected.iter().filter(|f| !stream.seen.contains(*f)).cloned().collect();
+        stream.seen.clear();
+        stream.packets += 1;
+        if !missing.is_empty() {
+            stream.incomplete += 1;
+            for field in &missing {
+                *stream.missing_counts.entry(field.clone()).or_default() += 1;
+            }
+        }
+        stream.last_missing = missing.clone();
+        stream.last_checked_at = Some(timestamp);
+        Some(missing)
+    }
+
+    /// Every checked stream, and the registered ones that haven't had a packet yet
+    pub fn health(&self) -> Vec<SchemaHealth> {
+        let mut stores: BTreeSet<&String> = self.streams.keys().collect();
+        stores.extend(self.expected.keys());
+        stores
+            .into_iter()
+            .filter_map(|store| {
+                let expected = self.expected(store)?;
+                let stream = self.streams.get(store);
+                let state = match stream {
+                    Some(s) if s.packets > 0 && s.last_missing.is_empty() => SchemaHealthState::Healthy,
+                    Some(s) if s.packets > 0 => SchemaHealthState::Degraded,
+                    _ => SchemaHealthState::NoData,
+                };
+                Some(SchemaHealth {
+                    store: store.clone(),
+                    state,
+                    expected: expected.clone(),
+                    packets: stream.map_or(0, |s| s.packets),
+                    incomplete: stream.map_or(0, |s| s.incomplete),
+                    missing_counts: stream.map(|s| s.missing_counts.clone()).unwrap_or_default(),
+                    last_missing: stream.map(|s| s.last_missing.clone()).unwrap_or_default(),
+                    last_checked_at: stream.and_then(|s| s.last_checked_at),
+                })
+            })
+            .collect()
+    }
+}
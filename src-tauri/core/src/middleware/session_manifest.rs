@@ -0,0 +1,167 @@
+// session.json at the top of each session folder, what someone opening the folder
+// later needs to know about how it was recorded: which build and schema wrote the
+// CSVs next to it, where the station was, who was running it and when each
+// recording started and stopped
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::config_file::write_config_file;
+use super::landing::LandingSummary;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeSyncRecord {
+    // "ntp pool.ntp.org" or "gpsd 127.0.0.1:2947"
+    pub source: String,
+    // reference minus our clock, positive means we're behind
+    pub offset_ms: f64,
+    pub checked_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct StationLocation {
+    // degrees / meters MSL
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingRecord {
+    pub started_at: String,
+    // None while it's still going, or if the app crashed before it stopped
+    pub stopped_at: Option<String>,
+    pub stores: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionManifest {
+    pub started_at: String,
+    pub hostname: Option<String>,
+    #[serde(default)]
+    pub app_version: Option<String>,
+    // hash of the telemetry schemas the app was built against, see build.rs
+    #[serde(default)]
+    pub schema_hash: Option<String>,
+    // the tracker's position, when it's been set
+    #[serde(default)]
+    pub station: Option<StationLocation>,
+    #[serde(default)]
+    pub operator: Option<String>,
+    #[serde(default)]
+    pub recordings: Vec<RecordingRecord>,
+    // latest check, plus the worst we saw so timestamps can be trusted (or not)
+    pub time_sync: Option<TimeSyncRecord>,
+    pub worst_time_offset_ms: Option<f64>,
+    // set when the session was laid out from a template, see session_template
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub mission: Option<String>,
+    #[serde(default)]
+    pub prepared_at: Option<String>,
+    // one per landing detected, see FlightStateMachine::landing_summary
+    #[serde(default)]
+    pub landings: Vec<LandingSummary>,
+}
+
+pub struct SessionManifestFile {
+    path: PathBuf,
+    manifest: SessionManifest,
+}
+
+impl SessionManifestFile {
+    pub fn new(path: PathBuf) -> Self {
+        let manifest = SessionManifest {
+            started_at: chrono::Local::now().to_rfc3339(),
+            hostname: host_name(),
+            app_version: None,
+            schema_hash: None,
+            station: None,
+            operator: None,
+            recordings: Vec::new(),
+            time_sync: None,
+            worst_time_offset_ms: None,
+            template: None,
+            mission: None,
+            prepared_at: None,
+            landings: Vec::new(),
+        };
+        let file = Self { path, manifest };
+        file.save();
+        file
+    }
+
+    pub fn get(&self) -> &SessionManifest {
+        &self.manifest
+    }
+
+    pub fn record_time_sync(&mut self, record: TimeSyncRecord) {
+        let worst = self.manifest.worst_time_offset_ms.unwrap_or(0.0);
+        if record.offset_ms.abs() >= worst.abs() {
+            self.manifest.worst_time_offset_ms = Some(record.offset_ms);
+        }
+        self.manifest.time_sync = Some(record);
+        self.save();
+    }
+
+    pub fn record_template(&mut self, template: &str, mission: Option<String>) {
+        self.manifest.template = Some(template.to_string());
+        self.manifest.mission = mission;
+        self.manifest.prepared_at = Some(chrono::Local::now().to_rfc3339());
+        self.save();
+    }
+
+    pub fn record_build(&mut self, app_version: &str, schema_hash: Option<&str>) {
+        self.manifest.app_version = Some(app_version.to_string());
+        self.manifest.schema_hash = schema_hash.map(str::to_string);
+        self.save();
+    }
+
+    pub fn record_station(&mut self, station: StationLocation) {
+        self.manifest.station = Some(station);
+        self.save();
+    }
+
+    pub fn record_operator(&mut self, operator: Option<String>) {
+        self.manifest.operator = operator;
+        self.save();
+    }
+
+    pub fn record_recording_start(&mut self, stores: Vec<String>) {
+        self.manifest.recordings.push(RecordingRecord {
+            started_at: chrono::Local::now().to_rfc3339(),
+            stopped_at: None,
+            stores,
+        });
+        self.save();
+    }
+
+    pub fn record_recording_stop(&mut self) {
+        if let Some(recording) = self.manifest.recordings.last_mut().filter(|r| r.stopped_at.is_none()) {
+            recording.stopped_at = Some(chrono::Local::now().to_rfc3339());
+            self.save();
+        }
+    }
+
+    pub fn record_landing(&mut self, summary: LandingSummary) {
+        self.manifest.landings.push(summary);
+        self.save();
+    }
+
+    fn save(&self) {
+        if let Err(e) = write_config_file(&self.path, &self.manifest) {
+            eprintln!("[session] {e}");
+        }
+    }
+}
+
+#[cfg(feature = "host-info")]
+fn host_name() -> Option<String> {
+    sysinfo::System::host_name()
+}
+
+// left out of the manifest without the host-info feature
+#[cfg(not(feature = "host-info"))]
+fn host_name() -> Option<String> {
+    None
+}
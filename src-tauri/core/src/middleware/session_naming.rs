@@ -0,0 +1,73 @@
+// What a session folder and the files in it are called, decided here and nowhere else
+//
+// Each run of the app gets <station>/<date>_flight-NN, numbered from 01 every day:
+// 2026-06-14_flight-01, 2026-06-14_flight-02 and so on. Inside it:
+//   <store>.csv              telemetry, one per store (csv_rotation numbers the segments)
+//   <source>_raw_<time>.bin  a raw byte capture from a radio
+//   <stream>_<time>.avi      video, one per time recording starts
+// where <time> is when that file was started, HH-MM-SS. The folders sort by name in the
+// order they were made, which restore_snapshot and the recording library rely on, as
+// long as no day goes past flight-99.
+use chrono::Local;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const FLIGHT: &str = "_flight-";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionPaths {
+    pub dir: PathBuf,
+    // store -> its CSV
+    pub telemetry: BTreeMap<String, PathBuf>,
+    // the capture going now, if there is one
+    pub raw: Option<PathBuf>,
+    // stream -> the video it's recording now
+    pub video: BTreeMap<String, PathBuf>,
+}
+
+/// Makes the next <date>_flight-NN folder in `station_dir` for today
+pub fn create_session_dir(station_dir: &Path) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(station_dir).map_err(|e| format!("Failed to create {}: {e}", station_dir.display()))?;
+    let date = Local::now().format("%Y-%m-%d").to_string();
+    let mut flight = last_flight(station_dir, &date) + 1;
+    loop {
+        let dir = station_dir.join(format!("{date}{FLIGHT}{flight:02}"));
+        match std::fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            // another instance got there first
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => flight += 1,
+            Err(e) => return Err(format!("Failed to create {}: {e}", dir.display())),
+        }
+    }
+}
+
+// the highest flight number already used on `date`, 0 if none
+fn last_flight(station_dir: &Path, date: &str) -> u32 {
+    let prefix = format!("{date}{FLIGHT}");
+    std::fs::read_dir(station_dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter_map(|e| e.file_name().to_str()?.strip_prefix(&prefix)?.parse::<u32>().ok())
+                .max()
+                .unwrap_or(0)
+        })
+        .unwrap_or(0)
+}
+
+pub fn telemetry_file(session: &Path, store: &str) -> PathBuf {
+    session.join(format!("{store}.csv"))
+}
+
+pub fn raw_file(session: &Path, source: &str) -> PathBuf {
+    session.join(format!("{source}_raw_{}.bin", started()))
+}
+
+pub fn video_file(session: &Path, stream: &str) -> PathBuf {
+    session.join(format!("{stream}_{}.avi", started()))
+}
+
+fn started() -> String {
+    Local::now().format("%H-%M-%S").to_string()
+}
@@ -0,0 +1,172 @@
+// Session templates: everything a launch day session needs, set up ahead of time
+//
+// A template (JSON or TOML in the station's templates folder) lists the folders and
+// empty log files the session should have, the mission profile to load and the
+// pre-flight checklist. Applying it at T-60 lays all of that out in the session folder,
+// writes a fresh checklist.json for the operators to tick off and notes the template in
+// session.json, so nothing is left to set up once the rocket is powered.
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+use super::config_file::{read_config_file, write_config_file};
+use super::session_manifest::SessionManifest;
+
+pub const CHECKLIST_FILE: &str = "checklist.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub mission: Option<String>,
+    // relative to the template file, or absolute
+    #[serde(default)]
+    pub mission_profile: Option<PathBuf>,
+    // created empty inside the session folder
+    #[serde(default)]
+    pub directories: Vec<String>,
+    #[serde(default)]
+    pub logs: Vec<String>,
+    #[serde(default)]
+    pub checklist: Vec<ChecklistItemTemplate>,
+    // start recording every store as soon as the session is laid out
+    #[serde(default)]
+    pub start_recording: bool,
+}
+
+// what the one button press did
+#[derive(Debug, Clone, Serialize)]
+pub struct PreparedSession {
+    pub session: String,
+    pub manifest: SessionManifest,
+    pub checklist: Checklist,
+    pub mission_profile: Option<String>,
+    pub recording: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChecklistItemTemplate {
+    pub id: String,
+    pub text: String,
+    // a GO needs every required item done
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChecklistItem {
+    pub id: String,
+    pub text: String,
+    pub required: bool,
+    pub done: bool,
+    pub done_by: Option<String>,
+    pub done_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checklist {
+    pub template: String,
+    pub items: Vec<ChecklistItem>,
+}
+
+impl Checklist {
+    pub fn complete(&self) -> bool {
+        self.items.iter().all(|item| item.done || !item.required)
+    }
+
+    pub fn set_done(&mut self, id: &str, done: bool, operator_role: &str) -> Result<ChecklistItem, String> {
+        let item = self
+            .items
+            .iter_mut()
+            .find(|item| item.id == id)
+            .ok_or_else(|| format!("No checklist item '{id}'"))?;
+        item.done = done;
+        item.done_by = done.then(|| operator_role.to_string());
+        item.done_at = done.then(|| chrono::Utc::now().timestamp_millis());
+        Ok(item.clone())
+    }
+}
+
+// template paths are names inside the session, not a way out of it
+fn session_relative(session: &Path, relative: &str) -> Result<PathBuf, String> {
+    let path = Path::new(relative);
+    if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!("'{relative}' has to be a plain path inside the session folder"));
+    }
+    Ok(session.join(path))
+}
+
+/// A template by name from `templates_dir`, or by path
+pub fn load(templates_dir: &Path, template: &str) -> Result<(SessionTemplate, PathBuf), String> {
+    let direct = PathBuf::from(template);
+    let path = if direct.is_file() {
+        direct
+    } else {
+        ["toml", "json"]
+            .iter()
+            .map(|ext| templates_dir.join(format!("{template}.{ext}")))
+            .find(|p| p.is_file())
+            .ok_or_else(|| format!("No session template '{template}' in {}", templates_dir.display()))?
+    };
+    let template: SessionTemplate = read_config_file(&path)?;
+    Ok((template, path))
+}
+
+pub fn list(templates_dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(templates_dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "toml" || ext == "json"))
+        .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Lay the template out in `session`. Existing logs are left alone, so applying the
+/// same template twice is harmless; the checklist is always a fresh one.
+pub fn apply(template: &SessionTemplate, session: &Path) -> Result<Checklist, String> {
+    for directory in &template.directories {
+        let path = session_relative(session, directory)?;
+        std::fs::create_dir_all(&path).map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+    }
+    for log in &template.logs {
+        let path = session_relative(session, log)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+    }
+
+    let checklist = Checklist {
+        template: template.name.clone(),
+        items: template
+            .checklist
+            .iter()
+            .map(|item| ChecklistItem {
+                id: item.id.clone(),
+                text: item.text.clone(),
+                required: item.required,
+                done: false,
+                done_by: None,
+                done_at: None,
+            })
+            .collect(),
+    };
+    write_config_file(&session.join(CHECKLIST_FILE), &checklist)?;
+    Ok(checklist)
+}
+
+pub fn save_checklist(session: &Path, checklist: &Checklist) -> Result<(), String> {
+    write_config_file(&session.join(CHECKLIST_FILE), checklist)
+}
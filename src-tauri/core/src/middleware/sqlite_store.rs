@@ -0,0 +1,323 @@
+// Every telemetry point in an SQLite database, so a crash mid-flight loses almost nothing
+//
+// With it on, each push goes into one tall table in the session folder's telemetry.sqlite:
+//   points(store TEXT, field TEXT, timestamp INTEGER, kind TEXT, value)
+// `kind` is the TelemetryValue variant ("f64", "i64", "u64" or "bool") so values come
+// back with their type; a u64 too big for SQLite's INTEGER is kept as text. Writes happen
+// on a background thread, batched into one transaction per COMMIT_INTERVAL, and the
+// database is in WAL mode, so only the last uncommitted batch is at risk if the app
+// dies. query() and streams() open any of these databases read only, e.g. one from
+// before a restart.
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
+
+use super::drops::SharedDrops;
+use super::lock_timing::TimedMutex;
+use super::telemetry_stores::{TelemetryData, TelemetryValue};
+
+pub const DATABASE_NAME: &str = "telemetry.sqlite";
+// bounded like the black box, points past this are dropped rather than held in memory
+const QUEUE_DEPTH: usize = 16_384;
+// worst case we lose this much on a crash
+const COMMIT_INTERVAL: Duration = Duration::from_millis(250);
+// or this many points, whichever comes first
+const COMMIT_EVERY: usize = 2_048;
+// a query with no limit still shouldn't hand the frontend a whole flight
+const QUERY_LIMIT: usize = 100_000;
+
+const SCHEMA: &str = "
+    PRAGMA journal_mode = WAL;
+    PRAGMA synchronous = NORMAL;
+    CREATE TABLE IF NOT EXISTS points (
+        store TEXT NOT NULL,
+        field TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        value
+    );
+    CREATE INDEX IF NOT EXISTS points_by_stream ON points (store, field, timestamp);
+";
+
+struct Point {
+    store: String,
+    field: String,
+    data: TelemetryData,
+}
+
+enum SqliteCommand {
+    Point(Point),
+    Stop,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SqliteStatus {
+    pub enabled: bool,
+    pub path: Option<String>,
+    // committed to the database since it was turned on
+    pub points: u64,
+    pub queued: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SqlitePoint {
+    pub store: String,
+    pub field: String,
+    pub timestamp: i64,
+    pub value: TelemetryValue,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SqliteStream {
+    pub store: String,
+    pub field: String,
+    pub points: u64,
+    pub first: i64,
+    pub last: i64,
+}
+
+struct SqliteWriter {
+    path: PathBuf,
+    tx: SyncSender<SqliteCommand>,
+    thread: JoinHandle<()>,
+}
+
+// &self everywhere like the journal, it's fed from push_data and toggled from commands
+pub struct SqliteStore {
+    writer: TimedMutex<Option<SqliteWriter>>,
+    // points sent but not yet committed, std channels can't tell us
+    queued: Arc<AtomicUsize>,
+    committed: Arc<AtomicU64>,
+    drops: SharedDrops,
+}
+
+impl SqliteStore {
+    pub fn new(drops: SharedDrops) -> Self {
+        Self {
+            writer: TimedMutex::new("sqlite", None),
+            queued: Arc::new(AtomicUsize::new(0)),
+            committed: Arc::new(AtomicU64::new(0)),
+            drops,
+        }
+    }
+
+    /// Opens (or creates) the database at `path` and starts writing to it, beginning
+    /// with `baseline`: whatever the stores already hold
+    pub fn start(&self, path: &Path, baseline: Vec<(String, String, TelemetryData)>) -> Result<(), String> {
+        let mut writer = self.writer.lock();
+        if writer.is_some() {
+            return Ok(());
+        }
+        let mut conn = Connection::open(path).map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+        conn.execute_batch(SCHEMA).map_err(|e| format!("Failed to set up {}: {e}", path.display()))?;
+        let baseline: Vec<Point> = baseline
+            .into_iter()
+            .map(|(store, field, data)| Point { store, field, data })
+            .collect();
+        insert(&mut conn, &baseline).map_err(|e| format!("Failed to write to {}: {e}", path.display()))?;
+        self.committed.store(baseline.len() as u64, Ordering::Relaxed);
+
+        let (tx, rx) = mpsc::sync_channel(QUEUE_DEPTH);
+        let queued = self.queued.clone();
+        let committed = self.committed.clone();
+        let thread_path = path.to_path_buf();
+        let thread = std::thread::spawn(move || {
+            let mut batch = Vec::with_capacity(COMMIT_EVERY);
+            let mut last_commit = Instant::now();
+            loop {
+                let stop = match rx.recv_timeout(COMMIT_INTERVAL) {
+                    Ok(SqliteCommand::Point(point)) => {
+                        batch.push(point);
+                        false
+                    }
+                    Ok(SqliteCommand::Stop) | Err(RecvTimeoutError::Disconnected) => true,
+                    Err(RecvTimeoutError::Timeout) => false,
+                };
+                let due = batch.len() >= COMMIT_EVERY || last_commit.elapsed() >= COMMIT_INTERVAL;
+                if !batch.is_empty() && (due || stop) {
+                    match insert(&mut conn, &batch) {
+                        Ok(()) => {
+                            committed.fetch_add(batch.len() as u64, Ordering::Relaxed);
+                        }
+                        Err(e) => eprintln!("[sqlite] Failed to write {} points to {}: {e}", batch.len(), thread_path.display()),
+                    }
+                    queued.fetch_sub(batch.len(), Ordering::Relaxed);
+                    batch.clear();
+                    last_commit = Instant::now();
+                }
+                if stop {
+                    break;
+                }
+            }
+        });
+        println!("[sqlite] Writing telemetry to {}", path.display());
+        *writer = Some(SqliteWriter { path: path.to_path_buf(), tx, thread });
+        Ok(())
+    }
+
+    /// Commits what's queued and closes the database
+    pub fn stop(&self) {
+        let Some(writer) = self.writer.lock().take() else {
+            return;
+        };
+        // blocking send, Stop has to get in behind everything queued
+        let _ = writer.tx.send(SqliteCommand::Stop);
+        let _ = writer.thread.join();
+        println!(
+            "[sqlite] Closed {} after {} points",
+            writer.path.display(),
+            self.committed.load(Ordering::Relaxed)
+        );
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.writer.lock().is_some()
+    }
+
+    pub fn record(&self, store: &str, field: &str, data: &TelemetryData) {
+        let writer = self.writer.lock();
+        let Some(writer) = writer.as_ref() else {
+            return;
+        };
+        let point = Point { store: store.to_string(), field: field.to_string(), data: data.clone() };
+        // never hold up the telemetry path for the database
+        match writer.tx.try_send(SqliteCommand::Point(point)) {
+            Ok(()) => {
+                self.queued.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Full(_)) => self.drops.note("sqlite", 1),
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    pub fn status(&self) -> SqliteStatus {
+        let writer = self.writer.lock();
+        SqliteStatus {
+            enabled: writer.is_some(),
+            path: writer.as_ref().map(|w| w.path.display().to_string()),
+            points: self.committed.load(Ordering::Relaxed),
+            queued: self.queue_depth(),
+        }
+    }
+}
+
+fn insert(conn: &mut Connection, points: &[Point]) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut statement = tx.prepare_cached(
+            "INSERT INTO points (store, field, timestamp, kind, value) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for point in points {
+            let (kind, value) = to_sql(point.data.value);
+            statement.execute(params![point.store, point.field, point.data.timestamp, kind, value])?;
+        }
+    }
+    tx.commit()
+}
+
+fn to_sql(value: TelemetryValue) -> (&'static str, Value) {
+    match value {
+        TelemetryValue::F64(v) => ("f64", Value::Real(v)),
+        TelemetryValue::I64(v) => ("i64", Value::Integer(v)),
+        TelemetryValue::U64(v) => match i64::try_from(v) {
+            Ok(v) => ("u64", Value::Integer(v)),
+            Err(_) => ("u64", Value::Text(v.to_string())),
+        },
+        TelemetryValue::Bool(v) => ("bool", Value::Integer(v as i64)),
+    }
+}
+
+fn from_sql(kind: &str, value: Value) -> Option<TelemetryValue> {
+    Some(match (kind, value) {
+        ("f64", Value::Real(v)) => TelemetryValue::F64(v),
+        // SQLite hands back NaN as NULL
+        ("f64", Value::Null) => TelemetryValue::F64(f64::NAN),
+        ("f64", Value::Integer(v)) => TelemetryValue::F64(v as f64),
+        ("i64", Value::Integer(v)) => TelemetryValue::I64(v),
+        ("u64", Value::Integer(v)) => TelemetryValue::U64(v as u64),
+        ("u64", Value::Text(v)) => TelemetryValue::U64(v.parse().ok()?),
+        ("bool", Value::Integer(v)) => TelemetryValue::Bool(v != 0),
+        _ => return None,
+    })
+}
+
+fn open_read_only(path: &Path) -> Result<Connection, String> {
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {}: {e}", path.display()))
+}
+
+/// Points from a telemetry database, oldest first. `field` None means every field of
+/// the store, `from`/`to` are inclusive unix ms.
+pub fn query(
+    path: &Path,
+    store: &str,
+    field: Option<&str>,
+    from: Option<i64>,
+    to: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<SqlitePoint>, String> {
+    let conn = open_read_only(path)?;
+    let limit = limit.unwrap_or(QUERY_LIMIT).min(QUERY_LIMIT) as i64;
+    let mut statement = conn
+        .prepare(
+            "SELECT store, field, timestamp, kind, value FROM points
+             WHERE store = ?1 AND (?2 IS NULL OR field = ?2)
+               AND (?3 IS NULL OR timestamp >= ?3) AND (?4 IS NULL OR timestamp <= ?4)
+             ORDER BY timestamp, rowid LIMIT ?5",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = statement
+        .query_map(params![store, field, from, to, limit], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Value>(4)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut points = Vec::new();
+    for row in rows {
+        let (store, field, timestamp, kind, value) = row.map_err(|e| e.to_string())?;
+        match from_sql(&kind, value) {
+            Some(value) => points.push(SqlitePoint { store, field, timestamp, value }),
+            None => eprintln!("[sqlite] Skipping {store}.{field} at {timestamp}, bad '{kind}' value"),
+        }
+    }
+    Ok(points)
+}
+
+/// Every store/field in a telemetry database, with how many points it holds and when
+pub fn streams(path: &Path) -> Result<Vec<SqliteStream>, String> {
+    let conn = open_read_only(path)?;
+    let mut statement = conn
+        .prepare(
+            "SELECT store, field, COUNT(*), MIN(timestamp), MAX(timestamp) FROM points
+             GROUP BY store, field ORDER BY store, field",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok(SqliteStream {
+                store: row.get(0)?,
+                field: row.get(1)?,
+                points: row.get::<_, i64>(2)? as u64,
+                first: row.get(3)?,
+                last: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
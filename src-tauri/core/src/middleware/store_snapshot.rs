@@ -0,0 +1,146 @@
+// The in-memory buffers on disk every few seconds, so a crash mid-flight keeps the ascent
+//
+// The CSVs only have what was recorded, and the black box only the last few minutes
+// as text. A snapshot is every buffered point with its type and quality, written to
+// store_snapshot.json in the session folder: a temp file first, then renamed over the
+// last one, so a crash part way through a write still leaves the previous snapshot
+// whole. It goes through the session encryption like everything else.
+//
+// A new launch is a new session folder, so nothing overwrites the crashed session's
+// snapshot. restore_snapshot takes the newest one from an earlier session (or a given
+// file) and puts its points back in front of whatever has arrived since.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::data_quality::DataQuality;
+use super::encryption::{self, SessionEncryption};
+use super::journal::JournalValue;
+use super::telemetry_stores::{TelemetryData, TelemetryStores, TelemetryValue};
+
+pub const SNAPSHOT_NAME: &str = "store_snapshot.json";
+
+// same shape as a journal set, NaN and inf survive the round trip
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotPoint {
+    pub timestamp: i64,
+    pub value: JournalValue,
+    #[serde(default, skip_serializing_if = "DataQuality::is_valid")]
+    pub quality: DataQuality,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreSnapshot {
+    // unix ms
+    pub taken_at: i64,
+    // store -> field -> points, oldest first
+    pub stores: BTreeMap<String, BTreeMap<String, Vec<SnapshotPoint>>>,
+}
+
+impl StoreSnapshot {
+    pub fn points(&self) -> usize {
+        self.stores.values().flat_map(|fields| fields.values()).map(Vec::len).sum()
+    }
+}
+
+pub fn read(path: &Path) -> Result<StoreSnapshot, String> {
+    serde_json::from_reader(BufReader::new(encryption::open(path)?))
+        .map_err(|e| format!("Invalid snapshot {}: {e}", path.display()))
+}
+
+/// The newest snapshot left by a session other than `session_dir`. Session folders
+/// are named by their start time, so the last one by name is the most recent.
+pub fn latest_previous(session_dir: &Path) -> Result<PathBuf, String> {
+    let station_dir = session_dir.parent().ok_or("The session folder has no parent")?;
+    let mut sessions: Vec<PathBuf> = std::fs::read_dir(station_dir)
+        .map_err(|e| format!("Failed to list {}: {e}", station_dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path != session_dir && path.join(SNAPSHOT_NAME).is_file())
+        .collect();
+    sessions.sort();
+    sessions
+        .pop()
+        .map(|session| session.join(SNAPSHOT_NAME))
+        .ok_or_else(|| "No earlier session left a snapshot".into())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreSummary {
+    pub path: String,
+    pub taken_at: i64,
+    // store -> points put back
+    pub restored: BTreeMap<String, usize>,
+    // already buffered or outside the buffer limits
+    pub skipped: usize,
+}
+
+// ── Writer ────────────────────────────────────────────────────────────────────
+
+// owns what a write needs, so the snapshot task never takes the middleware lock
+#[derive(Clone)]
+pub struct SnapshotWriter {
+    stores: Arc<TelemetryStores>,
+    path: PathBuf,
+    encryption: SessionEncryption,
+}
+
+impl SnapshotWriter {
+    pub(super) fn new(stores: Arc<TelemetryStores>, session_dir: &Path, encryption: SessionEncryption) -> Self {
+        Self { stores, path: session_dir.join(SNAPSHOT_NAME), encryption }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes every buffered point out, returning how many. Nothing is written while
+    /// the buffers are empty, a fresh launch shouldn't leave an empty snapshot behind.
+    pub fn write(&self) -> Result<usize, String> {
+        let stores: BTreeMap<_, _> = self
+            .stores
+            .snapshot()
+            .into_iter()
+            .map(|(store, fields)| {
+                let fields = fields
+                    .into_iter()
+                    .map(|(field, data)| {
+                        let points = data
+                            .into_iter()
+                            .map(|d| SnapshotPoint { timestamp: d.timestamp, value: d.value.into(), quality: d.quality })
+                            .collect();
+                        (field, points)
+                    })
+                    .collect();
+                (store, fields)
+            })
+            .collect();
+        let snapshot = StoreSnapshot { taken_at: chrono::Utc::now().timestamp_millis(), stores };
+        let points = snapshot.points();
+        if points == 0 {
+            return Ok(0);
+        }
+
+        let temp = self.path.with_extension("json.tmp");
+        let mut file = BufWriter::new(self.encryption.create(&temp)?);
+        serde_json::to_writer(&mut file, &snapshot).map_err(|e| format!("Failed to write {}: {e}", temp.display()))?;
+        file.flush().map_err(|e| format!("Failed to write {}: {e}", temp.display()))?;
+        // closed before the rename, so the file we swap in is complete
+        drop(file);
+        std::fs::rename(&temp, &self.path).map_err(|e| format!("Failed to replace {}: {e}", self.path.display()))?;
+        Ok(points)
+    }
+}
+
+pub(super) fn into_data(points: Vec<SnapshotPoint>) -> Vec<TelemetryData> {
+    points
+        .into_iter()
+        .map(|p| {
+            TelemetryData::new()
+                .with_timestamp(p.timestamp)
+                .with_value(TelemetryValue::from(p.value))
+                .with_quality(p.quality)
+        })
+        .collect()
+}
@@ -0,0 +1,85 @@
+// Reading the stores without taking the middleware lock
+//
+// The stores are DashMaps, sharded by store and then by field, so a read only waits
+// on a push into the same shard. Going through the middleware's mutex instead put
+// every UI poll in line behind ingest and everything else holding it. The reader
+// shares the stores, the time base clock and the field formats with the middleware,
+// so what it hands out matches what the middleware would.
+//
+// Reads that have to be consistent across fields (get_latest_consistent) still go
+// through the middleware, that's the lock that keeps a push from landing between them.
+use std::sync::Arc;
+
+use super::aggregate::{Accumulator, Aggregate, AggregateResult};
+use super::formatting::ValueFormatter;
+use super::telemetry_search::{self, Predicate, SearchResult};
+use super::telemetry_stores::{TelemetryData, TelemetryStores};
+use super::time_base::TimeBaseClock;
+use super::TelemetryDataFrontend;
+
+#[derive(Clone)]
+pub struct TelemetryReader {
+    stores: Arc<TelemetryStores>,
+    clock: TimeBaseClock,
+    formatter: ValueFormatter,
+}
+
+impl TelemetryReader {
+    pub(super) fn new(stores: Arc<TelemetryStores>, clock: TimeBaseClock, formatter: ValueFormatter) -> Self {
+        Self { stores, clock, formatter }
+    }
+
+    /// The last `count` points of a field, or all of them, in the current time base
+    pub fn get(&self, store: &str, field: &str, count: Option<usize>, formatted: bool) -> Result<Vec<TelemetryDataFrontend>, String> {
+        let data = match count {
+            Some(n) => self.stores.get_last_n(store, field, n)?.unwrap_or_default(),
+            None => self.stores.get_all(store, field)?,
+        };
+        Ok(data.into_iter().map(|d| self.frontend(store, field, d, formatted)).collect())
+    }
+
+    pub fn latest(&self, store: &str, field: &str, formatted: bool) -> Result<Option<TelemetryDataFrontend>, String> {
+        Ok(self.stores.get_last(store, field)?.map(|d| self.frontend(store, field, d, formatted)))
+    }
+
+    /// One aggregate over the last `window_ms` of a field (see aggregate), with the
+    /// ends of the window in the current time base
+    pub fn aggregate(&self, store: &str, field: &str, window_ms: Option<i64>, aggregate: Aggregate) -> Result<AggregateResult, String> {
+        if window_ms.is_some_and(|window| window <= 0) {
+            return Err("The window has to be at least 1 ms".into());
+        }
+        let mut accumulator = Accumulator::default();
+        self.stores.for_each_in_window(store, field, window_ms, |d| accumulator.add(d))?;
+        let mut result = accumulator.finish(aggregate);
+        let clock = self.clock.borrow();
+        result.from = result.from.map(|t| clock.convert(t));
+        result.to = result.to.map(|t| clock.convert(t));
+        Ok(result)
+    }
+
+    /// Where `predicate` held on a field (see telemetry_search), `from` and `to` in the
+    /// current time base
+    pub fn find(&self, store: &str, field: &str, predicate: Predicate, from: Option<i64>, to: Option<i64>) -> Result<SearchResult, String> {
+        predicate.validate()?;
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err("The time range ends before it starts".into());
+            }
+        }
+        let data = self.stores.get_all(store, field)?;
+        let clock = *self.clock.borrow();
+        Ok(telemetry_search::search(&data, predicate, from, to, |t| clock.convert(t)))
+    }
+
+    pub fn store_names(&self) -> Vec<String> {
+        self.stores.list_stores()
+    }
+
+    fn frontend(&self, store: &str, field: &str, data: TelemetryData, formatted: bool) -> TelemetryDataFrontend {
+        TelemetryDataFrontend {
+            timestamp: self.clock.borrow().convert(data.timestamp),
+            value: if formatted { self.formatter.format(store, field, &data.value) } else { data.value.to_string() },
+            quality: data.quality,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+// When did a condition hold, answered from the buffers instead of an exported CSV
+//
+// find_telemetry runs one predicate over a field and hands back every interval of
+// consecutive points that matched it, oldest first. The first time alt went over
+// 1524 m is the start of the first interval, every stretch of tilt above 20 is the
+// whole list. The predicate compares the stored value, in the field's own units
+// (field_metadata has them), not whatever the operator's display format shows.
+//   { "op": "gt", "value": 1524.0 }
+//   { "op": "between", "min": 10.0, "max": 20.0 }
+// The time range and every timestamp handed back are in the current time base, like
+// the rest of the reads. Points flagged suspect or out_of_range, and values that
+// aren't finite, are passed over without ending an interval, a single glitch
+// shouldn't split a burn in two or start one.
+use serde::{Deserialize, Serialize};
+
+use super::data_quality::DataQuality;
+use super::telemetry_stores::TelemetryData;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Predicate {
+    Gt { value: f64 },
+    Ge { value: f64 },
+    Lt { value: f64 },
+    Le { value: f64 },
+    Eq { value: f64 },
+    Ne { value: f64 },
+    // inclusive at both ends
+    Between { min: f64, max: f64 },
+    Outside { min: f64, max: f64 },
+}
+
+impl Predicate {
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            Predicate::Between { min, max } | Predicate::Outside { min, max } => {
+                if !min.is_finite() || !max.is_finite() || min > max {
+                    return Err("The predicate needs a finite min at or below its max".into());
+                }
+            }
+            Predicate::Gt { value }
+            | Predicate::Ge { value }
+            | Predicate::Lt { value }
+            | Predicate::Le { value }
+            | Predicate::Eq { value }
+            | Predicate::Ne { value } => {
+                if !value.is_finite() {
+                    return Err("The predicate needs a finite value".into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, v: f64) -> bool {
+        match *self {
+            Predicate::Gt { value } => v > value,
+            Predicate::Ge { value } => v >= value,
+            Predicate::Lt { value } => v < value,
+            Predicate::Le { value } => v <= value,
+            Predicate::Eq { value } => v == value,
+            Predicate::Ne { value } => v != value,
+            Predicate::Between { min, max } => v >= min && v <= max,
+            Predicate::Outside { min, max } => v < min || v > max,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchInterval {
+    // the first and last matching point
+    pub start: i64,
+    pub end: i64,
+    pub points: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResult {
+    pub intervals: Vec<MatchInterval>,
+    // points inside the range that were checked, and how many of them matched
+    pub searched: u64,
+    pub matched: u64,
+}
+
+/// Runs `predicate` over `data` (oldest first). `convert` maps a stored timestamp into
+/// the time base `from` and `to` are given in, and the result is handed back in.
+pub fn search(
+    data: &[TelemetryData],
+    predicate: Predicate,
+    from: Option<i64>,
+    to: Option<i64>,
+    convert: impl Fn(i64) -> i64,
+) -> SearchResult {
+    let mut result = SearchResult { intervals: Vec::new(), searched: 0, matched: 0 };
+    let mut open: Option<MatchInterval> = None;
+    for d in data {
+        let timestamp = convert(d.timestamp);
+        if from.is_some_and(|from| timestamp < from) {
+            continue;
+        }
+        if to.is_some_and(|to| timestamp > to) {
+            break;
+        }
+        let value = d.value.as_f64();
+        if !value.is_finite() || matches!(d.quality, DataQuality::Suspect | DataQuality::OutOfRange) {
+            continue;
+        }
+        result.searched += 1;
+        if predicate.matches(value) {
+            result.matched += 1;
+            let interval = open.get_or_insert(MatchInterval { start: timestamp, end: timestamp, points: 0 });
+            interval.end = timestamp;
+            interval.points += 1;
+        } else if let Some(interval) = open.take() {
+            result.intervals.push(interval);
+        }
+    }
+    result.intervals.extend(open);
+    result
+}
@@ -0,0 +1,1084 @@
+// Handles storing telemetry data and writing to CSV with dynamic fields
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicBool, Ordering};
+use dashmap::DashMap;
+use dashmap::mapref::one::Ref;
+use parking_lot::{Mutex, RwLock};
+use std::fmt;
+
+use super::buffer_policy::{BufferConfig, BufferLimit};
+use super::csv_rotation::{self, RecordingSegment, RotationConfig};
+use super::data_quality::DataQuality;
+use super::drops::SharedDrops;
+use super::encryption::{SessionEncryption, SessionWriter};
+use super::flush_policy::FlushPolicy;
+use super::lock_timing::TimedMutex;
+use super::recording_compression::RecordingCompression;
+use super::recording_format::{self, RecordingFormat};
+use super::recording_resume::{self, ResumePoint};
+use super::time_base::TimeBaseClock;
+
+// how long closing the app waits on each store's writer
+const SHUTDOWN_WAIT: Duration = Duration::from_secs(5);
+
+// a timestamp and each column's value at it, None where the field has nothing yet
+type Row = (i64, Vec<(String, Option<TelemetryValue>)>);
+
+// list of stores
+pub struct TelemetryStores {
+    stores: DashMap<String, TelemetryStore>,
+    clock: TimeBaseClock,
+    drops: SharedDrops,
+    encryption: SessionEncryption,
+    buffers: TimedMutex<BufferConfig>,
+    // shared with every writer task, read as rows go out
+    rotation: Arc<RwLock<RotationConfig>>,
+}
+impl TelemetryStores {
+    pub fn new(clock: TimeBaseClock, drops: SharedDrops, encryption: SessionEncryption) -> Self {
+        TelemetryStores { 
+            stores: DashMap::new(),
+            clock,
+            drops,
+            encryption,
+            buffers: TimedMutex::new("buffer_policy", BufferConfig::default()),
+            rotation: Arc::new(RwLock::new(RotationConfig::default())),
+        }
+    }
+
+    pub fn buffer_config(&self) -> BufferConfig {
+        self.buffers.lock().clone()
+    }
+
+    /// Swaps the buffer limits, trimming what's already buffered to the new ones
+    pub fn set_buffer_config(&self, config: BufferConfig) -> Result<(), String> {
+        config.validate()?;
+        // in first, so a field that turns up while we trim already gets the new limits
+        *self.buffers.lock() = config.clone();
+        for mut store in self.stores.iter_mut() {
+            let store_name = store.key().clone();
+            store.apply_limits(|field| config.limit(&store_name, field));
+        }
+        Ok(())
+    }
+
+    pub fn rotation(&self) -> RotationConfig {
+        *self.rotation.read()
+    }
+
+    // every writer picks it up on its next row
+    pub fn set_rotation(&self, config: RotationConfig) -> Result<(), String> {
+        config.validate()?;
+        *self.rotation.write() = config;
+        Ok(())
+    }
+
+    pub fn segments(&self, store_name: &str) -> Result<Vec<RecordingSegment>, String> {
+        Ok(self.get_store(store_name)?.segments.lock().clone())
+    }
+
+    /// Stops every writer and waits for them to get their files onto disk, up to
+    /// SHUTDOWN_WAIT each. Blocks, it's for the app closing and nothing else.
+    pub fn shutdown(&self) {
+        // iterate over all the stores we have
+        for store in self.stores.iter() {
+            store.value().shutdown_and_wait();
+        }
+    }
+
+    // flush and fsync every store's files now, recording or not
+    pub fn sync_all(&self) {
+        for store in self.stores.iter() {
+            let _ = store.csv_tx.try_send(CsvCommand::Sync);
+        }
+    }
+
+    pub fn create_new_store(&self, store_name: &str, path: PathBuf) -> Result<(), String>{
+        self.stores.
+        entry(store_name.to_string()).
+        or_insert_with(|| TelemetryStore::new(
+            path,
+            self.clock.clone(),
+            self.drops.clone(),
+            format!("csv.{store_name}"),
+            &self.encryption,
+            self.rotation.clone(),
+        ));
+
+        Ok(())
+    }
+
+    // closes the CSV, whatever was buffered is gone
+    pub fn remove_store(&self, store_name: &str) -> Result<(), String> {
+        let (_, store) = self.stores
+            .remove(store_name)
+            .ok_or_else(|| format!("No store named '{}'", store_name))?;
+        store.shutdown();
+        Ok(())
+    }
+
+    // every buffered point, store -> field -> data
+    pub fn snapshot(&self) -> BTreeMap<String, BTreeMap<String, Vec<TelemetryData>>> {
+        self.stores
+            .iter()
+            .map(|store| {
+                let fields = store
+                    .fields
+                    .iter()
+                    .map(|f| (f.key().clone(), f.value().iter().cloned().collect()))
+                    .collect();
+                (store.key().clone(), fields)
+            })
+            .collect()
+    }
+
+    pub fn store_snapshot(&self, store_name: &str) -> Result<BTreeMap<String, Vec<TelemetryData>>, String> {
+        let store = self.get_store(store_name)?;
+        Ok(store
+            .fields
+            .iter()
+            .map(|f| (f.key().clone(), f.value().iter().cloned().collect()))
+            .collect())
+    }
+
+    // drops the in-memory buffers, stores and their CSVs stay open
+    pub fn clear_all(&self) {
+        for mut store in self.stores.iter_mut() {
+            store.clear();
+        }
+    }
+
+    // push whatever rows are queued out to disk without stopping anything
+    pub fn flush_all(&self) {
+        for store in self.stores.iter() {
+            store.flush_row();
+        }
+    }
+
+    // rows waiting on each store's CSV writer
+    pub fn writer_queue_depths(&self) -> Vec<(String, usize)> {
+        self.stores
+            .iter()
+            .map(|store| (store.key().clone(), store.csv_tx.max_capacity() - store.csv_tx.capacity()))
+            .collect()
+    }
+
+    pub fn store_path(&self, store_name: &str) -> Result<PathBuf, String> {
+        Ok(self.get_store(store_name)?.path.clone())
+    }
+
+    pub fn list_stores(&self) -> Vec<String> {
+        self.stores.iter().map(|s| s.key().clone()).collect()
+    }
+    
+    pub fn has_store(&self, store_name: &str) -> bool {
+        self.stores.contains_key(store_name)
+    }
+
+    pub fn push(&self, store_name: &str, field: &str, data: TelemetryData) -> Result<(), String> {
+        let mut store = self.stores.get_mut(store_name).ok_or_else(|| format!("No store named '{}'", store_name))?;
+
+        if !store.limits.contains_key(field) {
+            let limit = self.buffers.lock().limit(store_name, field);
+            store.limits.insert(field.to_string(), limit);
+        }
+        store.push(field, data);
+        Ok(())
+    }
+
+    /// Puts `data` (oldest first) back in front of a field's buffer, only the points
+    /// older than anything already there. Returns how many stayed after the limits.
+    pub fn restore(&self, store_name: &str, field: &str, data: Vec<TelemetryData>) -> Result<usize, String> {
+        let mut store = self.stores.get_mut(store_name).ok_or_else(|| format!("No store named '{}'", store_name))?;
+
+        if !store.limits.contains_key(field) {
+            let limit = self.buffers.lock().limit(store_name, field);
+            store.limits.insert(field.to_string(), limit);
+        }
+        Ok(store.restore(field, data))
+    }
+
+    pub fn get_last(&self, store_name: &str, field: &str) -> Result<Option<TelemetryData>, String> {
+        let store = self.get_store(store_name)?;
+
+        store.get_last(field)
+    }
+
+    pub fn get_last_n(&self, store_name: &str, field: &str, n: usize) -> Result<Option<Vec<TelemetryData>>, String> {
+        let store = self.get_store(store_name)?;
+
+        store.get_last_n(field, n)
+    }
+
+    pub fn get_all(&self, store_name: &str, field: &str) -> Result<Vec<TelemetryData>, String> {
+        let store = self.get_store(store_name)?;
+
+        store.get_all(field)
+    }
+
+    // newest first, back to `window_ms` before the newest point, or all of them
+    pub fn for_each_in_window(
+        &self,
+        store_name: &str,
+        field: &str,
+        window_ms: Option<i64>,
+        f: impl FnMut(&TelemetryData),
+    ) -> Result<(), String> {
+        let store = self.get_store(store_name)?;
+
+        store.for_each_in_window(field, window_ms, f)
+    }
+
+    pub fn get_field_names(&self, store_name: &str) -> Result<Vec<String>, String> {
+        Ok(self.get_store(store_name)?.get_field_keys())
+    }
+
+    fn get_store(&self, store_name: &str,) -> Result<Ref<'_, String, TelemetryStore>, String> {
+        self.stores
+            .get(store_name)
+            .ok_or_else(|| format!("No store named '{}'", store_name))
+    }
+
+    // `header` is the columns the CSV starts with, anything else is added after them
+    pub fn start_recording(
+        &self,
+        store_name: &str,
+        header: &[String],
+        format: RecordingFormat,
+        compression: RecordingCompression,
+        flush: FlushPolicy,
+    ) -> Result<(), String> {
+        self.get_store(store_name)?.start_recording(header, format, compression, flush);
+        Ok(())
+    }
+
+    /// As start_recording, but appending to an earlier session's CSV (see recording_resume)
+    pub fn resume_recording(&self, store_name: &str, point: ResumePoint, flush: FlushPolicy) -> Result<(), String> {
+        self.get_store(store_name)?.resume_recording(point, flush);
+        Ok(())
+    }
+
+    pub fn stop_recording(&self, store_name: &str) -> Result<(), String> {
+        self.get_store(store_name)?.stop_recording();
+        Ok(())
+    }
+
+    /// Writes the buffered rows from `since` (unix ms) to the store's CSV, ahead of the
+    /// ones recording writes from here on. Returns how many went out.
+    pub fn backfill(&self, store_name: &str, since: i64) -> Result<usize, String> {
+        Ok(self.get_store(store_name)?.backfill(since))
+    }
+
+    pub fn is_recording(&self, store_name: &str) -> Result<bool, String> {
+        Ok(self.get_store(store_name)?.recording.load(Ordering::Acquire))
+    }
+
+
+}
+
+// A store has all grouped items under one label, 
+//  that will be written into it's own CSV file
+#[derive(Debug)]
+struct TelemetryStore {
+    fields: DashMap<String, VecDeque<TelemetryData>>,
+    // each field's buffer_policy limits, resolved on its first point
+    limits: HashMap<String, BufferLimit>,
+    path: PathBuf,
+    clock: TimeBaseClock,
+    drops: SharedDrops,
+    // what our dropped rows are counted under
+    drop_site: String,
+
+    csv_tx: tokio::sync::mpsc::Sender<CsvCommand>,
+    recording: AtomicBool,
+    // the CSV's segments so far, kept by the writer task (see csv_rotation)
+    segments: Arc<Mutex<Vec<RecordingSegment>>>,
+    format: Mutex<RecordingFormat>,
+    // the writer task says when its files are closed
+    done: Mutex<std::sync::mpsc::Receiver<()>>,
+
+    current_timestamp: Option<i64>,
+}
+impl TelemetryStore {
+    fn new(
+        path: PathBuf,
+        clock: TimeBaseClock,
+        drops: SharedDrops,
+        drop_site: String,
+        encryption: &SessionEncryption,
+        rotation: Arc<RwLock<RotationConfig>>,
+    ) -> Self {
+        let (tx, rx) = tokio::sync::mpsc::channel(1024);
+        let segments = Arc::new(Mutex::new(Vec::new()));
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+
+        spawn_csv_writer_task(rx, path.clone(), encryption.clone(), rotation, segments.clone(), done_tx);
+
+        Self { 
+            fields: DashMap::new(),
+            limits: HashMap::new(),
+            path,
+            clock,
+            drops,
+            drop_site,
+
+            csv_tx: tx,
+            recording: AtomicBool::new(false),
+            segments,
+            format: Mutex::new(RecordingFormat::default()),
+            done: Mutex::new(done_rx),
+
+            current_timestamp: None, 
+        }
+    }
+
+    // tell our async thread to close the file handle
+    fn shutdown(&self) {
+        self.recording.store(false, Ordering::Release);
+        let _ = self.csv_tx.try_send(CsvCommand::Stop);
+    }
+
+    // a full queue still drains, so keep offering the stop until it fits
+    fn shutdown_and_wait(&self) {
+        self.recording.store(false, Ordering::Release);
+        let deadline = Instant::now() + SHUTDOWN_WAIT;
+        let mut command = CsvCommand::Stop;
+        loop {
+            match self.csv_tx.try_send(command) {
+                Ok(()) => break,
+                Err(tokio::sync::mpsc::error::TrySendError::Full(c)) if Instant::now() < deadline => {
+                    command = c;
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(_) => {
+                    eprintln!("[telemetry] {} writer didn't stop, rows still queued are lost", self.path.display());
+                    return;
+                }
+            }
+        }
+        if self.done.lock().recv_timeout(deadline.saturating_duration_since(Instant::now())).is_err() {
+            eprintln!("[telemetry] {} writer didn't finish in time", self.path.display());
+        }
+    }
+
+
+    fn start_recording(&self, header: &[String], format: RecordingFormat, compression: RecordingCompression, flush: FlushPolicy) {
+        *self.format.lock() = format;
+        let _ = self.csv_tx.try_send(CsvCommand::Policy(flush));
+        let _ = self.csv_tx.try_send(CsvCommand::Compression(compression));
+        if !header.is_empty() {
+            let _ = self.csv_tx.try_send(CsvCommand::Header(header.to_vec()));
+        }
+        self.recording.store(true, Ordering::Release);
+    }
+
+    fn resume_recording(&self, point: ResumePoint, flush: FlushPolicy) {
+        *self.format.lock() = RecordingFormat::Csv;
+        let _ = self.csv_tx.try_send(CsvCommand::Policy(flush));
+        // the file carried on is plain, so are the segments after it
+        let _ = self.csv_tx.try_send(CsvCommand::Compression(RecordingCompression::None));
+        let _ = self.csv_tx.try_send(CsvCommand::Resume(point));
+        self.recording.store(true, Ordering::Release);
+    }
+
+    fn stop_recording(&self) {
+        // stop accepting new rows to the reader
+        self.recording.store(false, Ordering::Release);
+
+        // flush pending data async
+        let _ = self.csv_tx.try_send(CsvCommand::Flush);
+    }
+
+    fn push(&mut self, field: &str, data: TelemetryData) {
+        if self.current_timestamp != Some(data.timestamp) { // if our last recorded timestamp doesn't match the timestamp of our current datapoint
+            if self.recording.load(Ordering::Acquire) { // if we're recording
+                self.write_row(); // write the current row of data to the csv before getting any new data
+            }
+                    
+            self.current_timestamp = Some(data.timestamp); // update our timestamp
+        }
+
+        let limit = self.limits.get(field).copied();
+        let mut field_vec = self.fields
+            .entry(field.to_string())
+            .or_default();
+        field_vec.push_back(data);
+        if let Some(limit) = limit {
+            evict(&mut field_vec, limit);
+        }
+    }
+
+    // never written to the CSV, those points were recorded (or not) by the session they came from
+    fn restore(&mut self, field: &str, data: Vec<TelemetryData>) -> usize {
+        let limit = self.limits.get(field).copied();
+        let mut field_vec = self.fields
+            .entry(field.to_string())
+            .or_default();
+        let before = field_vec.len();
+        let oldest = field_vec.front().map(|d| d.timestamp);
+        for d in data.into_iter().rev().filter(|d| oldest.is_none_or(|t| d.timestamp < t)) {
+            field_vec.push_front(d);
+        }
+        if let Some(limit) = limit {
+            evict(&mut field_vec, limit);
+        }
+        field_vec.len().saturating_sub(before)
+    }
+
+    // re-resolves every field's limits and trims its buffer down to them
+    fn apply_limits(&mut self, limit: impl Fn(&str) -> BufferLimit) {
+        self.limits.clear();
+        for mut entry in self.fields.iter_mut() {
+            let field_limit = limit(entry.key());
+            evict(entry.value_mut(), field_limit);
+            self.limits.insert(entry.key().clone(), field_limit);
+        }
+    }
+
+    fn write_row(&self) {
+        let values = self
+            .fields
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().back().map(|d| d.value)))
+            .collect();
+        let timestamp = self.current_timestamp.unwrap_or(0);
+
+        // send our command through the channel to be written to csv async
+        self.send_rows(vec![(timestamp, values)]);
+    }
+
+    // rows in the recording's format, dropped (and counted) if the writer is that far behind
+    fn send_rows(&self, rows: Vec<Row>) -> bool {
+        let count = rows.len() as u64;
+        let clock = *self.clock.borrow();
+        let command = match *self.format.lock() {
+            RecordingFormat::Csv => CsvCommand::Rows(
+                rows.into_iter()
+                    .map(|(timestamp, values)| {
+                        let mut row: HashMap<String, String> = values
+                            .into_iter()
+                            .map(|(field, value)| (field, value.map(|v| v.to_string()).unwrap_or_default()))
+                            .collect();
+                        // add timestamp, raw unix ms plus the operator's chosen time base
+                        row.insert("timestamp".to_owned(), timestamp.to_string());
+                        row.insert("time".to_owned(), clock.label(timestamp));
+                        row
+                    })
+                    .collect(),
+            ),
+            RecordingFormat::Jsonl => CsvCommand::Lines(
+                rows.into_iter()
+                    .map(|(timestamp, values)| recording_format::json_line(timestamp, &clock.label(timestamp), values))
+                    .collect(),
+            ),
+        };
+        if let Err(tokio::sync::mpsc::error::TrySendError::Full(_)) = self.csv_tx.try_send(command) {
+            self.drops.note(&self.drop_site, count);
+            return false;
+        }
+        true
+    }
+
+    // the rows write_row would have written from `since` up to the current one, each
+    // field holding its last value the same way
+    fn backfill(&self, since: i64) -> usize {
+        let Some(until) = self.current_timestamp else {
+            return 0;
+        };
+        let buffers: Vec<(String, Vec<TelemetryData>)> = self
+            .fields
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().iter().filter(|d| d.timestamp < until).cloned().collect()))
+            .collect();
+        let timestamps: BTreeSet<i64> = buffers
+            .iter()
+            .flat_map(|(_, data)| data.iter().map(|d| d.timestamp))
+            .filter(|&t| t >= since)
+            .collect();
+
+        let mut cursors = vec![0usize; buffers.len()];
+        let rows: Vec<Row> = timestamps
+            .into_iter()
+            .map(|timestamp| {
+                let values = buffers
+                    .iter()
+                    .zip(cursors.iter_mut())
+                    .map(|((field, data), cursor)| {
+                        while data.get(*cursor).is_some_and(|d| d.timestamp <= timestamp) {
+                            *cursor += 1;
+                        }
+                        (field.clone(), cursor.checked_sub(1).map(|i| data[i].value))
+                    })
+                    .collect();
+                (timestamp, values)
+            })
+            .collect();
+
+        let count = rows.len();
+        if count == 0 || !self.send_rows(rows) {
+            return 0;
+        }
+        count
+    }
+
+    fn flush_row(&self) {
+        let _ = self.csv_tx.try_send(CsvCommand::Flush);
+    }
+
+    fn reset_row(&mut self) {
+        self.current_timestamp = None;
+    }
+
+    fn clear(&mut self) {
+        self.fields.clear();
+        self.reset_row();
+    }
+
+
+    fn get_last(&self, field: &str) -> Result<Option<TelemetryData>, String> {
+        Ok(
+            self.fields
+            .get(field)
+            .map(|v| v.back().cloned())
+            .ok_or_else(|| format!("No field named '{}'", field))
+            .ok()
+            .flatten()
+        )
+    }
+
+    fn get_last_n(&self, field: &str, n: usize) -> Result<Option<Vec<TelemetryData>>, String> {
+        let vec = self
+            .fields
+            .get(field)
+            .ok_or_else(|| format!("No field named '{}'", field))?;
+
+        if vec.is_empty() || n == 0 {
+            return Ok(None);
+        }
+
+        let start = vec.len().saturating_sub(n);
+        Ok(Some(vec.iter().skip(start).cloned().collect()))
+    }
+
+    fn get_all(&self, field: &str) -> Result<Vec<TelemetryData>, String> {
+        self.fields
+            .get(field)
+            .map(|v| v.iter().cloned().collect())
+            .ok_or_else(|| format!("No field named '{}'", field))
+    }
+
+    fn for_each_in_window(&self, field: &str, window_ms: Option<i64>, mut f: impl FnMut(&TelemetryData)) -> Result<(), String> {
+        let data = self
+            .fields
+            .get(field)
+            .ok_or_else(|| format!("No field named '{}'", field))?;
+        let Some(newest) = data.back().map(|d| d.timestamp) else {
+            return Ok(());
+        };
+        for d in data.iter().rev() {
+            if window_ms.is_some_and(|window| d.timestamp < newest - window) {
+                break;
+            }
+            f(d);
+        }
+        Ok(())
+    }
+
+    fn get_field_keys(&self) -> Vec<String> {
+        self.fields.iter().map(|e| e.key().clone()).collect() 
+    }
+
+}
+
+// oldest points out until the buffer is inside both limits
+fn evict(buffer: &mut VecDeque<TelemetryData>, limit: BufferLimit) {
+    if let Some(max_points) = limit.max_points {
+        let excess = buffer.len().saturating_sub(max_points);
+        buffer.drain(..excess);
+    }
+    if let (Some(max_age_ms), Some(newest)) = (limit.max_age_ms, buffer.back().map(|d| d.timestamp)) {
+        while buffer.front().is_some_and(|d| d.timestamp < newest - max_age_ms) {
+            buffer.pop_front();
+        }
+    }
+}
+
+
+
+// single datapoint
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetryData {
+    pub timestamp: i64,
+    pub value: TelemetryValue,
+    // left out when valid, most points are and the windows get a lot of them
+    #[serde(skip_serializing_if = "DataQuality::is_valid")]
+    pub quality: DataQuality,
+}
+impl TelemetryData {
+    pub fn new() -> Self {
+        Self {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            value: TelemetryValue::default(),
+            quality: DataQuality::Valid,
+        }
+    }
+    pub fn with_timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+    pub fn with_value<T: Into<TelemetryValue>>(mut self, value: T) -> Self {
+        self.value = value.into();
+        self
+    }
+    pub fn with_quality(mut self, quality: DataQuality) -> Self {
+        self.quality = quality;
+        self
+    }
+}
+impl Serialize for TelemetryValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            TelemetryValue::F64(v) => serializer.serialize_f64(*v),
+            TelemetryValue::I64(v) => serializer.serialize_i64(*v),
+            TelemetryValue::U64(v) => serializer.serialize_u64(*v),
+            TelemetryValue::Bool(v) => serializer.serialize_bool(*v),
+        }
+    }
+}
+impl Default for TelemetryData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TelemetryValue {
+    F64(f64),
+    I64(i64),
+    U64(u64),
+    Bool(bool),
+}
+impl Default for TelemetryValue {
+    fn default() -> Self {
+        Self::F64(0.0)
+    }
+}
+impl TelemetryValue {
+    // numeric view for thresholds and math, bools map to 0/1
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            TelemetryValue::F64(v) => *v,
+            TelemetryValue::I64(v) => *v as f64,
+            TelemetryValue::U64(v) => *v as f64,
+            TelemetryValue::Bool(v) => *v as u8 as f64,
+        }
+    }
+}
+impl From<f64> for TelemetryValue {
+    fn from(v: f64) -> Self {
+        TelemetryValue::F64(v)
+    }
+}
+impl From<i64> for TelemetryValue {
+    fn from(v: i64) -> Self {
+        TelemetryValue::I64(v)
+    }
+}
+impl From<u64> for TelemetryValue {
+    fn from(v: u64) -> Self {
+        TelemetryValue::U64(v)
+    }
+}
+impl From<bool> for TelemetryValue {
+    fn from(v: bool) -> Self {
+        TelemetryValue::Bool(v)
+    }
+}
+impl From<i32> for TelemetryValue {
+    fn from(v: i32) -> Self {
+        TelemetryValue::I64(v as i64)
+    }
+}
+impl From<u32> for TelemetryValue {
+    fn from(v: u32) -> Self {
+        TelemetryValue::U64(v as u64)
+    }
+}
+impl fmt::Display for TelemetryValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TelemetryValue::F64(v) => write!(f, "{}", v),
+            TelemetryValue::I64(v) => write!(f, "{}", v),
+            TelemetryValue::U64(v) => write!(f, "{}", v),
+            TelemetryValue::Bool(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+
+// used for async writing of our csv files to keep the main program thread responsive
+enum CsvCommand {
+    // the columns to start with, ignored once the header is written
+    Header(Vec<String>),
+    // in order, in one message so a burst of them can't fill the queue
+    Rows(Vec<HashMap<String, String>>),
+    // the same for a JSON Lines recording (see recording_format)
+    Lines(Vec<String>),
+    // how often to flush from here on (see flush_policy)
+    Policy(FlushPolicy),
+    // for the files opened from here on (see recording_compression)
+    Compression(RecordingCompression),
+    Flush,
+    // flush and fsync, for the moments a recording can't lose
+    Sync,
+    // carry on an earlier file instead of this one
+    Resume(ResumePoint),
+    Stop,
+}
+
+fn spawn_csv_writer_task(
+    mut rx: tokio::sync::mpsc::Receiver<CsvCommand>,
+    path: PathBuf,
+    encryption: SessionEncryption,
+    rotation: Arc<RwLock<RotationConfig>>,
+    segments: Arc<Mutex<Vec<RecordingSegment>>>,
+    done: std::sync::mpsc::Sender<()>,
+) { tokio::spawn(async move {
+        
+    let (file, sync) = encryption.create_synced(&path)
+        .expect("failed to create CSV file");
+
+    let mut task = CsvWriter {
+        writer: csv::Writer::from_writer(file),
+        sync,
+        path,
+        encryption,
+        rotation,
+        rotation_failed: false,
+        segments,
+        headers: Vec::new(),
+        buffered_rows: Vec::new(),
+        header_written: false,
+        jsonl: None,
+        jsonl_failed: false,
+        compression: RecordingCompression::None,
+        policy: FlushPolicy::default(),
+        unflushed: 0,
+        last_flush: Instant::now(),
+    };
+
+    while let Some(cmd) = rx.recv().await {
+        match cmd {
+            CsvCommand::Stop => break,
+            cmd => task.handle(cmd),
+        }
+    }
+
+    task.finish();
+    let _ = done.send(());
+    });
+}
+
+// everything one store's writer task owns
+struct CsvWriter {
+    writer: csv::Writer<SessionWriter>,
+    // the current segment again, for fsync
+    sync: File,
+    path: PathBuf,
+    encryption: SessionEncryption,
+    rotation: Arc<RwLock<RotationConfig>>,
+    rotation_failed: bool,
+    segments: Arc<Mutex<Vec<RecordingSegment>>>,
+    headers: Vec<String>,
+    // rows from before the header is known
+    buffered_rows: Vec<HashMap<String, String>>,
+    header_written: bool,
+    // opened on the first line, most recordings are CSV only
+    jsonl: Option<(BufWriter<SessionWriter>, File)>,
+    jsonl_failed: bool,
+    compression: RecordingCompression,
+    policy: FlushPolicy,
+    // rows since the last flush
+    unflushed: u64,
+    last_flush: Instant,
+}
+
+impl CsvWriter {
+    fn handle(&mut self, cmd: CsvCommand) {
+        match cmd {
+            CsvCommand::Header(fields) => {
+                if !self.header_written {
+                    self.headers = vec!["timestamp".to_owned(), "time".to_owned()];
+                    for field in fields {
+                        if !self.headers.contains(&field) {
+                            self.headers.push(field);
+                        }
+                    }
+                }
+            }
+            CsvCommand::Rows(rows) => {
+                self.unflushed += rows.len() as u64;
+                if !self.header_written {
+                    self.buffered_rows.extend(rows);
+                } else {
+                    for row in rows {
+                        self.write_row(row);
+                    }
+                }
+                self.flush_if_due();
+            }
+            CsvCommand::Lines(lines) => {
+                self.unflushed += lines.len() as u64;
+                self.write_lines(lines);
+                self.flush_if_due();
+            }
+            CsvCommand::Policy(policy) => self.policy = policy,
+            CsvCommand::Compression(compression) => self.set_compression(compression),
+            CsvCommand::Flush => self.flush(),
+            CsvCommand::Sync => {
+                self.flush();
+                self.sync();
+            }
+            CsvCommand::Resume(point) => self.resume(point),
+            CsvCommand::Stop => {}
+        }
+    }
+
+    fn resume(&mut self, point: ResumePoint) {
+        let opened = recording_resume::open_for_append(&point.path)
+            .and_then(|file| file.try_clone().map(|sync| (file, sync)).map_err(|e| e.to_string()));
+        let (file, sync) = match opened {
+            Ok(opened) => opened,
+            Err(e) => {
+                eprintln!("[telemetry] couldn't resume {}, recording here instead: {e}", point.path.display());
+                return;
+            }
+        };
+        self.flush();
+        let bytes = file.metadata().map(|m| m.len()).unwrap_or(0);
+        self.replace_writer(Box::new(file), sync);
+        self.path = point.base;
+        self.headers = point.headers;
+        self.header_written = true;
+        self.buffered_rows.clear();
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut segments = self.segments.lock();
+        segments.clear();
+        segments.push(RecordingSegment {
+            index: point.segment,
+            path: point.path,
+            started_at: now,
+            ended_at: None,
+            rows: 0,
+            bytes,
+        });
+    }
+
+    // nothing is in the file until the header, so until then it can be reopened
+    // compressed (or not) in place of the one made when the store was
+    fn set_compression(&mut self, compression: RecordingCompression) {
+        let current = self.compression.file_path(&self.path);
+        self.compression = compression;
+        if self.header_written || current == compression.file_path(&self.path) {
+            return;
+        }
+        match self.open(&self.path) {
+            Ok((file, sync)) => {
+                self.replace_writer(file, sync);
+                std::fs::remove_file(&current).ok();
+            }
+            Err(e) => eprintln!("[telemetry] {e}, {} stays as it is", current.display()),
+        }
+    }
+
+    // a new file at `path`, through the compression and encryption
+    fn open(&self, path: &Path) -> Result<(SessionWriter, File), String> {
+        let path = self.compression.file_path(path);
+        let (file, sync) = self.encryption.create_synced(&path)?;
+        Ok((self.compression.wrap(file), sync))
+    }
+
+    // dropping the old writer finishes its file (a gzip trailer, the last encrypted
+    // chunk), then it's synced
+    fn replace_writer(&mut self, writer: SessionWriter, sync: File) {
+        drop(std::mem::replace(&mut self.writer, csv::Writer::from_writer(writer)));
+        self.sync.sync_data().ok();
+        self.sync = sync;
+    }
+
+    fn write_row(&mut self, row: HashMap<String, String>) {
+        let bytes = write_csv_row(&mut self.writer, &self.headers, row);
+        note_row(&self.segments, bytes);
+        if !self.rotation_failed {
+            self.rotation_failed = !self.rotate_if_due();
+        }
+    }
+
+    fn write_lines(&mut self, lines: Vec<String>) {
+        if self.jsonl.is_none() && !self.jsonl_failed {
+            let jsonl_path = self.path.with_extension("jsonl");
+            match self.open(&jsonl_path) {
+                Ok((file, sync)) => self.jsonl = Some((BufWriter::new(file), sync)),
+                Err(e) => {
+                    eprintln!("[telemetry] JSON Lines recording failed for {}: {e}", jsonl_path.display());
+                    self.jsonl_failed = true;
+                }
+            }
+        }
+        if let Some((file, _)) = self.jsonl.as_mut() {
+            for line in lines {
+                let _ = writeln!(file, "{line}");
+            }
+        }
+    }
+
+    fn flush_if_due(&mut self) {
+        if self.policy.due(self.unflushed, self.last_flush.elapsed()) {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if !self.header_written && !self.buffered_rows.is_empty() {
+            // build header
+            for row in &self.buffered_rows {
+                for k in row.keys() {
+                    if !self.headers.contains(k) {
+                        self.headers.push(k.clone());
+                    }
+                }
+            }
+
+            self.writer.write_record(&self.headers).ok();
+            start_segment(&self.segments, &self.path, self.compression, &self.headers);
+            self.header_written = true;
+
+            for row in std::mem::take(&mut self.buffered_rows) {
+                self.write_row(row);
+            }
+        }
+
+        self.writer.flush().ok();
+        if let Some((file, _)) = self.jsonl.as_mut() {
+            file.flush().ok();
+        }
+        self.unflushed = 0;
+        self.last_flush = Instant::now();
+    }
+
+    // after a flush, so the file has it all
+    fn sync(&self) {
+        self.sync.sync_data().ok();
+        if let Some((_, sync)) = &self.jsonl {
+            sync.sync_data().ok();
+        }
+    }
+
+    fn finish(&mut self) {
+        self.flush();
+        // dropped for the same reason as in replace_writer
+        drop(std::mem::replace(&mut self.writer, csv::Writer::from_writer(Box::new(std::io::sink()))));
+        self.sync.sync_data().ok();
+        if let Some((file, sync)) = self.jsonl.take() {
+            drop(file);
+            sync.sync_data().ok();
+        }
+        if let Some(segment) = self.segments.lock().last_mut() {
+            segment.ended_at = Some(chrono::Utc::now().timestamp_millis());
+        }
+    }
+
+    // rolls over to the next segment once the current one is past a limit, false if the
+    // next one couldn't be created
+    fn rotate_if_due(&mut self) -> bool {
+        let rotation = *self.rotation.read();
+        let now = chrono::Utc::now().timestamp_millis();
+        let (due, index) = {
+            let segments = self.segments.lock();
+            let due = segments.last().is_some_and(|s| rotation.due(s.bytes, s.started_at, now));
+            (due, next_index(&segments))
+        };
+        if !due {
+            return true;
+        }
+
+        let path = csv_rotation::segment_path(&self.path, index);
+        match self.open(&path) {
+            Ok((file, sync)) => {
+                self.writer.flush().ok();
+                self.replace_writer(file, sync);
+                self.writer.write_record(&self.headers).ok();
+                start_segment(&self.segments, &self.path, self.compression, &self.headers);
+                true
+            }
+            Err(e) => {
+                // one try, not one per row, the recording carries on in the current file
+                eprintln!("[telemetry] rotation stopped for {}: {e}", self.path.display());
+                false
+            }
+        }
+    }
+}
+
+// bytes the row took, near enough: quoting isn't counted
+fn write_csv_row(
+    writer: &mut csv::Writer<SessionWriter>,
+    headers: &[String],
+    row: HashMap<String, String>,
+) -> u64 {
+    let record = headers
+        .iter()
+        .map(|h| row.get(h).cloned().unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    let _ = writer.write_record(&record);
+    record_bytes(&record)
+}
+
+// the fields plus a comma or newline after each
+fn record_bytes(record: &[String]) -> u64 {
+    record.iter().map(|field| field.len() as u64 + 1).sum()
+}
+
+// a resumed recording's segments don't start at 1
+fn next_index(segments: &[RecordingSegment]) -> u32 {
+    segments.last().map_or(1, |s| s.index + 1)
+}
+
+fn start_segment(
+    segments: &Mutex<Vec<RecordingSegment>>,
+    base: &Path,
+    compression: RecordingCompression,
+    headers: &[String],
+) {
+    let mut segments = segments.lock();
+    let now = chrono::Utc::now().timestamp_millis();
+    if let Some(last) = segments.last_mut() {
+        last.ended_at = Some(now);
+    }
+    let index = next_index(&segments);
+    segments.push(RecordingSegment {
+        index,
+        path: compression.file_path(&csv_rotation::segment_path(base, index)),
+        started_at: now,
+        ended_at: None,
+        rows: 0,
+        bytes: record_bytes(headers),
+    });
+}
+
+fn note_row(segments: &Mutex<Vec<RecordingSegment>>, bytes: u64) {
+    if let Some(segment) = segments.lock().last_mut() {
+        segment.rows += 1;
+        segment.bytes += bytes;
+    }
+}
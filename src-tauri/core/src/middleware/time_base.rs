@@ -0,0 +1,81 @@
+// One place that decides what "t" means, so plots, CSVs and reports agree
+//
+// Everything is stored as unix milliseconds; the time base only changes how a
+// timestamp is handed out. MET counts from T-0, which defaults to when the ground
+// station started until someone sets the real one.
+use chrono::{Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeBase {
+    // mission elapsed time, negative before T-0
+    Met,
+    #[default]
+    Utc,
+    Local,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TimeBaseConfig {
+    pub base: TimeBase,
+    // unix ms
+    pub t0: i64,
+}
+
+impl TimeBaseConfig {
+    /// Numeric timestamp in this base, milliseconds.
+    /// Local is shifted by the UTC offset so a plot axis reads wall clock time.
+    pub fn convert(&self, timestamp: i64) -> i64 {
+        match self.base {
+            TimeBase::Met => timestamp - self.t0,
+            TimeBase::Utc => timestamp,
+            TimeBase::Local => match Local.timestamp_millis_opt(timestamp).single() {
+                Some(t) => timestamp + t.offset().local_minus_utc() as i64 * 1000,
+                None => timestamp,
+            },
+        }
+    }
+
+    /// Human readable form, e.g. "T+00:01:23.456" or an RFC 3339 string
+    pub fn label(&self, timestamp: i64) -> String {
+        match self.base {
+            TimeBase::Met => format_met(timestamp - self.t0),
+            TimeBase::Utc => Utc
+                .timestamp_millis_opt(timestamp)
+                .single()
+                .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+                .unwrap_or_default(),
+            TimeBase::Local => Local
+                .timestamp_millis_opt(timestamp)
+                .single()
+                .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Millis, false))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+fn format_met(elapsed_ms: i64) -> String {
+    let sign = if elapsed_ms < 0 { '-' } else { '+' };
+    let ms = elapsed_ms.unsigned_abs();
+    format!(
+        "T{}{:02}:{:02}:{:02}.{:03}",
+        sign,
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+// shared with the CSV writers so files agree with what the windows show
+pub type TimeBaseClock = Arc<watch::Sender<TimeBaseConfig>>;
+
+pub fn new_clock() -> TimeBaseClock {
+    Arc::new(watch::Sender::new(TimeBaseConfig {
+        base: TimeBase::default(),
+        t0: Utc::now().timestamp_millis(),
+    }))
+}
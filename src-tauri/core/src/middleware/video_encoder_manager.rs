@@ -1,16 +1,17 @@
 // Specifically for encoding/writing video into MJPEG files
 
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::io::Write;
 use std::process::{Command, Stdio};
-use tauri::async_runtime;
 use uuid::Uuid;
 use tokio::sync::mpsc;
 
 
 
 use crate::middleware::video_streams::VideoFrame;
+use crate::middleware::drops::SharedDrops;
+use crate::middleware::lock_timing::TimedMutex;
 
 pub type EncoderId = Uuid;
 
@@ -23,16 +24,23 @@ enum VideoCommand {
     },
     Frame(VideoFrame),
     Stop,
+    // re-encode the last finished recording at `speedup`x into `output`
+    Timelapse {
+        output: String,
+        speedup: f64,
+    },
 }
 
 pub struct EncoderManager {
-    encoders: Mutex<HashMap<EncoderId, Arc<VideoEncoder>>>,
+    encoders: TimedMutex<HashMap<EncoderId, Arc<VideoEncoder>>>,
+    drops: SharedDrops,
 }
 
 impl EncoderManager {
-    pub fn new() -> Self {
+    pub fn new(drops: SharedDrops) -> Self {
         Self {
-            encoders: Mutex::new(HashMap::new()),
+            encoders: TimedMutex::new("video_encoders", HashMap::new()),
+            drops,
         }
     }
 
@@ -40,7 +48,7 @@ impl EncoderManager {
         let id = uuid::Uuid::new_v4();
         let encoder = Arc::new(VideoEncoder::new());
 
-        self.encoders.lock().unwrap().insert(id, encoder);
+        self.encoders.lock().insert(id, encoder);
         id
     }
 
@@ -53,7 +61,7 @@ impl EncoderManager {
         fps: i32,
     ) -> Result<(), String> {
         let enc = {
-            let encoders = self.encoders.lock().unwrap();
+            let encoders = self.encoders.lock();
             encoders.get(&id).cloned()
         }.ok_or("Encoder not found")?;
         enc.start(path, width, height, fps)
@@ -65,24 +73,37 @@ impl EncoderManager {
         frame: VideoFrame,
     ) -> Result<(), String> {
         let enc = {
-            let encoders = self.encoders.lock().unwrap();
+            let encoders = self.encoders.lock();
             encoders.get(&id).cloned()
         }.ok_or("Encoder not found")?;
-        enc.send_frame(frame)
+        let sent = enc.send_frame(frame);
+        if sent.is_err() {
+            // ffmpeg fell behind, the frame never makes it into the file
+            self.drops.note("video.encoder", 1);
+        }
+        sent
     }
 
     pub fn stop(&self, id: EncoderId) -> Result<(), String> {
         let enc = {
-            let encoders = self.encoders.lock().unwrap();
+            let encoders = self.encoders.lock();
             encoders.get(&id).cloned()
         }.ok_or("Encoder not found")?;
         enc.stop()
     }
 
+    pub fn timelapse(&self, id: EncoderId, output: String, speedup: f64) -> Result<(), String> {
+        let enc = {
+            let encoders = self.encoders.lock();
+            encoders.get(&id).cloned()
+        }.ok_or("Encoder not found")?;
+        enc.timelapse(output, speedup)
+    }
+
+    // doesn't send Stop, callers stop it first; the thread works through whatever is
+    // still queued (Stop, a timelapse) and exits once its last sender is dropped here
     pub fn remove_encoder(&self, id: EncoderId) -> Result<(), String> {
-        if let Some(enc) = self.encoders.lock().unwrap().remove(&id) {
-            enc.stop()?;
-        }
+        self.encoders.lock().remove(&id);
         Ok(())
     }
 }
@@ -92,6 +113,12 @@ pub struct VideoEncoder {
     tx: mpsc::Sender<VideoCommand>,
 }
 
+impl Default for VideoEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl VideoEncoder {
     pub fn new() -> Self {
         let (tx, rx) = mpsc::channel(32);
@@ -127,11 +154,21 @@ impl VideoEncoder {
             .try_send(VideoCommand::Stop)
             .map_err(|e| e.to_string())
     }
+
+    // queued behind Stop, so the source file is finalized before we read it
+    pub fn timelapse(&self, output: impl Into<String>, speedup: f64) -> Result<(), String> {
+        self.tx
+            .try_send(VideoCommand::Timelapse {
+                output: output.into(),
+                speedup,
+            })
+            .map_err(|e| e.to_string())
+    }
 }
 
 // private function to help spawn a thread for a encoder
 fn spawn_encoder_task(mut rx: mpsc::Receiver<VideoCommand>) {
-    async_runtime::spawn_blocking(move || {
+    std::thread::spawn(move || {
         // Optional: print FFmpeg initialization
         println!("Starting MJPEG encoder thread...");
 
@@ -140,6 +177,7 @@ fn spawn_encoder_task(mut rx: mpsc::Receiver<VideoCommand>) {
         let mut width = 0;
         let mut height = 0;
         let mut fps;
+        let mut last_path: Option<String> = None;
 
         while let Some(cmd) = rx.blocking_recv() {
             match cmd {
@@ -160,7 +198,7 @@ fn spawn_encoder_task(mut rx: mpsc::Receiver<VideoCommand>) {
 
                     // Spawn FFmpeg subprocess for MJPEG encoding
                     let mut ffmpeg = Command::new("ffmpeg")
-                        .args(&[
+                        .args([
                             "-y",                     // overwrite output
                             "-f", "rawvideo",         // input format
                             "-pix_fmt", "rgb24",      // pixel format
@@ -177,6 +215,7 @@ fn spawn_encoder_task(mut rx: mpsc::Receiver<VideoCommand>) {
 
                     stdin = ffmpeg.stdin.take();
                     child = Some(ffmpeg);
+                    last_path = Some(path.clone());
 
                     println!("FFmpeg encoder started: {}", path);
                 }
@@ -209,7 +248,41 @@ fn spawn_encoder_task(mut rx: mpsc::Receiver<VideoCommand>) {
                         println!("FFmpeg encoding finished");
                     }
                 }
+
+                VideoCommand::Timelapse { output, speedup } => {
+                    let Some(input) = last_path.clone() else {
+                        eprintln!("No finished recording to build a timelapse from");
+                        continue;
+                    };
+                    spawn_timelapse_job(input, output, speedup);
+                }
             }
         }
     });
-}
\ No newline at end of file
+}
+
+// runs on its own thread so a long re-encode never holds up the encoder loop
+fn spawn_timelapse_job(input: String, output: String, speedup: f64) {
+    std::thread::spawn(move || {
+        println!("Generating {}x timelapse: {}", speedup, output);
+
+        let status = Command::new("ffmpeg")
+            .args([
+                "-y",
+                "-i", &input,
+                "-vf", &format!("setpts=PTS/{}", speedup), // drop timestamps by the speedup factor
+                "-an",                                      // no audio track
+                "-c:v", "mjpeg",
+                "-q:v", "5",
+                &output,
+            ])
+            .stdin(Stdio::null())
+            .status();
+
+        match status {
+            Ok(s) if s.success() => println!("Timelapse finished: {}", output),
+            Ok(s) => eprintln!("Timelapse ffmpeg exited with {}", s),
+            Err(e) => eprintln!("Failed to spawn ffmpeg for timelapse: {}", e),
+        }
+    });
+}
@@ -1,6 +1,6 @@
 // Middleware module for video streaming, recording, and display
 use dashmap::DashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
 use serde::{Deserialize, Serialize};
 use base64::{Engine as _, engine::general_purpose};
@@ -34,6 +34,8 @@ struct VideoStream {
 
     latest_frame: Option<SharedFrame>,
     encoder_id: Option<EncoderId>,
+    // what the current recording was started at
+    fps: i32,
 }
 
 // create constructor function
@@ -45,6 +47,7 @@ impl VideoStream {
             frame_count: 0,
             latest_frame: None,
             encoder_id: None,
+            fps: 0,
         }
     }
 
@@ -69,14 +72,23 @@ impl VideoStream {
         self.video_path = Some(path);
         self.encoder_id = Some(encoder_id);
         self.frame_count = 0;
+        self.fps = fps;
 
         Ok(())
     }
 
-    /// Stop recording
-    pub fn stop_recording(&mut self, encoder_pool: &EncoderManager) -> Result<(), String> {
+    /// Stop recording, optionally queueing a sped-up copy of the finished file
+    pub fn stop_recording(
+        &mut self,
+        encoder_pool: &EncoderManager,
+        timelapse_speedup: Option<f64>,
+    ) -> Result<(), String> {
         if let Some(encoder_id) = self.encoder_id.take() {
             encoder_pool.stop(encoder_id)?;
+            if let (Some(speedup), Some(path)) = (timelapse_speedup, self.video_path.as_ref()) {
+                let output = timelapse_path(path, speedup);
+                encoder_pool.timelapse(encoder_id, output.to_string_lossy().to_string(), speedup)?;
+            }
             encoder_pool.remove_encoder(encoder_id)?;
         }
 
@@ -105,14 +117,34 @@ impl VideoStream {
     pub fn latest_frame(&self) -> Option<SharedFrame> {
         self.latest_frame.clone()
     }
+
+    /// File the current (or last) recording went to
+    pub fn video_path(&self) -> Option<PathBuf> {
+        self.video_path.clone()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.load(Ordering::Acquire)
+    }
 }
 
 
 
+// place the timelapse beside the source recording, e.g. pad_timelapse_60x.avi
+fn timelapse_path(source: &Path, speedup: f64) -> PathBuf {
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "video".to_string());
+    source.with_file_name(format!("{}_timelapse_{}x.avi", stem, speedup))
+}
+
 /// Store all video streams
 pub struct VideoStreams {
     streams: DashMap<String, VideoStream>,
     encoder_pool: Arc<EncoderManager>,
+    // per-stream speedup factor for post-session timelapses
+    timelapse: DashMap<String, f64>,
 }
 
 // functions regarding our video streams
@@ -121,24 +153,67 @@ impl VideoStreams {
         Self{
             streams: DashMap::new(),
             encoder_pool,
+            timelapse: DashMap::new(),
         }
     }
 
     pub fn shutdown(&self) {
+        // no timelapses here, the app is exiting and would kill the job anyway
         for mut stream in self.streams.iter_mut() {
-            let _ = stream.stop_recording(&self.encoder_pool);
+            let _ = stream.stop_recording(&self.encoder_pool, None);
         }
     }
 
+    /// Enable (Some) or disable (None) timelapse generation for a stream
+    pub fn set_timelapse(&self, name: &str, speedup: Option<f64>) -> Result<(), String> {
+        match speedup {
+            Some(s) if !s.is_finite() || s <= 1.0 => Err(format!("Timelapse speedup must be > 1, got {}", s)),
+            Some(s) => {
+                self.timelapse.insert(name.to_string(), s);
+                Ok(())
+            }
+            None => {
+                self.timelapse.remove(name);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn get_timelapse(&self, name: &str) -> Option<f64> {
+        self.timelapse.get(name).map(|s| *s)
+    }
+
 
 
     pub fn create_stream(&self, name: &str) {
         self.streams
             .entry(name.to_string())
-            .or_insert_with(|| VideoStream::new());
+            .or_insert_with(VideoStream::new);
+    }
+
+    // stops any recording first
+    pub fn remove_stream(&self, name: &str) -> Result<(), String> {
+        let (_, mut stream) = self
+            .streams
+            .remove(name)
+            .ok_or_else(|| format!("Stream not found: '{}'", name))?;
+        stream.stop_recording(&self.encoder_pool, None)
+    }
+
+    pub fn video_path(&self, name: &str) -> Option<PathBuf> {
+        self.streams.get(name).and_then(|s| s.video_path())
     }
 
     // List all stream names
+    pub fn is_recording(&self, name: &str) -> bool {
+        self.streams.get(name).is_some_and(|s| s.is_recording())
+    }
+
+    /// The fps a stream's recording was started at, None when it isn't recording
+    pub fn recording_fps(&self, name: &str) -> Option<i32> {
+        self.streams.get(name).filter(|s| s.is_recording()).map(|s| s.fps)
+    }
+
     pub fn list_streams(&self) -> Vec<String> {
         self.streams.iter().map(|e| e.key().clone()).collect()
     }
@@ -182,7 +257,8 @@ impl VideoStreams {
             .get_mut(name)
             .ok_or_else(|| format!("Stream not found: {}", name))?;
 
-        stream.stop_recording(&self.encoder_pool)
+        let speedup = self.get_timelapse(name);
+        stream.stop_recording(&self.encoder_pool, speedup)
     }
 
     // Get latest frame for a named stream (base64 for frontend)
@@ -0,0 +1,198 @@
+// Reading a recorded session back in
+//
+// A session folder is one CSV per store, named after the store, with a timestamp
+// column and one column per field. Loading it lines every row of every store up on
+// one timeline, zeroed at the earliest timestamp in the folder (or one row per
+// EMIT_INTERVAL for a file with no timestamps). Packet logs (.frames from the packet
+// recorder, or .pb length-delimited records off the flight computer's SD card) load
+// as raw frames instead, one per EMIT_INTERVAL, for a decoder to put through. A folder
+// with packet logs in it loads those and leaves its CSVs alone, since the CSVs are
+// the same flight already decoded.
+//
+// Rows that don't parse are skipped and kept as DecodeErrors on their store; only an
+// unreadable file fails the load.
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::framing::length_delimited;
+use crate::middleware::decode_errors::DecodeError;
+use crate::middleware::encryption;
+use crate::middleware::recording_compression;
+use crate::middleware::telemetry_stores::TelemetryValue;
+
+// our flight computer's downlink rate, for CSVs without timestamps
+pub const EMIT_INTERVAL: Duration = Duration::from_millis(125);
+const PACKET_LOG_EXTENSIONS: [&str; 2] = ["frames", "pb"];
+
+pub struct PlaybackStore {
+    pub name: String,
+    pub rows: Vec<Vec<(String, TelemetryValue)>>,
+    // rows that were skipped, for the caller to report
+    pub errors: Vec<DecodeError>,
+}
+
+#[derive(Clone, Copy)]
+pub enum Emission {
+    Row { store: usize, row: usize },
+    Packet(usize),
+}
+
+pub struct Recording {
+    pub stores: Vec<PlaybackStore>,
+    pub packets: Vec<Vec<u8>>,
+    pub packet_logs: Vec<String>,
+    // (ms from the start, what goes out) for every row or packet, in order
+    pub timeline: Vec<(i64, Emission)>,
+    // where the player is, the next timeline entry to go out
+    pub next_row: usize,
+}
+
+pub fn is_packet_log(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|ext| PACKET_LOG_EXTENSIONS.iter().any(|e| ext == *e))
+}
+
+impl Recording {
+    pub fn load(folder: &Path) -> Result<Self, String> {
+        if is_packet_log(folder) {
+            return Self::load_packet_logs(&[folder.to_path_buf()]);
+        }
+        let files: Vec<PathBuf> = std::fs::read_dir(folder)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .collect();
+        let mut logs: Vec<PathBuf> = files.iter().filter(|p| is_packet_log(p)).cloned().collect();
+        if !logs.is_empty() {
+            logs.sort();
+            return Self::load_packet_logs(&logs);
+        }
+        let mut csvs: Vec<PathBuf> = files
+            .into_iter()
+            .filter(|p| {
+                let name = p.to_string_lossy();
+                name.ends_with(".csv") || name.ends_with(".csv.gz")
+            })
+            .collect();
+        csvs.sort();
+
+        let loaded = csvs
+            .iter()
+            .map(|path| load_store(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        if loaded.is_empty() {
+            return Err("no CSV files in folder".into());
+        }
+
+        // every store shares the earliest timestamp as its zero so they stay lined up
+        let start = loaded.iter().filter_map(|(_, times)| times.iter().flatten().min()).min().copied();
+        let mut timeline = Vec::new();
+        let mut stores = Vec::new();
+        for (index, (store, times)) in loaded.into_iter().enumerate() {
+            let mut previous = 0;
+            for (row, time) in times.iter().enumerate() {
+                let position = match (time, start) {
+                    (Some(t), Some(start)) => t - start,
+                    _ => row as i64 * EMIT_INTERVAL.as_millis() as i64,
+                };
+                // a clock step backwards in the file plays straight on
+                previous = position.max(previous);
+                timeline.push((previous, Emission::Row { store: index, row }));
+            }
+            stores.push(store);
+        }
+        // stable, so rows with the same time keep their file order
+        timeline.sort_by_key(|&(position, _)| position);
+        Ok(Self { stores, packets: Vec::new(), packet_logs: Vec::new(), timeline, next_row: 0 })
+    }
+
+    // each log starts at zero, so a primary and backup log of the same flight play
+    // side by side and the radio's dedup drops the copies
+    pub fn load_packet_logs(paths: &[PathBuf]) -> Result<Self, String> {
+        let mut packets = Vec::new();
+        let mut timeline = Vec::new();
+        for path in paths {
+            let mut file = encryption::open(path)?;
+            let mut count = 0i64;
+            while let Some(packet) = length_delimited::read_delimited(&mut file)
+                .map_err(|e| format!("{}: bad record: {e}", path.display()))?
+            {
+                timeline.push((count * EMIT_INTERVAL.as_millis() as i64, Emission::Packet(packets.len())));
+                packets.push(packet);
+                count += 1;
+            }
+        }
+        if packets.is_empty() {
+            return Err("no packets in the packet logs".into());
+        }
+        timeline.sort_by_key(|&(position, _)| position);
+        let packet_logs = paths.iter().map(|p| p.display().to_string()).collect();
+        Ok(Self { stores: Vec::new(), packets, packet_logs, timeline, next_row: 0 })
+    }
+
+    pub fn next_position(&self) -> Option<i64> {
+        self.timeline.get(self.next_row).map(|&(position, _)| position)
+    }
+
+    pub fn duration_ms(&self) -> i64 {
+        self.timeline.last().map(|&(position, _)| position).unwrap_or(0)
+    }
+}
+
+// the rows, and each row's timestamp if the file has a usable one
+pub fn load_store(path: &Path) -> Result<(PlaybackStore, Vec<Option<i64>>), String> {
+    let name = recording_compression::recording_stem(path)
+        .ok_or_else(|| format!("bad file name {}", path.display()))?;
+    // flexible so a short or long row can be reported and skipped instead of ending the file
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(recording_compression::open(path)?);
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let timestamp_column = headers.iter().position(|h| h == "timestamp");
+
+    let mut rows = Vec::new();
+    let mut times = Vec::new();
+    let mut errors = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) if record.len() == headers.len() => record,
+            Ok(record) => {
+                let line = record.position().map_or(0, |p| p.line());
+                let error = format!("{} fields, the header has {}", record.len(), headers.len());
+                errors.push(DecodeError::row(&name, line, &record.iter().collect::<Vec<_>>().join(","), error));
+                continue;
+            }
+            Err(e) if e.is_io_error() => return Err(format!("{}: {e}", path.display())),
+            Err(e) => {
+                let line = e.position().map_or(0, |p| p.line());
+                errors.push(DecodeError::row(&name, line, "", e.to_string()));
+                continue;
+            }
+        };
+        times.push(
+            timestamp_column
+                .and_then(|column| record.get(column))
+                .and_then(|t| t.trim().parse::<f64>().ok())
+                .map(|t| t as i64),
+        );
+        let row = headers
+            .iter()
+            .zip(record.iter())
+            // timestamps are regenerated on the way out
+            .filter(|(h, _)| *h != "timestamp")
+            .filter_map(|(h, v)| parse_value(v).map(|v| (h.to_string(), v)))
+            .collect();
+        rows.push(row);
+    }
+    Ok((PlaybackStore { name, rows, errors }, times))
+}
+
+pub fn parse_value(text: &str) -> Option<TelemetryValue> {
+    let text = text.trim();
+    if let Ok(b) = text.parse::<bool>() {
+        return Some(TelemetryValue::Bool(b));
+    }
+    if let Ok(i) = text.parse::<i64>() {
+        return Some(TelemetryValue::I64(i));
+    }
+    text.parse::<f64>().ok().map(TelemetryValue::F64)
+}
@@ -0,0 +1,206 @@
+// Command macros: named sequences of uplink commands, run with one (gated) command
+//
+// Macros live in the mission profile, e.g. "safe the vehicle" = disarm pyros, stop the
+// camera, enter beacon mode. Each step sends one command and can wait for the vehicle
+// to acknowledge it, which is the command showing up as last_command_received in the
+// vehicle's telemetry after we sent it. A step that isn't acked is retried, then the
+// macro stops there: the rest of a sequence usually assumes the earlier steps happened.
+// Progress goes out as COMMAND_MACRO_EVENT after every step.
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::backend::services::catch_panic;
+use crate::backend::telemetry_radio_interface::{hprc, TelemetryRadioHandle};
+use crate::middleware::alerts::AlertSeverity;
+use crate::middleware::lock_timing::TimedMutex;
+use crate::middleware::Middleware;
+// the macros themselves are mission profile data
+pub use crate::middleware::mission_profile::{CommandMacro, MacroStep};
+
+pub const COMMAND_MACRO_EVENT: &str = "command_macro";
+const ACK_FIELD: &str = "last_command_received";
+const ACK_POLL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MacroState {
+    Running,
+    WaitingForAck,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MacroProgress {
+    pub name: String,
+    pub state: MacroState,
+    // index of the step in progress (or the one that failed)
+    pub step: usize,
+    pub steps: usize,
+    pub label: Option<String>,
+    pub attempt: u32,
+    pub error: Option<String>,
+}
+
+impl MacroProgress {
+    fn finished(&self) -> bool {
+        matches!(self.state, MacroState::Done | MacroState::Failed | MacroState::Cancelled)
+    }
+}
+
+struct Run {
+    progress: MacroProgress,
+    cancel: CancellationToken,
+}
+
+// ── Handle ────────────────────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct CommandMacroHandle {
+    middleware: Arc<Mutex<Middleware>>,
+    radio: TelemetryRadioHandle,
+    current: Arc<TimedMutex<Option<Run>>>,
+    shutdown: CancellationToken,
+}
+
+pub fn new(middleware: Arc<Mutex<Middleware>>, radio: TelemetryRadioHandle, shutdown: CancellationToken) -> CommandMacroHandle {
+    CommandMacroHandle { middleware, radio, current: Arc::new(TimedMutex::new("command_macros", None)), shutdown }
+}
+
+impl CommandMacroHandle {
+    /// Start the macro in the background, one at a time
+    pub fn start(&self, command_macro: CommandMacro) -> Result<MacroProgress, String> {
+        if command_macro.steps.is_empty() {
+            return Err(format!("Macro '{}' has no steps", command_macro.name));
+        }
+        let mut current = self.current.lock();
+        if let Some(run) = current.as_ref().filter(|r| !r.progress.finished()) {
+            return Err(format!("Macro '{}' is still running", run.progress.name));
+        }
+        let progress = MacroProgress {
+            name: command_macro.name.clone(),
+            state: MacroState::Running,
+            step: 0,
+            steps: command_macro.steps.len(),
+            label: command_macro.steps[0].label.clone(),
+            attempt: 0,
+            error: None,
+        };
+        let cancel = self.shutdown.child_token();
+        *current = Some(Run { progress: progress.clone(), cancel: cancel.clone() });
+
+        let runner = self.clone();
+        tauri::async_runtime::spawn(async move {
+            // a panicking macro still has to finish, or it blocks every macro after it
+            if let Err(message) = catch_panic(runner.run(command_macro, cancel)).await {
+                runner.report(|p| {
+                    p.state = MacroState::Failed;
+                    p.error = Some(format!("macro crashed: {message}"));
+                }).await;
+                runner.middleware.lock().await.raise_alert(
+                    "command_macro_panic",
+                    AlertSeverity::Critical,
+                    format!("A command macro crashed partway through: {message}"),
+                );
+            }
+        });
+        Ok(progress)
+    }
+
+    pub fn cancel(&self) -> Option<MacroProgress> {
+        let current = self.current.lock();
+        let run = current.as_ref()?;
+        run.cancel.cancel();
+        Some(run.progress.clone())
+    }
+
+    pub fn status(&self) -> Option<MacroProgress> {
+        self.current.lock().as_ref().map(|r| r.progress.clone())
+    }
+
+    async fn report(&self, update: impl FnOnce(&mut MacroProgress)) {
+        let progress = {
+            let mut current = self.current.lock();
+            let Some(run) = current.as_mut() else { return };
+            update(&mut run.progress);
+            run.progress.clone()
+        };
+        if progress.finished() {
+            println!(
+                "[command_macros] '{}' {:?}{}",
+                progress.name,
+                progress.state,
+                progress.error.as_deref().map(|e| format!(": {e}")).unwrap_or_default()
+            );
+        }
+        self.middleware.lock().await.emit(COMMAND_MACRO_EVENT, progress);
+    }
+
+    async fn run(&self, command_macro: CommandMacro, cancel: CancellationToken) {
+        for (index, step) in command_macro.steps.iter().enumerate() {
+            let result = tokio::select! {
+                _ = cancel.cancelled() => Err(None),
+                result = self.run_step(&command_macro, index, step) => result.map_err(Some),
+            };
+            if let Err(error) = result {
+                let state = if error.is_some() { MacroState::Failed } else { MacroState::Cancelled };
+                self.report(|p| {
+                    p.state = state;
+                    p.error = error;
+                })
+                .await;
+                return;
+            }
+            if step.delay_ms > 0 {
+                tokio::select! {
+                    _ = cancel.cancelled() => {
+                        self.report(|p| p.state = MacroState::Cancelled).await;
+                        return;
+                    }
+                    _ = sleep(Duration::from_millis(step.delay_ms)) => {}
+                }
+            }
+        }
+        self.report(|p| p.state = MacroState::Done).await;
+    }
+
+    async fn run_step(&self, command_macro: &CommandMacro, index: usize, step: &MacroStep) -> Result<(), String> {
+        let label = step.label.clone().unwrap_or_else(|| format!("command {}", step.command));
+        for attempt in 1..=step.retries + 1 {
+            self.report(|p| {
+                p.state = MacroState::Running;
+                p.step = index;
+                p.label = Some(label.clone());
+                p.attempt = attempt;
+            })
+            .await;
+            let sent_at = chrono::Utc::now().timestamp_millis();
+            self.radio.send_command(hprc::Command(step.command)).await?;
+            if !step.expect_ack {
+                return Ok(());
+            }
+
+            self.report(|p| p.state = MacroState::WaitingForAck).await;
+            let deadline = Instant::now() + Duration::from_millis(step.ack_timeout_ms);
+            while Instant::now() < deadline {
+                if self.acked(&command_macro.ack_store, step.command, sent_at).await {
+                    return Ok(());
+                }
+                sleep(ACK_POLL).await;
+            }
+        }
+        Err(format!("No ack for {label} after {} tries", step.retries + 1))
+    }
+
+    async fn acked(&self, store: &str, command: u8, sent_at: i64) -> bool {
+        let middleware = self.middleware.lock().await;
+        matches!(
+            middleware.get_last(store, ACK_FIELD),
+            Ok(Some(last)) if last.timestamp >= sent_at && last.value.as_f64() == command as f64
+        )
+    }
+}
@@ -0,0 +1,285 @@
+// Physical button box / control surface over serial
+//
+// The box speaks a tiny line protocol at 115200 baud:
+//   box -> gs:  "BTN <n>\n"          button n was pressed
+//   gs  -> box: "LED <n> <0|1>\n"    turn LED n off/on
+//
+// Stream Deck style HID surfaces can run a small bridge that speaks the same protocol.
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::sync::mpsc as std_mpsc;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{sleep, Duration};
+use tokio_util::sync::CancellationToken;
+
+use crate::backend::serial_interface::{self, Reconnect, SerialDevice};
+use crate::middleware::{telemetry_stores::TelemetryData, Middleware};
+
+const BAUD_RATE: u32 = 115200;
+const EVENT_STORE: &str = "events";
+const RECORDING_LED: u8 = 0;
+const LED_POLL_MS: u64 = 50;
+// recorded in the alert audit log for acknowledgments made from the box
+const OPERATOR_ROLE: &str = "control_surface";
+
+// what a button press does on the backend
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ButtonAction {
+    StartRecording,
+    StopRecording,
+    ToggleRecording,
+    MarkEvent,
+    AcknowledgeAlerts,
+}
+
+// cheap to clone, handed to tauri so the frontend can pick a port and assign buttons
+#[derive(Clone)]
+pub struct ControlSurfaceHandle {
+    port_tx: mpsc::Sender<String>,
+    assignments: Arc<DashMap<u8, ButtonAction>>,
+}
+
+impl ControlSurfaceHandle {
+    pub async fn set_port(&self, port: String) -> Result<(), String> {
+        self.port_tx.send(port).await.map_err(|e| e.to_string())
+    }
+
+    // for HardwarePorts, which routes port selections to every serial device
+    pub fn port_sender(&self) -> mpsc::Sender<String> {
+        self.port_tx.clone()
+    }
+
+    pub fn assign(&self, button: u8, action: Option<ButtonAction>) {
+        match action {
+            Some(a) => { self.assignments.insert(button, a); }
+            None => { self.assignments.remove(&button); }
+        }
+    }
+
+    pub fn assignments(&self) -> Vec<(u8, ButtonAction)> {
+        let mut out: Vec<(u8, ButtonAction)> = self.assignments
+            .iter()
+            .map(|e| (*e.key(), *e.value()))
+            .collect();
+        out.sort_by_key(|(b, _)| *b);
+        out
+    }
+}
+
+// ── Constructor ───────────────────────────────────────────────────────────────
+
+pub fn new(middleware: Arc<Mutex<Middleware>>) -> (ControlSurface, ControlSurfaceHandle) {
+    let (port_tx, port_rx) = mpsc::channel::<String>(8);
+
+    // sensible defaults so a fresh box does something useful
+    let assignments = Arc::new(DashMap::new());
+    assignments.insert(0, ButtonAction::ToggleRecording);
+    assignments.insert(1, ButtonAction::MarkEvent);
+    assignments.insert(2, ButtonAction::AcknowledgeAlerts);
+
+    let surface = ControlSurface {
+        middleware,
+        port_rx,
+        assignments: assignments.clone(),
+        marker_count: 0,
+        reconnect: Reconnect::new(SerialDevice::ControlSurface),
+        current_port: None,
+    };
+    (surface, ControlSurfaceHandle { port_tx, assignments })
+}
+
+// ── Actor ─────────────────────────────────────────────────────────────────────
+
+pub struct ControlSurface {
+    middleware: Arc<Mutex<Middleware>>,
+    port_rx: mpsc::Receiver<String>,
+    assignments: Arc<DashMap<u8, ButtonAction>>,
+    marker_count: u32,
+    reconnect: Reconnect,
+    // kept across a service stop/start so the box comes back on the same port
+    current_port: Option<String>,
+}
+
+impl ControlSurface {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
+        loop {
+            let port_name = match self.current_port.clone() {
+                Some(p) => p,
+                None => tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    p = self.port_rx.recv() => match p {
+                        Some(p) => p,
+                        None => return,
+                    },
+                },
+            };
+
+            self.current_port = Some(port_name.clone());
+
+            match self.run_connected(&port_name, &shutdown).await {
+                SurfaceResult::Shutdown => return,
+                SurfaceResult::PortChanged(p) => {
+                    self.reconnect.reset();
+                    self.current_port = Some(p);
+                }
+                SurfaceResult::Error(e) => {
+                    let delay = {
+                        let middleware = self.middleware.lock().await;
+                        self.reconnect.failed(&middleware, &port_name, &e)
+                    };
+                    eprintln!("[control_surface] error on {port_name}: {e}. Retrying in {}ms...", delay.as_millis());
+                    tokio::select! {
+                        _ = sleep(delay) => {}
+                        _ = shutdown.cancelled() => return,
+                        Some(p) = self.port_rx.recv() => {
+                            self.reconnect.reset();
+                            self.current_port = Some(p);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn run_connected(&mut self, port_name: &str, shutdown: &CancellationToken) -> SurfaceResult {
+        let port = match serial_interface::open(port_name, BAUD_RATE, Duration::from_millis(100)) {
+            Ok(p) => p,
+            Err(e) => return SurfaceResult::Error(e),
+        };
+        let mut writer = match port.try_clone_link() {
+            Ok(p) => p,
+            Err(e) => return SurfaceResult::Error(format!("clone failed: {e}")),
+        };
+        let mut reader = port;
+
+        // ── Reader thread: split incoming bytes into lines ─────────────────────
+        let (line_tx, mut line_rx) = mpsc::unbounded_channel::<Result<String, String>>();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            let mut line: Vec<u8> = Vec::new();
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) => {
+                        let _ = line_tx.send(Err("port closed".into()));
+                        return;
+                    }
+                    Ok(n) => {
+                        for &b in &buf[..n] {
+                            if b == b'\n' {
+                                let text = String::from_utf8_lossy(&line).trim().to_string();
+                                line.clear();
+                                if line_tx.send(Ok(text)).is_err() {
+                                    return;
+                                }
+                            } else {
+                                line.push(b);
+                            }
+                        }
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+                    Err(e) => {
+                        let _ = line_tx.send(Err(e.to_string()));
+                        return;
+                    }
+                }
+            }
+        });
+
+        // ── Writer thread: LED updates ────────────────────────────────────────
+        let (led_tx, led_rx) = std_mpsc::channel::<(u8, bool)>();
+        std::thread::spawn(move || {
+            while let Ok((led, on)) = led_rx.recv() {
+                let msg = format!("LED {} {}\n", led, on as u8);
+                if writer.write_all(msg.as_bytes()).is_err() {
+                    return;
+                }
+            }
+        });
+
+        println!("[control_surface] connected to {port_name}");
+        {
+            let middleware = self.middleware.lock().await;
+            self.reconnect.connected(&middleware, port_name);
+        }
+
+        // force an LED refresh on connect
+        let mut last_recording: Option<bool> = None;
+
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return SurfaceResult::Shutdown,
+                Some(p) = self.port_rx.recv() => return SurfaceResult::PortChanged(p),
+                line = line_rx.recv() => match line {
+                    Some(Ok(line)) => self.handle_line(&line).await,
+                    Some(Err(e)) => return SurfaceResult::Error(e),
+                    None => return SurfaceResult::Error("reader thread died".into()),
+                },
+                _ = sleep(Duration::from_millis(LED_POLL_MS)) => {
+                    let recording = self.middleware.lock().await.get_recording_status();
+                    if last_recording != Some(recording) {
+                        if led_tx.send((RECORDING_LED, recording)).is_err() {
+                            return SurfaceResult::Error("writer thread died".into());
+                        }
+                        last_recording = Some(recording);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn handle_line(&mut self, line: &str) {
+        let Some(button) = line
+            .strip_prefix("BTN ")
+            .and_then(|n| n.trim().parse::<u8>().ok())
+        else {
+            eprintln!("[control_surface] ignoring unknown message '{line}'");
+            return;
+        };
+
+        let Some(action) = self.assignments.get(&button).map(|a| *a) else {
+            return; // unassigned button
+        };
+
+        if let Err(e) = self.perform(action).await {
+            eprintln!("[control_surface] {action:?} failed: {e}");
+        }
+    }
+
+    async fn perform(&mut self, action: ButtonAction) -> Result<(), String> {
+        let mut middleware = self.middleware.lock().await;
+        match action {
+            ButtonAction::StartRecording => middleware.start_recording_all(),
+            ButtonAction::StopRecording => middleware.stop_recording_all(),
+            ButtonAction::ToggleRecording => {
+                if middleware.get_recording_status() {
+                    middleware.stop_recording_all()
+                } else {
+                    middleware.start_recording_all()
+                }
+            }
+            ButtonAction::MarkEvent => {
+                self.marker_count += 1;
+                middleware.push_data(
+                    EVENT_STORE,
+                    "marker",
+                    TelemetryData::new().with_value(self.marker_count),
+                )
+            }
+            ButtonAction::AcknowledgeAlerts => {
+                middleware.acknowledge_all_alerts(OPERATOR_ROLE);
+                Ok(())
+            }
+        }
+    }
+}
+
+// ── Internal result type ──────────────────────────────────────────────────────
+
+enum SurfaceResult {
+    Shutdown,
+    PortChanged(String),
+    Error(String),
+}
@@ -0,0 +1,427 @@
+// Countdown automations: things that should happen at a countdown mark without anyone
+// having to remember them at T-2:00
+//
+// Each automation is an action at a mark before T-0, e.g.
+//   T-5:00  start the livestream
+//   T-2:00  start recording the live video
+//   T-0:30  start recording every telemetry store
+// T-0 is the mission T-0 from the time base. Starting the countdown sets it and arms
+// every automation; moving T-0 while it runs (a hold, a recycle) re-arms them against
+// the new time. A mark that went by while the station wasn't watching for more than
+// LATE_GRACE_MS is missed rather than fired late. Any automation can be cancelled on
+// its own. Every firing, failure, miss and cancel is appended to countdown_log.jsonl.
+//
+// The list is kept in countdown.toml next to the session folders, e.g.
+//   [[automations]]
+//   id = "video"
+//   t_minus_ms = 120000
+//   action = { kind = "start_video_recording", stream = "live_vide", fps = 30 }
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::middleware::config_file::{append_json_line, read_config_file, write_config_file};
+use crate::middleware::lock_timing::TimedMutex;
+use crate::middleware::Middleware;
+
+pub const COUNTDOWN_EVENT: &str = "countdown_automation";
+// the livestream window does the streaming, this just tells it to go live
+pub const LIVESTREAM_EVENT: &str = "livestream_start";
+const TICK: Duration = Duration::from_millis(100);
+const LATE_GRACE_MS: i64 = 5000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AutomationAction {
+    StartVideoRecording {
+        stream: String,
+        #[serde(default = "default_fps")]
+        fps: i32,
+    },
+    // every telemetry store and video stream, same as the record button
+    StartRecordingAll,
+    StartLivestream {
+        stream: String,
+    },
+}
+
+fn default_fps() -> i32 {
+    30
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Automation {
+    pub id: String,
+    #[serde(default)]
+    pub label: String,
+    // how long before T-0 it fires
+    pub t_minus_ms: u64,
+    pub action: AutomationAction,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CountdownConfig {
+    #[serde(default)]
+    pub automations: Vec<Automation>,
+}
+
+impl CountdownConfig {
+    fn launch_day() -> Self {
+        let automation = |id: &str, label: &str, t_minus_ms, action| Automation {
+            id: id.into(),
+            label: label.into(),
+            t_minus_ms,
+            action,
+        };
+        Self {
+            automations: vec![
+                automation(
+                    "livestream",
+                    "Start livestream",
+                    300_000,
+                    AutomationAction::StartLivestream { stream: "live_vide".into() },
+                ),
+                automation(
+                    "video",
+                    "Start video recording",
+                    120_000,
+                    AutomationAction::StartVideoRecording { stream: "live_vide".into(), fps: default_fps() },
+                ),
+                automation("auto_record", "Arm auto-record", 30_000, AutomationAction::StartRecordingAll),
+            ],
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let mut ids = HashSet::new();
+        for automation in &self.automations {
+            if automation.id.trim().is_empty() {
+                return Err("Every automation needs an id".into());
+            }
+            if !ids.insert(automation.id.as_str()) {
+                return Err(format!("Automation id '{}' is used twice", automation.id));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutomationState {
+    // the countdown isn't running
+    Idle,
+    Armed,
+    Fired,
+    Failed,
+    Missed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledAutomation {
+    #[serde(flatten)]
+    pub automation: Automation,
+    pub state: AutomationState,
+    // unix ms, None until the countdown is started
+    pub mark: Option<i64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutomationEvent {
+    Fired,
+    Failed,
+    Missed,
+    Cancelled,
+    Restored,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutomationRecord {
+    pub timestamp: i64,
+    pub id: String,
+    pub label: String,
+    pub event: AutomationEvent,
+    // the mark it was scheduled for and the T-0 that put it there
+    pub mark: Option<i64>,
+    pub t0: Option<i64>,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CountdownStatus {
+    pub running: bool,
+    pub t0: Option<i64>,
+    pub automations: Vec<ScheduledAutomation>,
+}
+
+struct Schedule {
+    automations: Vec<ScheduledAutomation>,
+    // Some while the countdown runs
+    t0: Option<i64>,
+    log: Vec<AutomationRecord>,
+    config_path: PathBuf,
+    log_path: PathBuf,
+}
+
+impl Schedule {
+    fn load(config_path: PathBuf, log_path: PathBuf) -> Self {
+        let config = if config_path.exists() {
+            read_config_file(&config_path)
+                .and_then(|config: CountdownConfig| config.validate().map(|_| config))
+                .unwrap_or_else(|e| {
+                    eprintln!("[countdown] {e}, using the default automations");
+                    CountdownConfig::launch_day()
+                })
+        } else {
+            CountdownConfig::launch_day()
+        };
+        let mut schedule = Self { automations: Vec::new(), t0: None, log: Vec::new(), config_path, log_path };
+        schedule.replace(config);
+        schedule
+    }
+
+    fn replace(&mut self, config: CountdownConfig) {
+        self.automations = config
+            .automations
+            .into_iter()
+            .map(|automation| ScheduledAutomation { automation, state: AutomationState::Idle, mark: None, error: None })
+            .collect();
+        if let Some(t0) = self.t0 {
+            self.arm(t0);
+        }
+    }
+
+    fn record(&mut self, index: usize, event: AutomationEvent, detail: Option<String>) -> AutomationRecord {
+        let scheduled = &self.automations[index];
+        let record = AutomationRecord {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            id: scheduled.automation.id.clone(),
+            label: scheduled.automation.label.clone(),
+            event,
+            mark: scheduled.mark,
+            t0: self.t0,
+            detail,
+        };
+        if let Err(e) = append_json_line(&self.log_path, &record) {
+            eprintln!("[countdown] Failed to write automation log: {e}");
+        }
+        self.log.push(record.clone());
+        record
+    }
+
+    // everything not cancelled is scheduled against `t0`, fired or not
+    fn arm(&mut self, t0: i64) {
+        self.t0 = Some(t0);
+        for scheduled in self.automations.iter_mut() {
+            scheduled.mark = Some(t0 - scheduled.automation.t_minus_ms as i64);
+            if scheduled.state != AutomationState::Cancelled {
+                scheduled.state = AutomationState::Armed;
+                scheduled.error = None;
+            }
+        }
+    }
+
+    fn index(&self, id: &str) -> Result<usize, String> {
+        self.automations
+            .iter()
+            .position(|s| s.automation.id == id)
+            .ok_or_else(|| format!("No countdown automation '{id}'"))
+    }
+
+    fn status(&self) -> CountdownStatus {
+        CountdownStatus { running: self.t0.is_some(), t0: self.t0, automations: self.automations.clone() }
+    }
+}
+
+// ── Handle ────────────────────────────────────────────────────────────────────
+
+#[derive(Clone)]
+pub struct CountdownHandle {
+    schedule: Arc<TimedMutex<Schedule>>,
+}
+
+impl CountdownHandle {
+    /// Arm every automation against `t0`. The caller sets the mission T-0 to match.
+    pub fn start(&self, t0: i64) -> CountdownStatus {
+        let mut schedule = self.schedule.lock();
+        schedule.arm(t0);
+        println!("[countdown] Started, T-0 at {t0}");
+        schedule.status()
+    }
+
+    // a scrub: nothing fires until the countdown is started again
+    pub fn stop(&self) -> CountdownStatus {
+        let mut schedule = self.schedule.lock();
+        schedule.t0 = None;
+        for scheduled in schedule.automations.iter_mut() {
+            if scheduled.state == AutomationState::Armed {
+                scheduled.state = AutomationState::Idle;
+            }
+        }
+        println!("[countdown] Stopped");
+        schedule.status()
+    }
+
+    pub fn cancel(&self, id: &str, operator_role: &str) -> Result<AutomationRecord, String> {
+        let mut schedule = self.schedule.lock();
+        let index = schedule.index(id)?;
+        if schedule.automations[index].state == AutomationState::Cancelled {
+            return Err(format!("Automation '{id}' is already cancelled"));
+        }
+        schedule.automations[index].state = AutomationState::Cancelled;
+        Ok(schedule.record(index, AutomationEvent::Cancelled, Some(operator_role.to_string())))
+    }
+
+    /// Undo a cancel. It goes back to armed if the countdown is running and its mark
+    /// hasn't gone by yet.
+    pub fn restore(&self, id: &str, operator_role: &str) -> Result<AutomationRecord, String> {
+        let mut schedule = self.schedule.lock();
+        let index = schedule.index(id)?;
+        if schedule.automations[index].state != AutomationState::Cancelled {
+            return Err(format!("Automation '{id}' isn't cancelled"));
+        }
+        let now = chrono::Utc::now().timestamp_millis();
+        let running = schedule.t0.is_some();
+        let scheduled = &mut schedule.automations[index];
+        scheduled.state = match scheduled.mark {
+            Some(mark) if running && mark > now => AutomationState::Armed,
+            Some(_) if running => AutomationState::Missed,
+            _ => AutomationState::Idle,
+        };
+        Ok(schedule.record(index, AutomationEvent::Restored, Some(operator_role.to_string())))
+    }
+
+    pub fn set_config(&self, config: CountdownConfig) -> Result<(), String> {
+        config.validate()?;
+        let mut schedule = self.schedule.lock();
+        write_config_file(&schedule.config_path, &config)?;
+        schedule.replace(config);
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> CountdownConfig {
+        let schedule = self.schedule.lock();
+        CountdownConfig { automations: schedule.automations.iter().map(|s| s.automation.clone()).collect() }
+    }
+
+    pub fn status(&self) -> CountdownStatus {
+        self.schedule.lock().status()
+    }
+
+    pub fn log(&self) -> Vec<AutomationRecord> {
+        self.schedule.lock().log.clone()
+    }
+}
+
+// ── Constructor ───────────────────────────────────────────────────────────────
+
+pub fn new(middleware: Arc<Mutex<Middleware>>, config_path: PathBuf, log_path: PathBuf) -> (Countdown, CountdownHandle) {
+    let schedule = Arc::new(TimedMutex::new("countdown", Schedule::load(config_path, log_path)));
+    (Countdown { middleware, schedule: schedule.clone() }, CountdownHandle { schedule })
+}
+
+// ── Actor ─────────────────────────────────────────────────────────────────────
+
+pub struct Countdown {
+    middleware: Arc<Mutex<Middleware>>,
+    schedule: Arc<TimedMutex<Schedule>>,
+}
+
+impl Countdown {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
+        let mut tick = interval(TICK);
+        tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = tick.tick() => self.check().await,
+            }
+        }
+    }
+
+    async fn check(&mut self) {
+        let mission_t0 = self.middleware.lock().await.get_time_base().t0;
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let mut missed = Vec::new();
+        let due: Vec<(usize, ScheduledAutomation)> = {
+            let mut schedule = self.schedule.lock();
+            let Some(t0) = schedule.t0 else { return };
+            if t0 != mission_t0 {
+                println!("[countdown] T-0 moved to {mission_t0}, re-arming");
+                schedule.arm(mission_t0);
+            }
+            let mut due = Vec::new();
+            for index in 0..schedule.automations.len() {
+                let scheduled = &schedule.automations[index];
+                let Some(mark) = scheduled.mark.filter(|&m| scheduled.state == AutomationState::Armed && now >= m) else {
+                    continue;
+                };
+                if now - mark > LATE_GRACE_MS {
+                    schedule.automations[index].state = AutomationState::Missed;
+                    let detail = format!("{} ms past its mark", now - mark);
+                    missed.push(schedule.record(index, AutomationEvent::Missed, Some(detail)));
+                } else {
+                    due.push((index, scheduled.clone()));
+                }
+            }
+            due
+        };
+        for record in missed {
+            self.report(record).await;
+        }
+
+        for (index, scheduled) in due {
+            let result = self.fire(&scheduled.automation.action).await;
+            let record = {
+                let mut schedule = self.schedule.lock();
+                // the list could have been replaced while the action ran
+                if schedule.automations.get(index).map(|s| &s.automation.id) != Some(&scheduled.automation.id) {
+                    continue;
+                }
+                let entry = &mut schedule.automations[index];
+                match result {
+                    Ok(()) => {
+                        entry.state = AutomationState::Fired;
+                        schedule.record(index, AutomationEvent::Fired, None)
+                    }
+                    Err(e) => {
+                        entry.state = AutomationState::Failed;
+                        entry.error = Some(e.clone());
+                        schedule.record(index, AutomationEvent::Failed, Some(e))
+                    }
+                }
+            };
+            self.report(record).await;
+        }
+    }
+
+    async fn fire(&self, action: &AutomationAction) -> Result<(), String> {
+        let mut middleware = self.middleware.lock().await;
+        match action {
+            AutomationAction::StartVideoRecording { stream, fps } => middleware.start_recording_video(stream, *fps),
+            AutomationAction::StartRecordingAll => middleware.start_recording_all(),
+            AutomationAction::StartLivestream { stream } => {
+                middleware.emit(LIVESTREAM_EVENT, stream.clone());
+                Ok(())
+            }
+        }
+    }
+
+    async fn report(&self, record: AutomationRecord) {
+        println!(
+            "[countdown] '{}' {:?}{}",
+            record.id,
+            record.event,
+            record.detail.as_deref().map(|d| format!(": {d}")).unwrap_or_default()
+        );
+        self.middleware.lock().await.emit(COUNTDOWN_EVENT, record);
+    }
+}
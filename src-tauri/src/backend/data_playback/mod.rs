@@ -1 +1,395 @@
-// Replays stored data from a folder containing CSVs and video files
\ No newline at end of file
+// Replays stored data from a folder containing CSVs and video files
+//
+// This doubles as our simulator for frontend work: every CSV in the folder becomes
+// a store named after the file, and its rows are emitted while the playback state is
+// Running, spaced out the way their timestamp column says they were recorded (or one
+// per EMIT_INTERVAL if a file has no timestamps).
+//
+// Emission times are worked out from a monotonic anchor rather than counted off a
+// fixed interval, so a stall (the middleware lock held for a while) is caught up on
+// instead of pushing every later row back. Pausing and changing the speed move the
+// anchor, which keeps the recording's position where it was.
+//
+// A packet log (.frames from the packet recorder, or .pb length-delimited records off
+// the flight computer's SD card) plays back through the telemetry radio's decoder
+// instead, so it lands in the stores exactly the way the live link would have put it.
+// Those logs carry no timestamps, so packets go out at the downlink rate. A folder
+// with packet logs in it plays those and leaves its CSVs alone, since the CSVs are
+// the same flight already decoded.
+//
+// Reading the folder is groundstation_core::playback, this is the player around it.
+//
+// Real radio links don't deliver on a metronome, so each emission can be delayed
+// by a configurable latency model. Packets are still delivered in order (a serial
+// link never reorders), so a slow one holds up the ones behind it.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio::time::{sleep_until, Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use groundstation_core::playback::{is_packet_log, Emission, Recording};
+
+use crate::backend::telemetry_radio_interface::TelemetryRadioHandle;
+use crate::channels::PlaybackState;
+use crate::middleware::decode_errors::DecodeError;
+use crate::middleware::telemetry_stores::TelemetryData;
+use crate::middleware::Middleware;
+
+const MAX_SPEED: f64 = 100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JitterDistribution {
+    // anywhere in [0, jitter_ms]
+    Uniform,
+    // |N(0, jitter_ms)|
+    Normal,
+    // mean jitter_ms, long tail like a link doing retries
+    Exponential,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LatencyModel {
+    // fixed delay every packet sees
+    pub base_ms: f64,
+    pub jitter_ms: f64,
+    pub distribution: JitterDistribution,
+}
+
+impl Default for LatencyModel {
+    fn default() -> Self {
+        Self { base_ms: 0.0, jitter_ms: 0.0, distribution: JitterDistribution::Uniform }
+    }
+}
+
+impl LatencyModel {
+    fn validate(&self) -> Result<(), String> {
+        if !(self.base_ms >= 0.0 && self.jitter_ms >= 0.0) {
+            return Err("Latency and jitter must be non-negative".into());
+        }
+        Ok(())
+    }
+
+    fn sample(&self, rng: &mut Rng) -> Duration {
+        let jitter = match self.distribution {
+            JitterDistribution::Uniform => rng.next_f64() * self.jitter_ms,
+            JitterDistribution::Normal => rng.next_normal().abs() * self.jitter_ms,
+            JitterDistribution::Exponential => -rng.next_f64().ln() * self.jitter_ms,
+        };
+        Duration::from_secs_f64((self.base_ms + jitter).max(0.0) / 1000.0)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlaybackStatus {
+    pub folder: Option<String>,
+    pub stores: Vec<String>,
+    pub packet_logs: Vec<String>,
+    // rows (or packets) emitted so far and in total, across every store
+    pub row: usize,
+    pub rows: usize,
+    // where playback is in the recording and how long it is, ms from its first row
+    pub position_ms: i64,
+    pub duration_ms: i64,
+}
+
+#[derive(Clone)]
+pub struct DataPlaybackHandle {
+    folder_tx: mpsc::Sender<PathBuf>,
+    latency_tx: Arc<watch::Sender<LatencyModel>>,
+    speed_tx: Arc<watch::Sender<f64>>,
+    status_tx: Arc<watch::Sender<PlaybackStatus>>,
+}
+
+impl DataPlaybackHandle {
+    // a folder of CSVs and/or packet logs, or one packet log
+    pub async fn load_folder(&self, folder: PathBuf) -> Result<(), String> {
+        if !folder.is_dir() && !is_packet_log(&folder) {
+            return Err(format!("{} is not a folder or a packet log", folder.display()));
+        }
+        self.folder_tx.send(folder).await.map_err(|e| e.to_string())
+    }
+
+    pub fn set_latency(&self, model: LatencyModel) -> Result<(), String> {
+        model.validate()?;
+        self.latency_tx.send_replace(model);
+        Ok(())
+    }
+
+    pub fn get_latency(&self) -> LatencyModel {
+        *self.latency_tx.borrow()
+    }
+
+    // 1.0 is as recorded
+    pub fn set_speed(&self, speed: f64) -> Result<(), String> {
+        if !(speed > 0.0 && speed <= MAX_SPEED) {
+            return Err(format!("Playback speed must be above 0 and at most {MAX_SPEED}"));
+        }
+        self.speed_tx.send_replace(speed);
+        Ok(())
+    }
+
+    pub fn get_speed(&self) -> f64 {
+        *self.speed_tx.borrow()
+    }
+
+    pub fn get_status(&self) -> PlaybackStatus {
+        self.status_tx.borrow().clone()
+    }
+}
+
+// ── Constructor ───────────────────────────────────────────────────────────────
+
+pub fn new(
+    middleware: Arc<Mutex<Middleware>>,
+    playback_rx: watch::Receiver<PlaybackState>,
+    radio: TelemetryRadioHandle,
+) -> (DataPlayback, DataPlaybackHandle) {
+    let (folder_tx, folder_rx) = mpsc::channel(4);
+    let latency_tx = Arc::new(watch::Sender::new(LatencyModel::default()));
+    let speed_tx = Arc::new(watch::Sender::new(1.0));
+    let status_tx = Arc::new(watch::Sender::new(PlaybackStatus::default()));
+    let playback = DataPlayback {
+        middleware,
+        radio,
+        playback_rx,
+        folder_rx,
+        latency_rx: latency_tx.subscribe(),
+        speed_rx: speed_tx.subscribe(),
+        status_tx: status_tx.clone(),
+        recording: None,
+        clock: PlaybackClock::new(1.0, 0),
+        rng: Rng::seeded(),
+    };
+    (playback, DataPlaybackHandle { folder_tx, latency_tx, speed_tx, status_tx })
+}
+
+// ── Actor ─────────────────────────────────────────────────────────────────────
+
+pub struct DataPlayback {
+    middleware: Arc<Mutex<Middleware>>,
+    radio: TelemetryRadioHandle,
+    playback_rx: watch::Receiver<PlaybackState>,
+    folder_rx: mpsc::Receiver<PathBuf>,
+    latency_rx: watch::Receiver<LatencyModel>,
+    speed_rx: watch::Receiver<f64>,
+    status_tx: Arc<watch::Sender<PlaybackStatus>>,
+    recording: Option<Recording>,
+    clock: PlaybackClock,
+    rng: Rng,
+}
+
+impl DataPlayback {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
+        // when the previous packet "arrived", later ones can't overtake it
+        let mut last_delivery = Instant::now();
+        // pick up from the next row after a restart, not from where the old clock would be
+        let position = self.recording.as_ref().and_then(|r| r.next_position()).unwrap_or(0);
+        self.clock = PlaybackClock::new(*self.speed_rx.borrow_and_update(), position);
+        self.follow_state();
+
+        loop {
+            let next = self.recording.as_ref().and_then(|r| r.next_position());
+            let due = next.and_then(|position| self.clock.due(position));
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                Some(folder) = self.folder_rx.recv() => {
+                    self.load(&folder).await;
+                    continue;
+                }
+                Ok(()) = self.playback_rx.changed() => {
+                    self.follow_state();
+                    continue;
+                }
+                Ok(()) = self.speed_rx.changed() => {
+                    let speed = *self.speed_rx.borrow_and_update();
+                    self.clock.set_speed(Instant::now(), speed);
+                    continue;
+                }
+                _ = sleep_until(due.unwrap_or_else(Instant::now)), if due.is_some() => {}
+            }
+            let Some(scheduled) = due else { continue };
+
+            // stamp with when the flight computer would have sent it, not when it shows up
+            let generated_ms = chrono::Utc::now().timestamp_millis()
+                - Instant::now().saturating_duration_since(scheduled).as_millis() as i64;
+
+            let delay = self.latency_rx.borrow().sample(&mut self.rng);
+            let delivery = (scheduled + delay).max(last_delivery);
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = sleep_until(delivery) => {}
+            }
+            last_delivery = delivery;
+
+            self.emit_row(generated_ms).await;
+        }
+    }
+
+    // only Running moves the recording along, everything else holds it where it is
+    fn follow_state(&mut self) {
+        let running = matches!(*self.playback_rx.borrow_and_update(), PlaybackState::Running);
+        let now = Instant::now();
+        if running {
+            self.clock.resume(now);
+        } else {
+            self.clock.pause(now);
+        }
+    }
+
+    async fn load(&mut self, folder: &Path) {
+        match Recording::load(folder) {
+            Ok(mut recording) => {
+                println!(
+                    "[playback] loaded {} stores and {} packet logs from {}",
+                    recording.stores.len(),
+                    recording.packet_logs.len(),
+                    folder.display()
+                );
+                self.status_tx.send_replace(PlaybackStatus {
+                    folder: Some(folder.display().to_string()),
+                    stores: recording.stores.iter().map(|s| s.name.clone()).collect(),
+                    packet_logs: recording.packet_logs.clone(),
+                    row: 0,
+                    rows: recording.timeline.len(),
+                    position_ms: 0,
+                    duration_ms: recording.duration_ms(),
+                });
+                // rows that didn't parse are left out of the playback, say so up front
+                let errors: Vec<DecodeError> = recording.stores.iter_mut().flat_map(|s| s.errors.drain(..)).collect();
+                if !errors.is_empty() {
+                    eprintln!("[playback] skipping {} rows that didn't parse in {}", errors.len(), folder.display());
+                    let mut mw = self.middleware.lock().await;
+                    for error in errors {
+                        mw.report_decode_error(error);
+                    }
+                }
+                self.recording = Some(recording);
+                self.clock.rewind(Instant::now());
+            }
+            Err(e) => eprintln!("[playback] failed to load {}: {e}", folder.display()),
+        }
+    }
+
+    async fn emit_row(&mut self, timestamp: i64) {
+        let Some(recording) = self.recording.as_mut() else {
+            return;
+        };
+        let Some(&(position, emission)) = recording.timeline.get(recording.next_row) else {
+            return;
+        };
+        recording.next_row += 1;
+        let emitted = recording.next_row;
+
+        match emission {
+            Emission::Row { store, row } => {
+                let store = &recording.stores[store];
+                let mut mw = self.middleware.lock().await;
+                for (field, value) in &store.rows[row] {
+                    let _ = mw.push_data(
+                        &store.name,
+                        field,
+                        TelemetryData::new().with_timestamp(timestamp).with_value(*value),
+                    );
+                }
+            }
+            // the radio stamps these itself when it decodes them
+            Emission::Packet(index) => {
+                if let Err(e) = self.radio.inject_frame(recording.packets[index].clone()).await {
+                    eprintln!("[playback] radio isn't taking packets: {e}");
+                }
+            }
+        }
+
+        self.status_tx.send_modify(|s| {
+            s.row = emitted;
+            s.position_ms = position;
+        });
+    }
+}
+
+// ── Clock ─────────────────────────────────────────────────────────────────────
+
+// maps a position in the recording (ms from its first row) to an Instant. Pausing
+// or changing speed re-anchors at the current position, so nothing jumps.
+struct PlaybackClock {
+    anchor: Instant,
+    anchor_position: f64,
+    speed: f64,
+    paused: bool,
+}
+
+impl PlaybackClock {
+    fn new(speed: f64, position: i64) -> Self {
+        Self { anchor: Instant::now(), anchor_position: position as f64, speed, paused: true }
+    }
+
+    fn position(&self, now: Instant) -> f64 {
+        if self.paused {
+            return self.anchor_position;
+        }
+        self.anchor_position + now.saturating_duration_since(self.anchor).as_secs_f64() * 1000.0 * self.speed
+    }
+
+    // None while paused
+    fn due(&self, position: i64) -> Option<Instant> {
+        if self.paused {
+            return None;
+        }
+        let ahead_ms = (position as f64 - self.anchor_position) / self.speed;
+        Some(self.anchor + Duration::from_secs_f64(ahead_ms.max(0.0) / 1000.0))
+    }
+
+    fn pause(&mut self, now: Instant) {
+        self.anchor_position = self.position(now);
+        self.anchor = now;
+        self.paused = true;
+    }
+
+    fn resume(&mut self, now: Instant) {
+        if self.paused {
+            self.anchor = now;
+            self.paused = false;
+        }
+    }
+
+    fn set_speed(&mut self, now: Instant, speed: f64) {
+        self.anchor_position = self.position(now);
+        self.anchor = now;
+        self.speed = speed;
+    }
+
+    fn rewind(&mut self, now: Instant) {
+        self.anchor = now;
+        self.anchor_position = 0.0;
+    }
+}
+
+// ── Randomness ────────────────────────────────────────────────────────────────
+
+// xorshift64*, plenty for timing noise and saves pulling in rand
+struct Rng(u64);
+
+impl Rng {
+    fn seeded() -> Self {
+        let seed = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(1) as u64;
+        Self(seed | 1)
+    }
+
+    // uniform in (0, 1]
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let bits = self.0.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11;
+        (bits as f64 + 1.0) / (1u64 << 53) as f64
+    }
+
+    // standard normal via Box-Muller
+    fn next_normal(&mut self) -> f64 {
+        let (u1, u2) = (self.next_f64(), self.next_f64());
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
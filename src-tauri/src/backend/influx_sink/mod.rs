@@ -0,0 +1,238 @@
+// Live telemetry sink for InfluxDB (or anything else that takes line protocol over HTTP)
+//
+// So the Grafana dashboards in the trailer update during flight. Every point pushed
+// into the middleware becomes one line:
+//   <store>[,station=<id>] <field>=<value> <unix ns>
+// batched and POSTed to the configured write URL, e.g.
+//   http://trailer:8086/api/v2/write?org=hprc&bucket=flight
+// A batch that fails to send is kept and retried, up to MAX_PENDING lines.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{watch, Mutex};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::middleware::drops::SharedDrops;
+use crate::middleware::telemetry_stores::TelemetryValue;
+use crate::middleware::{Middleware, TelemetryPoint};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_BATCH: usize = 5_000;
+// about a minute of everything, older lines are dropped past this
+const MAX_PENDING: usize = 100_000;
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InfluxConfig {
+    pub enabled: bool,
+    // full write endpoint, query string included
+    pub url: Option<String>,
+    // sent as "Authorization: Token <token>"
+    #[serde(default)]
+    pub token: Option<String>,
+    // added as a station tag when set, for telling chase and pad apart
+    #[serde(default)]
+    pub station_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InfluxStatus {
+    // last write succeeded
+    pub healthy: bool,
+    pub written: u64,
+    pub pending: usize,
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct InfluxSinkHandle {
+    config_tx: Arc<watch::Sender<InfluxConfig>>,
+    status_tx: Arc<watch::Sender<InfluxStatus>>,
+}
+
+impl InfluxSinkHandle {
+    pub fn set_config(&self, config: InfluxConfig) -> Result<(), String> {
+        if config.enabled && config.url.as_deref().unwrap_or("").is_empty() {
+            return Err("Set a write URL before enabling the InfluxDB sink".into());
+        }
+        self.config_tx.send_replace(config);
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> InfluxConfig {
+        self.config_tx.borrow().clone()
+    }
+
+    pub fn get_status(&self) -> InfluxStatus {
+        self.status_tx.borrow().clone()
+    }
+}
+
+// ── Constructor ───────────────────────────────────────────────────────────────
+
+pub fn new(middleware: Arc<Mutex<Middleware>>) -> (InfluxSink, InfluxSinkHandle) {
+    let config_tx = Arc::new(watch::Sender::new(InfluxConfig::default()));
+    let status_tx = Arc::new(watch::Sender::new(InfluxStatus::default()));
+    let sink = InfluxSink {
+        middleware,
+        config_rx: config_tx.subscribe(),
+        status_tx: status_tx.clone(),
+        client: reqwest::Client::new(),
+        pending: VecDeque::new(),
+    };
+    (sink, InfluxSinkHandle { config_tx, status_tx })
+}
+
+// ── Actor ─────────────────────────────────────────────────────────────────────
+
+pub struct InfluxSink {
+    middleware: Arc<Mutex<Middleware>>,
+    config_rx: watch::Receiver<InfluxConfig>,
+    status_tx: Arc<watch::Sender<InfluxStatus>>,
+    client: reqwest::Client,
+    pending: VecDeque<String>,
+}
+
+impl InfluxSink {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
+        loop {
+            let config = self.config_rx.borrow_and_update().clone();
+            let url = match (&config.url, config.enabled) {
+                (Some(url), true) => url.clone(),
+                _ => {
+                    // not subscribed while idle, so nothing piles up
+                    self.pending.clear();
+                    tokio::select! {
+                        _ = shutdown.cancelled() => return,
+                        _ = self.config_rx.changed() => continue,
+                    }
+                }
+            };
+
+            let (mut points, drops) = {
+                let mw = self.middleware.lock().await;
+                (mw.subscribe_telemetry(), mw.drops())
+            };
+            let mut flush = interval(FLUSH_INTERVAL);
+            flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    _ = self.config_rx.changed() => break,
+                    point = points.recv() => match point {
+                        Ok(point) => {
+                            let Some(line) = to_line(&point, config.station_id.as_deref()) else {
+                                continue;
+                            };
+                            self.push_line(line, &drops);
+                            if self.pending.len() >= MAX_BATCH {
+                                self.write(&url, config.token.as_deref()).await;
+                            }
+                        }
+                        Err(RecvError::Lagged(n)) => drops.note("influx.lagged", n),
+                        Err(RecvError::Closed) => return,
+                    },
+                    _ = flush.tick() => self.write(&url, config.token.as_deref()).await,
+                }
+            }
+        }
+    }
+
+    fn push_line(&mut self, line: String, drops: &SharedDrops) {
+        if self.pending.len() >= MAX_PENDING {
+            self.pending.pop_front();
+            drops.note("influx.overflow", 1);
+        }
+        self.pending.push_back(line);
+    }
+
+    async fn write(&mut self, url: &str, token: Option<&str>) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let count = self.pending.len().min(MAX_BATCH);
+        let body = self
+            .pending
+            .iter()
+            .take(count)
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut request = self.client.post(url).timeout(WRITE_TIMEOUT).body(body);
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Token {token}"));
+        }
+        let result = match request.send().await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => Err(format!("HTTP {}", response.status())),
+            Err(e) => Err(e.to_string()),
+        };
+
+        match result {
+            Ok(()) => {
+                self.pending.drain(..count);
+                let pending = self.pending.len();
+                self.status_tx.send_modify(|s| {
+                    s.healthy = true;
+                    s.written += count as u64;
+                    s.pending = pending;
+                    s.last_error = None;
+                });
+            }
+            Err(e) => {
+                // keep the batch, the next tick tries again
+                if self.status_tx.borrow().healthy {
+                    eprintln!("[influx] write to {url} failed: {e}");
+                }
+                let pending = self.pending.len();
+                self.status_tx.send_modify(|s| {
+                    s.healthy = false;
+                    s.pending = pending;
+                    s.last_error = Some(e);
+                });
+            }
+        }
+    }
+}
+
+// ── Line protocol ─────────────────────────────────────────────────────────────
+
+// None for values line protocol can't carry (NaN, inf)
+fn to_line(point: &TelemetryPoint, station_id: Option<&str>) -> Option<String> {
+    let mut line = escape(&point.store, &[',', ' ']);
+    if let Some(station) = station_id {
+        line.push_str(",station=");
+        line.push_str(&escape(station, &[',', '=', ' ']));
+    }
+    let value = match point.data.value {
+        TelemetryValue::F64(v) if !v.is_finite() => return None,
+        TelemetryValue::F64(v) => v.to_string(),
+        TelemetryValue::I64(v) => format!("{v}i"),
+        // InfluxDB 1.x won't take the "u" suffix, and nothing we send is past i64::MAX
+        TelemetryValue::U64(v) => format!("{}i", v.min(i64::MAX as u64)),
+        TelemetryValue::Bool(v) => v.to_string(),
+    };
+    Some(format!(
+        "{} {}={} {}",
+        line,
+        escape(&point.field, &[',', '=', ' ']),
+        value,
+        point.data.timestamp * 1_000_000
+    ))
+}
+
+fn escape(text: &str, special: &[char]) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if special.contains(&c) || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
@@ -1,29 +1,103 @@
-use gilrs::{Gilrs, Event, EventType, Axis};
+use gilrs::{Gilrs, Event, EventType, Axis, Button};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex};
 use tokio_util::sync::CancellationToken;
 
 use crate::backend::telemetry_radio_interface::TelemetryRadioPayloadControlHandle;
+use crate::backend::tracker_interface::{JogRates, TrackerHandle};
 use crate::middleware::{Middleware, telemetry_stores::TelemetryData};
 
 const STORE_NAME: &str = "payload";
 
-pub struct JoystickHandle;
+// what the sticks are currently driving, cycled with the Select button
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JoystickMode {
+    Payload,
+    Tracker,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct JoystickConfig {
+    pub mode: JoystickMode,
+    // stick deflection below this (0..1) is treated as zero
+    pub deadzone: f32,
+    // full-deflection slew rate for the tracker and PTZ, deg/s
+    pub tracker_sensitivity: f32,
+    pub ptz_sensitivity: f32,
+}
+
+impl Default for JoystickConfig {
+    fn default() -> Self {
+        Self {
+            mode: JoystickMode::Payload,
+            deadzone: 0.08,
+            tracker_sensitivity: 30.0,
+            ptz_sensitivity: 45.0,
+        }
+    }
+}
+
+pub struct JoystickHandle {
+    config_tx: Arc<watch::Sender<JoystickConfig>>,
+}
+
+impl JoystickHandle {
+    pub fn set_config(&self, config: JoystickConfig) -> Result<(), String> {
+        if !(0.0..1.0).contains(&config.deadzone) {
+            return Err(format!("Deadzone must be in [0, 1), got {}", config.deadzone));
+        }
+        // send_replace never fails, even if the joystick task hasn't started
+        self.config_tx.send_replace(config);
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> JoystickConfig {
+        *self.config_tx.borrow()
+    }
+}
 
 pub struct JoystickInput {
     telem_handle: TelemetryRadioPayloadControlHandle,
+    tracker_handle: TrackerHandle,
     middleware: Arc<Mutex<Middleware>>,
+    // shared with the handle, the Select button flips the mode from here
+    config: Arc<watch::Sender<JoystickConfig>>,
 }
 
 pub fn new(
     telem_handle: TelemetryRadioPayloadControlHandle,
+    tracker_handle: TrackerHandle,
     middleware: Arc<Mutex<Middleware>>,
 ) -> (JoystickInput, JoystickHandle) {
-    (JoystickInput { telem_handle, middleware }, JoystickHandle)
+    let config = Arc::new(watch::Sender::new(JoystickConfig::default()));
+    (
+        JoystickInput { telem_handle, tracker_handle, middleware, config: config.clone() },
+        JoystickHandle { config_tx: config },
+    )
+}
+
+// remove the deadzone and rescale so output still spans the full [-1, 1]
+fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    if value.abs() < deadzone {
+        0.0
+    } else {
+        value.signum() * (value.abs() - deadzone) / (1.0 - deadzone)
+    }
+}
+
+#[derive(Default)]
+struct Sticks {
+    left_x: f32,
+    left_y: f32,
+    right_x: f32,
+    right_y: f32,
+    zoom_in: f32,
+    zoom_out: f32,
 }
 
 impl JoystickInput {
-    pub async fn run(self, shutdown: CancellationToken) {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
         let mut gilrs = match Gilrs::new() {
             Ok(g) => g,
             Err(e) => {
@@ -32,8 +106,7 @@ impl JoystickInput {
             }
         };
 
-        let mut x: f32 = 0.0;
-        let mut y: f32 = 0.0;
+        let mut sticks = Sticks::default();
 
         loop {
             if shutdown.is_cancelled() {
@@ -42,29 +115,74 @@ impl JoystickInput {
 
             while let Some(Event { event, .. }) = gilrs.next_event() {
                 match event {
-                    EventType::AxisChanged(Axis::LeftStickX, value, _) => x = value,
-                    EventType::AxisChanged(Axis::LeftStickY, value, _) => y = value,
+                    EventType::AxisChanged(Axis::LeftStickX, value, _) => sticks.left_x = value,
+                    EventType::AxisChanged(Axis::LeftStickY, value, _) => sticks.left_y = value,
+                    EventType::AxisChanged(Axis::RightStickX, value, _) => sticks.right_x = value,
+                    EventType::AxisChanged(Axis::RightStickY, value, _) => sticks.right_y = value,
+                    EventType::ButtonChanged(Button::RightTrigger2, value, _) => sticks.zoom_in = value,
+                    EventType::ButtonChanged(Button::LeftTrigger2, value, _) => sticks.zoom_out = value,
+                    EventType::ButtonPressed(Button::Select, _) => {
+                        self.config.send_modify(|c| {
+                            c.mode = match c.mode {
+                                JoystickMode::Payload => JoystickMode::Tracker,
+                                JoystickMode::Tracker => JoystickMode::Payload,
+                            };
+                        });
+                        eprintln!("[joystick] Switched to {:?} mode", self.config.borrow().mode);
+                        // stop whatever we were driving before handing the sticks over
+                        let _ = self.tracker_handle.jog(JogRates::default());
+                        let _ = self.telem_handle.send_payload_control(0.0, 0.0).await;
+                        continue;
+                    }
                     _ => {}
                 }
 
-                if let Err(e) = self.telem_handle.send_payload_control(y, x).await {
-                    eprintln!("[joystick] Failed to send payload control: {e}");
+                let config = *self.config.borrow();
+                match config.mode {
+                    JoystickMode::Payload => self.drive_payload(&sticks, &config).await,
+                    JoystickMode::Tracker => self.drive_tracker(&sticks, &config),
                 }
-
-                let mut mw = self.middleware.lock().await;
-                let _ = mw.push_data(
-                    STORE_NAME,
-                    "joystick_x",
-                    TelemetryData::new().with_value(x as f64),
-                );
-                let _ = mw.push_data(
-                    STORE_NAME,
-                    "joystick_y",
-                    TelemetryData::new().with_value(y as f64),
-                );
             }
 
             tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
         }
     }
-}
\ No newline at end of file
+
+    async fn drive_payload(&self, sticks: &Sticks, config: &JoystickConfig) {
+        let x = apply_deadzone(sticks.left_x, config.deadzone);
+        let y = apply_deadzone(sticks.left_y, config.deadzone);
+
+        let sent = self.telem_handle.send_payload_control(y, x).await;
+
+        let mut mw = self.middleware.lock().await;
+        if let Err(e) = sent {
+            eprintln!("[joystick] Failed to send payload control: {e}");
+            mw.drops().note("uplink.payload_control", 1);
+        }
+        let _ = mw.push_data(
+            STORE_NAME,
+            "joystick_x",
+            TelemetryData::new().with_value(x as f64),
+        );
+        let _ = mw.push_data(
+            STORE_NAME,
+            "joystick_y",
+            TelemetryData::new().with_value(y as f64),
+        );
+    }
+
+    fn drive_tracker(&self, sticks: &Sticks, config: &JoystickConfig) {
+        let dz = config.deadzone;
+        let rates = JogRates {
+            azimuth: apply_deadzone(sticks.left_x, dz) * config.tracker_sensitivity,
+            elevation: apply_deadzone(sticks.left_y, dz) * config.tracker_sensitivity,
+            pan: apply_deadzone(sticks.right_x, dz) * config.ptz_sensitivity,
+            tilt: apply_deadzone(sticks.right_y, dz) * config.ptz_sensitivity,
+            zoom: apply_deadzone(sticks.zoom_in - sticks.zoom_out, dz),
+        };
+
+        if let Err(e) = self.tracker_handle.jog(rates) {
+            eprintln!("[joystick] Failed to send tracker jog: {e}");
+        }
+    }
+}
@@ -4,9 +4,30 @@
 // use crate::middleware::Middleware;
 
 // // define our backend modules that the program will interact with
+#[cfg(feature = "sim")]
 pub mod data_playback;
 pub mod telemetry_radio_interface;
+#[cfg(feature = "df")]
 pub mod tracker_interface;
+#[cfg(feature = "video")]
 pub mod video_capture_interface;
+// steers the payload and the tracker, so it needs both
+#[cfg(all(feature = "uplink", feature = "df"))]
 pub mod joystick_input;
-
+pub mod control_surface;
+pub mod serial_interface;
+pub mod relay;
+pub mod services;
+pub mod self_test;
+pub mod preflight;
+pub mod power_monitor;
+pub mod resource_monitor;
+pub mod influx_sink;
+#[cfg(feature = "df")]
+pub mod ros_bridge;
+pub mod rebroadcast;
+pub mod time_sync;
+#[cfg(feature = "uplink")]
+pub mod command_macros;
+pub mod countdown;
+pub mod store_snapshot;
@@ -0,0 +1,157 @@
+// Watches the laptop battery so a field laptop dying costs seconds of data, not the flight
+//
+// Once the battery is discharging below the threshold we flush everything to disk
+// once (see Middleware::emergency_flush) and raise a critical alert. It re-arms when
+// the laptop is plugged back in or climbs back over the threshold.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex};
+use tokio::time::{interval, Duration};
+use tokio_util::sync::CancellationToken;
+
+use crate::middleware::Middleware;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+// need to climb this far back over the threshold before we'll flush again
+const REARM_MARGIN_PERCENT: f32 = 5.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerConfig {
+    pub threshold_percent: f32,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self { threshold_percent: 15.0 }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PowerStatus {
+    // None on desktops or if the OS won't tell us
+    pub battery_percent: Option<f32>,
+    pub discharging: bool,
+    // tripped and not yet re-armed
+    pub flushed: bool,
+    pub last_snapshot: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct PowerMonitorHandle {
+    config_tx: Arc<watch::Sender<PowerConfig>>,
+    status_tx: Arc<watch::Sender<PowerStatus>>,
+}
+
+impl PowerMonitorHandle {
+    pub fn set_config(&self, config: PowerConfig) -> Result<(), String> {
+        if !(0.0..=100.0).contains(&config.threshold_percent) {
+            return Err("Threshold must be between 0 and 100 percent".into());
+        }
+        self.config_tx.send_replace(config);
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> PowerConfig {
+        self.config_tx.borrow().clone()
+    }
+
+    pub fn get_status(&self) -> PowerStatus {
+        self.status_tx.borrow().clone()
+    }
+}
+
+// ── Constructor ───────────────────────────────────────────────────────────────
+
+pub fn new(middleware: Arc<Mutex<Middleware>>) -> (PowerMonitor, PowerMonitorHandle) {
+    let config_tx = Arc::new(watch::Sender::new(PowerConfig::default()));
+    let status_tx = Arc::new(watch::Sender::new(PowerStatus::default()));
+    let monitor = PowerMonitor {
+        middleware,
+        config_rx: config_tx.subscribe(),
+        status_tx: status_tx.clone(),
+    };
+    (monitor, PowerMonitorHandle { config_tx, status_tx })
+}
+
+// ── Actor ─────────────────────────────────────────────────────────────────────
+
+pub struct PowerMonitor {
+    middleware: Arc<Mutex<Middleware>>,
+    config_rx: watch::Receiver<PowerConfig>,
+    status_tx: Arc<watch::Sender<PowerStatus>>,
+}
+
+impl PowerMonitor {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
+        let mut poll = interval(POLL_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = poll.tick() => self.check().await,
+            }
+        }
+    }
+
+    async fn check(&mut self) {
+        let reading = match tokio::task::spawn_blocking(read_battery).await {
+            Ok(Ok(reading)) => reading,
+            Ok(Err(e)) => {
+                eprintln!("[power] Failed to read battery: {e}");
+                None
+            }
+            Err(e) => {
+                eprintln!("[power] Battery task failed: {e}");
+                None
+            }
+        };
+        let Some(BatteryReading { percent, discharging }) = reading else {
+            self.status_tx.send_modify(|s| s.battery_percent = None);
+            return;
+        };
+
+        let threshold = self.config_rx.borrow().threshold_percent;
+        let flushed = self.status_tx.borrow().flushed;
+        self.status_tx.send_modify(|s| {
+            s.battery_percent = Some(percent);
+            s.discharging = discharging;
+        });
+
+        if !flushed && discharging && percent < threshold {
+            let reason = format!("Laptop battery at {:.0}%", percent);
+            let result = self.middleware.lock().await.emergency_flush(&reason);
+            match result {
+                Ok(snapshot) => self.status_tx.send_modify(|s| {
+                    s.flushed = true;
+                    s.last_snapshot = Some(snapshot.display().to_string());
+                }),
+                // try again next poll
+                Err(e) => eprintln!("[power] Emergency flush failed: {e}"),
+            }
+        } else if flushed && (!discharging || percent >= threshold + REARM_MARGIN_PERCENT) {
+            self.status_tx.send_modify(|s| s.flushed = false);
+            self.middleware.lock().await.clear_alert("data.emergency_flush");
+        }
+    }
+}
+
+// ── OS battery ────────────────────────────────────────────────────────────────
+
+struct BatteryReading {
+    percent: f32,
+    discharging: bool,
+}
+
+// the first battery the OS reports, None if there isn't one
+fn read_battery() -> Result<Option<BatteryReading>, String> {
+    let manager = starship_battery::Manager::new().map_err(|e| e.to_string())?;
+    let mut batteries = manager.batteries().map_err(|e| e.to_string())?;
+    let Some(battery) = batteries.next() else {
+        return Ok(None);
+    };
+    let battery = battery.map_err(|e| e.to_string())?;
+    Ok(Some(BatteryReading {
+        percent: battery.state_of_charge().value * 100.0,
+        discharging: battery.state() == starship_battery::State::Discharging,
+    }))
+}
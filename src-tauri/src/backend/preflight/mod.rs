@@ -0,0 +1,299 @@
+// "Will this laptop work at the launch site" check, run before anyone leaves
+//
+// self_test proves the pipeline works. This is about the machine around it, where
+// field laptops actually fail: a serial port the user isn't allowed to open (the
+// Linux dialout group), a radio with no driver so it never shows up as a port (FTDI
+// on Windows), a camera the OS hasn't given us permission to (macOS), no ffmpeg on
+// the PATH, a nearly full disk, a clock nobody has synced. Every check comes back
+// pass, warn or fail with what was found and, when it isn't a pass, how to fix it.
+// Nothing is changed and nothing is written.
+//
+// Probing a port opens it for a moment. A port the station already has open reports
+// busy, which is a pass, someone is using it.
+
+use serde::Serialize;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use sysinfo::Disks;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+use tokio::time::{timeout, Duration};
+
+use crate::backend::time_sync::TimeSyncHandle;
+use crate::middleware::Middleware;
+
+const FFMPEG_TIMEOUT: Duration = Duration::from_secs(5);
+const PORT_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(100);
+// a flight's CSVs are small, it's the video that fills a disk
+const DISK_WARN_BYTES: u64 = 20 * 1024 * 1024 * 1024;
+const DISK_FAIL_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreflightCheck {
+    // serial, camera, ffmpeg, disk or clock
+    pub category: String,
+    pub name: String,
+    pub status: PreflightStatus,
+    pub detail: String,
+    // what to do about it, for anything that didn't pass
+    pub fix: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreflightReport {
+    // the worst of the checks
+    pub status: PreflightStatus,
+    pub checks: Vec<PreflightCheck>,
+}
+
+#[derive(Default)]
+struct Report {
+    checks: Vec<PreflightCheck>,
+}
+
+impl Report {
+    fn add(&mut self, category: &str, name: &str, status: PreflightStatus, detail: String, fix: Option<&str>) {
+        self.checks.push(PreflightCheck {
+            category: category.to_string(),
+            name: name.to_string(),
+            status,
+            detail,
+            fix: fix.map(str::to_string),
+        });
+    }
+
+    fn finish(self) -> PreflightReport {
+        PreflightReport {
+            status: self.checks.iter().map(|c| c.status).max().unwrap_or(PreflightStatus::Pass),
+            checks: self.checks,
+        }
+    }
+}
+
+pub async fn run_preflight_check(middleware: &Arc<Mutex<Middleware>>, time_sync: &TimeSyncHandle) -> PreflightReport {
+    let mut report = Report::default();
+    let session_dir = middleware.lock().await.session_dir();
+
+    let serial = tokio::task::spawn_blocking(check_serial).await.unwrap_or_default();
+    report.checks.extend(serial);
+    #[cfg(feature = "video")]
+    {
+        let camera = tokio::task::spawn_blocking(check_camera).await.unwrap_or_default();
+        report.checks.extend(camera);
+    }
+    check_ffmpeg(&mut report).await;
+    check_disk(&mut report, &session_dir);
+    check_clock(&mut report, time_sync);
+    report.finish()
+}
+
+// ── Serial ────────────────────────────────────────────────────────────────────
+
+fn check_serial() -> Vec<PreflightCheck> {
+    let mut report = Report::default();
+    let ports = match serialport::available_ports() {
+        Ok(ports) => ports,
+        Err(e) => {
+            report.add("serial", "ports", PreflightStatus::Fail, format!("Couldn't list serial ports: {e}"), None);
+            return report.checks;
+        }
+    };
+
+    let usb: Vec<_> = ports
+        .iter()
+        .filter_map(|p| match &p.port_type {
+            serialport::SerialPortType::UsbPort(info) => Some((p.port_name.as_str(), info)),
+            _ => None,
+        })
+        .collect();
+    if usb.is_empty() {
+        report.add(
+            "serial",
+            "ports",
+            PreflightStatus::Warn,
+            format!("No USB serial ports ({} other ports)", ports.len()),
+            Some(no_port_fix()),
+        );
+    }
+
+    for (name, info) in usb {
+        let device = match (&info.manufacturer, &info.product) {
+            (_, Some(product)) => format!("{product} ({:04x}:{:04x})", info.vid, info.pid),
+            (Some(manufacturer), None) => format!("{manufacturer} ({:04x}:{:04x})", info.vid, info.pid),
+            (None, None) => format!("{:04x}:{:04x}", info.vid, info.pid),
+        };
+        let (status, detail, fix) = match serialport::new(name, 115_200).timeout(PORT_TIMEOUT).open() {
+            Ok(_) => (PreflightStatus::Pass, format!("{device} opens"), None),
+            Err(e) => match e.kind() {
+                serialport::ErrorKind::Io(std::io::ErrorKind::PermissionDenied) => {
+                    (PreflightStatus::Fail, format!("{device}: permission denied"), Some(permission_fix()))
+                }
+                serialport::ErrorKind::NoDevice => {
+                    (PreflightStatus::Fail, format!("{device} disappeared: {e}"), Some("Replug the device and check the cable"))
+                }
+                _ if is_busy(&e) => (PreflightStatus::Pass, format!("{device} is in use"), None),
+                _ => (PreflightStatus::Warn, format!("{device}: {e}"), None),
+            },
+        };
+        report.add("serial", name, status, detail, fix);
+    }
+    report.checks
+}
+
+// the OS says busy a few different ways
+fn is_busy(e: &serialport::Error) -> bool {
+    let text = e.to_string().to_lowercase();
+    text.contains("busy") || text.contains("in use")
+}
+
+fn permission_fix() -> &'static str {
+    if cfg!(target_os = "linux") {
+        "sudo usermod -aG dialout $USER (uucp on Arch), then log out and back in"
+    } else if cfg!(target_os = "macos") {
+        "Check the port isn't held by another app, /dev/cu.* needs no extra permission"
+    } else {
+        // Windows says access denied for a port someone else has open
+        "Close anything else using the port (a serial monitor, another station)"
+    }
+}
+
+fn no_port_fix() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "If the radio is plugged in, install the FTDI VCP driver and check Device Manager for a COM port"
+    } else if cfg!(target_os = "macos") {
+        "If the radio is plugged in, check System Information > USB, older FTDI boards need the VCP driver"
+    } else {
+        "If the radio is plugged in, check dmesg for ftdi_sio or cdc_acm and try another cable"
+    }
+}
+
+// ── Camera ────────────────────────────────────────────────────────────────────
+
+#[cfg(feature = "video")]
+fn check_camera() -> Vec<PreflightCheck> {
+    let mut report = Report::default();
+    #[cfg(target_os = "macos")]
+    if !nokhwa::nokhwa_check() {
+        report.add(
+            "camera",
+            "permission",
+            PreflightStatus::Fail,
+            "Camera access hasn't been granted".into(),
+            Some("System Settings > Privacy & Security > Camera, allow the ground station, then restart it"),
+        );
+        return report.checks;
+    }
+    match nokhwa::query(nokhwa::utils::ApiBackend::Auto) {
+        Ok(cameras) if cameras.is_empty() => report.add(
+            "camera",
+            "devices",
+            PreflightStatus::Warn,
+            "No cameras found".into(),
+            Some("Plug in the capture card or camera and run the check again"),
+        ),
+        Ok(cameras) => {
+            let names: Vec<String> = cameras.iter().map(|c| c.human_name()).collect();
+            report.add("camera", "devices", PreflightStatus::Pass, names.join(", "), None);
+        }
+        Err(e) => report.add("camera", "devices", PreflightStatus::Fail, format!("Couldn't list cameras: {e}"), None),
+    }
+    report.checks
+}
+
+// ── ffmpeg ────────────────────────────────────────────────────────────────────
+
+async fn check_ffmpeg(report: &mut Report) {
+    let output = Command::new("ffmpeg")
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+    let (status, detail, fix) = match timeout(FFMPEG_TIMEOUT, output).await {
+        Ok(Ok(output)) if output.status.success() => {
+            let version = String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or_default().to_string();
+            (PreflightStatus::Pass, version, None)
+        }
+        Ok(Ok(output)) => (PreflightStatus::Fail, format!("ffmpeg -version exited with {}", output.status), None),
+        Ok(Err(e)) => (
+            PreflightStatus::Fail,
+            format!("ffmpeg isn't runnable: {e}"),
+            Some("Install ffmpeg and make sure it's on the PATH, video recording needs it"),
+        ),
+        Err(_) => (PreflightStatus::Fail, "ffmpeg -version didn't finish".into(), None),
+    };
+    report.add("ffmpeg", "ffmpeg", status, detail, fix);
+}
+
+// ── Disk ──────────────────────────────────────────────────────────────────────
+
+fn check_disk(report: &mut Report, session_dir: &Path) {
+    let disks = Disks::new_with_refreshed_list();
+    // the disk mounted deepest above the session folder is the one it's on
+    let disk = disks
+        .list()
+        .iter()
+        .filter(|d| session_dir.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len());
+    let Some(disk) = disk else {
+        report.add(
+            "disk",
+            "session",
+            PreflightStatus::Warn,
+            format!("Couldn't tell which disk {} is on", session_dir.display()),
+            None,
+        );
+        return;
+    };
+
+    let free = disk.available_space();
+    let gib = free as f64 / (1024.0 * 1024.0 * 1024.0);
+    let detail = format!("{gib:.1} GiB free on {} for {}", disk.mount_point().display(), session_dir.display());
+    let status = if free < DISK_FAIL_BYTES {
+        PreflightStatus::Fail
+    } else if free < DISK_WARN_BYTES {
+        PreflightStatus::Warn
+    } else {
+        PreflightStatus::Pass
+    };
+    let fix = (status != PreflightStatus::Pass).then_some("Clear out old sessions and recordings, video is the big one");
+    report.add("disk", "session", status, detail, fix);
+}
+
+// ── Clock ─────────────────────────────────────────────────────────────────────
+
+// what time_sync last measured, it's already checking in the background
+fn check_clock(report: &mut Report, time_sync: &TimeSyncHandle) {
+    let config = time_sync.get_config();
+    let status = time_sync.get_status();
+    let (state, detail, fix) = if !config.enabled {
+        (PreflightStatus::Warn, "Clock checking is off".into(), Some("Turn time sync on, or check the clock by hand"))
+    } else if let Some(error) = status.last_error {
+        (
+            PreflightStatus::Warn,
+            format!("Couldn't reach the time reference: {error}"),
+            Some("At the site, point time sync at gpsd with a GPS plugged in"),
+        )
+    } else if let Some(offset) = status.offset_ms {
+        if status.within_threshold {
+            (PreflightStatus::Pass, format!("{offset:+.0} ms off the reference"), None)
+        } else {
+            (
+                PreflightStatus::Warn,
+                format!("{offset:+.0} ms off the reference, past {} ms", config.threshold_ms),
+                Some("Sync the OS clock (chrony or the OS time settings)"),
+            )
+        }
+    } else {
+        (PreflightStatus::Warn, "No measurement yet".into(), Some("Run the check again in a minute"))
+    };
+    report.add("clock", "offset", state, detail, fix);
+}
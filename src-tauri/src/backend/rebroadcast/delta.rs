@@ -0,0 +1,123 @@
+// Per-store delta encoding for the rebroadcast
+//
+// The encoder remembers the last value it sent for every field of a store. Each tick
+// a store with new data becomes one message: a keyframe with every field every
+// `keyframe_interval` messages, a delta with only the fields whose value changed in
+// between. seq counts a store's messages, so a client that sees a jump knows it
+// missed a delta and holds off until the next keyframe.
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::middleware::telemetry_stores::TelemetryValue;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKind {
+    Key,
+    Delta,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreMessage<'a> {
+    #[serde(rename = "type")]
+    pub kind: MessageKind,
+    pub store: &'a str,
+    pub seq: u64,
+    // unix ms of the newest value in the message
+    pub t: i64,
+    pub fields: BTreeMap<&'a str, Value>,
+}
+
+#[derive(Default)]
+struct StoreState {
+    seq: u64,
+    timestamp: i64,
+    sent: BTreeMap<String, TelemetryValue>,
+    // arrived since the last message
+    pending: BTreeMap<String, TelemetryValue>,
+}
+
+#[derive(Default)]
+pub struct DeltaEncoder {
+    stores: HashMap<String, StoreState>,
+}
+
+// NaN and inf have no JSON, they go out as null
+fn to_json(value: TelemetryValue) -> Value {
+    match value {
+        TelemetryValue::F64(v) => serde_json::Number::from_f64(v).map(Value::Number).unwrap_or(Value::Null),
+        TelemetryValue::I64(v) => v.into(),
+        TelemetryValue::U64(v) => v.into(),
+        TelemetryValue::Bool(v) => v.into(),
+    }
+}
+
+impl DeltaEncoder {
+    pub fn push(&mut self, store: &str, field: &str, timestamp: i64, value: TelemetryValue) {
+        let state = self.stores.entry(store.to_string()).or_default();
+        state.timestamp = state.timestamp.max(timestamp);
+        state.pending.insert(field.to_string(), value);
+    }
+
+    /// One message per store with new data since the last call, serialized. A store
+    /// whose new values are all the same as before sends nothing, unless it's due a
+    /// keyframe.
+    pub fn flush(&mut self, keyframe_interval: u64) -> Vec<(MessageKind, String)> {
+        let mut messages = Vec::new();
+        for (store, state) in self.stores.iter_mut() {
+            if state.pending.is_empty() {
+                continue;
+            }
+            let keyframe = state.seq % keyframe_interval.max(1) == 0;
+            let mut changed = BTreeMap::new();
+            for (field, value) in std::mem::take(&mut state.pending) {
+                if state.sent.get(&field) != Some(&value) {
+                    changed.insert(field.clone(), value);
+                }
+                state.sent.insert(field, value);
+            }
+            if !keyframe && changed.is_empty() {
+                continue;
+            }
+            let fields = if keyframe { &state.sent } else { &changed };
+            let kind = if keyframe { MessageKind::Key } else { MessageKind::Delta };
+            let message = StoreMessage {
+                kind,
+                store,
+                seq: state.seq,
+                t: state.timestamp,
+                fields: fields.iter().map(|(f, v)| (f.as_str(), to_json(*v))).collect(),
+            };
+            state.seq += 1;
+            if let Ok(text) = serde_json::to_string(&message) {
+                messages.push((kind, text));
+            }
+        }
+        messages
+    }
+
+    /// The full state of every store as keyframes, for a client that just connected.
+    /// They carry the seq of the last message sent, so a delta the client already has
+    /// queued with that seq or older can be skipped.
+    pub fn snapshot(&self) -> Vec<String> {
+        self.stores
+            .iter()
+            .filter(|(_, state)| !state.sent.is_empty())
+            .filter_map(|(store, state)| {
+                serde_json::to_string(&StoreMessage {
+                    kind: MessageKind::Key,
+                    store,
+                    seq: state.seq.saturating_sub(1),
+                    t: state.timestamp,
+                    fields: state.sent.iter().map(|(f, v)| (f.as_str(), to_json(*v))).collect(),
+                })
+                .ok()
+            })
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.stores.clear();
+    }
+}
@@ -0,0 +1,301 @@
+// Live telemetry over WebSocket for other machines on the LAN
+//
+// Anyone on the trailer network can open ws://<ground station>:<port> and get every
+// store's telemetry as JSON text frames, one per store per PUBLISH_INTERVAL:
+//   {"type":"key","store":"rocket","seq":40,"t":1760000000000,"fields":{"alt":812.4,...}}
+//   {"type":"delta","store":"rocket","seq":41,"t":1760000000125,"fields":{"alt":815.1}}
+// A keyframe has every field, a delta only the ones that changed since the message
+// before it. Resending the whole field set at 10 Hz was most of the LAN traffic, and
+// most fields sit still most of the time.
+//
+// Applying them on the client: keep the last seq per store, replace the state on a
+// key, merge a delta whose seq is one more than the last, ignore anything at or below
+// it. A delta that skips a seq means one went missing, so wait for the next key.
+// A new client gets a keyframe of every store straight away.
+
+mod delta;
+
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch, Mutex};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+
+use crate::backend::services::catch_panic;
+use crate::middleware::lock_timing::TimedMutex;
+use crate::middleware::Middleware;
+use delta::{DeltaEncoder, MessageKind};
+
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+// messages a slow client can fall behind by before it starts missing them
+const CLIENT_BACKLOG: usize = 256;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebroadcastConfig {
+    pub enabled: bool,
+    #[serde(default = "default_bind")]
+    pub bind: String,
+    // empty means every store
+    #[serde(default)]
+    pub stores: Vec<String>,
+    // a keyframe every this many messages of a store, 1 sends nothing but keyframes
+    #[serde(default = "default_keyframe_interval")]
+    pub keyframe_interval: u64,
+}
+
+fn default_bind() -> String {
+    "0.0.0.0:8765".into()
+}
+
+fn default_keyframe_interval() -> u64 {
+    50
+}
+
+impl Default for RebroadcastConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: default_bind(),
+            stores: Vec::new(),
+            keyframe_interval: default_keyframe_interval(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RebroadcastStatus {
+    pub listening: bool,
+    pub clients: usize,
+    pub keyframes: u64,
+    pub deltas: u64,
+    // to every client, so a second client doubles it
+    pub bytes_sent: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct RebroadcastHandle {
+    config_tx: Arc<watch::Sender<RebroadcastConfig>>,
+    status_tx: Arc<watch::Sender<RebroadcastStatus>>,
+}
+
+impl RebroadcastHandle {
+    pub fn set_config(&self, config: RebroadcastConfig) -> Result<(), String> {
+        if config.bind.parse::<std::net::SocketAddr>().is_err() {
+            return Err(format!("'{}' isn't an address to listen on, e.g. 0.0.0.0:8765", config.bind));
+        }
+        if config.keyframe_interval == 0 {
+            return Err("The keyframe interval must be at least 1".into());
+        }
+        self.config_tx.send_replace(config);
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> RebroadcastConfig {
+        self.config_tx.borrow().clone()
+    }
+
+    pub fn get_status(&self) -> RebroadcastStatus {
+        self.status_tx.borrow().clone()
+    }
+}
+
+// ── Constructor ───────────────────────────────────────────────────────────────
+
+pub fn new(middleware: Arc<Mutex<Middleware>>) -> (Rebroadcast, RebroadcastHandle) {
+    let config_tx = Arc::new(watch::Sender::new(RebroadcastConfig::default()));
+    let status_tx = Arc::new(watch::Sender::new(RebroadcastStatus::default()));
+    let rebroadcast = Rebroadcast {
+        middleware,
+        config_rx: config_tx.subscribe(),
+        status_tx: status_tx.clone(),
+        encoder: Arc::new(TimedMutex::new("rebroadcast", DeltaEncoder::default())),
+    };
+    (rebroadcast, RebroadcastHandle { config_tx, status_tx })
+}
+
+// ── Actor ─────────────────────────────────────────────────────────────────────
+
+pub struct Rebroadcast {
+    middleware: Arc<Mutex<Middleware>>,
+    config_rx: watch::Receiver<RebroadcastConfig>,
+    status_tx: Arc<watch::Sender<RebroadcastStatus>>,
+    // shared with the client tasks for their first keyframes
+    encoder: Arc<TimedMutex<DeltaEncoder>>,
+}
+
+impl Rebroadcast {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
+        loop {
+            let config = self.config_rx.borrow_and_update().clone();
+            // clients count themselves out as they close
+            self.status_tx.send_modify(|s| s.listening = false);
+            if !config.enabled {
+                tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    _ = self.config_rx.changed() => continue,
+                }
+            }
+
+            let listener = match TcpListener::bind(&config.bind).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    eprintln!("[rebroadcast] Couldn't listen on {}: {e}", config.bind);
+                    self.status_tx.send_modify(|s| s.last_error = Some(format!("{}: {e}", config.bind)));
+                    tokio::select! {
+                        _ = shutdown.cancelled() => return,
+                        _ = self.config_rx.changed() => continue,
+                    }
+                }
+            };
+            println!("[rebroadcast] Listening on ws://{}", config.bind);
+            self.status_tx.send_modify(|s| {
+                s.listening = true;
+                s.last_error = None;
+            });
+
+            // every connection ends with the listener, on a config change or shutdown
+            let stop = shutdown.child_token();
+            self.serve(&listener, &config, &shutdown, &stop).await;
+            stop.cancel();
+            self.encoder.lock().clear();
+            if shutdown.is_cancelled() {
+                return;
+            }
+        }
+    }
+
+    async fn serve(&mut self, listener: &TcpListener, config: &RebroadcastConfig, shutdown: &CancellationToken, stop: &CancellationToken) {
+        let (mut points, drops) = {
+            let mw = self.middleware.lock().await;
+            (mw.subscribe_telemetry(), mw.drops())
+        };
+        let (message_tx, _) = broadcast::channel::<Arc<String>>(CLIENT_BACKLOG);
+        let mut publish = interval(PUBLISH_INTERVAL);
+        publish.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = self.config_rx.changed() => return,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        let client = Client {
+                            messages: message_tx.subscribe(),
+                            encoder: self.encoder.clone(),
+                            status_tx: self.status_tx.clone(),
+                            stop: stop.clone(),
+                        };
+                        let peer = peer.to_string();
+                        tauri::async_runtime::spawn(async move {
+                            // one bad client shouldn't take the server down with it
+                            if let Err(message) = catch_panic(client.run(stream, peer.clone())).await {
+                                eprintln!("[rebroadcast] client {peer} panicked: {message}");
+                            }
+                        });
+                    }
+                    Err(e) => eprintln!("[rebroadcast] accept failed: {e}"),
+                },
+                point = points.recv() => match point {
+                    Ok(point) => {
+                        if config.stores.is_empty() || config.stores.contains(&point.store) {
+                            self.encoder.lock().push(&point.store, &point.field, point.data.timestamp, point.data.value);
+                        }
+                    }
+                    Err(RecvError::Lagged(n)) => drops.note("rebroadcast.lagged", n),
+                    Err(RecvError::Closed) => return,
+                },
+                _ = publish.tick() => {
+                    let messages = self.encoder.lock().flush(config.keyframe_interval);
+                    let (mut keyframes, mut deltas) = (0, 0);
+                    for (kind, text) in messages {
+                        match kind {
+                            MessageKind::Key => keyframes += 1,
+                            MessageKind::Delta => deltas += 1,
+                        }
+                        // no receivers is fine, nobody's connected
+                        let _ = message_tx.send(Arc::new(text));
+                    }
+                    if keyframes + deltas > 0 {
+                        self.status_tx.send_modify(|s| {
+                            s.keyframes += keyframes;
+                            s.deltas += deltas;
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+// ── Clients ───────────────────────────────────────────────────────────────────
+
+struct Client {
+    messages: broadcast::Receiver<Arc<String>>,
+    encoder: Arc<TimedMutex<DeltaEncoder>>,
+    status_tx: Arc<watch::Sender<RebroadcastStatus>>,
+    stop: CancellationToken,
+}
+
+impl Client {
+    async fn run(mut self, stream: TcpStream, peer: String) {
+        let socket = match tokio_tungstenite::accept_async(stream).await {
+            Ok(socket) => socket,
+            Err(e) => {
+                eprintln!("[rebroadcast] {peer}: handshake failed: {e}");
+                return;
+            }
+        };
+        println!("[rebroadcast] {peer} connected");
+        self.status_tx.send_modify(|s| s.clients += 1);
+        let result = self.serve(socket).await;
+        self.status_tx.send_modify(|s| s.clients = s.clients.saturating_sub(1));
+        match result {
+            Ok(()) => println!("[rebroadcast] {peer} disconnected"),
+            Err(e) => eprintln!("[rebroadcast] {peer} dropped: {e}"),
+        }
+    }
+
+    async fn serve(&mut self, socket: tokio_tungstenite::WebSocketStream<TcpStream>) -> Result<(), String> {
+        let (mut tx, mut rx) = socket.split();
+        // subscribed before the snapshot, so nothing falls between the two
+        let snapshot = self.encoder.lock().snapshot();
+        for text in snapshot {
+            self.send(&mut tx, text).await?;
+        }
+        loop {
+            tokio::select! {
+                _ = self.stop.cancelled() => {
+                    let _ = tx.send(Message::Close(None)).await;
+                    return Ok(());
+                }
+                message = self.messages.recv() => match message {
+                    Ok(text) => self.send(&mut tx, text.as_ref().clone()).await?,
+                    // it will pick back up at the next keyframe
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+                incoming = rx.next() => match incoming {
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e.to_string()),
+                },
+            }
+        }
+    }
+
+    async fn send<S>(&self, tx: &mut S, text: String) -> Result<(), String>
+    where
+        S: SinkExt<Message> + Unpin,
+        S::Error: std::fmt::Display,
+    {
+        let bytes = text.len() as u64;
+        tx.send(Message::Text(text.into())).await.map_err(|e| e.to_string())?;
+        self.status_tx.send_modify(|s| s.bytes_sent += bytes);
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+// Store-and-forward relay to a peer ground station / server
+//
+// Raw radio frames are queued here while the chase vehicle has no coverage and
+// uploaded once the peer is reachable again. The wire format is one JSON object
+// per line over TCP:
+//   gs   -> peer: {"station":..,"session":..,"seq":..,"received_at":..,"frame":"<base64>"}
+//   peer -> gs:   "ACK <seq>\n"   everything up to and including seq is safe
+//
+// Unacked frames are resent after a reconnect, so the peer should dedup on
+// (session, seq). Identical frames heard twice locally are dropped before queueing.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, watch};
+use tokio::time::{sleep, timeout, Duration};
+use tokio_util::sync::CancellationToken;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+// sent but unacked frames allowed on the wire at once
+const SEND_WINDOW: usize = 64;
+// about an hour of 10 Hz telemetry, oldest frames go first past this
+const MAX_QUEUED: usize = 50_000;
+// how many recent frame hashes we remember for dedup
+const DEDUP_HISTORY: usize = 1024;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RelayConfig {
+    pub enabled: bool,
+    // "host:port" of the peer
+    pub peer: Option<String>,
+    // identifies us to the peer, e.g. "chase-1"
+    #[serde(default)]
+    pub station_id: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RelayStatus {
+    pub connected: bool,
+    pub queued: usize,
+    pub uploaded: u64,
+    pub duplicates_dropped: u64,
+    pub overflow_dropped: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RelayRecord<'a> {
+    station: &'a str,
+    session: &'a str,
+    seq: u64,
+    received_at: i64,
+    frame: String,
+}
+
+struct QueuedFrame {
+    seq: u64,
+    received_at: i64,
+    frame: Vec<u8>,
+}
+
+#[derive(Clone)]
+pub struct RelayHandle {
+    frame_tx: mpsc::UnboundedSender<(i64, Vec<u8>)>,
+    config_tx: Arc<watch::Sender<RelayConfig>>,
+    status_tx: Arc<watch::Sender<RelayStatus>>,
+}
+
+impl RelayHandle {
+    // never blocks the radio, the relay drops it if it isn't enabled
+    pub fn forward(&self, frame: &[u8]) {
+        let _ = self
+            .frame_tx
+            .send((chrono::Utc::now().timestamp_millis(), frame.to_vec()));
+    }
+
+    pub fn set_config(&self, config: RelayConfig) -> Result<(), String> {
+        if config.enabled && config.peer.as_deref().unwrap_or("").is_empty() {
+            return Err("Relay needs a peer address to be enabled".into());
+        }
+        self.config_tx.send_replace(config);
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> RelayConfig {
+        self.config_tx.borrow().clone()
+    }
+
+    pub fn get_status(&self) -> RelayStatus {
+        self.status_tx.borrow().clone()
+    }
+}
+
+// ── Constructor ───────────────────────────────────────────────────────────────
+
+pub fn new() -> (Relay, RelayHandle) {
+    let (frame_tx, frame_rx) = mpsc::unbounded_channel();
+    let config_tx = Arc::new(watch::Sender::new(RelayConfig::default()));
+    let status_tx = Arc::new(watch::Sender::new(RelayStatus::default()));
+    let relay = Relay {
+        frame_rx,
+        config_rx: config_tx.subscribe(),
+        status_tx: status_tx.clone(),
+        session: uuid::Uuid::new_v4().to_string(),
+        next_seq: 0,
+        queue: VecDeque::new(),
+        recent: VecDeque::with_capacity(DEDUP_HISTORY),
+        recent_set: HashSet::with_capacity(DEDUP_HISTORY),
+    };
+    (relay, RelayHandle { frame_tx, config_tx, status_tx })
+}
+
+// ── Actor ─────────────────────────────────────────────────────────────────────
+
+pub struct Relay {
+    frame_rx: mpsc::UnboundedReceiver<(i64, Vec<u8>)>,
+    config_rx: watch::Receiver<RelayConfig>,
+    status_tx: Arc<watch::Sender<RelayStatus>>,
+    // new per launch of the app so seq numbers never collide on the peer
+    session: String,
+    next_seq: u64,
+    queue: VecDeque<QueuedFrame>,
+    recent: VecDeque<u64>,
+    recent_set: HashSet<u64>,
+}
+
+impl Relay {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
+        loop {
+            let config = self.config_rx.borrow_and_update().clone();
+            let peer = match (&config.peer, config.enabled) {
+                (Some(peer), true) => peer.clone(),
+                _ => {
+                    // idle until configured, nothing gets queued meanwhile
+                    tokio::select! {
+                        _ = shutdown.cancelled() => return,
+                        _ = self.config_rx.changed() => continue,
+                        Some(_) = self.frame_rx.recv() => continue,
+                    }
+                }
+            };
+
+            match self.run_connected(&peer, &config.station_id, &shutdown).await {
+                RelayResult::Shutdown => return,
+                RelayResult::ConfigChanged => {}
+                RelayResult::Error(e) => {
+                    eprintln!("[relay] {peer}: {e}. Retrying in {}s...", RETRY_DELAY.as_secs());
+                    self.status_tx.send_modify(|s| {
+                        s.connected = false;
+                        s.last_error = Some(e);
+                    });
+
+                    // keep queueing while we wait, that's the whole point
+                    let retry = sleep(RETRY_DELAY);
+                    tokio::pin!(retry);
+                    loop {
+                        tokio::select! {
+                            _ = &mut retry => break,
+                            _ = shutdown.cancelled() => return,
+                            _ = self.config_rx.changed() => break,
+                            Some((ts, frame)) = self.frame_rx.recv() => { self.enqueue(ts, frame); }
+                        }
+                    }
+                }
+            }
+            self.status_tx.send_modify(|s| s.connected = false);
+        }
+    }
+
+    async fn run_connected(
+        &mut self,
+        peer: &str,
+        station_id: &str,
+        shutdown: &CancellationToken,
+    ) -> RelayResult {
+        let stream = match timeout(CONNECT_TIMEOUT, TcpStream::connect(peer)).await {
+            Ok(Ok(s)) => s,
+            Ok(Err(e)) => return RelayResult::Error(e.to_string()),
+            Err(_) => return RelayResult::Error("connect timed out".into()),
+        };
+        let (read_half, mut write_half) = stream.into_split();
+        let mut acks = BufReader::new(read_half).lines();
+
+        println!("[relay] connected to {peer}, {} frames waiting", self.queue.len());
+        self.status_tx.send_modify(|s| {
+            s.connected = true;
+            s.last_error = None;
+        });
+
+        // everything in the queue is unacked, so after a reconnect we start from the front
+        let mut in_flight = 0usize;
+
+        loop {
+            while in_flight < SEND_WINDOW && in_flight < self.queue.len() {
+                let line = self.encode(&self.queue[in_flight], station_id);
+                if let Err(e) = write_half.write_all(line.as_bytes()).await {
+                    return RelayResult::Error(e.to_string());
+                }
+                in_flight += 1;
+            }
+
+            tokio::select! {
+                _ = shutdown.cancelled() => return RelayResult::Shutdown,
+                _ = self.config_rx.changed() => return RelayResult::ConfigChanged,
+                Some((ts, frame)) = self.frame_rx.recv() => {
+                    if self.enqueue(ts, frame) {
+                        // the oldest frame was evicted, and it was one of ours on the wire
+                        in_flight = in_flight.saturating_sub(1);
+                    }
+                }
+                line = acks.next_line() => match line {
+                    Ok(Some(line)) => {
+                        let Some(acked) = line.trim().strip_prefix("ACK ").and_then(|n| n.trim().parse::<u64>().ok()) else {
+                            eprintln!("[relay] ignoring unknown reply '{line}'");
+                            continue;
+                        };
+                        let mut popped = 0;
+                        while self.queue.front().is_some_and(|f| f.seq <= acked) {
+                            self.queue.pop_front();
+                            popped += 1;
+                        }
+                        in_flight = in_flight.saturating_sub(popped);
+                        let queued = self.queue.len();
+                        self.status_tx.send_modify(|s| {
+                            s.uploaded += popped as u64;
+                            s.queued = queued;
+                        });
+                    }
+                    Ok(None) => return RelayResult::Error("peer closed the connection".into()),
+                    Err(e) => return RelayResult::Error(e.to_string()),
+                },
+            }
+        }
+    }
+
+    // returns true if the queue was full and the oldest frame had to go
+    fn enqueue(&mut self, received_at: i64, frame: Vec<u8>) -> bool {
+        if !self.config_rx.borrow().enabled {
+            return false;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        frame.hash(&mut hasher);
+        let hash = hasher.finish();
+        if !self.recent_set.insert(hash) {
+            self.status_tx.send_modify(|s| s.duplicates_dropped += 1);
+            return false;
+        }
+        self.recent.push_back(hash);
+        if self.recent.len() > DEDUP_HISTORY {
+            if let Some(old) = self.recent.pop_front() {
+                self.recent_set.remove(&old);
+            }
+        }
+
+        let overflow = self.queue.len() >= MAX_QUEUED;
+        if overflow {
+            self.queue.pop_front();
+        }
+        self.queue.push_back(QueuedFrame { seq: self.next_seq, received_at, frame });
+        self.next_seq += 1;
+
+        let queued = self.queue.len();
+        self.status_tx.send_modify(|s| {
+            s.queued = queued;
+            s.overflow_dropped += overflow as u64;
+        });
+        overflow
+    }
+
+    fn encode(&self, frame: &QueuedFrame, station_id: &str) -> String {
+        let record = RelayRecord {
+            station: station_id,
+            session: &self.session,
+            seq: frame.seq,
+            received_at: frame.received_at,
+            frame: BASE64.encode(&frame.frame),
+        };
+        // serializing a struct of plain strings and numbers can't fail
+        let mut line = serde_json::to_string(&record).unwrap_or_default();
+        line.push('\n');
+        line
+    }
+}
+
+// ── Internal result type ──────────────────────────────────────────────────────
+
+enum RelayResult {
+    Shutdown,
+    ConfigChanged,
+    Error(String),
+}
@@ -0,0 +1,102 @@
+// Records the ground station's own CPU, memory, disk I/O and queue depths as telemetry
+//
+// Lands in the "ground_station" store like any other stream, so when the UI stutters
+// during boost it's in the CSV next to the flight data and can be plotted afterwards.
+// Each sample also feeds the middleware's load shedder (see load_shedding), and its
+// level and counts are recorded here with the rest. It's also when lock overruns
+// (see lock_timing) get turned into alerts.
+
+use std::sync::Arc;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tokio::sync::Mutex;
+use tokio::time::{interval, Duration};
+use tokio_util::sync::CancellationToken;
+
+use crate::backend::relay::RelayHandle;
+use crate::middleware::telemetry_stores::TelemetryData;
+use crate::middleware::Middleware;
+
+const STORE: &str = "ground_station";
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+// ── Constructor ───────────────────────────────────────────────────────────────
+
+// nothing to control, it just reports
+pub fn new(middleware: Arc<Mutex<Middleware>>, relay: RelayHandle) -> ResourceMonitor {
+    ResourceMonitor {
+        middleware,
+        relay,
+        system: System::new(),
+        pid: sysinfo::get_current_pid().ok(),
+    }
+}
+
+// ── Actor ─────────────────────────────────────────────────────────────────────
+
+pub struct ResourceMonitor {
+    middleware: Arc<Mutex<Middleware>>,
+    relay: RelayHandle,
+    system: System,
+    // None if the OS won't tell us who we are, we still report queues
+    pid: Option<Pid>,
+}
+
+impl ResourceMonitor {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
+        let mut sample = interval(SAMPLE_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = sample.tick() => self.sample().await,
+            }
+        }
+    }
+
+    async fn sample(&mut self) {
+        let mut values: Vec<(String, f64)> = Vec::new();
+        let mut cpu_percent = None;
+
+        if let Some(pid) = self.pid {
+            self.system.refresh_processes_specifics(
+                ProcessesToUpdate::Some(&[pid]),
+                true,
+                ProcessRefreshKind::nothing().with_cpu().with_memory().with_disk_usage(),
+            );
+            if let Some(process) = self.system.process(pid) {
+                let disk = process.disk_usage();
+                let secs = SAMPLE_INTERVAL.as_secs_f64();
+                values.push(("cpu_percent".into(), process.cpu_usage() as f64));
+                // cpu_usage is per core, 400% on a busy quad core
+                let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+                cpu_percent = Some(process.cpu_usage() as f64 / cores as f64);
+                values.push(("memory_mb".into(), process.memory() as f64 / 1_048_576.0));
+                // bytes since the last refresh, i.e. one sample interval
+                values.push(("disk_read_bytes_per_s".into(), disk.read_bytes as f64 / secs));
+                values.push(("disk_write_bytes_per_s".into(), disk.written_bytes as f64 / secs));
+            }
+        }
+
+        values.push(("queue.relay".into(), self.relay.get_status().queued as f64));
+
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        let mut mw = self.middleware.lock().await;
+        let mut deepest = 0;
+        for (queue, depth) in mw.get_queue_depths() {
+            deepest = deepest.max(depth);
+            values.push((format!("queue.{queue}"), depth as f64));
+        }
+        mw.observe_load(cpu_percent, deepest);
+        let shedding = mw.load_shedding_status();
+        values.push(("shed_level".into(), shedding.level as f64));
+        values.push(("shed.normal".into(), shedding.shed_normal as f64));
+        values.push(("shed.debug".into(), shedding.shed_debug as f64));
+        values.push(("lock_overruns".into(), mw.check_lock_budgets().len() as f64));
+        for (field, value) in values {
+            let _ = mw.push_data(
+                STORE,
+                &field,
+                TelemetryData::new().with_timestamp(timestamp).with_value(value),
+            );
+        }
+    }
+}
@@ -0,0 +1,312 @@
+// Optional bridge to the tracking team's ROS 2 stack, through a rosbridge websocket
+//
+// For every configured store we publish, whenever it has a new fix or attitude:
+//   <prefix>/<store>/fix       sensor_msgs/msg/NavSatFix        from lat, lon, alt
+//   <prefix>/<store>/attitude  geometry_msgs/msg/QuaternionStamped from w, i, j, k
+// and we listen on
+//   <prefix>/tracker_cmd       geometry_msgs/msg/Twist
+// for external tracker jogs: angular.z is azimuth rate, angular.y elevation rate
+// (rad/s, as ROS likes it). Going through rosbridge means no ROS install on the
+// ground station laptop.
+
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex};
+use tokio::time::{interval, sleep, timeout, Duration};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+
+use crate::backend::tracker_interface::{JogRates, TrackerHandle};
+use crate::middleware::Middleware;
+
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+const NAV_SAT_FIX: &str = "sensor_msgs/msg/NavSatFix";
+const QUATERNION_STAMPED: &str = "geometry_msgs/msg/QuaternionStamped";
+const TWIST: &str = "geometry_msgs/msg/Twist";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RosBridgeConfig {
+    pub enabled: bool,
+    // e.g. "ws://tracker-pi:9090"
+    pub url: Option<String>,
+    #[serde(default = "default_prefix")]
+    pub topic_prefix: String,
+    #[serde(default = "default_stores")]
+    pub stores: Vec<String>,
+    // external jogs are ignored unless this is set, the joystick stays in charge
+    #[serde(default)]
+    pub accept_tracker_commands: bool,
+}
+
+fn default_prefix() -> String {
+    "/groundstation".into()
+}
+
+fn default_stores() -> Vec<String> {
+    vec!["rocket".into()]
+}
+
+impl Default for RosBridgeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: None,
+            topic_prefix: default_prefix(),
+            stores: default_stores(),
+            accept_tracker_commands: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RosBridgeStatus {
+    pub connected: bool,
+    pub published: u64,
+    pub commands_received: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct RosBridgeHandle {
+    config_tx: Arc<watch::Sender<RosBridgeConfig>>,
+    status_tx: Arc<watch::Sender<RosBridgeStatus>>,
+}
+
+impl RosBridgeHandle {
+    pub fn set_config(&self, config: RosBridgeConfig) -> Result<(), String> {
+        if config.enabled && config.url.as_deref().unwrap_or("").is_empty() {
+            return Err("Set a rosbridge URL before enabling the ROS bridge".into());
+        }
+        if !config.topic_prefix.starts_with('/') {
+            return Err(format!("Topic prefix must start with '/', got '{}'", config.topic_prefix));
+        }
+        self.config_tx.send_replace(config);
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> RosBridgeConfig {
+        self.config_tx.borrow().clone()
+    }
+
+    pub fn get_status(&self) -> RosBridgeStatus {
+        self.status_tx.borrow().clone()
+    }
+}
+
+// ── Constructor ───────────────────────────────────────────────────────────────
+
+pub fn new(middleware: Arc<Mutex<Middleware>>, tracker: TrackerHandle) -> (RosBridge, RosBridgeHandle) {
+    let config_tx = Arc::new(watch::Sender::new(RosBridgeConfig::default()));
+    let status_tx = Arc::new(watch::Sender::new(RosBridgeStatus::default()));
+    let bridge = RosBridge {
+        middleware,
+        tracker,
+        config_rx: config_tx.subscribe(),
+        status_tx: status_tx.clone(),
+        last_sent: HashMap::new(),
+    };
+    (bridge, RosBridgeHandle { config_tx, status_tx })
+}
+
+// ── Actor ─────────────────────────────────────────────────────────────────────
+
+pub struct RosBridge {
+    middleware: Arc<Mutex<Middleware>>,
+    tracker: TrackerHandle,
+    config_rx: watch::Receiver<RosBridgeConfig>,
+    status_tx: Arc<watch::Sender<RosBridgeStatus>>,
+    // topic -> timestamp of the sample we last published, so we only send new ones
+    last_sent: HashMap<String, i64>,
+}
+
+enum BridgeResult {
+    Shutdown,
+    ConfigChanged,
+    Error(String),
+}
+
+impl RosBridge {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
+        loop {
+            let config = self.config_rx.borrow_and_update().clone();
+            let url = match (&config.url, config.enabled) {
+                (Some(url), true) => url.clone(),
+                _ => {
+                    tokio::select! {
+                        _ = shutdown.cancelled() => return,
+                        _ = self.config_rx.changed() => continue,
+                    }
+                }
+            };
+
+            match self.run_connected(&url, &config, &shutdown).await {
+                BridgeResult::Shutdown => return,
+                BridgeResult::ConfigChanged => {}
+                BridgeResult::Error(e) => {
+                    eprintln!("[ros] {url}: {e}. Retrying in {}s...", RETRY_DELAY.as_secs());
+                    self.status_tx.send_modify(|s| {
+                        s.connected = false;
+                        s.last_error = Some(e);
+                    });
+                    tokio::select! {
+                        _ = shutdown.cancelled() => return,
+                        _ = self.config_rx.changed() => {}
+                        _ = sleep(RETRY_DELAY) => {}
+                    }
+                }
+            }
+            // whatever was running off an external jog stops with the bridge
+            let _ = self.tracker.jog(JogRates::default());
+            self.status_tx.send_modify(|s| s.connected = false);
+        }
+    }
+
+    async fn run_connected(
+        &mut self,
+        url: &str,
+        config: &RosBridgeConfig,
+        shutdown: &CancellationToken,
+    ) -> BridgeResult {
+        let (socket, _) = match timeout(CONNECT_TIMEOUT, tokio_tungstenite::connect_async(url)).await {
+            Ok(Ok(s)) => s,
+            Ok(Err(e)) => return BridgeResult::Error(e.to_string()),
+            Err(_) => return BridgeResult::Error("connect timed out".into()),
+        };
+        let (mut tx, mut rx) = socket.split();
+        println!("[ros] connected to {url}");
+        self.status_tx.send_modify(|s| {
+            s.connected = true;
+            s.last_error = None;
+        });
+        self.last_sent.clear();
+
+        let prefix = config.topic_prefix.trim_end_matches('/');
+        let command_topic = format!("{prefix}/tracker_cmd");
+        let mut setup = vec![json!({"op": "subscribe", "topic": command_topic, "type": TWIST})];
+        for store in &config.stores {
+            setup.push(json!({"op": "advertise", "topic": format!("{prefix}/{store}/fix"), "type": NAV_SAT_FIX}));
+            setup.push(json!({"op": "advertise", "topic": format!("{prefix}/{store}/attitude"), "type": QUATERNION_STAMPED}));
+        }
+        for op in setup {
+            if let Err(e) = tx.send(Message::Text(op.to_string().into())).await {
+                return BridgeResult::Error(e.to_string());
+            }
+        }
+
+        let mut publish = interval(PUBLISH_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return BridgeResult::Shutdown,
+                _ = self.config_rx.changed() => return BridgeResult::ConfigChanged,
+                _ = publish.tick() => {
+                    for message in self.collect_messages(prefix, &config.stores).await {
+                        if let Err(e) = tx.send(Message::Text(message.to_string().into())).await {
+                            return BridgeResult::Error(e.to_string());
+                        }
+                        self.status_tx.send_modify(|s| s.published += 1);
+                    }
+                }
+                incoming = rx.next() => match incoming {
+                    Some(Ok(Message::Text(text))) => {
+                        self.handle_incoming(&text, &command_topic, config.accept_tracker_commands)
+                    }
+                    Some(Ok(Message::Close(_))) | None => return BridgeResult::Error("rosbridge closed the connection".into()),
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return BridgeResult::Error(e.to_string()),
+                },
+            }
+        }
+    }
+
+    async fn collect_messages(&mut self, prefix: &str, stores: &[String]) -> Vec<Value> {
+        let mw = self.middleware.lock().await;
+        let mut messages = Vec::new();
+        for store in stores {
+            let latest = |field: &str| mw.get_last(store, field).ok().flatten();
+
+            if let (Some(lat), Some(lon), Some(alt)) = (latest("lat"), latest("lon"), latest("alt")) {
+                let topic = format!("{prefix}/{store}/fix");
+                if self.last_sent.get(&topic) != Some(&lat.timestamp) {
+                    self.last_sent.insert(topic.clone(), lat.timestamp);
+                    messages.push(json!({
+                        "op": "publish",
+                        "topic": topic,
+                        "msg": {
+                            "header": header(lat.timestamp, store),
+                            // STATUS_FIX, SERVICE_GPS
+                            "status": {"status": 0, "service": 1},
+                            "latitude": lat.value.as_f64(),
+                            "longitude": lon.value.as_f64(),
+                            "altitude": alt.value.as_f64(),
+                            "position_covariance": [0.0; 9],
+                            // COVARIANCE_TYPE_UNKNOWN
+                            "position_covariance_type": 0,
+                        }
+                    }));
+                }
+            }
+
+            if let (Some(w), Some(i), Some(j), Some(k)) = (latest("w"), latest("i"), latest("j"), latest("k")) {
+                let topic = format!("{prefix}/{store}/attitude");
+                if self.last_sent.get(&topic) != Some(&w.timestamp) {
+                    self.last_sent.insert(topic.clone(), w.timestamp);
+                    messages.push(json!({
+                        "op": "publish",
+                        "topic": topic,
+                        "msg": {
+                            "header": header(w.timestamp, store),
+                            "quaternion": {
+                                "w": w.value.as_f64(),
+                                "x": i.value.as_f64(),
+                                "y": j.value.as_f64(),
+                                "z": k.value.as_f64(),
+                            }
+                        }
+                    }));
+                }
+            }
+        }
+        messages
+    }
+
+    fn handle_incoming(&mut self, text: &str, command_topic: &str, accept: bool) {
+        let Ok(message) = serde_json::from_str::<Value>(text) else {
+            return;
+        };
+        if message["op"] != "publish" || message["topic"] != command_topic {
+            return;
+        }
+        self.status_tx.send_modify(|s| s.commands_received += 1);
+        if !accept {
+            return;
+        }
+
+        let angular = &message["msg"]["angular"];
+        let rate = |v: &Value| (v.as_f64().unwrap_or(0.0).to_degrees()) as f32;
+        let rates = JogRates {
+            azimuth: rate(&angular["z"]),
+            elevation: rate(&angular["y"]),
+            ..JogRates::default()
+        };
+        if let Err(e) = self.tracker.jog(rates) {
+            eprintln!("[ros] Failed to forward tracker command: {e}");
+        }
+    }
+}
+
+// std_msgs/Header from a unix ms timestamp
+fn header(timestamp: i64, frame_id: &str) -> Value {
+    json!({
+        "stamp": {
+            "sec": timestamp.div_euclid(1000),
+            "nanosec": timestamp.rem_euclid(1000) * 1_000_000,
+        },
+        "frame_id": frame_id,
+    })
+}
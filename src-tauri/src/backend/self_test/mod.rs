@@ -0,0 +1,249 @@
+// Pre-flight "is the ground station actually working" check
+//
+// Pushes a known packet through the same framing, decoding, store and CSV code
+// the radio uses, then records a short dummy video, and reports each stage.
+// Everything goes into a throwaway "self_test" store/stream which is removed
+// (files included) afterwards.
+
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration};
+
+use crate::backend::telemetry_radio_interface::{frame_body, frame_payload, hprc, next_frame};
+use crate::middleware::telemetry_stores::TelemetryData;
+use crate::middleware::video_streams::VideoFrame;
+use crate::middleware::Middleware;
+
+const STORE_NAME: &str = "self_test";
+const STREAM_NAME: &str = "self_test";
+const TEST_THROTTLE: f32 = 0.25;
+const TEST_ROTATION: f32 = -0.75;
+const VIDEO_FPS: i32 = 10;
+const VIDEO_SECONDS: u64 = 1;
+// the CSV writer and ffmpeg both finish in the background
+const FILE_WAIT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageResult {
+    pub stage: String,
+    pub passed: bool,
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub stages: Vec<StageResult>,
+}
+
+#[derive(Default)]
+struct Report {
+    stages: Vec<StageResult>,
+}
+
+impl Report {
+    fn record(&mut self, stage: &str, started: Instant, result: Result<String, String>) -> bool {
+        let passed = result.is_ok();
+        let detail = result.unwrap_or_else(|e| e);
+        self.stages.push(StageResult {
+            stage: stage.to_string(),
+            passed,
+            detail,
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+        passed
+    }
+
+    fn finish(self) -> SelfTestReport {
+        SelfTestReport {
+            passed: self.stages.iter().all(|s| s.passed),
+            stages: self.stages,
+        }
+    }
+}
+
+pub async fn run_self_test(middleware: &Arc<Mutex<Middleware>>) -> SelfTestReport {
+    let mut report = Report::default();
+
+    // ── Encode + frame ────────────────────────────────────────────────────
+    let t = Instant::now();
+    let frame = frame_payload(&encode_test_packet());
+    report.record("encode", t, Ok(format!("{} byte frame", frame.len())));
+
+    // ── Deframe, with line noise in front like a real serial stream ──────
+    let t = Instant::now();
+    let mut accumulator = vec![0x00, 0xFF, b'K', b'V', 0x13];
+    accumulator.extend_from_slice(&frame);
+    let deframed = next_frame(&mut accumulator);
+    let framing_ok = report.record("framing", t, match &deframed {
+        Some(f) if *f == frame => Ok("frame recovered from noisy stream".into()),
+        Some(f) => Err(format!("recovered {} bytes, expected {}", f.len(), frame.len())),
+        None => Err("no frame found".into()),
+    });
+
+    // ── Decode ────────────────────────────────────────────────────────────
+    let t = Instant::now();
+    let decoded = match deframed.as_deref().filter(|_| framing_ok) {
+        Some(f) => decode_test_packet(frame_body(f)),
+        None => Err("skipped, framing failed".into()),
+    };
+    let decode_ok = report.record("decode", t, decoded.clone().map(|(throttle, rotation)| {
+        format!("throttle {throttle}, rotation {rotation}")
+    }));
+
+    // ── Store ─────────────────────────────────────────────────────────────
+    let t = Instant::now();
+    let store_ok = report.record("store", t, match decoded {
+        Ok(values) if decode_ok => store_round_trip(middleware, values).await,
+        _ => Err("skipped, decode failed".into()),
+    });
+
+    // ── CSV ───────────────────────────────────────────────────────────────
+    let t = Instant::now();
+    let csv_result = if store_ok {
+        csv_round_trip(middleware).await
+    } else {
+        Err("skipped, store failed".into())
+    };
+    report.record("csv", t, csv_result);
+
+    // ── Video ─────────────────────────────────────────────────────────────
+    let t = Instant::now();
+    let video_result = video_round_trip(middleware).await;
+    report.record("video", t, video_result);
+
+    cleanup(middleware).await;
+    report.finish()
+}
+
+fn encode_test_packet() -> Vec<u8> {
+    let mut builder = flatbuffers::FlatBufferBuilder::with_capacity(32);
+    let control_pack = hprc::PayloadControlPacket::create(&mut builder, &hprc::PayloadControlPacketArgs {
+        throttle: TEST_THROTTLE,
+        rotation: TEST_ROTATION,
+    });
+    let packet = hprc::Packet::create(&mut builder, &hprc::PacketArgs {
+        packet_type: hprc::PacketUnion::PayloadControlPacket,
+        packet: Some(control_pack.as_union_value()),
+    });
+    builder.finish(packet, None);
+    builder.finished_data().to_vec()
+}
+
+fn decode_test_packet(body: &[u8]) -> Result<(f32, f32), String> {
+    let packet = hprc::root_as_packet(body).map_err(|e| format!("flatbuffer error: {e}"))?;
+    let control = packet
+        .packet_as_payload_control_packet()
+        .ok_or_else(|| format!("wrong packet type {:?}", packet.packet_type()))?;
+    let values = (control.throttle(), control.rotation());
+    if values != (TEST_THROTTLE, TEST_ROTATION) {
+        return Err(format!("decoded {values:?}, expected {:?}", (TEST_THROTTLE, TEST_ROTATION)));
+    }
+    Ok(values)
+}
+
+async fn store_round_trip(middleware: &Arc<Mutex<Middleware>>, (throttle, rotation): (f32, f32)) -> Result<String, String> {
+    let mut mw = middleware.lock().await;
+    let timestamp = chrono::Utc::now().timestamp_millis();
+    mw.push_data(STORE_NAME, "throttle", TelemetryData::new().with_timestamp(timestamp).with_value(throttle as f64))?;
+    mw.push_data(STORE_NAME, "rotation", TelemetryData::new().with_timestamp(timestamp).with_value(rotation as f64))?;
+
+    let read = mw
+        .get_last(STORE_NAME, "rotation")?
+        .ok_or("value missing after push")?;
+    if read.value.as_f64() != rotation as f64 || read.timestamp != timestamp {
+        return Err(format!("read back {} @ {}", read.value, read.timestamp));
+    }
+    Ok("pushed and read back".into())
+}
+
+async fn csv_round_trip(middleware: &Arc<Mutex<Middleware>>) -> Result<String, String> {
+    let path = {
+        let mut mw = middleware.lock().await;
+        mw.start_recording(STORE_NAME)?;
+        // rows are written when the timestamp moves on, so push a few
+        let start = chrono::Utc::now().timestamp_millis() + 1;
+        for i in 0..3 {
+            mw.push_data(STORE_NAME, "throttle", TelemetryData::new().with_timestamp(start + i).with_value(i))?;
+        }
+        mw.stop_recording(STORE_NAME)?;
+        mw.get_store_path(STORE_NAME)?
+    };
+
+    let text = wait_for_file(&path, |text| text.lines().count() >= 2)
+        .await
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut lines = text.lines();
+    let header = lines.next().unwrap_or_default();
+    if !header.split(',').any(|h| h == "throttle") {
+        return Err(format!("header is missing throttle: '{header}'"));
+    }
+    Ok(format!("{} rows written to {}", lines.count(), path.display()))
+}
+
+async fn video_round_trip(middleware: &Arc<Mutex<Middleware>>) -> Result<String, String> {
+    let (width, height) = (64u32, 48u32);
+    let frame = |n: u64| {
+        Arc::new(VideoFrame {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            data: vec![(n * 20 % 256) as u8; (width * height * 3) as usize],
+            width,
+            height,
+        })
+    };
+
+    // the stream has to have a frame before it can start recording
+    middleware.lock().await.process_video_frame(STREAM_NAME, frame(0))?;
+    middleware.lock().await.start_recording_video(STREAM_NAME, VIDEO_FPS)?;
+
+    let frames = VIDEO_FPS as u64 * VIDEO_SECONDS;
+    for n in 1..=frames {
+        sleep(Duration::from_millis(1000 / VIDEO_FPS as u64)).await;
+        middleware.lock().await.process_video_frame(STREAM_NAME, frame(n))?;
+    }
+
+    let path = {
+        let mw = middleware.lock().await;
+        mw.stop_recording_video(STREAM_NAME)?;
+        mw.get_video_path(STREAM_NAME).ok_or("stream has no recording path")?
+    };
+
+    let deadline = Instant::now() + FILE_WAIT;
+    loop {
+        match std::fs::metadata(&path) {
+            Ok(m) if m.len() > 0 => return Ok(format!("{} frames, {} bytes", frames, m.len())),
+            _ if Instant::now() > deadline => return Err(format!("no video written to {}", path.display())),
+            _ => sleep(Duration::from_millis(100)).await,
+        }
+    }
+}
+
+async fn wait_for_file(path: &Path, done: impl Fn(&str) -> bool) -> Result<String, String> {
+    let deadline = Instant::now() + FILE_WAIT;
+    loop {
+        match std::fs::read_to_string(path) {
+            Ok(text) if done(&text) => return Ok(text),
+            Ok(_) if Instant::now() > deadline => return Err("file was not fully written".into()),
+            Err(e) if Instant::now() > deadline => return Err(e.to_string()),
+            _ => sleep(Duration::from_millis(100)).await,
+        }
+    }
+}
+
+// throw away everything the test made so it doesn't end up in the session
+async fn cleanup(middleware: &Arc<Mutex<Middleware>>) {
+    let mw = middleware.lock().await;
+    let csv = mw.get_store_path(STORE_NAME).ok();
+    let video = mw.get_video_path(STREAM_NAME);
+    let _ = mw.remove_store(STORE_NAME);
+    let _ = mw.remove_video_stream(STREAM_NAME);
+    drop(mw);
+
+    for path in [csv, video].into_iter().flatten() {
+        let _ = std::fs::remove_file(path);
+    }
+}
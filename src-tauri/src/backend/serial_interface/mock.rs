@@ -0,0 +1,158 @@
+// Stand-in serial port, so the serial backends run on a laptop or in CI with no hardware
+//
+// Opened through serial_interface::open like a real port, by name:
+//   mock:loopback      whatever is written comes back to be read
+//   mock:file=<path>   the bytes of a file, paced at the baud rate, then "unplugged"
+// or built directly with MockSerialPort::generator for anything else. Reads time out
+// the way a real port's do when there's nothing to read.
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+
+use super::SerialLink;
+
+pub const MOCK_PREFIX: &str = "mock:";
+// how much of what was written we keep around for a test to look at
+const WRITTEN_KEPT: usize = 64 * 1024;
+const FILE_CHUNK: usize = 64;
+
+// more bytes to hand out, None once the source has run dry
+type Source = Box<dyn FnMut() -> Option<Vec<u8>> + Send>;
+
+struct MockState {
+    rx: VecDeque<u8>,
+    source: Option<Source>,
+    loopback: bool,
+    written: VecDeque<u8>,
+    // source ran dry, reads return 0 like an unplugged port
+    closed: bool,
+}
+
+#[derive(Clone)]
+pub struct MockSerialPort {
+    state: Arc<(Mutex<MockState>, Condvar)>,
+    timeout: Duration,
+}
+
+impl MockSerialPort {
+    fn with(source: Option<Source>, loopback: bool, timeout: Duration) -> Self {
+        let state = MockState { rx: VecDeque::new(), source, loopback, written: VecDeque::new(), closed: false };
+        Self { state: Arc::new((Mutex::new(state), Condvar::new())), timeout }
+    }
+
+    pub fn loopback(timeout: Duration) -> Self {
+        Self::with(None, true, timeout)
+    }
+
+    /// Bytes from `next` until it returns None
+    pub fn generator(timeout: Duration, next: impl FnMut() -> Option<Vec<u8>> + Send + 'static) -> Self {
+        Self::with(Some(Box::new(next)), false, timeout)
+    }
+
+    /// The file's contents, taking as long as they would over a real link at `baud`
+    pub fn file(path: &Path, baud: u32, timeout: Duration) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let mut offset = 0;
+        // 10 bits a byte with start and stop bits
+        let per_chunk = Duration::from_secs_f64(FILE_CHUNK as f64 * 10.0 / baud.max(1) as f64);
+        let mut due = Instant::now();
+        Ok(Self::generator(timeout, move || {
+            if offset >= data.len() {
+                return None;
+            }
+            due += per_chunk;
+            if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                std::thread::sleep(wait);
+            }
+            let end = (offset + FILE_CHUNK).min(data.len());
+            let chunk = data[offset..end].to_vec();
+            offset = end;
+            Some(chunk)
+        }))
+    }
+
+    /// Parse a mock: port name, None if it isn't one
+    pub fn from_name(name: &str, baud: u32, timeout: Duration) -> Option<Result<Self, String>> {
+        let spec = name.strip_prefix(MOCK_PREFIX)?;
+        Some(match spec.split_once('=') {
+            None if spec == "loopback" => Ok(Self::loopback(timeout)),
+            Some(("file", path)) => Self::file(Path::new(path), baud, timeout),
+            _ => Err(format!("Unknown mock port '{name}', try mock:loopback or mock:file=<path>")),
+        })
+    }
+
+    /// Hand bytes to whoever is reading, as if they came off the wire
+    pub fn inject(&self, data: &[u8]) {
+        let (lock, ready) = &*self.state;
+        lock.lock().rx.extend(data);
+        ready.notify_all();
+    }
+
+    /// Everything written so far (the most recent WRITTEN_KEPT bytes of it), emptying the log
+    pub fn take_written(&self) -> Vec<u8> {
+        self.state.0.lock().written.drain(..).collect()
+    }
+}
+
+impl Read for MockSerialPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let (lock, ready) = &*self.state;
+        let mut state = lock.lock();
+        if state.rx.is_empty() && !state.closed {
+            // pull from the source without holding the lock, a file source sleeps
+            if let Some(mut source) = state.source.take() {
+                drop(state);
+                let next = source();
+                state = lock.lock();
+                state.source = Some(source);
+                match next {
+                    Some(data) => state.rx.extend(data),
+                    None => state.closed = true,
+                }
+            }
+            if state.rx.is_empty() && !state.closed {
+                ready.wait_while_for(&mut state, |s| s.rx.is_empty(), self.timeout);
+            }
+        }
+        if state.rx.is_empty() {
+            if state.closed {
+                return Ok(0);
+            }
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "mock port timed out"));
+        }
+        let n = buf.len().min(state.rx.len());
+        for (slot, byte) in buf.iter_mut().zip(state.rx.drain(..n)) {
+            *slot = byte;
+        }
+        Ok(n)
+    }
+}
+
+impl Write for MockSerialPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (lock, ready) = &*self.state;
+        let mut state = lock.lock();
+        if state.loopback {
+            state.rx.extend(buf);
+            ready.notify_all();
+        }
+        state.written.extend(buf);
+        let excess = state.written.len().saturating_sub(WRITTEN_KEPT);
+        state.written.drain(..excess);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SerialLink for MockSerialPort {
+    fn try_clone_link(&self) -> Result<Box<dyn SerialLink>, String> {
+        Ok(Box::new(self.clone()))
+    }
+}
@@ -0,0 +1,210 @@
+// Serial port discovery, shared by everything that talks to a USB serial device
+//
+// Two FTDI radios look identical by name, so we hand the frontend the USB details
+// (VID/PID, serial number, manufacturer) and let the operator decide which is which.
+//
+// Also home to the reconnect logic: a bumped USB cable makes the port vanish, and
+// every device should ride that out the same way, retrying with exponential backoff
+// and telling the UI where it stands through SERIAL_CONNECTION_EVENT.
+//
+// Backends open ports through open() and get a SerialLink back, which is either a real
+// port or a mock one (see mock.rs) when the port name starts with "mock:". Mock ports
+// are part of the sim feature.
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+use serialport::SerialPortType;
+use tokio::time::Duration;
+
+use crate::middleware::Middleware;
+
+#[cfg(feature = "sim")]
+pub mod mock;
+#[cfg(feature = "sim")]
+use mock::{MockSerialPort, MOCK_PREFIX};
+
+pub const SERIAL_CONNECTION_EVENT: &str = "serial_connection";
+
+const BACKOFF_INITIAL: Duration = Duration::from_millis(250);
+const BACKOFF_MAX: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SerialDevice {
+    TelemetryRadio,
+    // second radio on another band, see telemetry_radio_interface::dedup
+    TelemetryRadioBackup,
+    ControlSurface,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SerialPortInfo {
+    pub name: String,
+    // "usb", "bluetooth", "pci", "mock" or "unknown"
+    pub port_type: String,
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    // which of our devices the operator has put on this port
+    pub assigned_to: Option<SerialDevice>,
+}
+
+// ── Ports ─────────────────────────────────────────────────────────────────────
+
+// what a backend needs from a port: bytes both ways, from two threads
+pub trait SerialLink: Read + Write + Send {
+    fn try_clone_link(&self) -> Result<Box<dyn SerialLink>, String>;
+}
+
+impl SerialLink for Box<dyn serialport::SerialPort> {
+    fn try_clone_link(&self) -> Result<Box<dyn SerialLink>, String> {
+        self.try_clone().map(|p| Box::new(p) as Box<dyn SerialLink>).map_err(|e| e.to_string())
+    }
+}
+
+// reads give up with ErrorKind::TimedOut after `timeout`, same for real and mock ports
+pub fn open(port_name: &str, baud_rate: u32, timeout: Duration) -> Result<Box<dyn SerialLink>, String> {
+    #[cfg(feature = "sim")]
+    if let Some(mock) = MockSerialPort::from_name(port_name, baud_rate, timeout) {
+        return mock.map(|m| Box::new(m) as Box<dyn SerialLink>);
+    }
+    serialport::new(port_name, baud_rate)
+        .timeout(timeout)
+        .open()
+        .map(|p| Box::new(p) as Box<dyn SerialLink>)
+        .map_err(|e| e.to_string())
+}
+
+pub fn list_ports() -> Result<Vec<SerialPortInfo>, String> {
+    let ports = serialport::available_ports().map_err(|e| e.to_string())?;
+    #[cfg_attr(not(feature = "sim"), allow(unused_mut))]
+    let mut list: Vec<_> = ports
+        .into_iter()
+        .map(|p| {
+            let mut info = SerialPortInfo {
+                name: p.port_name,
+                port_type: "unknown".into(),
+                vid: None,
+                pid: None,
+                serial_number: None,
+                manufacturer: None,
+                product: None,
+                assigned_to: None,
+            };
+            match p.port_type {
+                SerialPortType::UsbPort(usb) => {
+                    info.port_type = "usb".into();
+                    info.vid = Some(usb.vid);
+                    info.pid = Some(usb.pid);
+                    info.serial_number = usb.serial_number;
+                    info.manufacturer = usb.manufacturer;
+                    info.product = usb.product;
+                }
+                SerialPortType::BluetoothPort => info.port_type = "bluetooth".into(),
+                SerialPortType::PciPort => info.port_type = "pci".into(),
+                SerialPortType::Unknown => {}
+            }
+            info
+        })
+        .collect();
+    // somewhere to point a backend on a dev build without hardware
+    #[cfg(all(debug_assertions, feature = "sim"))]
+    {
+        list.push(SerialPortInfo {
+            name: format!("{MOCK_PREFIX}loopback"),
+            port_type: "mock".into(),
+            vid: None,
+            pid: None,
+            serial_number: None,
+            manufacturer: None,
+            product: None,
+            assigned_to: None,
+        });
+    }
+    Ok(list)
+}
+
+// ── Reconnect ─────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Connected,
+    // waiting to try the port again
+    Reconnecting,
+    // an open port just went away, sent once before the first retry
+    Lost,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionEvent {
+    pub device: SerialDevice,
+    pub port: String,
+    pub state: ConnectionState,
+    // retries since the port was last open
+    pub attempt: u32,
+    pub retry_in_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+// one per device actor, call connected() once the port is open and failed() on any
+// error, then wait out the returned delay (or a new port selection) before reopening
+pub struct Reconnect {
+    device: SerialDevice,
+    attempt: u32,
+    connected: bool,
+}
+
+impl Reconnect {
+    pub fn new(device: SerialDevice) -> Self {
+        Self { device, attempt: 0, connected: false }
+    }
+
+    pub fn connected(&mut self, middleware: &Middleware, port: &str) {
+        self.attempt = 0;
+        self.connected = true;
+        self.emit(middleware, port, ConnectionState::Connected, None, None);
+    }
+
+    pub fn failed(&mut self, middleware: &Middleware, port: &str, error: &str) -> Duration {
+        if std::mem::take(&mut self.connected) {
+            self.emit(middleware, port, ConnectionState::Lost, None, Some(error));
+        }
+        // 250ms, 500ms, 1s ... capped at 10s
+        let delay = BACKOFF_INITIAL
+            .saturating_mul(1 << self.attempt.min(16))
+            .min(BACKOFF_MAX);
+        self.attempt += 1;
+        self.emit(middleware, port, ConnectionState::Reconnecting, Some(delay), Some(error));
+        delay
+    }
+
+    // a different port was picked, start the backoff over
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+        self.connected = false;
+    }
+
+    fn emit(
+        &self,
+        middleware: &Middleware,
+        port: &str,
+        state: ConnectionState,
+        retry_in: Option<Duration>,
+        error: Option<&str>,
+    ) {
+        middleware.emit(
+            SERIAL_CONNECTION_EVENT,
+            ConnectionEvent {
+                device: self.device,
+                port: port.to_string(),
+                state,
+                attempt: self.attempt,
+                retry_in_ms: retry_in.map(|d| d.as_millis() as u64),
+                error: error.map(str::to_string),
+            },
+        );
+    }
+}
@@ -0,0 +1,143 @@
+// Wiring the backend actors up at launch
+//
+// setup_backend builds each actor in dependency order, taking what it needs from the
+// Bootstrap (the middleware, the shutdown token, where its config lives) and the
+// handles of the actors built before it. spawn() then registers it with the
+// ServiceRegistry, so it shows up in list_services and can be stopped, started and
+// supervised, and drives its run() until the app shuts down. A new backend is:
+//   let (actor, handle) = my_backend::new(boot.middleware());
+//   boot.spawn_managed("my_backend", actor, handle);
+// plus a line in the actors! list below.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use super::{install_panic_hook, ServiceRegistry};
+use crate::middleware::Middleware;
+
+/// Anything spawn() can drive: a run loop that returns once its token is cancelled
+pub trait Actor: Send + 'static {
+    fn run(&mut self, run: CancellationToken) -> impl Future<Output = ()> + Send;
+}
+
+// every actor already has the right run(), this just names it for the trait
+macro_rules! actors {
+    ($($(#[$cfg:meta])* $actor:ty),* $(,)?) => {
+        $(
+            $(#[$cfg])*
+            impl Actor for $actor {
+                fn run(&mut self, run: CancellationToken) -> impl Future<Output = ()> + Send {
+                    <$actor>::run(self, run)
+                }
+            }
+        )*
+    };
+}
+
+actors!(
+    crate::backend::relay::Relay,
+    crate::backend::telemetry_radio_interface::TelemetryRadio,
+    #[cfg(feature = "sim")]
+    crate::backend::data_playback::DataPlayback,
+    #[cfg(feature = "video")]
+    crate::backend::video_capture_interface::CameraInput,
+    #[cfg(feature = "video")]
+    crate::backend::video_capture_interface::test_pattern::TestPatternSource,
+    #[cfg(feature = "df")]
+    crate::backend::tracker_interface::TrackerInterface,
+    #[cfg(all(feature = "uplink", feature = "df"))]
+    crate::backend::joystick_input::JoystickInput,
+    #[cfg(feature = "df")]
+    crate::backend::ros_bridge::RosBridge,
+    crate::backend::control_surface::ControlSurface,
+    crate::backend::power_monitor::PowerMonitor,
+    crate::backend::influx_sink::InfluxSink,
+    crate::backend::rebroadcast::Rebroadcast,
+    crate::backend::time_sync::TimeSync,
+    crate::backend::countdown::Countdown,
+    crate::backend::resource_monitor::ResourceMonitor,
+    crate::backend::store_snapshot::StoreSnapshotter,
+);
+
+pub struct Bootstrap {
+    app: AppHandle,
+    middleware: Arc<Mutex<Middleware>>,
+    session_dir: PathBuf,
+    shutdown: CancellationToken,
+    services: ServiceRegistry,
+    // in the order they were spawned, for the startup log
+    spawned: Vec<String>,
+}
+
+impl Bootstrap {
+    /// Hands the middleware to tauri and sets up the registry every actor joins.
+    /// Actors stop when `shutdown` is cancelled.
+    pub fn new(app: &AppHandle, middleware: Arc<Mutex<Middleware>>, session_dir: PathBuf, shutdown: &CancellationToken) -> Self {
+        // a panicking backend gets stopped and alerted on, the rest keep running
+        install_panic_hook();
+        // which backends to start is a station setting, so it lives above the session folders
+        let services_path = session_dir.parent().unwrap_or(&session_dir).join("services.toml");
+        let services = ServiceRegistry::new(services_path, middleware.clone());
+        app.manage(middleware.clone());
+        Self {
+            app: app.clone(),
+            middleware,
+            session_dir,
+            shutdown: shutdown.child_token(),
+            services,
+            spawned: Vec::new(),
+        }
+    }
+
+    pub fn middleware(&self) -> Arc<Mutex<Middleware>> {
+        self.middleware.clone()
+    }
+
+    // for the few backends that manage their own tasks rather than being an Actor
+    pub fn shutdown(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// A file in this session's folder, e.g. a log that belongs to this run
+    pub fn session_path(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.session_dir.join(name)
+    }
+
+    /// A file shared by every session, e.g. a backend's saved config
+    pub fn station_path(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.session_dir.parent().unwrap_or(&self.session_dir).join(name)
+    }
+
+    /// Gives tauri a handle so the commands can reach it
+    pub fn manage<T: Send + Sync + 'static>(&self, state: T) {
+        self.app.manage(state);
+    }
+
+    /// Registers `actor` as the service `name` and keeps it running until shutdown,
+    /// re-entering run() each time the service is started again
+    pub fn spawn<A: Actor>(&mut self, name: &str, mut actor: A) {
+        let mut service = self.services.register(name);
+        let shutdown = self.shutdown.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(run) = service.next_run(&shutdown).await {
+                service.supervise(actor.run(run)).await;
+            }
+        });
+        self.spawned.push(name.to_string());
+    }
+
+    pub fn spawn_managed<A: Actor, H: Send + Sync + 'static>(&mut self, name: &str, actor: A, handle: H) {
+        self.spawn(name, actor);
+        self.manage(handle);
+    }
+
+    /// Hands the registry to tauri for the service commands, once everything is spawned
+    pub fn finish(self) {
+        println!("[services] wired {} backends: {}", self.spawned.len(), self.spawned.join(", "));
+        self.app.manage(self.services);
+    }
+}
@@ -0,0 +1,259 @@
+// Start/stop control over the backend actors
+//
+// Each actor's run loop is wrapped so it only runs while its service is enabled.
+// Stopping a service cancels its run token, which makes the actor drop its
+// serial port / camera / gamepad the same way it would on app shutdown. The
+// actor (and the handle tauri has) stays alive, so starting it again just
+// re-enters run().
+//
+// Which services start at launch is kept in services.toml next to the session
+// folders, e.g.
+//   [enabled]
+//   live_video = false
+//   control_surface = false
+//
+// A panic in an actor's run() is caught by supervise(): the service is marked stopped,
+// a critical alert goes up and everything else carries on. Starting the service again
+// re-enters run() on the same actor, which is as good as a restart for most of them.
+//
+// The actors are registered and driven this way by the Bootstrap (see bootstrap) as
+// setup_backend builds them.
+
+use dashmap::DashMap;
+use futures_util::FutureExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex as AsyncMutex};
+use tokio_util::sync::CancellationToken;
+
+use crate::middleware::alerts::AlertSeverity;
+use crate::middleware::lock_timing::TimedMutex;
+use crate::middleware::{config_file, Middleware};
+
+pub mod bootstrap;
+
+thread_local! {
+    // where the last panic on this thread happened, filled in by the panic hook
+    static LAST_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Logs every panic with its location, wherever it happens (actor, std thread or
+/// spawned task), and keeps it around for catch_panic to report
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let message = match info.location() {
+            Some(location) => format!("{} at {}:{}", panic_message(info.payload()), location.file(), location.line()),
+            None => panic_message(info.payload()),
+        };
+        let thread = std::thread::current();
+        eprintln!("[services] thread '{}' panicked: {message}", thread.name().unwrap_or("unnamed"));
+        LAST_PANIC.with(|last| *last.borrow_mut() = Some(message));
+        // the default hook prints the backtrace when RUST_BACKTRACE is set
+        if std::env::var_os("RUST_BACKTRACE").is_some() {
+            default_hook(info);
+        }
+    }));
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".into())
+}
+
+/// Runs a future, turning a panic inside it into an error with the panic's message
+pub async fn catch_panic<F: Future>(fut: F) -> Result<F::Output, String> {
+    AssertUnwindSafe(fut).catch_unwind().await.map_err(|payload| {
+        LAST_PANIC
+            .with(|last| last.borrow_mut().take())
+            .unwrap_or_else(|| panic_message(&*payload))
+    })
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServicesConfig {
+    // anything not listed here is enabled
+    #[serde(default)]
+    pub enabled: BTreeMap<String, bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceInfo {
+    pub name: String,
+    pub enabled: bool,
+    pub running: bool,
+    // how many times run() has panicked this session, and the latest panic
+    pub panics: u32,
+    pub last_panic: Option<String>,
+}
+
+// what a service's supervisor knows about its panics
+#[derive(Default)]
+struct PanicRecord {
+    count: AtomicU32,
+    last: Mutex<Option<String>>,
+}
+
+struct ServiceEntry {
+    enabled: Arc<watch::Sender<bool>>,
+    running: Arc<AtomicBool>,
+    panics: Arc<PanicRecord>,
+}
+
+pub struct ServiceRegistry {
+    services: DashMap<String, ServiceEntry>,
+    config: TimedMutex<ServicesConfig>,
+    config_path: PathBuf,
+    middleware: Arc<AsyncMutex<Middleware>>,
+}
+
+impl ServiceRegistry {
+    pub fn new(config_path: PathBuf, middleware: Arc<AsyncMutex<Middleware>>) -> Self {
+        let config = if config_path.exists() {
+            config_file::read_config_file(&config_path).unwrap_or_else(|e| {
+                eprintln!("[services] {e}, starting everything");
+                ServicesConfig::default()
+            })
+        } else {
+            ServicesConfig::default()
+        };
+
+        Self {
+            services: DashMap::new(),
+            config: TimedMutex::new("services", config),
+            config_path,
+            middleware,
+        }
+    }
+
+    pub fn register(&self, name: &str) -> ServiceControl {
+        let enabled = *self.config.lock().enabled.get(name).unwrap_or(&true);
+        let enabled = Arc::new(watch::Sender::new(enabled));
+        let running = Arc::new(AtomicBool::new(false));
+        let panics = Arc::new(PanicRecord::default());
+        self.services.insert(
+            name.to_string(),
+            ServiceEntry { enabled: enabled.clone(), running: running.clone(), panics: panics.clone() },
+        );
+        ServiceControl {
+            name: name.to_string(),
+            enabled,
+            running,
+            panics,
+            middleware: self.middleware.clone(),
+            last_run: None,
+        }
+    }
+
+    pub fn start(&self, name: &str) -> Result<(), String> {
+        self.set_enabled(name, true)
+    }
+
+    pub fn stop(&self, name: &str) -> Result<(), String> {
+        self.set_enabled(name, false)
+    }
+
+    pub fn list(&self) -> Vec<ServiceInfo> {
+        let mut out: Vec<ServiceInfo> = self
+            .services
+            .iter()
+            .map(|e| ServiceInfo {
+                name: e.key().clone(),
+                enabled: *e.enabled.borrow(),
+                running: e.running.load(Ordering::Acquire),
+                panics: e.panics.count.load(Ordering::Relaxed),
+                last_panic: e.panics.last.lock().clone(),
+            })
+            .collect();
+        out.sort_by(|a, b| a.name.cmp(&b.name));
+        out
+    }
+
+    fn set_enabled(&self, name: &str, enabled: bool) -> Result<(), String> {
+        let entry = self
+            .services
+            .get(name)
+            .ok_or_else(|| format!("Unknown service '{name}'"))?;
+        entry.enabled.send_replace(enabled);
+
+        // remember it for the next launch
+        let mut config = self.config.lock();
+        config.enabled.insert(name.to_string(), enabled);
+        config_file::write_config_file(&self.config_path, &*config)
+    }
+}
+
+// handed to the task that drives one actor
+pub struct ServiceControl {
+    name: String,
+    enabled: Arc<watch::Sender<bool>>,
+    running: Arc<AtomicBool>,
+    panics: Arc<PanicRecord>,
+    middleware: Arc<AsyncMutex<Middleware>>,
+    last_run: Option<CancellationToken>,
+}
+
+impl ServiceControl {
+    /// Drives one run() of the actor. A panic leaves the service stopped until the
+    /// operator starts it again, and raises a critical alert saying where it happened.
+    pub async fn supervise(&mut self, run: impl Future<Output = ()>) {
+        let Err(message) = catch_panic(run).await else { return };
+        eprintln!("[services] {} panicked, stopping it: {message}", self.name);
+        self.panics.count.fetch_add(1, Ordering::Relaxed);
+        *self.panics.last.lock() = Some(message.clone());
+        // stopped on purpose, so next_run doesn't take it for run() returning by itself
+        if let Some(run) = &self.last_run {
+            run.cancel();
+        }
+        self.enabled.send_replace(false);
+        self.middleware.lock().await.raise_alert(
+            &format!("service_panic.{}", self.name),
+            AlertSeverity::Critical,
+            format!("The {} service crashed and was stopped: {message}", self.name),
+        );
+    }
+
+    /// Waits until the service is enabled and returns a token that is cancelled
+    /// when it gets stopped or the app shuts down. None means the app is shutting down.
+    pub async fn next_run(&mut self, shutdown: &CancellationToken) -> Option<CancellationToken> {
+        self.running.store(false, Ordering::Release);
+
+        // run() came back without being asked to, don't spin restarting it
+        if let Some(last) = self.last_run.take() {
+            if !last.is_cancelled() && !shutdown.is_cancelled() {
+                eprintln!("[services] {} exited on its own, marking it stopped", self.name);
+                self.enabled.send_replace(false);
+            }
+            last.cancel();
+        }
+
+        let mut enabled_rx = self.enabled.subscribe();
+        tokio::select! {
+            _ = shutdown.cancelled() => return None,
+            _ = enabled_rx.wait_for(|e| *e) => {}
+        }
+
+        let run = shutdown.child_token();
+        let watcher = run.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = watcher.cancelled() => {}
+                _ = enabled_rx.wait_for(|e| !*e) => watcher.cancel(),
+            }
+        });
+
+        self.running.store(true, Ordering::Release);
+        self.last_run = Some(run.clone());
+        Some(run)
+    }
+}
@@ -0,0 +1,47 @@
+// Writes the store snapshot (see middleware::store_snapshot) on a timer
+//
+// Serializing every buffered point takes a while with a full flight in memory, so it
+// runs on a blocking thread, and a tick that comes due mid-write waits for it. The
+// middleware lock is never taken, ingest carries on while it writes.
+
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::middleware::store_snapshot::SnapshotWriter;
+
+// worst case a crash loses this much of what was buffered
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(5);
+
+// ── Constructor ───────────────────────────────────────────────────────────────
+
+pub fn new(writer: SnapshotWriter) -> StoreSnapshotter {
+    StoreSnapshotter { writer }
+}
+
+// ── Actor ─────────────────────────────────────────────────────────────────────
+
+pub struct StoreSnapshotter {
+    writer: SnapshotWriter,
+}
+
+impl StoreSnapshotter {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
+        let mut tick = interval(SNAPSHOT_INTERVAL);
+        tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = tick.tick() => self.snapshot().await,
+            }
+        }
+    }
+
+    async fn snapshot(&self) {
+        let writer = self.writer.clone();
+        match tokio::task::spawn_blocking(move || writer.write()).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => eprintln!("[store_snapshot] Failed to write {}: {e}", self.writer.path().display()),
+            Err(e) => eprintln!("[store_snapshot] Snapshot task failed: {e}"),
+        }
+    }
+}
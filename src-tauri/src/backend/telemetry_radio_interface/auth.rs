@@ -0,0 +1,153 @@
+// Optional packet authentication, for flight computers that sign what they send
+//
+// A signed frame carries its tag at the end of the body, inside the length byte:
+//   KV0R | len | flatbuffer | tag
+// and the tag covers everything before it, header included. Two schemes:
+//   HmacSha256  shared secret, tag is the first 16 bytes of HMAC-SHA256
+//   Ed25519     the vehicle's public key, tag is the 64 byte signature
+// Ed25519 leaves 191 bytes for the packet itself, so HMAC is the one to pick for
+// the bigger telemetry packets.
+use ed25519_dalek::{Signature, VerifyingKey};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::HEADER_LEN;
+
+const HMAC_TAG_LEN: usize = 16;
+const ED25519_TAG_LEN: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum AuthScheme {
+    #[default]
+    None,
+    HmacSha256,
+    Ed25519,
+}
+
+// what to do with a frame that fails verification
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum AuthPolicy {
+    // decode it anyway and raise an alert, for bring-up with a half-configured vehicle
+    #[default]
+    Flag,
+    Reject,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthConfig {
+    pub scheme: AuthScheme,
+    // hex, the shared secret for HMAC or the 32 byte public key for Ed25519
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub policy: AuthPolicy,
+}
+
+// what the frontend is shown, the key never goes back out
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthConfigSummary {
+    pub scheme: AuthScheme,
+    pub key_set: bool,
+    pub policy: AuthPolicy,
+}
+
+impl AuthConfig {
+    pub fn summary(&self) -> AuthConfigSummary {
+        AuthConfigSummary {
+            scheme: self.scheme,
+            key_set: self.key.as_deref().is_some_and(|k| !k.is_empty()),
+            policy: self.policy,
+        }
+    }
+}
+
+pub enum AuthOutcome {
+    // authentication is off
+    Unchecked,
+    Verified,
+    Failed(String),
+}
+
+enum Key {
+    None,
+    Hmac(Vec<u8>),
+    Ed25519(VerifyingKey),
+}
+
+pub struct PacketVerifier {
+    key: Key,
+    policy: AuthPolicy,
+}
+
+impl PacketVerifier {
+    pub fn new(config: &AuthConfig) -> Result<Self, String> {
+        let bytes = || -> Result<Vec<u8>, String> {
+            let hex_key = config.key.as_deref().unwrap_or("").trim();
+            if hex_key.is_empty() {
+                return Err(format!("{:?} needs a key", config.scheme));
+            }
+            hex::decode(hex_key).map_err(|e| format!("Key is not valid hex: {e}"))
+        };
+        let key = match config.scheme {
+            AuthScheme::None => Key::None,
+            AuthScheme::HmacSha256 => Key::Hmac(bytes()?),
+            AuthScheme::Ed25519 => {
+                let bytes: [u8; 32] = bytes()?
+                    .try_into()
+                    .map_err(|_| "Ed25519 public keys are 32 bytes".to_string())?;
+                Key::Ed25519(VerifyingKey::from_bytes(&bytes).map_err(|e| e.to_string())?)
+            }
+        };
+        Ok(Self { key, policy: config.policy })
+    }
+
+    pub fn disabled() -> Self {
+        Self { key: Key::None, policy: AuthPolicy::Flag }
+    }
+
+    pub fn policy(&self) -> AuthPolicy {
+        self.policy
+    }
+
+    fn tag_len(&self) -> usize {
+        match self.key {
+            Key::None => 0,
+            Key::Hmac(_) => HMAC_TAG_LEN,
+            Key::Ed25519(_) => ED25519_TAG_LEN,
+        }
+    }
+
+    // returns the packet bytes to decode (tag stripped) and whether the tag checked out
+    pub fn check<'a>(&self, frame: &'a [u8]) -> (&'a [u8], AuthOutcome) {
+        let body_start = HEADER_LEN.min(frame.len());
+        let tag_len = self.tag_len();
+        if tag_len == 0 {
+            return (&frame[body_start..], AuthOutcome::Unchecked);
+        }
+        if frame.len() < body_start + tag_len {
+            return (&frame[body_start..], AuthOutcome::Failed("frame too short for a tag".into()));
+        }
+
+        let (signed, tag) = frame.split_at(frame.len() - tag_len);
+        let outcome = match &self.key {
+            Key::None => AuthOutcome::Unchecked,
+            Key::Hmac(secret) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+                mac.update(signed);
+                match mac.verify_truncated_left(tag) {
+                    Ok(()) => AuthOutcome::Verified,
+                    Err(_) => AuthOutcome::Failed("bad HMAC".into()),
+                }
+            }
+            Key::Ed25519(key) => match Signature::from_slice(tag) {
+                Ok(signature) => match key.verify_strict(signed, &signature) {
+                    Ok(()) => AuthOutcome::Verified,
+                    Err(_) => AuthOutcome::Failed("bad signature".into()),
+                },
+                Err(e) => AuthOutcome::Failed(e.to_string()),
+            },
+        };
+        (&signed[body_start..], outcome)
+    }
+}
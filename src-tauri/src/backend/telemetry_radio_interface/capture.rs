@@ -0,0 +1,101 @@
+// Raw serial capture files, so field problems can be replayed at the desk
+//
+// A capture is a flat list of records, one per read from the port:
+//   [u64 LE microseconds since unix epoch][u32 LE length][length bytes]
+// possibly inside an encrypted session file, see middleware::encryption
+//
+// A .frames file is the other thing we replay: whole frames, already off the wire
+// framing, as varint length-delimited messages. That's what the bench bridge and
+// other tools dump. There are no timestamps, so the frames go out at the downlink rate.
+use std::io::{BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use groundstation_core::framing::length_delimited;
+use crate::middleware::lock_timing::TimedMutex;
+use crate::middleware::encryption::{self, SessionEncryption, SessionReader, SessionWriter};
+
+pub const FRAMES_EXTENSION: &str = "frames";
+// our flight computer's downlink rate
+const FRAMES_INTERVAL_US: u64 = 125_000;
+
+// shared between the handle (start/stop) and the reader thread (writes)
+pub type CaptureTap = Arc<TimedMutex<Option<CaptureWriter>>>;
+
+pub fn new_tap() -> CaptureTap {
+    Arc::new(TimedMutex::new("radio.capture", None))
+}
+
+pub struct CaptureWriter {
+    path: PathBuf,
+    file: BufWriter<SessionWriter>,
+    bytes: u64,
+}
+
+impl CaptureWriter {
+    pub fn create(path: &Path, encryption: &SessionEncryption) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let file = encryption.create(path)?;
+        Ok(Self { path: path.to_path_buf(), file: BufWriter::new(file), bytes: 0 })
+    }
+
+    pub fn record(&mut self, data: &[u8]) -> Result<(), String> {
+        let timestamp = chrono::Utc::now().timestamp_micros() as u64;
+        self.file.write_all(&timestamp.to_le_bytes()).map_err(|e| e.to_string())?;
+        self.file.write_all(&(data.len() as u32).to_le_bytes()).map_err(|e| e.to_string())?;
+        self.file.write_all(data).map_err(|e| e.to_string())?;
+        // flush every read so a crash mid-flight still leaves a usable capture
+        self.file.flush().map_err(|e| e.to_string())?;
+        self.bytes += data.len() as u64;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+pub struct CaptureReader {
+    file: SessionReader,
+    // Some(frames read so far) for a .frames file
+    frames: Option<u64>,
+}
+
+impl CaptureReader {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let frames = path.extension().is_some_and(|ext| ext == FRAMES_EXTENSION).then_some(0);
+        Ok(Self { file: encryption::open(path)?, frames })
+    }
+
+    // records are whole frames rather than bytes off a port, so skip the deframer
+    pub fn framed(&self) -> bool {
+        self.frames.is_some()
+    }
+
+    /// Next (timestamp in microseconds, bytes) record, None at a clean end of file
+    pub fn next_record(&mut self) -> Result<Option<(u64, Vec<u8>)>, String> {
+        if let Some(count) = self.frames.as_mut() {
+            let frame = length_delimited::read_delimited(&mut self.file).map_err(|e| format!("bad frame: {e}"))?;
+            let timestamp = *count * FRAMES_INTERVAL_US;
+            *count += 1;
+            return Ok(frame.map(|frame| (timestamp, frame)));
+        }
+        let mut timestamp = [0u8; 8];
+        match self.file.read_exact(&mut timestamp) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        }
+        let mut len = [0u8; 4];
+        self.file.read_exact(&mut len).map_err(|e| format!("truncated record: {e}"))?;
+        let mut data = vec![0u8; u32::from_le_bytes(len) as usize];
+        self.file.read_exact(&mut data).map_err(|e| format!("truncated record: {e}"))?;
+        Ok(Some((u64::from_le_bytes(timestamp), data)))
+    }
+}
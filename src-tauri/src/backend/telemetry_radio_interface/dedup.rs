@@ -0,0 +1,140 @@
+// Two radios, one flight: the primary 900 MHz link and a backup hear the same packets,
+// and only the first copy of each should reach the telemetry stores
+//
+// A packet carries the flight computer's loop count and time from boot, so two copies of
+// the same packet are byte for byte the same and two different packets never are. We
+// remember a hash of every packet body for DEDUP_WINDOW, longer than the two links will
+// ever be apart, and count per link who got there first.
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::backend::serial_interface::SerialDevice;
+use crate::middleware::lock_timing::TimedMutex;
+
+const DEDUP_WINDOW: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RadioLink {
+    Primary,
+    Backup,
+}
+
+impl RadioLink {
+    pub fn device(self) -> SerialDevice {
+        match self {
+            RadioLink::Primary => SerialDevice::TelemetryRadio,
+            RadioLink::Backup => SerialDevice::TelemetryRadioBackup,
+        }
+    }
+
+    // what frames and errors from this radio go under
+    pub fn source(self) -> &'static str {
+        match self {
+            RadioLink::Primary => "telemetry_radio",
+            RadioLink::Backup => "telemetry_radio_backup",
+        }
+    }
+
+    // each radio's link health goes in its own store
+    pub fn store(self) -> &'static str {
+        match self {
+            RadioLink::Primary => "link",
+            RadioLink::Backup => "link_backup",
+        }
+    }
+
+    // alert keys stay as they were for the primary
+    pub fn alert(self, key: &str) -> String {
+        match self {
+            RadioLink::Primary => key.to_string(),
+            RadioLink::Backup => format!("{key}.backup"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkStats {
+    pub link: RadioLink,
+    // decoded packets off this radio
+    pub packets: u64,
+    // ones this radio delivered before the other did (or that only it heard)
+    pub first: u64,
+    pub duplicates: u64,
+    // share of all delivered packets this radio was first with, 0-100
+    pub carried_pct: f64,
+    pub last_packet_at: Option<i64>,
+}
+
+pub type SharedDedup = Arc<TimedMutex<PacketDedup>>;
+
+#[derive(Default)]
+pub struct PacketDedup {
+    seen: HashMap<u64, Instant>,
+    stats: HashMap<RadioLink, LinkStats>,
+    last_prune: Option<Instant>,
+}
+
+impl PacketDedup {
+    pub fn shared() -> SharedDedup {
+        Arc::new(TimedMutex::new("radio.dedup", Self::default()))
+    }
+
+    /// True if this is the first copy of `body` (the packet after any CRC and auth tag
+    /// came off), so it should be handled
+    pub fn first(&mut self, link: RadioLink, body: &[u8], timestamp: i64) -> bool {
+        let now = Instant::now();
+        if self.last_prune.is_none_or(|at| now.duration_since(at) >= DEDUP_WINDOW) {
+            self.seen.retain(|_, seen| now.duration_since(*seen) < DEDUP_WINDOW);
+            self.last_prune = Some(now);
+        }
+
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        let key = hasher.finish();
+        let first = match self.seen.get(&key) {
+            Some(seen) if now.duration_since(*seen) < DEDUP_WINDOW => false,
+            _ => {
+                self.seen.insert(key, now);
+                true
+            }
+        };
+
+        let stats = self.stats.entry(link).or_insert_with(|| LinkStats {
+            link,
+            packets: 0,
+            first: 0,
+            duplicates: 0,
+            carried_pct: 0.0,
+            last_packet_at: None,
+        });
+        stats.packets += 1;
+        stats.last_packet_at = Some(timestamp);
+        if first {
+            stats.first += 1;
+        } else {
+            stats.duplicates += 1;
+        }
+
+        let delivered: u64 = self.stats.values().map(|s| s.first).sum();
+        for stats in self.stats.values_mut() {
+            stats.carried_pct = stats.first as f64 * 100.0 / delivered.max(1) as f64;
+        }
+        first
+    }
+
+    pub fn stats(&self) -> Vec<LinkStats> {
+        let mut stats: Vec<_> = self.stats.values().cloned().collect();
+        stats.sort_by_key(|s| s.link != RadioLink::Primary);
+        stats
+    }
+
+    pub fn link_stats(&self, link: RadioLink) -> Option<LinkStats> {
+        self.stats.get(&link).cloned()
+    }
+}
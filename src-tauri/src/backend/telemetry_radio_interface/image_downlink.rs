@@ -0,0 +1,70 @@
+// Putting the payload's photos back together from camera packets
+//
+// Each photo is cut into fragments numbered 0..count. The pinned telemetry-2026 schema
+// has no image id on CameraPacket, so only one photo is in flight at a time: a change
+// in fragment count, a fragment we already have, or a long enough silence means the
+// payload has moved on and whatever was left of the last photo is given up on. There
+// is no uplink packet for asking after missing fragments either, so a photo that loses
+// one is lost.
+use std::time::{Duration, Instant};
+
+// no fragments for this long and the photo in progress is dropped
+const ABANDON_AFTER: Duration = Duration::from_secs(30);
+
+struct PendingImage {
+    fragments: Vec<Option<Vec<u8>>>,
+    last_fragment: Instant,
+}
+
+impl PendingImage {
+    fn new(count: usize, now: Instant) -> Self {
+        Self { fragments: vec![None; count], last_fragment: now }
+    }
+
+    fn missing(&self) -> usize {
+        self.fragments.iter().filter(|f| f.is_none()).count()
+    }
+}
+
+#[derive(Default)]
+pub struct ImageAssembler {
+    pending: Option<PendingImage>,
+    // numbers the photos, the payload doesn't
+    completed: u16,
+}
+
+impl ImageAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// (photo number, whole image) once its last fragment is in
+    pub fn insert(&mut self, num: usize, count: usize, data: Vec<u8>) -> Result<Option<(u16, Vec<u8>)>, String> {
+        if count == 0 || num >= count {
+            return Err(format!("fragment {num} of {count}"));
+        }
+        let now = Instant::now();
+        let moved_on = self.pending.as_ref().is_some_and(|image| {
+            image.fragments.len() != count
+                || image.fragments[num].is_some()
+                || now.duration_since(image.last_fragment) >= ABANDON_AFTER
+        });
+        if moved_on {
+            if let Some(image) = self.pending.take() {
+                eprintln!("[camera] giving up on image {}, {} fragments missing", self.completed, image.missing());
+            }
+        }
+
+        let image = self.pending.get_or_insert_with(|| PendingImage::new(count, now));
+        image.fragments[num] = Some(data);
+        image.last_fragment = now;
+        if image.missing() > 0 {
+            return Ok(None);
+        }
+
+        let image = self.pending.take().unwrap();
+        let number = self.completed;
+        self.completed = self.completed.wrapping_add(1);
+        Ok(Some((number, image.fragments.into_iter().flatten().flatten().collect())))
+    }
+}
@@ -0,0 +1,92 @@
+// Tracks packet arrival to tell normal telemetry from post-landing beacons,
+// and how long we can go without a packet before the link counts as lost
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// gaps longer than this between packets look like beacon pacing
+const BEACON_INTERVAL: Duration = Duration::from_secs(2);
+// consecutive slow or reduced packets needed to switch into beacon mode
+const BEACON_CONFIRM_COUNT: usize = 3;
+// how many intervals (of the current mode's expected rate) before we call it lost
+const MISSED_PACKETS_BEFORE_LOST: u32 = 5;
+
+const NORMAL_EXPECTED_INTERVAL: Duration = Duration::from_millis(200);
+const BEACON_EXPECTED_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LinkMode {
+    Normal,
+    Beacon,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LinkHealth {
+    NoData,
+    Ok,
+    Lost,
+}
+
+pub struct LinkWatchdog {
+    mode: LinkMode,
+    last_rx: Option<Instant>,
+    // true = this packet looked like a beacon (slow and/or reduced)
+    recent: VecDeque<bool>,
+}
+
+impl LinkWatchdog {
+    pub fn new() -> Self {
+        Self {
+            mode: LinkMode::Normal,
+            last_rx: None,
+            recent: VecDeque::with_capacity(BEACON_CONFIRM_COUNT),
+        }
+    }
+
+    /// Feed a received packet. `reduced` means it lacked the full sensor/EKF
+    /// payload. Returns the interval since the previous packet, if any.
+    pub fn on_packet(&mut self, now: Instant, reduced: bool) -> Option<Duration> {
+        let interval = self.last_rx.map(|t| now.duration_since(t));
+        self.last_rx = Some(now);
+
+        let slow = interval.is_some_and(|i| i >= BEACON_INTERVAL);
+        if self.recent.len() == BEACON_CONFIRM_COUNT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(slow || reduced);
+
+        self.mode = if self.recent.len() == BEACON_CONFIRM_COUNT && self.recent.iter().all(|b| *b) {
+            LinkMode::Beacon
+        } else if !reduced && !slow {
+            // one full-rate packet is enough to know we're back in flight mode
+            LinkMode::Normal
+        } else {
+            self.mode
+        };
+
+        interval
+    }
+
+    pub fn mode(&self) -> LinkMode {
+        self.mode
+    }
+
+    pub fn expected_interval(&self) -> Duration {
+        match self.mode {
+            LinkMode::Normal => NORMAL_EXPECTED_INTERVAL,
+            LinkMode::Beacon => BEACON_EXPECTED_INTERVAL,
+        }
+    }
+
+    pub fn since_last(&self, now: Instant) -> Option<Duration> {
+        self.last_rx.map(|t| now.duration_since(t))
+    }
+
+    pub fn health(&self, now: Instant) -> LinkHealth {
+        match self.since_last(now) {
+            None => LinkHealth::NoData,
+            Some(gap) if gap > self.expected_interval() * MISSED_PACKETS_BEFORE_LOST => LinkHealth::Lost,
+            Some(_) => LinkHealth::Ok,
+        }
+    }
+}
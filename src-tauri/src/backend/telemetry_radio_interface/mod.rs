@@ -6,70 +6,87 @@ mod packet_generated;
 pub use packet_generated::hprc;
 use tokio_util::sync::CancellationToken;
 
+mod link_watchdog;
+use link_watchdog::{LinkHealth, LinkMode, LinkWatchdog};
+mod capture;
+use capture::{CaptureReader, CaptureTap, CaptureWriter};
+mod auth;
+mod image_downlink;
+use image_downlink::ImageAssembler;
+mod dedup;
+pub use dedup::{LinkStats, PacketDedup, RadioLink, SharedDedup};
+mod uplink_confirm;
+mod uplink_tracker;
+pub use uplink_tracker::{SharedUplinks, TrackedUplink, UplinkTracker, UPLINK_STATUS_EVENT};
+mod schema_check;
+pub mod test_vectors;
+use schema_check::{SchemaMismatchEvent, SCHEMA_MISMATCH_EVENT};
+pub use uplink_confirm::{
+    HazardConfig, PendingUplink, SharedConfirmations, UplinkAuditRecord, UplinkConfirmations, UPLINK_CONFIRMATION_EVENT,
+};
+use groundstation_core::framing::{crc_check, CALLSIGN, HEADER_LEN};
+pub use groundstation_core::framing::{frame_body, frame_payload, next_frame};
+pub use crc_check::{CrcConfig, CrcMode};
+pub use auth::{AuthConfig, AuthConfigSummary, AuthPolicy, AuthScheme};
+use auth::{AuthOutcome, PacketVerifier};
+
+use crate::middleware::alerts::AlertSeverity;
+use crate::middleware::decode_errors::DecodeError;
+use crate::middleware::encryption::SessionEncryption;
+use crate::middleware::field_metadata::FieldMetadata;
+use crate::middleware::flatten::{self, flatten_fields};
+use crate::middleware::packet_log::InspectedFrame;
+use crate::middleware::packet_stats::LINK_STATS_EVENT;
 use crate::middleware::telemetry_stores::TelemetryData;
 use crate::middleware::{Middleware};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc as std_mpsc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use tokio::sync::Mutex;
-use tokio::time::{sleep, Duration};
+use tokio::sync::{watch, Mutex};
+use tokio::time::{interval, sleep, Duration, Instant};
 // #[allow(dead_code, unused_assignments, unused_variables)]
 
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
 use image::io::Reader as ImageReader;
 use std::io::Cursor;
 
-const CALLSIGN: &[u8] = &[b'K', b'V', b'0', b'R'];
-const HEADER_LEN: usize = CALLSIGN.len() + 1; // magic + length byte
 
-use crate::middleware::video_streams::VideoFrame;
-
-
-struct FragmentBuffer {
-    fragments: Vec<Option<Vec<u8>>>,
-    total: usize,
-}
-
-impl FragmentBuffer {
-    fn new(total: usize) -> Self {
-        Self {
-            fragments: vec![None; total],
-            total,
-        }
-    }
-
-    fn insert(&mut self, num: usize, data: Vec<u8>) {
-        if num < self.total {
-            self.fragments[num] = Some(data);
-        }
-    }
+// packets into a store after connecting before we compare it to the mission profile's schema
+const SCHEMA_CHECK_PACKETS: u32 = 20;
+const LINK_CHECK_MS: u64 = 500;
+const LINK_LOST_ALERT: &str = "radio.link_lost";
+const BEACON_ALERT: &str = "radio.beacon";
+const AUTH_ALERT: &str = "radio.unauthenticated";
+const SCHEMA_ALERT: &str = "radio.schema_mismatch";
+// emitted to every window after each decoded telemetry packet
+const TELEMETRY_EVENT: &str = "telemetry_packet";
+// emitted for frames failing the CRC, when the CRC config asks for it
+const PACKET_ERROR_EVENT: &str = "packet_error";
+// the Shared field echoing the last command the vehicle got
+const LAST_COMMAND_FIELD: &str = "last_command_received";
+// video stream key downlinked photos are shown under
+const IMAGE_STREAM: &str = "payload";
 
-    fn is_complete(&self) -> bool {
-        self.fragments.iter().all(|f| f.is_some())
-    }
-
-    fn assemble(self) -> Vec<u8> {
-        self.fragments
-            .into_iter()
-            .flat_map(|f| f.unwrap())
-            .collect()
-    }
-}
+use crate::middleware::video_streams::VideoFrame;
+use crate::backend::relay::RelayHandle;
+use groundstation_core::framing::cobs::{self, CobsDecoder};
+use groundstation_core::framing::length_delimited::{self, DelimitedDecoder};
+use crate::backend::serial_interface::{self, Reconnect};
 
-fn decode_camera_packet(
-    buffer: FragmentBuffer,
-    middleware: &Arc<Mutex<Middleware>>,
-) -> Result<(), String> {
-    let assembled = buffer.assemble();
 
+fn decode_camera_packet(assembled: &[u8]) -> Result<(Vec<u8>, VideoFrame), String> {
     // Base64 decode
     let jpeg_bytes = BASE64
-        .decode(&assembled)
+        .decode(assembled)
         .map_err(|e| format!("Base64 decode error: {e}"))?;
 
     // JPEG decompress
-    let img = ImageReader::new(Cursor::new(jpeg_bytes))
+    let img = ImageReader::new(Cursor::new(&jpeg_bytes))
         .with_guessed_format()
         .map_err(|e| format!("Image format error: {e}"))?
         .decode()
@@ -79,7 +96,7 @@ fn decode_camera_packet(
     let rgb = img.to_rgb8();
     let (width, height) = rgb.dimensions();
 
-    let frame = Arc::new(VideoFrame {
+    let frame = VideoFrame {
         timestamp: std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
@@ -87,11 +104,8 @@ fn decode_camera_packet(
         data: rgb.into_raw(),
         width,
         height,
-    });
-
-    tokio::runtime::Handle::current().block_on(async {
-        middleware.lock().await.process_video_frame("payload", frame)
-    })
+    };
+    Ok((jpeg_bytes, frame))
 }
 
 
@@ -102,6 +116,21 @@ fn decode_camera_packet(
 pub struct TelemetryRadioHandle {
     pub command_tx: mpsc::Sender<hprc::Command>,
     pub port_tx: mpsc::Sender<String>,
+    pub replay_tx: mpsc::Sender<PathBuf>,
+    inject_tx: mpsc::Sender<Vec<u8>>,
+    capture: CaptureTap,
+    auth_tx: Arc<watch::Sender<AuthConfig>>,
+    framing_tx: Arc<watch::Sender<LinkFraming>>,
+    crc_tx: Arc<watch::Sender<CrcConfig>>,
+    raw_capture_tx: Arc<watch::Sender<bool>>,
+    confirmations: SharedConfirmations,
+    uplinks: SharedUplinks,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureStatus {
+    pub path: String,
+    pub bytes: u64,
 }
 
 #[derive(Clone)]
@@ -109,17 +138,80 @@ pub struct TelemetryRadioPayloadControlHandle {
     pub payload_control_tx: mpsc::Sender<(f32, f32)> // throttle, rotation
 }
 
+// only the joystick drives the payload
+#[cfg_attr(not(all(feature = "uplink", feature = "df")), allow(dead_code))]
 impl TelemetryRadioPayloadControlHandle {
     pub async fn send_payload_control(&self, drive: f32, rotation: f32) -> Result<(), String> {
         self.payload_control_tx.try_send((drive, rotation)).map_err(|e| e.to_string())
     }
 }
 
+// operator uplink, called from the uplink commands and macros. A build without them
+// still sends and acks through the radio's own path, so these stay compiled.
+#[cfg_attr(not(feature = "uplink"), allow(dead_code))]
 impl TelemetryRadioHandle {
 
+    // hazardous commands only go out through request_command and a second operator
     pub async fn send_command(&self, cmd: hprc::Command) -> Result<(), String> {
+        if self.confirmations.lock().is_hazardous(cmd.0) {
+            return Err(format!("Command {} is hazardous and needs a second operator to confirm it", cmd.0));
+        }
         self.command_tx.send(cmd).await.map_err(|e| e.to_string())
     }
+
+    /// Send a command if it's safe, otherwise hold it for confirmation and return the
+    /// pending uplink
+    pub async fn request_command(&self, cmd: hprc::Command, window: &str) -> Result<Option<PendingUplink>, String> {
+        let pending = {
+            let mut confirmations = self.confirmations.lock();
+            if !confirmations.is_hazardous(cmd.0) {
+                None
+            } else {
+                Some(confirmations.request(cmd.0, window)?)
+            }
+        };
+        match pending {
+            Some(uplink) => Ok(Some(uplink)),
+            None => self.command_tx.send(cmd).await.map(|_| None).map_err(|e| e.to_string()),
+        }
+    }
+
+    // the second operator's yes, which sends the command
+    pub async fn approve_command(&self, id: u32, window: &str) -> Result<PendingUplink, String> {
+        let uplink = self.confirmations.lock().approve(id, window)?;
+        let result = self.command_tx.send(hprc::Command(uplink.command)).await.map_err(|e| e.to_string());
+        self.confirmations.lock().sent(&uplink, &result, window);
+        result.map(|_| uplink)
+    }
+
+    pub fn reject_command(&self, id: u32, window: &str) -> Result<PendingUplink, String> {
+        self.confirmations.lock().reject(id, window)
+    }
+
+    pub fn pending_commands(&self) -> Vec<PendingUplink> {
+        self.confirmations.lock().pending()
+    }
+
+    pub fn uplink_audit_log(&self) -> Vec<UplinkAuditRecord> {
+        self.confirmations.lock().audit_log()
+    }
+
+    pub fn set_hazard_config(&self, config: HazardConfig, window: &str) -> Result<(), String> {
+        self.confirmations.lock().set_config(config, window)
+    }
+
+    // commands sent, acked or not, newest first
+    pub fn uplink_status(&self) -> Vec<TrackedUplink> {
+        self.uplinks.lock().recent()
+    }
+
+    pub fn get_hazard_config(&self) -> HazardConfig {
+        self.confirmations.lock().config()
+    }
+}
+
+impl TelemetryRadioHandle {
+
     // gives us a list of available serial ports
     pub fn available_ports() -> Vec<String> {
         serialport::available_ports()
@@ -132,17 +224,121 @@ impl TelemetryRadioHandle {
     pub async fn send_serial_port(&self, port: String) -> Result<(), String> {
         self.port_tx.send(port).await.map_err(|e| e.to_string())
     }
+
+    // tap everything read off the port into a capture file, replacing any running capture
+    pub fn start_capture(&self, path: &Path, encryption: &SessionEncryption) -> Result<(), String> {
+        let writer = CaptureWriter::create(path, encryption)?;
+        *self.capture.lock() = Some(writer);
+        Ok(())
+    }
+
+    pub fn stop_capture(&self) -> Option<CaptureStatus> {
+        self.capture.lock().take().map(|w| w.status())
+    }
+
+    pub fn capture_status(&self) -> Option<CaptureStatus> {
+        self.capture.lock().as_ref().map(|w| w.status())
+    }
+
+    // raw capture mode: a capture into the session folder whenever the radio is connected,
+    // started again on every connect if it isn't running. Turning it off stops whatever
+    // capture is running.
+    pub fn set_raw_capture(&self, enabled: bool) -> Option<CaptureStatus> {
+        self.raw_capture_tx.send_replace(enabled);
+        if enabled { None } else { self.stop_capture() }
+    }
+
+    pub fn get_raw_capture(&self) -> bool {
+        *self.raw_capture_tx.borrow()
+    }
+
+    // plays a capture back through the decoder at its original timing,
+    // the serial port is released for the duration
+    pub async fn replay_capture(&self, path: PathBuf) -> Result<(), String> {
+        if !path.is_file() {
+            return Err(format!("No capture at {}", path.display()));
+        }
+        self.replay_tx.send(path).await.map_err(|e| e.to_string())
+    }
+
+    /// One logged packet through the normal decode path, as if it had come off the
+    /// port. Bare packets (an SD card log) get the header and CRC the live link would
+    /// have put on them.
+    #[cfg_attr(not(feature = "sim"), allow(dead_code))]
+    pub async fn inject_frame(&self, record: Vec<u8>) -> Result<(), String> {
+        self.inject_tx.send(record).await.map_err(|e| e.to_string())
+    }
+
+    // checked here so a bad key is an error for the operator, not a log line
+    pub fn set_auth_config(&self, config: AuthConfig) -> Result<(), String> {
+        PacketVerifier::new(&config)?;
+        self.auth_tx.send_replace(config);
+        Ok(())
+    }
+
+    pub fn get_auth_config(&self) -> AuthConfigSummary {
+        self.auth_tx.borrow().summary()
+    }
+
+    // reconnects the port if it changes
+    pub fn set_framing(&self, framing: LinkFraming) {
+        self.framing_tx.send_replace(framing);
+    }
+
+    pub fn get_framing(&self) -> LinkFraming {
+        *self.framing_tx.borrow()
+    }
+
+    pub fn set_crc_config(&self, config: CrcConfig) {
+        self.crc_tx.send_replace(config);
+    }
+
+    pub fn get_crc_config(&self) -> CrcConfig {
+        *self.crc_tx.borrow()
+    }
+}
+
+impl CaptureWriter {
+    fn status(&self) -> CaptureStatus {
+        CaptureStatus {
+            path: self.path().display().to_string(),
+            bytes: self.bytes(),
+        }
+    }
 }
 
 // ── Constructor ───────────────────────────────────────────────────────────────
 
-pub fn new(middleware: Arc<Mutex<Middleware>>) -> (TelemetryRadio, TelemetryRadioHandle, TelemetryRadioPayloadControlHandle) {
+pub fn new(
+    middleware: Arc<Mutex<Middleware>>,
+    relay: RelayHandle,
+    link: RadioLink,
+    dedup: SharedDedup,
+    confirmations: SharedConfirmations,
+    uplinks: SharedUplinks,
+) -> (TelemetryRadio, TelemetryRadioHandle, TelemetryRadioPayloadControlHandle) {
     let (command_tx, command_rx) = mpsc::channel::<hprc::Command>(32);
     let (payload_control_tx, payload_control_rx) = mpsc::channel::<(f32, f32)>(32);
     let (port_tx, port_rx) = mpsc::channel::<String>(32);
+    let (replay_tx, replay_rx) = mpsc::channel::<PathBuf>(4);
+    let (inject_tx, inject_rx) = mpsc::channel::<Vec<u8>>(64);
+    let capture = capture::new_tap();
+    let auth_tx = Arc::new(watch::Sender::new(AuthConfig::default()));
+    let framing_tx = Arc::new(watch::Sender::new(LinkFraming::default()));
+    let crc_tx = Arc::new(watch::Sender::new(CrcConfig::default()));
+    let raw_capture_tx = Arc::new(watch::Sender::new(false));
     let handle = TelemetryRadioHandle {
         command_tx,
         port_tx,
+        replay_tx,
+        inject_tx,
+        capture: capture.clone(),
+        auth_tx: auth_tx.clone(),
+        framing_tx: framing_tx.clone(),
+        crc_tx: crc_tx.clone(),
+        raw_capture_tx: raw_capture_tx.clone(),
+        confirmations: confirmations.clone(),
+        uplinks: uplinks.clone(),
     };
     let radio = TelemetryRadio {
         middleware,
@@ -151,7 +347,33 @@ pub fn new(middleware: Arc<Mutex<Middleware>>) -> (TelemetryRadio, TelemetryRadi
         payload_control_rx,
         baud_rate: 115200,
         command_sent_count: 0,
-        fragment_buffer: None,
+        images: ImageAssembler::new(),
+        watchdog: LinkWatchdog::new(),
+        relay,
+        replay_rx,
+        inject_rx,
+        capture,
+        schema_check_counts: HashMap::new(),
+        reconnect: Reconnect::new(link.device()),
+        auth_rx: auth_tx.subscribe(),
+        verifier: PacketVerifier::disabled(),
+        auth_verified: 0,
+        auth_failed: 0,
+        framing_rx: framing_tx.subscribe(),
+        frame_errors: 0,
+        crc_rx: crc_tx.subscribe(),
+        crc_errors: 0,
+        raw_capture_rx: raw_capture_tx.subscribe(),
+        confirmations,
+        uplinks,
+        link,
+        dedup,
+        stats_packets: 0,
+        unrouted_packets: 0,
+        unrouted_types: HashSet::new(),
+        legacy_packets: 0,
+        schema_mismatches: 0,
+        current_port: None,
     };
     let payload = TelemetryRadioPayloadControlHandle {
         payload_control_tx,
@@ -168,28 +390,85 @@ pub struct TelemetryRadio {
     payload_control_rx: mpsc::Receiver<(f32, f32)>,
     baud_rate: u32,
     command_sent_count: u16,
-    fragment_buffer: Option<FragmentBuffer>,
+    images: ImageAssembler,
+    watchdog: LinkWatchdog,
+    relay: RelayHandle,
+    replay_rx: mpsc::Receiver<PathBuf>,
+    // packets from a log being played back by data_playback
+    inject_rx: mpsc::Receiver<Vec<u8>>,
+    capture: CaptureTap,
+    // packets per store since the current connection (or replay) started
+    schema_check_counts: HashMap<&'static str, u32>,
+    reconnect: Reconnect,
+    auth_rx: watch::Receiver<AuthConfig>,
+    verifier: PacketVerifier,
+    // running totals, pushed into the link store
+    auth_verified: u64,
+    auth_failed: u64,
+    framing_rx: watch::Receiver<LinkFraming>,
+    // last deframer error count pushed into the link store
+    frame_errors: u64,
+    crc_rx: watch::Receiver<CrcConfig>,
+    crc_errors: u64,
+    raw_capture_rx: watch::Receiver<bool>,
+    confirmations: SharedConfirmations,
+    uplinks: SharedUplinks,
+    link: RadioLink,
+    dedup: SharedDedup,
+    // packet count at the last link stats push
+    stats_packets: u64,
+    unrouted_packets: u64,
+    // packet types we've already warned about
+    unrouted_types: HashSet<u8>,
+    legacy_packets: u64,
+    schema_mismatches: u64,
+    current_port: Option<String>,
 }
 
 impl TelemetryRadio {
-    pub async fn run(mut self, shutdown_rx: CancellationToken) {
-        let mut current_port: Option<String> = None;
-
+    pub async fn run(&mut self, shutdown_rx: CancellationToken) {
+        let mut pending_replay: Option<PathBuf> = None;
 
         loop {
-            if current_port.is_none() {
+            if let Some(path) = pending_replay.take() {
+                match self.run_replay(&path, &shutdown_rx).await {
+                    RunResult::Shutdown => return,
+                    RunResult::PortChanged(new_port) => self.current_port = Some(new_port),
+                    RunResult::Replay(next) => pending_replay = Some(next),
+                    RunResult::ReplayFinished => {
+                        tracing::info!("telem_radio: finished replaying {}", path.display());
+                    }
+                    // replays pick the framing up at the start, nothing to reopen
+                    RunResult::FramingChanged => {}
+                    RunResult::Error(e) => {
+                        tracing::error!("telem_radio: replay of {} failed: {e}", path.display());
+                    }
+                }
+                continue;
+            }
+
+            if self.current_port.is_none() {
                 tokio::select! {
                     _ = shutdown_rx.cancelled() => {
                         tracing::info!("telem_radio: shutdown before port selected");
                         return;
                     }
                     Some(port) = self.port_rx.recv() => {
-                        current_port = Some(port);
+                        self.current_port = Some(port);
+                    }
+                    Some(path) = self.replay_rx.recv() => {
+                        pending_replay = Some(path);
+                        continue;
+                    }
+                    Some(record) = self.inject_rx.recv() => {
+                        self.handle_injected(record).await;
+                        continue;
                     }
                 }
             }
 
-            let port_name = current_port.take().unwrap();
+            // left set when the service stops, so a restart reopens the same port
+            let port_name = self.current_port.clone().unwrap();
             match self.run_connected(&port_name, &shutdown_rx).await {
                 RunResult::Shutdown => {
                     tracing::info!("telem_radio: clean shutdown");
@@ -197,16 +476,27 @@ impl TelemetryRadio {
                 }
                 RunResult::PortChanged(new_port) => {
                     tracing::info!("telem_radio: switching to {new_port}");
-                    current_port = Some(new_port);
+                    self.reconnect.reset();
+                    self.current_port = Some(new_port);
+                }
+                // go back to the port once the replay is done
+                RunResult::Replay(path) => pending_replay = Some(path),
+                RunResult::ReplayFinished => {}
+                RunResult::FramingChanged => {
+                    tracing::info!("telem_radio: framing changed, reopening {port_name}");
                 }
                 RunResult::Error(e) => {
-                    tracing::error!("telem_radio: error on {port_name}: {e}. Retrying in 2s...");
-                    current_port = Some(port_name);
+                    let delay = {
+                        let middleware = self.middleware.lock().await;
+                        self.reconnect.failed(&middleware, &port_name, &e)
+                    };
+                    tracing::error!("telem_radio: error on {port_name}: {e}. Retrying in {}ms...", delay.as_millis());
                     tokio::select! {
-                        _ = sleep(Duration::from_secs(2)) => {}
+                        _ = sleep(delay) => {}
                         _ = shutdown_rx.cancelled() => return,
                         Some(new_port) = self.port_rx.recv() => {
-                            current_port = Some(new_port);
+                            self.reconnect.reset();
+                            self.current_port = Some(new_port);
                         }
                     }
                 }
@@ -219,19 +509,19 @@ impl TelemetryRadio {
         port_name: &str,
         shutdown_rx: &CancellationToken,
     ) -> RunResult {
-        let port = match serialport::new(port_name, self.baud_rate)
-            .timeout(Duration::from_millis(100))
-            .open()
-        {
+        let port = match serial_interface::open(port_name, self.baud_rate, Duration::from_millis(100)) {
             Ok(p) => p,
-            Err(e) => return RunResult::Error(e.to_string()),
+            Err(e) => return RunResult::Error(e),
         };
 
-        let writer = match port.try_clone() {
+        let writer = match port.try_clone_link() {
             Ok(p) => p,
             Err(e) => return RunResult::Error(format!("clone failed: {e}")),
         };
         let mut reader = port;
+        if *self.raw_capture_rx.borrow() {
+            self.start_raw_capture().await;
+        }
 
         // Unbounded so the reader thread can send without blocking on the runtime
         let (frame_tx, mut frame_rx) =
@@ -240,11 +530,17 @@ impl TelemetryRadio {
         // Write channel — std mpsc, receiver lives on the writer thread
         let (write_tx, write_rx) = std_mpsc::channel::<Vec<u8>>();
 
+        let framing = *self.framing_rx.borrow_and_update();
+        let frame_errors = Arc::new(AtomicU64::new(0));
+        self.frame_errors = 0;
+
         // ── Reader thread ─────────────────────────────────────────────────────
         let reader_frame_tx = frame_tx.clone();
+        let capture = self.capture.clone();
+        let reader_frame_errors = frame_errors.clone();
         std::thread::spawn(move || {
             let mut buf = vec![0u8; 1024];
-            let mut accumulator: Vec<u8> = Vec::new();
+            let mut deframer = Deframer::new(framing);
 
             loop {
                 match reader.read(&mut buf) {
@@ -253,50 +549,13 @@ impl TelemetryRadio {
                         return;
                     }
                     Ok(n) => {
-                        accumulator.extend_from_slice(&buf[..n]);
-
-                        loop {
-                            // Find the magic header
-                            let Some(start) = accumulator
-                                .windows(CALLSIGN.len())
-                                .position(|w| w == CALLSIGN)
-                            else {
-                                // No magic found — discard everything except the last
-                                // (CALLSIGN.len() - 1) bytes in case magic is split across reads
-                                if accumulator.len() > CALLSIGN.len() {
-                                    accumulator.drain(..accumulator.len() - (CALLSIGN.len() - 1));
-                                }
-                                break;
-                            };
-
-                            // Discard anything before the magic
-                            if start > 0 {
-                                tracing::warn!(
-                                    "telem_radio: discarding {} bytes before magic",
-                                    start
-                                );
-                                accumulator.drain(..start);
-                            }
-
-                            // Do we have enough bytes to read the length?
-                            if accumulator.len() < HEADER_LEN {
-                                break; // wait for more data
-                            }
-
-                            let payload_len = accumulator[CALLSIGN.len()] as usize;
-                            let total_len = HEADER_LEN + payload_len;
-
-                            // Do we have the full packet?
-                            if accumulator.len() < total_len {
-                                break; // wait for more data
-                            }
-
-                            // Extract the complete packet and send it
-                            let packet = accumulator.drain(..total_len).collect::<Vec<u8>>();
+                        tap(&capture, &buf[..n]);
+                        for packet in deframer.push(&buf[..n]) {
                             if reader_frame_tx.send(Ok(packet)).is_err() {
                                 return;
                             }
                         }
+                        reader_frame_errors.store(deframer.frame_errors(), Ordering::Relaxed);
                     }
                     Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
                     Err(e) => {
@@ -320,16 +579,45 @@ impl TelemetryRadio {
         });
 
         tracing::info!("telem_radio: connected to {port_name}");
+        {
+            let middleware = self.middleware.lock().await;
+            self.reconnect.connected(&middleware, port_name);
+        }
+        self.schema_check_counts.clear();
 
         // ── Select loop ───────────────────────────────────────────────────────
+        let mut link_check = interval(Duration::from_millis(LINK_CHECK_MS));
         loop {
             tokio::select! {
                 _ = shutdown_rx.cancelled() => {
                     return RunResult::Shutdown;
                 }
+                _ = link_check.tick() => {
+                    self.check_link().await;
+                    self.note_link_stats().await;
+                    self.emit_packet_stats().await;
+                    self.note_frame_errors(frame_errors.load(Ordering::Relaxed)).await;
+                    let expired = self.confirmations.lock().expire();
+                    for uplink in expired {
+                        tracing::warn!("telem_radio: hazardous command {} from {} expired unconfirmed", uplink.command, uplink.requested_by);
+                        self.middleware.lock().await.emit(UPLINK_CONFIRMATION_EVENT, uplink);
+                    }
+                    let unacked = self.uplinks.lock().expire(chrono::Utc::now().timestamp_millis());
+                    for uplink in unacked {
+                        tracing::warn!("telem_radio: command {} (#{}) was never acked", uplink.command, uplink.id);
+                        self.middleware.lock().await.emit(UPLINK_STATUS_EVENT, uplink);
+                    }
+                }
+                _ = self.framing_rx.changed() => return RunResult::FramingChanged,
                 Some(new_port) = self.port_rx.recv() => {
                     return RunResult::PortChanged(new_port);
                 }
+                Some(path) = self.replay_rx.recv() => {
+                    return RunResult::Replay(path);
+                }
+                Some(record) = self.inject_rx.recv() => {
+                    self.handle_injected(record).await;
+                }
                 Some(payload_control) = self.payload_control_rx.recv() => {
                     let mut builder = flatbuffers::FlatBufferBuilder::with_capacity(32);
 
@@ -347,13 +635,7 @@ impl TelemetryRadio {
 
                     builder.finish(command_packet, None);
 
-                    // add framing
-                    let mut send_buffer: Vec<u8> = Vec::new();
-                    send_buffer.extend_from_slice(CALLSIGN); // magic header/callsign
-                    send_buffer.push(builder.finished_data().len() as u8); // length
-                    send_buffer.extend_from_slice(builder.finished_data());
-
-                    if write_tx.send(send_buffer).is_err() {
+                    if write_tx.send(frame_out(framing, builder.finished_data())).is_err() {
                         return RunResult::Error("writer thread died".into());
                     }
                 }
@@ -373,18 +655,19 @@ impl TelemetryRadio {
                     });
 
                     builder.finish(command_packet, None);
-                    
-
-                    // add framing
-                    let mut send_buffer: Vec<u8> = Vec::new();
-                    send_buffer.extend_from_slice(CALLSIGN); // magic header/callsign
-                    send_buffer.push(builder.finished_data().len() as u8); // length
-                    send_buffer.extend_from_slice(builder.finished_data());
 
-                    if write_tx.send(send_buffer).is_err() {
+                    let sent = write_tx.send(frame_out(framing, builder.finished_data())).is_ok();
+                    let timestamp = chrono::Utc::now().timestamp_millis();
+                    let uplink = if sent {
+                        self.uplinks.lock().sent(self.link.source(), self.command_sent_count, cmd.0, timestamp)
+                    } else {
+                        let error = "writer thread died".to_string();
+                        self.uplinks.lock().failed(self.link.source(), self.command_sent_count, cmd.0, timestamp, error)
+                    };
+                    self.middleware.lock().await.emit(UPLINK_STATUS_EVENT, uplink);
+                    if !sent {
                         return RunResult::Error("writer thread died".into());
                     }
-
                 }
                 result = frame_rx.recv() => {
                     match result {
@@ -397,14 +680,117 @@ impl TelemetryRadio {
         }
     }
 
+    // feed a capture through the same deframer and handlers as a live port
+    async fn run_replay(&mut self, path: &Path, shutdown_rx: &CancellationToken) -> RunResult {
+        let mut reader = match CaptureReader::open(path) {
+            Ok(r) => r,
+            Err(e) => return RunResult::Error(e),
+        };
+        tracing::info!("telem_radio: replaying {}", path.display());
+        self.schema_check_counts.clear();
+
+        let started = Instant::now();
+        let mut first_timestamp: Option<u64> = None;
+        let mut deframer = Deframer::new(*self.framing_rx.borrow());
+
+        loop {
+            let (timestamp, data) = match reader.next_record() {
+                Ok(Some(record)) => record,
+                Ok(None) => return RunResult::ReplayFinished,
+                Err(e) => return RunResult::Error(e),
+            };
+            let first = *first_timestamp.get_or_insert(timestamp);
+            let due = started + Duration::from_micros(timestamp.saturating_sub(first));
+
+            tokio::select! {
+                _ = shutdown_rx.cancelled() => return RunResult::Shutdown,
+                Some(new_port) = self.port_rx.recv() => return RunResult::PortChanged(new_port),
+                Some(next) = self.replay_rx.recv() => return RunResult::Replay(next),
+                _ = tokio::time::sleep_until(due) => {}
+            }
+
+            if reader.framed() {
+                self.handle_frame(data).await;
+                continue;
+            }
+            for frame in deframer.push(&data) {
+                self.handle_frame(frame).await;
+            }
+        }
+    }
+
+    async fn handle_injected(&mut self, record: Vec<u8>) {
+        if record.starts_with(CALLSIGN) {
+            self.handle_frame(record).await;
+            return;
+        }
+        let mut frame = frame_payload(&record);
+        crc_check::append(self.crc_rx.borrow().mode, &mut frame);
+        self.handle_frame(frame).await;
+    }
+
     async fn handle_frame(&mut self, frame: Vec<u8>) {
         tracing::debug!("telem_radio: rx {} bytes", frame.len());
 
-        // take off framing header
-        let frame_payload = &frame[HEADER_LEN..];
+        // pass the raw frame on to the server before we touch it, the backup's frames
+        // only once we know they aren't copies
+        if self.link == RadioLink::Primary {
+            self.relay.forward(&frame);
+        }
+
+        let timestamp = chrono::Utc::now().timestamp_millis();
+
+        if self.auth_rx.has_changed().unwrap_or(false) {
+            let config = self.auth_rx.borrow_and_update().clone();
+            // set_auth_config already validated it
+            self.verifier = PacketVerifier::new(&config).unwrap_or_else(|_| PacketVerifier::disabled());
+        }
+
+        let crc = *self.crc_rx.borrow();
+        let checked = match crc_check::check(crc.mode, &frame) {
+            Ok(checked) => checked,
+            Err(reason) => {
+                self.note_crc_error(&frame, timestamp, reason, crc.emit_errors).await;
+                return;
+            }
+        };
+
+        // take off framing header (and the tag, on a signed link)
+        let (frame_payload, auth) = self.verifier.check(checked);
+        // nothing to count with authentication off, so no middleware lock per frame
+        if !matches!(auth, AuthOutcome::Unchecked) && self.note_auth(&frame, timestamp, auth).await {
+            return;
+        }
+        let decoded = hprc::root_as_packet(frame_payload);
+        if let Some(current_error) = schema_check::mismatch(&decoded) {
+            self.handle_legacy_frame(&frame, frame_payload, timestamp, current_error).await;
+            return;
+        }
+
+        let inspected = match &decoded {
+            Ok(packet) => InspectedFrame::decoded(
+                timestamp,
+                &frame,
+                format!("{:?}", packet.packet_type()),
+                format!("{packet:?}"),
+            ),
+            Err(e) => InspectedFrame::failed(timestamp, &frame, e.to_string()),
+        };
+        {
+            let middleware = self.middleware.lock().await;
+            if decoded.is_ok() {
+                middleware.record_packet(self.link.source(), &frame);
+            }
+            middleware.record_frame(self.link.source(), inspected);
+        }
 
-        if let Ok(packet) = hprc::root_as_packet(&frame_payload) {
+        if let Ok(packet) = decoded {
                 let packet_type = packet.packet_type();
+                // with two radios up, the other one may have handled this packet already
+                let first = self.dedup.lock().first(self.link, frame_payload, timestamp);
+                if first && self.link == RadioLink::Backup {
+                    self.relay.forward(&frame);
+                }
     
     // Extract camera data before any borrows of self
     let camera_data = if packet_type == hprc::PacketUnion::CameraPacket {
@@ -420,7 +806,17 @@ impl TelemetryRadio {
     };
 
     {
-            let mut middleware = self.middleware.lock().await;
+            // our own Arc so the guard doesn't hold a borrow of self while the watchdog updates
+            let shared_middleware = self.middleware.clone();
+            let mut middleware = shared_middleware.lock().await;
+            let source = telemetry_source(&packet);
+            middleware.record_packet_stats(self.link.source(), timestamp);
+            if let Some((store, reduced)) = source {
+                self.note_telemetry_packet(&mut middleware, store, reduced);
+            }
+            if !first {
+                return;
+            }
             match packet.packet_type() {
                 hprc::PacketUnion::Rocket30KTelemetryPacket => self.handle_rocket30_kpacket(
                     &mut middleware,
@@ -442,39 +838,389 @@ impl TelemetryRadio {
                     // .unwrap() is safe here bc we've already type matched in the match statement
                     packet.packet_as_payload_telemetry_packet().unwrap(),
                 ),
-                hprc::PacketUnion::CameraPacket => {},
-                _ => (),
+                // reassembled below, once the guard is gone
+                hprc::PacketUnion::CameraPacket => {}
+                // our own uplink, heard back on a loopback or off another ground station
+                hprc::PacketUnion::RemoteControl
+                | hprc::PacketUnion::PayloadControlPacket => {}
+                other => self.note_unrouted(&mut middleware, other),
+            }
+            if let Some((store, _)) = source {
+                emit_telemetry_event(&middleware, store, format!("{:?}", packet_type), timestamp);
+                self.check_schema_after_connect(&middleware, store);
+                self.check_uplink_ack(&middleware, store);
             }
         }
         if let Some((fragment_num, fragment_count, data)) = camera_data {
-        self.handle_camera_packet(fragment_num, fragment_count, data);
+        self.handle_camera_packet(fragment_num, fragment_count, data).await;
     }
     }
 }
-       
-fn handle_camera_packet(
-    &mut self,
-    fragment_num: usize,
-    fragment_count: usize,
-    data: Vec<u8>,
-) {
-    let buf = self.fragment_buffer.get_or_insert_with(|| FragmentBuffer::new(fragment_count));
+}
 
-    if buf.total != fragment_count {
-        eprintln!("[camera] Fragment count mismatch, dropping frame");
-        self.fragment_buffer = Some(FragmentBuffer::new(fragment_count));
+#[derive(Debug, Clone, Serialize)]
+struct PacketErrorEvent {
+    source: &'static str,
+    timestamp: i64,
+    reason: String,
+    length: usize,
+    hexdump: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TelemetryPacketEvent {
+    store: &'static str,
+    packet_type: String,
+    received_at: i64,
+    // latest value of every field in the store, so panels don't need a round trip
+    values: BTreeMap<String, f64>,
+}
+
+fn emit_telemetry_event(middleware: &Middleware, store: &'static str, packet_type: String, received_at: i64) {
+    let values = middleware
+        .get_field_names(store)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|field| {
+            let value = middleware.get_last(store, &field).ok().flatten()?.value.as_f64();
+            Some((field, value))
+        })
+        .collect();
+    middleware.emit(TELEMETRY_EVENT, TelemetryPacketEvent { store, packet_type, received_at, values });
+}
+
+impl TelemetryRadio {
+    // bad frames never reach the decoder, they're counted and shown in the inspector
+    async fn start_raw_capture(&self) {
+        if self.capture.lock().is_some() {
+            return;
+        }
+        let (path, encryption) = {
+            let middleware = self.middleware.lock().await;
+            (middleware.raw_capture_path(self.link.source()), middleware.session_encryption())
+        };
+        match CaptureWriter::create(&path, &encryption) {
+            Ok(writer) => {
+                tracing::info!("telem_radio: raw capture to {}", path.display());
+                *self.capture.lock() = Some(writer);
+            }
+            Err(e) => tracing::error!("telem_radio: couldn't start raw capture at {}: {e}", path.display()),
+        }
     }
 
-    if let Some(buf) = &mut self.fragment_buffer {
-        buf.insert(fragment_num, data);
+    async fn note_crc_error(&mut self, frame: &[u8], timestamp: i64, reason: String, emit: bool) {
+        self.crc_errors += 1;
+        let mut middleware = self.middleware.lock().await;
+        middleware.record_crc_failure(self.link.source());
+        let _ = middleware.push_data(
+            self.link.store(),
+            "crc_errors",
+            TelemetryData::new().with_value(self.crc_errors),
+        );
+        if emit {
+            middleware.emit(
+                PACKET_ERROR_EVENT,
+                PacketErrorEvent {
+                    source: self.link.source(),
+                    timestamp,
+                    reason: reason.clone(),
+                    length: frame.len(),
+                    hexdump: crc_check::hexdump(frame),
+                },
+            );
+        }
+        middleware.record_frame(self.link.source(), InspectedFrame::failed(timestamp, frame, reason));
+    }
 
-        if buf.is_complete() {
-            let completed = self.fragment_buffer.take().unwrap();
-            if let Err(e) = decode_camera_packet(completed, &self.middleware) {
-                eprintln!("[camera] Failed to decode camera packet: {e}");
+    // count the outcome in the link store, true if the frame should be dropped
+    async fn note_auth(&mut self, frame: &[u8], timestamp: i64, outcome: AuthOutcome) -> bool {
+        let mut middleware = self.middleware.lock().await;
+        match outcome {
+            AuthOutcome::Unchecked => false,
+            AuthOutcome::Verified => {
+                self.auth_verified += 1;
+                let _ = middleware.push_data(
+                    self.link.store(),
+                    "auth_verified",
+                    TelemetryData::new().with_value(self.auth_verified),
+                );
+                false
+            }
+            AuthOutcome::Failed(reason) => {
+                self.auth_failed += 1;
+                let _ = middleware.push_data(
+                    self.link.store(),
+                    "auth_failed",
+                    TelemetryData::new().with_value(self.auth_failed),
+                );
+                let reject = self.verifier.policy() == AuthPolicy::Reject;
+                middleware.raise_alert(
+                    &self.link.alert(AUTH_ALERT),
+                    AlertSeverity::Warning,
+                    format!(
+                        "{} unauthenticated packet(s), last: {reason} ({})",
+                        self.auth_failed,
+                        if reject { "dropped" } else { "decoded anyway" }
+                    ),
+                );
+                if reject {
+                    middleware.record_frame(
+                        self.link.source(),
+                        InspectedFrame::failed(timestamp, frame, format!("authentication failed: {reason}")),
+                    );
+                }
+                reject
             }
         }
     }
+
+    // keep the watchdog fed, and flag beacons loudly so recovery doesn't miss one
+    fn note_telemetry_packet(
+        &mut self,
+        middleware: &mut tokio::sync::MutexGuard<'_, Middleware>,
+        store: &'static str,
+        reduced: bool,
+    ) {
+        let interval = self.watchdog.on_packet(Instant::now(), reduced);
+        let mode = self.watchdog.mode();
+
+        let _ = middleware.push_data(
+            self.link.store(),
+            "beacon_mode",
+            TelemetryData::new().with_value(mode == LinkMode::Beacon),
+        );
+        if let Some(interval) = interval {
+            let _ = middleware.push_data(
+                self.link.store(),
+                "packet_interval_ms",
+                TelemetryData::new().with_value(interval.as_millis() as u64),
+            );
+        }
+        middleware.clear_alert(&self.link.alert(LINK_LOST_ALERT));
+
+        if mode == LinkMode::Beacon {
+            let store = middleware.session_store(self.link.source(), store);
+            let latest = |field: &str| {
+                middleware.get_last(&store, field).ok().flatten().map(|d| d.value.as_f64())
+            };
+            let position = match (latest("lat"), latest("lon")) {
+                (Some(lat), Some(lon)) => format!(" last fix {lat:.6}, {lon:.6}"),
+                _ => String::new(),
+            };
+            let message = format!(
+                "Beacon received from {store} at {}{position}",
+                chrono::Local::now().format("%H:%M:%S")
+            );
+            // clear first so every beacon shows up as a fresh, unacknowledged alert
+            middleware.clear_alert(&self.link.alert(BEACON_ALERT));
+            middleware.raise_alert(&self.link.alert(BEACON_ALERT), AlertSeverity::Warning, message);
+        } else {
+            middleware.clear_alert(&self.link.alert(BEACON_ALERT));
+        }
+    }
+
+    // once a store has seen a few packets on this connection, make sure the vehicle
+    // is sending what the mission profile says it should
+    fn check_schema_after_connect(
+        &mut self,
+        middleware: &tokio::sync::MutexGuard<'_, Middleware>,
+        store: &'static str,
+    ) {
+        let count = self.schema_check_counts.entry(store).or_insert(0);
+        *count += 1;
+        if *count != SCHEMA_CHECK_PACKETS {
+            return;
+        }
+        match middleware.check_schema(store) {
+            Ok(Some(diff)) if !diff.is_clean() => tracing::warn!("telem_radio: {}", diff.describe()),
+            Ok(_) => {}
+            Err(e) => tracing::error!("telem_radio: schema check on {store} failed: {e}"),
+        }
+    }
+
+    // a packet this build's schema doesn't take, up-converted if it's from 2024/2025 firmware
+    async fn handle_legacy_frame(&mut self, frame: &[u8], payload: &[u8], timestamp: i64, current_error: String) {
+        let upconverted = match schema_check::upconvert(payload) {
+            Ok(upconverted) => upconverted,
+            Err(legacy_error) => {
+                let reason = format!("not a packet in this build's schema ({current_error}) or a legacy one ({legacy_error})");
+                self.middleware
+                    .lock()
+                    .await
+                    .report_decode_error(DecodeError::frame(self.link.store(), timestamp, frame, reason.clone()));
+                self.note_schema_mismatch(frame, timestamp, reason).await;
+                return;
+            }
+        };
+        let shared_middleware = self.middleware.clone();
+        let mut middleware = shared_middleware.lock().await;
+        middleware.record_frame(
+            self.link.source(),
+            InspectedFrame::decoded(timestamp, frame, upconverted.packet_type.clone(), format!("{} fields", upconverted.fields.len())),
+        );
+        middleware.record_packet(self.link.source(), frame);
+        middleware.record_packet_stats(self.link.source(), timestamp);
+        if upconverted.uplink || !self.dedup.lock().first(self.link, payload, timestamp) {
+            return;
+        }
+        if self.link == RadioLink::Backup {
+            self.relay.forward(frame);
+        }
+
+        self.legacy_packets += 1;
+        if self.legacy_packets == 1 {
+            tracing::warn!("telem_radio: {} is sending 2024/2025 packets, up-converting them", upconverted.store);
+        }
+        let _ = middleware.push_data(
+            self.link.store(),
+            "legacy_packets",
+            TelemetryData::new().with_value(self.legacy_packets),
+        );
+        self.note_telemetry_packet(&mut middleware, upconverted.store, upconverted.reduced);
+        for (field, value) in upconverted.fields {
+            let _ = middleware.push_data(upconverted.store, &field, TelemetryData::new().with_value(value));
+        }
+        emit_telemetry_event(&middleware, upconverted.store, upconverted.packet_type, timestamp);
+        self.check_schema_after_connect(&middleware, upconverted.store);
+        self.check_uplink_ack(&middleware, upconverted.store);
+    }
+
+    // the packet just handled says which command the vehicle got last, which acks it
+    fn check_uplink_ack(&self, middleware: &Middleware, store: &'static str) {
+        let mut uplinks = self.uplinks.lock();
+        if !uplinks.waiting() {
+            return;
+        }
+        let Ok(Some(last)) = middleware.get_last(store, LAST_COMMAND_FIELD) else {
+            return;
+        };
+        if let Some(uplink) = uplinks.on_report(store, last.value.as_f64() as u8, last.timestamp) {
+            tracing::info!("telem_radio: command {} (#{}) acked by {store}", uplink.command, uplink.id);
+            middleware.emit(UPLINK_STATUS_EVENT, uplink);
+        }
+    }
+
+    // a packet we can't map onto the current fields, dropped rather than guessed at
+    async fn note_schema_mismatch(&mut self, frame: &[u8], timestamp: i64, reason: String) {
+        self.schema_mismatches += 1;
+        let mut middleware = self.middleware.lock().await;
+        let _ = middleware.push_data(
+            self.link.store(),
+            "schema_mismatches",
+            TelemetryData::new().with_value(self.schema_mismatches),
+        );
+        middleware.raise_alert(
+            &self.link.alert(SCHEMA_ALERT),
+            AlertSeverity::Warning,
+            format!("{} packet(s) dropped for their schema, last: {reason}", self.schema_mismatches),
+        );
+        middleware.emit(
+            SCHEMA_MISMATCH_EVENT,
+            SchemaMismatchEvent {
+                source: self.link.source(),
+                timestamp,
+                reason: reason.clone(),
+                length: frame.len(),
+            },
+        );
+        middleware.record_frame(self.link.source(), InspectedFrame::failed(timestamp, frame, reason));
+    }
+
+    // COBS framing errors, into the link store whenever the count moves
+    async fn note_frame_errors(&mut self, errors: u64) {
+        if errors == self.frame_errors {
+            return;
+        }
+        self.frame_errors = errors;
+        let _ = self.middleware.lock().await.push_data(
+            self.link.store(),
+            "frame_errors",
+            TelemetryData::new().with_value(errors),
+        );
+    }
+
+    // a packet type this build doesn't know where to put, most likely a newer schema on
+    // the vehicle. Counted, and logged once per type so the log doesn't drown.
+    fn note_unrouted(&mut self, middleware: &mut tokio::sync::MutexGuard<'_, Middleware>, packet_type: hprc::PacketUnion) {
+        self.unrouted_packets += 1;
+        let _ = middleware.push_data(
+            self.link.store(),
+            "unrouted_packets",
+            TelemetryData::new().with_value(self.unrouted_packets),
+        );
+        if self.unrouted_types.insert(packet_type.0) {
+            tracing::warn!("telem_radio: no route for packet type {:?} ({}), dropping them", packet_type, packet_type.0);
+        }
+    }
+
+    // how much of the flight this radio is carrying, next to the rest of its link health
+    async fn note_link_stats(&mut self) {
+        let Some(stats) = self.dedup.lock().link_stats(self.link) else {
+            return;
+        };
+        if stats.packets == self.stats_packets {
+            return;
+        }
+        self.stats_packets = stats.packets;
+        let mut middleware = self.middleware.lock().await;
+        let store = self.link.store();
+        let _ = middleware.push_data(store, "packets_first", TelemetryData::new().with_value(stats.first));
+        let _ = middleware.push_data(store, "packets_duplicate", TelemetryData::new().with_value(stats.duplicates));
+        let _ = middleware.push_data(store, "carried_pct", TelemetryData::new().with_value(stats.carried_pct));
+    }
+
+    async fn emit_packet_stats(&self) {
+        let middleware = self.middleware.lock().await;
+        if let Some(stats) = middleware.get_stream_link_stats(self.link.source()) {
+            middleware.emit(LINK_STATS_EVENT, stats);
+        }
+    }
+
+    async fn check_link(&self) {
+        let now = Instant::now();
+        if self.watchdog.health(now) != LinkHealth::Lost {
+            return;
+        }
+        let gap = self.watchdog.since_last(now).unwrap_or_default();
+        let mode = match self.watchdog.mode() {
+            LinkMode::Normal => "telemetry",
+            LinkMode::Beacon => "beacon",
+        };
+        self.middleware.lock().await.raise_alert(
+            &self.link.alert(LINK_LOST_ALERT),
+            AlertSeverity::Warning,
+            format!("No {mode} packets for {:.1}s", gap.as_secs_f64()),
+        );
+    }
+
+async fn handle_camera_packet(
+    &mut self,
+    fragment_num: usize,
+    fragment_count: usize,
+    data: Vec<u8>,
+) {
+    let (image_id, assembled) = match self.images.insert(fragment_num, fragment_count, data) {
+        Ok(Some(image)) => image,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("[camera] Dropping fragment: {e}");
+            return;
+        }
+    };
+    let (jpeg, frame) = match decode_camera_packet(&assembled) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            eprintln!("[camera] Failed to decode camera packet: {e}");
+            let timestamp = chrono::Utc::now().timestamp_millis();
+            let error = DecodeError::frame(IMAGE_STREAM, timestamp, &assembled, format!("image {image_id}: {e}"));
+            self.middleware.lock().await.report_decode_error(error);
+            return;
+        }
+    };
+    let mut middleware = self.middleware.lock().await;
+    if let Err(e) = middleware.publish_downlinked_image(IMAGE_STREAM, image_id, &jpeg, Arc::new(frame)) {
+        eprintln!("[camera] Failed to publish image {image_id}: {e}");
+    }
 }
 
     fn handle_rocket30_kpacket(
@@ -482,33 +1228,37 @@ fn handle_camera_packet(
         middleware: &mut tokio::sync::MutexGuard<'_, Middleware>,
         packet: hprc::Rocket30KTelemetryPacket<'_>,
     ) {
+        // a launch session that owns this radio gets its own rocket store
+        let store = middleware.session_store(self.link.source(), "rocket");
         let _ = middleware.push_data(
-            "rocket",
+            &store,
             "state",
             TelemetryData::new().with_value(packet.state().0 as u32),
         );
 
         if let Some(shared) = packet.shared() {
-            self.handle_shared(middleware, shared, "rocket".to_string());
+            self.handle_shared(middleware, shared, store.clone());
         };
         if let Some(sensors) = packet.sensor_values() {
-            self.handle_sensors(middleware, &sensors, "rocket".to_string());
+            self.handle_sensors(middleware, &sensors, store.clone());
         };
         if let Some(ekf) = packet.ekf_values() {
-            self.handle_ekf(middleware, ekf, "rocket".to_string());
+            self.handle_ekf(middleware, ekf, store.clone());
         };
 
         if let Some(covariance) = packet.covariance_diagonal() {
             let mut covariance_index = 0;
             for val in covariance {
                 let _ = middleware.push_data(
-                    "rocket",
+                    &store,
                     &format!("covariance_diagonal{}",covariance_index).to_string(), 
                     TelemetryData::new().with_value(val as f64),
                 );
                 covariance_index +=1;
             };
         };
+        // the packet's fields are all in, check them against its schema
+        middleware.end_packet(&store);
     }
 
     fn handle_rocket2_stage_packet(
@@ -516,26 +1266,28 @@ fn handle_camera_packet(
         middleware: &mut tokio::sync::MutexGuard<'_, Middleware>,
         packet: hprc::Rocket2StageTelemetryPacket<'_>,
     ) {
+        let store = middleware.session_store(self.link.source(), "rocket");
         let _ = middleware.push_data(
-            "rocket",
+            &store,
             "state",
             TelemetryData::new().with_value(packet.state().0 as u32),
         );
 
         if let Some(shared) = packet.shared() {
-            self.handle_shared(middleware, shared, "rocket".to_string());
+            self.handle_shared(middleware, shared, store.clone());
         };
         if let Some(sensors) = packet.sensor_values() {
-            self.handle_sensors(middleware, &sensors, "rocket".to_string());
+            self.handle_sensors(middleware, &sensors, store.clone());
         };
         if let Some(ekf) = packet.ekf_values() {
-            self.handle_ekf(middleware, ekf, "rocket".to_string());
+            self.handle_ekf(middleware, ekf, store.clone());
         };
 
         // if let Some(airbrakes) = packet.airbrakes() {
         // airbrakes.commanded();
         // airbrakes.actual();
         // }
+        middleware.end_packet(&store);
     }
 
     fn handle_rocket_canards_packet(
@@ -543,66 +1295,67 @@ fn handle_camera_packet(
         middleware: &mut tokio::sync::MutexGuard<'_, Middleware>,
         packet: hprc::RocketCanardsTelemetryPacket<'_>,
     ) {
+        let store = middleware.session_store(self.link.source(), "rocket");
         let _ = middleware.push_data(
-            "rocket",
+            &store,
             "state",
             TelemetryData::new().with_value(packet.state().0 as u32),
         );
 
         if let Some(shared) = packet.shared() {
-            self.handle_shared(middleware, shared, "rocket".to_string());
+            self.handle_shared(middleware, shared, store.clone());
         };
         if let Some(sensors) = packet.sensor_values() {
-            self.handle_sensors(middleware, &sensors, "rocket".to_string());
+            self.handle_sensors(middleware, &sensors, store.clone());
         };
         if let Some(ekf) = packet.ekf_values() {
-            self.handle_ekf(middleware, ekf, "rocket".to_string());
+            self.handle_ekf(middleware, ekf, store.clone());
         };
 
         if let Some(canard1) = packet.canard1() {
             let _ = middleware.push_data(
-                "rocket",
+                &store,
                 "canard 1 commanded",
                 TelemetryData::new().with_value(canard1.commanded() as f64),
             );
             let _ = middleware.push_data(
-                "rocket",
+                &store,
                 "canard 1 actual",
                 TelemetryData::new().with_value(canard1.actual() as f64),
             );
         }
         if let Some(canard2) = packet.canard2() {
             let _ = middleware.push_data(
-                "rocket",
+                &store,
                 "canard 2 commanded",
                 TelemetryData::new().with_value(canard2.commanded() as f64),
             );
             let _ = middleware.push_data(
-                "rocket",
+                &store,
                 "canard 2 actual",
                 TelemetryData::new().with_value(canard2.actual() as f64),
             );
         }
         if let Some(canard3) = packet.canard3() {
             let _ = middleware.push_data(
-                "rocket",
+                &store,
                 "canard 3 commanded",
                 TelemetryData::new().with_value(canard3.commanded() as f64),
             );
             let _ = middleware.push_data(
-                "rocket",
+                &store,
                 "canard 3 actual",
                 TelemetryData::new().with_value(canard3.actual() as f64),
             );
         }
         if let Some(canard4) = packet.canard4() {
             let _ = middleware.push_data(
-                "rocket",
+                &store,
                 "canard 4 commanded",
                 TelemetryData::new().with_value(canard4.commanded() as f64),
             );
             let _ = middleware.push_data(
-                "rocket",
+                &store,
                 "canard 4 actual",
                 TelemetryData::new().with_value(canard4.actual() as f64),
             );
@@ -612,13 +1365,14 @@ fn handle_camera_packet(
             let mut covariance_index = 0;
             for val in covariance {
                 let _ = middleware.push_data(
-                    "rocket",
+                    &store,
                     &format!("covariance {}",covariance_index).to_string(), 
                     TelemetryData::new().with_value(val as f64),
                 );
                 covariance_index +=1;
             };
         };
+        middleware.end_packet(&store);
     }
 
     fn handle_payload_packet(
@@ -626,39 +1380,40 @@ fn handle_camera_packet(
         middleware: &mut tokio::sync::MutexGuard<'_, Middleware>,
         packet: hprc::PayloadTelemetryPacket<'_>,
     ) {
+        let store = middleware.session_store(self.link.source(), "payload");
         let _ = middleware.push_data(
-            "payload",
+            &store,
             "state",
             TelemetryData::new().with_value(packet.state().0 as u32),
         );
 
         if let Some(shared) = packet.shared() {
-            self.handle_shared(middleware, shared, "payload".to_string());
+            self.handle_shared(middleware, shared, store.clone());
         };
         if let Some(sensors) = packet.sensor_values() {
-            self.handle_sensors(middleware, &sensors, "payload".to_string());
+            self.handle_sensors(middleware, &sensors, store.clone());
         };
         if let Some(ekf) = packet.ekf_values() {
-            self.handle_ekf(middleware, ekf, "payload".to_string());
+            self.handle_ekf(middleware, ekf, store.clone());
         };
 
         if let Some(self_righting1_servo) = packet.self_righting1_servo() {
-            let _ = middleware.push_data("payload", "self_righting1_servo", 
+            let _ = middleware.push_data(&store, "self_righting1_servo", 
         TelemetryData::new().with_value(self_righting1_servo.commanded() as f64));
         }
 
         if let Some(self_righting2_servo) = packet.self_righting2_servo() {
-            let _ = middleware.push_data("payload", "self_righting2_servo", 
+            let _ = middleware.push_data(&store, "self_righting2_servo", 
         TelemetryData::new().with_value(self_righting2_servo.commanded() as f64));
         }
 
         if let Some(latch_servo) = packet.latch_servo() {
-            let _ = middleware.push_data("payload", "latch_servo", 
+            let _ = middleware.push_data(&store, "latch_servo", 
         TelemetryData::new().with_value(latch_servo.commanded() as f64));
         }
 
         if let Some(antenna_servo) = packet.antenna_servo() {
-            let _ = middleware.push_data("payload", "antenna_servo", 
+            let _ = middleware.push_data(&store, "antenna_servo", 
         TelemetryData::new().with_value(antenna_servo.commanded() as f64));
         }
 
@@ -666,64 +1421,65 @@ fn handle_camera_packet(
             for blob in blob_data {
                 
                 let _ = middleware.push_data(
-                    "payload", 
+                    &store, 
                     &format!("blob_x{}",blob.index()).to_string(), 
                     TelemetryData::new().with_value(blob.x() as i32));
                 let _ = middleware.push_data(
-                    "payload", 
+                    &store, 
                     &format!("blob_y{}",blob.index()).to_string(), 
                     TelemetryData::new().with_value(blob.y() as i32));
                 let _ = middleware.push_data(
-                    "payload", 
+                    &store, 
                     &format!("blob_width{}",blob.index()).to_string(), 
                     TelemetryData::new().with_value(blob.width() as i32));
                 let _ = middleware.push_data(
-                    "payload", 
+                    &store, 
                     &format!("blob_height{}",blob.index()).to_string(), 
                     TelemetryData::new().with_value(blob.height() as i32));
                 let _ = middleware.push_data(
-                    "payload", 
+                    &store, 
                     &format!("blob_ellipse_a{}",blob.index()).to_string(), 
                     TelemetryData::new().with_value(blob.ellipse_a() as i32));
                 let _ = middleware.push_data(
-                    "payload", 
+                    &store, 
                     &format!("blob_ellipse_b{}",blob.index()).to_string(), 
                     TelemetryData::new().with_value(blob.ellipse_b() as i32));
                 let _ = middleware.push_data(
-                    "payload", 
+                    &store, 
                     &format!("blob_rotation{}",blob.index()).to_string(), 
                     TelemetryData::new().with_value(blob.rotation() as i32));
                 let _ = middleware.push_data(
-                    "payload", 
+                    &store, 
                     &format!("blob_confidence{}",blob.index()).to_string(), 
                     TelemetryData::new().with_value(blob.confidence() as f64));
             }
         }
 
         let _ = middleware.push_data(
-            "payload", 
+            &store, 
             "horiz_x1", 
             TelemetryData::new().with_value(packet.horiz_x1() as i32));
 
         let _ = middleware.push_data(
-            "payload", 
+            &store, 
             "horiz_x2", 
             TelemetryData::new().with_value(packet.horiz_x2() as i32));
 
         let _ = middleware.push_data(
-            "payload", 
+            &store, 
             "horiz_y1", 
             TelemetryData::new().with_value(packet.horiz_y1() as i32));
 
         let _ = middleware.push_data(
-            "payload", 
+            &store, 
             "horiz_y2", 
             TelemetryData::new().with_value(packet.horiz_y2() as i32));
 
         let _ = middleware.push_data(
-            "payload", 
+            &store, 
             "horiz_valid", 
             TelemetryData::new().with_value(packet.horiz_valid()));
+        middleware.end_packet(&store);
     }
 
     fn handle_shared(
@@ -732,41 +1488,7 @@ fn handle_camera_packet(
         shared: &hprc::Shared,
         name: String,
     ) {
-        let _ = middleware.push_data(
-            &name,
-            "time_from_boot",
-            TelemetryData::new().with_value(shared.time_from_boot()),
-        );
-        let _ = middleware.push_data(
-            &name,
-            "loop_count",
-            TelemetryData::new().with_value(shared.loop_count()),
-        );
-        let _ = middleware.push_data(
-            &name,
-            "sd_file_no",
-            TelemetryData::new().with_value(shared.sd_file_no() as i32),
-        );
-        let _ = middleware.push_data(
-            &name,
-            "battery_voltage",
-            TelemetryData::new().with_value(shared.battery_voltage() as f64),
-        );
-        let _ = middleware.push_data(
-            &name,
-            "mosfet_current",
-            TelemetryData::new().with_value(shared.mosfet_current() as f64),
-        );
-        let _ = middleware.push_data(
-            &name,
-            "mosfet_state",
-            TelemetryData::new().with_value(shared.mosfet_state()),
-        );
-        let _ = middleware.push_data(
-            &name,
-            "last_command_received",
-            TelemetryData::new().with_value(shared.last_command_received() as u32),
-        );
+        let _ = middleware.push_flattened(&name, shared);
     }
 
     fn handle_sensors(
@@ -912,40 +1634,156 @@ fn handle_camera_packet(
         ekf: &hprc::EKF,
         name: String,
     ) {
-        let _ = middleware.push_data(&name, "w", TelemetryData::new().with_value(ekf.w() as f64));
-        let _ = middleware.push_data(&name, "i", TelemetryData::new().with_value(ekf.i() as f64));
-        let _ = middleware.push_data(&name, "j", TelemetryData::new().with_value(ekf.j() as f64));
-        let _ = middleware.push_data(&name, "k", TelemetryData::new().with_value(ekf.k() as f64));
-        let _ = middleware.push_data(
-            &name,
-            "pos_x",
-            TelemetryData::new().with_value(ekf.pos_x() as f64),
-        );
-        let _ = middleware.push_data(
-            &name,
-            "pos_y",
-            TelemetryData::new().with_value(ekf.pos_y() as f64),
-        );
-        let _ = middleware.push_data(
-            &name,
-            "pos_z",
-            TelemetryData::new().with_value(ekf.pos_z() as f64),
-        );
-        let _ = middleware.push_data(
-            &name,
-            "vel_x",
-            TelemetryData::new().with_value(ekf.vel_x() as f64),
-        );
-        let _ = middleware.push_data(
-            &name,
-            "vel_y",
-            TelemetryData::new().with_value(ekf.vel_y() as f64),
-        );
-        let _ = middleware.push_data(
-            &name,
-            "vel_z",
-            TelemetryData::new().with_value(ekf.vel_z() as f64),
-        );
+        let _ = middleware.push_flattened(&name, ekf);
+    }
+}
+
+// ── Flattening ────────────────────────────────────────────────────────────────
+
+// these keep their accessor names as field names. Sensors stay hand written above,
+// their fields were named (asm330_accel0, temp) before this and the frontend uses them.
+flatten_fields!(hprc::Shared {
+    time_from_boot,
+    loop_count,
+    sd_file_no,
+    battery_voltage,
+    mosfet_current,
+    mosfet_state,
+    last_command_received,
+});
+flatten_fields!(hprc::EKF { w, i, j, k, pos_x, pos_y, pos_z, vel_x, vel_y, vel_z });
+
+// what handle_sensors pushes, in the same order
+const SENSOR_FIELDS: &[&str] = &[
+    "asm330_accel0", "asm330_accel1", "asm330_accel2", "asm330_gyr0", "asm330_gyr1", "asm330_gyr2",
+    "lsm6_accel0", "lsm6_accel1", "lsm6_accel2", "lsm6_gyr0", "lsm6_gyr1", "lsm6_gyr2",
+    "mag0", "mag1", "mag2",
+    "pressure", "temp",
+    "gps_lock", "satellites", "lat", "lon", "alt", "epoch_time",
+];
+
+/// The stores the vehicles' packets go into and the fields each one gets, from the
+/// generated packet types, so every flight's CSVs start with the same columns
+pub fn store_schemas() -> Vec<(&'static str, Vec<String>)> {
+    let mut vehicle = flatten::field_names::<hprc::Shared<'_>>("");
+    vehicle.extend(SENSOR_FIELDS.iter().map(|f| f.to_string()));
+    vehicle.extend(flatten::field_names::<hprc::EKF<'_>>(""));
+    vec![("rocket", vehicle.clone()), ("payload", vehicle)]
+}
+
+/// Units for the vehicle fields, by bare field name so they cover both vehicles. The
+/// raw IMU, mag and pressure channels are left out, their scaling depends on how the
+/// board set the sensors up.
+pub fn field_metadata() -> BTreeMap<String, FieldMetadata> {
+    let fields = [
+        ("battery_voltage", FieldMetadata::unit("V").named("Battery voltage")),
+        ("mosfet_current", FieldMetadata::unit("A").named("MOSFET current")),
+        ("temp", FieldMetadata::unit("°C").named("Temperature")),
+        ("lat", FieldMetadata::unit("deg").named("Latitude").range(-90.0, 90.0)),
+        ("lon", FieldMetadata::unit("deg").named("Longitude").range(-180.0, 180.0)),
+        ("alt", FieldMetadata::unit("m").named("GPS altitude")),
+        ("pos_x", FieldMetadata::unit("m").named("EKF position x")),
+        ("pos_y", FieldMetadata::unit("m").named("EKF position y")),
+        ("pos_z", FieldMetadata::unit("m").named("EKF position z")),
+        ("vel_x", FieldMetadata::unit("m/s").named("EKF velocity x")),
+        ("vel_y", FieldMetadata::unit("m/s").named("EKF velocity y")),
+        ("vel_z", FieldMetadata::unit("m/s").named("EKF velocity z")),
+    ];
+    fields.into_iter().map(|(field, metadata)| (field.to_string(), metadata)).collect()
+}
+
+// ── Framing ───────────────────────────────────────────────────────────────────
+
+// write raw reads to the capture file if one is running
+fn tap(capture: &CaptureTap, data: &[u8]) {
+    let mut capture = capture.lock();
+    if let Some(writer) = capture.as_mut() {
+        if let Err(e) = writer.record(data) {
+            tracing::error!("telem_radio: capture to {} failed, stopping it: {e}", writer.path().display());
+            *capture = None;
+        }
+    }
+}
+
+// how frames are delimited on the wire. The KV0R header is inside the frame either
+// way (it's the callsign, it has to go out on air), COBS adds zero delimiters around
+// it so a corrupted length byte can't swallow the frames after it. LengthDelimited is
+// a varint length before each frame, for bridges and radios that speak protobuf style
+// delimited streams
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum LinkFraming {
+    #[default]
+    Callsign,
+    Cobs,
+    LengthDelimited,
+}
+
+// bytes off the port (or a capture) -> frames, whichever framing the link uses
+enum Deframer {
+    Callsign(Vec<u8>),
+    Cobs(CobsDecoder),
+    LengthDelimited(DelimitedDecoder),
+}
+
+impl Deframer {
+    fn new(framing: LinkFraming) -> Self {
+        match framing {
+            LinkFraming::Callsign => Deframer::Callsign(Vec::new()),
+            LinkFraming::Cobs => Deframer::Cobs(CobsDecoder::new()),
+            LinkFraming::LengthDelimited => Deframer::LengthDelimited(DelimitedDecoder::new()),
+        }
+    }
+
+    fn push(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        match self {
+            Deframer::Callsign(accumulator) => {
+                accumulator.extend_from_slice(data);
+                std::iter::from_fn(|| next_frame(accumulator)).collect()
+            }
+            Deframer::Cobs(decoder) => decoder.push(data),
+            Deframer::LengthDelimited(decoder) => decoder.push(data),
+        }
+    }
+
+    // the callsign deframer just skips to the next header, so it doesn't count these
+    fn frame_errors(&self) -> u64 {
+        match self {
+            Deframer::Callsign(_) => 0,
+            Deframer::Cobs(decoder) => decoder.errors(),
+            Deframer::LengthDelimited(decoder) => decoder.errors(),
+        }
+    }
+}
+
+fn frame_out(framing: LinkFraming, payload: &[u8]) -> Vec<u8> {
+    match framing {
+        LinkFraming::Callsign => frame_payload(payload),
+        LinkFraming::Cobs => cobs::encode(&frame_payload(payload)),
+        LinkFraming::LengthDelimited => length_delimited::encode(&frame_payload(payload)),
+    }
+}
+
+// Which store a telemetry packet feeds, and whether it came in with the trimmed-down
+// field set the flight computer uses for post-landing beacons
+fn telemetry_source(packet: &hprc::Packet<'_>) -> Option<(&'static str, bool)> {
+    match packet.packet_type() {
+        hprc::PacketUnion::Rocket30KTelemetryPacket => {
+            let p = packet.packet_as_rocket_30_ktelemetry_packet()?;
+            Some(("rocket", p.sensor_values().is_none() && p.ekf_values().is_none()))
+        }
+        hprc::PacketUnion::Rocket2StageTelemetryPacket => {
+            let p = packet.packet_as_rocket_2_stage_telemetry_packet()?;
+            Some(("rocket", p.sensor_values().is_none() && p.ekf_values().is_none()))
+        }
+        hprc::PacketUnion::RocketCanardsTelemetryPacket => {
+            let p = packet.packet_as_rocket_canards_telemetry_packet()?;
+            Some(("rocket", p.sensor_values().is_none() && p.ekf_values().is_none()))
+        }
+        hprc::PacketUnion::PayloadTelemetryPacket => {
+            let p = packet.packet_as_payload_telemetry_packet()?;
+            Some(("payload", p.sensor_values().is_none() && p.ekf_values().is_none()))
+        }
+        _ => None,
     }
 }
 
@@ -954,5 +1792,8 @@ fn handle_camera_packet(
 enum RunResult {
     Shutdown,
     PortChanged(String),
+    Replay(PathBuf),
+    ReplayFinished,
+    FramingChanged,
     Error(String),
 }
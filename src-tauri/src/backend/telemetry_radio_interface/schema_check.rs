@@ -0,0 +1,202 @@
+// Packets that aren't in this build's schema
+//
+// The pinned telemetry-2026 Packet table has no version field, so a packet from other
+// flight software can only be told by how it decodes: it fails to verify against the
+// current tables, or its packet type isn't one this build knows. Either way it's tried
+// against the old bindings kept in src/generated, for boards still on the 2024/2025
+// firmware, and up-converted into the fields the current handlers push. Anything that
+// isn't a legacy packet either is dropped with a schema_mismatch event.
+//
+// A legacy packet that happens to verify as a current one with a known type can't be
+// caught this way, that takes a version field in the schema.
+use serde::Serialize;
+
+use super::hprc;
+use crate::middleware::telemetry_stores::TelemetryValue;
+
+#[path = "../../generated/Packet_generated.rs"]
+#[allow(deprecated, non_camel_case_types, non_snake_case, clippy::all)]
+mod legacy_generated;
+use legacy_generated::hprc as legacy;
+
+pub const SCHEMA_MISMATCH_EVENT: &str = "schema_mismatch";
+
+/// Why a packet doesn't look like one of this build's, None if it does
+pub fn mismatch(decoded: &Result<hprc::Packet<'_>, flatbuffers::InvalidFlatbuffer>) -> Option<String> {
+    match decoded {
+        Ok(packet) if packet.packet_type().variant_name().is_none() => {
+            Some(format!("packet type {}", packet.packet_type().0))
+        }
+        Ok(_) => None,
+        Err(e) => Some(e.to_string()),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaMismatchEvent {
+    pub source: &'static str,
+    pub timestamp: i64,
+    pub reason: String,
+    pub length: usize,
+}
+
+// a legacy packet, as the store fields the current handlers would have pushed
+pub struct Upconverted {
+    pub store: &'static str,
+    pub packet_type: String,
+    pub reduced: bool,
+    pub fields: Vec<(String, TelemetryValue)>,
+    // our own uplink heard back, nothing to store
+    pub uplink: bool,
+}
+
+struct Fields(Vec<(String, TelemetryValue)>);
+
+impl Fields {
+    fn push(&mut self, name: impl Into<String>, value: impl Into<TelemetryValue>) {
+        self.0.push((name.into(), value.into()));
+    }
+
+    fn shared(&mut self, shared: Option<&legacy::Shared>) {
+        let Some(shared) = shared else { return };
+        self.push("time_from_boot", shared.time_from_boot());
+        self.push("loop_count", shared.loop_count());
+        self.push("sd_file_no", shared.sd_file_no() as i32);
+        self.push("battery_voltage", shared.battery_voltage() as f64);
+    }
+
+    // the old boards had two IMUs in the same slots the ASM330 and LSM6 took over
+    fn sensors(&mut self, sensors: Option<&legacy::Sensors>) {
+        let Some(s) = sensors else { return };
+        self.push("asm330_accel0", s.acc_1_x() as f64);
+        self.push("asm330_accel1", s.acc_1_y() as f64);
+        self.push("asm330_accel2", s.acc_1_z() as f64);
+        self.push("asm330_gyr0", s.gyro_1_x() as f64);
+        self.push("asm330_gyr1", s.gyro_1_y() as f64);
+        self.push("asm330_gyr2", s.gyro_1_z() as f64);
+        self.push("lsm6_accel0", s.acc_2_x() as f64);
+        self.push("lsm6_accel1", s.acc_2_y() as f64);
+        self.push("lsm6_accel2", s.acc_2_z() as f64);
+        self.push("lsm6_gyr0", s.gyro_2_x() as f64);
+        self.push("lsm6_gyr1", s.gyro_2_y() as f64);
+        self.push("lsm6_gyr2", s.gyro_2_z() as f64);
+        self.push("mag0", s.mag_x() as f64);
+        self.push("mag1", s.mag_y() as f64);
+        self.push("mag2", s.mag_z() as f64);
+        self.push("pressure", s.pressure() as f64);
+        self.push("temp", s.temperature() as f64);
+    }
+
+    fn ekf(&mut self, ekf: Option<&legacy::EKF>) {
+        let Some(e) = ekf else { return };
+        self.push("w", e.w() as f64);
+        self.push("i", e.i() as f64);
+        self.push("j", e.j() as f64);
+        self.push("k", e.k() as f64);
+        self.push("pos_x", e.pos_x() as f64);
+        self.push("pos_y", e.pos_y() as f64);
+        self.push("pos_z", e.pos_z() as f64);
+        self.push("vel_x", e.vel_x() as f64);
+        self.push("vel_y", e.vel_y() as f64);
+        self.push("vel_z", e.vel_z() as f64);
+    }
+
+    // the old GPS sent ECEF, the stores have always been lat/lon/alt
+    fn gps(&mut self, gps: Option<&legacy::GPS>) {
+        let Some(g) = gps else { return };
+        self.push("gps_lock", g.has_lock());
+        self.push("satellites", g.satellites() as u32);
+        self.push("epoch_time", g.epoch_time());
+        if g.has_lock() {
+            let (lat, lon, alt) = ecef_to_geodetic(g.ecef_x(), g.ecef_y(), g.ecef_z());
+            self.push("lat", lat);
+            self.push("lon", lon);
+            self.push("alt", alt);
+        }
+    }
+}
+
+/// Decode an unversioned packet with the old bindings and map it onto today's fields
+pub fn upconvert(payload: &[u8]) -> Result<Upconverted, String> {
+    let packet = legacy::root_as_packet(payload).map_err(|e| e.to_string())?;
+    let mut fields = Fields(Vec::new());
+    let packet_type = format!("{:?} (legacy)", packet.packet_type());
+    let (store, reduced) = match packet.packet_type() {
+        legacy::PacketUnion::Rocket30KTelemetryPacket => {
+            let p = packet.packet_as_rocket_30_ktelemetry_packet().ok_or("missing packet body")?;
+            fields.push("state", p.state().0 as u32);
+            fields.shared(p.shared());
+            fields.sensors(p.sensor_values());
+            fields.gps(p.gps_values());
+            fields.ekf(p.ekf_values());
+            ("rocket", p.sensor_values().is_none() && p.ekf_values().is_none())
+        }
+        legacy::PacketUnion::Rocket2StageTelemetryPacket => {
+            let p = packet.packet_as_rocket_2_stage_telemetry_packet().ok_or("missing packet body")?;
+            fields.push("state", p.state().0 as u32);
+            fields.shared(p.shared());
+            fields.sensors(p.sensor_values());
+            fields.ekf(p.ekf_values());
+            ("rocket", p.sensor_values().is_none() && p.ekf_values().is_none())
+        }
+        legacy::PacketUnion::RocketCanardsTelemetryPacket => {
+            let p = packet.packet_as_rocket_canards_telemetry_packet().ok_or("missing packet body")?;
+            fields.push("state", p.state().0 as u32);
+            fields.shared(p.shared());
+            fields.sensors(p.sensor_values());
+            fields.ekf(p.ekf_values());
+            for (n, canard) in [p.canard1(), p.canard2(), p.canard3(), p.canard4()].into_iter().enumerate() {
+                if let Some(canard) = canard {
+                    fields.push(format!("canard {} commanded", n + 1), canard.commanded() as f64);
+                    fields.push(format!("canard {} actual", n + 1), canard.actual() as f64);
+                }
+            }
+            if let Some(covariance) = p.covariance_diagonal() {
+                for (n, value) in covariance.iter().enumerate() {
+                    fields.push(format!("covariance {n}"), value as f64);
+                }
+            }
+            ("rocket", p.sensor_values().is_none() && p.ekf_values().is_none())
+        }
+        legacy::PacketUnion::PayloadTelemetryPacket => {
+            let p = packet.packet_as_payload_telemetry_packet().ok_or("missing packet body")?;
+            fields.push("state", p.state().0 as u32);
+            fields.shared(p.shared());
+            fields.sensors(p.sensor_values());
+            fields.ekf(p.ekf_values());
+            let servos = [
+                ("self_righting1_servo", p.self_righting1_servo()),
+                ("self_righting2_servo", p.self_righting2_servo()),
+                ("latch_servo", p.latch_servo()),
+                ("antenna_servo", p.antenna_servo()),
+            ];
+            for (name, servo) in servos {
+                if let Some(servo) = servo {
+                    fields.push(name, servo.commanded() as f64);
+                }
+            }
+            ("payload", p.sensor_values().is_none() && p.ekf_values().is_none())
+        }
+        legacy::PacketUnion::RemoteControl => {
+            return Ok(Upconverted { store: "", packet_type, reduced: false, fields: Vec::new(), uplink: true });
+        }
+        other => return Err(format!("no legacy route for packet type {}", other.0)),
+    };
+    Ok(Upconverted { store, packet_type, reduced, fields: fields.0, uplink: false })
+}
+
+// WGS84, Bowring's method: well under a metre of error at any altitude we fly to
+fn ecef_to_geodetic(x: f64, y: f64, z: f64) -> (f64, f64, f64) {
+    const A: f64 = 6_378_137.0;
+    const F: f64 = 1.0 / 298.257_223_563;
+    let b = A * (1.0 - F);
+    let e2 = F * (2.0 - F);
+    let ep2 = (A * A - b * b) / (b * b);
+
+    let p = x.hypot(y);
+    let theta = (z * A).atan2(p * b);
+    let lat = (z + ep2 * b * theta.sin().powi(3)).atan2(p - e2 * A * theta.cos().powi(3));
+    let n = A / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+    let alt = p / lat.cos() - n;
+    (lat.to_degrees(), y.atan2(x).to_degrees(), alt)
+}
@@ -0,0 +1,275 @@
+// Protocol conformance vectors, the frames both sides agree on
+//
+// Each vector is one whole KV0R frame as it comes off the air and what the ground
+// station's decoder has to make of it. The built in set covers the cases that have
+// bitten us: a good packet, a CRC that doesn't match, a frame cut off mid-air, a
+// payload too short to be a flatbuffer and a packet type this build doesn't know.
+// The flight software keeps the same vectors as JSON (write() produces it, load()
+// reads theirs back), so a schema or framing change on either side shows up as a
+// failed vector before it shows up at the pad.
+//
+// decode() runs a frame through the same steps handle_frame does, minus auth (the
+// vectors are unsigned) and everything with side effects.
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::crc_check::{self, CrcMode};
+use super::schema_check;
+use super::{frame_body, hprc, next_frame};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Decoded,
+    // the length byte promises more than arrived, the deframer keeps waiting
+    Incomplete,
+    CrcFailed,
+    // framed and checksummed fine, but not a packet under either schema
+    Malformed,
+    // decodes, but the packet type isn't one this build knows
+    UnknownType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestVector {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub crc: CrcMode,
+    // the whole frame, hex
+    pub frame: String,
+    pub expect: Outcome,
+    // for Decoded, the packet type it has to decode as
+    #[serde(default)]
+    pub packet_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VectorResult {
+    pub name: String,
+    pub passed: bool,
+    pub expected: Outcome,
+    pub actual: Outcome,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConformanceReport {
+    pub passed: bool,
+    pub vectors: Vec<VectorResult>,
+}
+
+// what the decoder made of a frame, and the packet type or the reason
+pub fn decode(crc: CrcMode, frame: &[u8]) -> (Outcome, String) {
+    let mut accumulator = frame.to_vec();
+    let Some(frame) = next_frame(&mut accumulator) else {
+        return (Outcome::Incomplete, format!("no complete frame in {} bytes", accumulator.len()));
+    };
+    let checked = match crc_check::check(crc, &frame) {
+        Ok(checked) => checked,
+        Err(reason) => return (Outcome::CrcFailed, reason),
+    };
+    let payload = frame_body(checked);
+    let decoded = hprc::root_as_packet(payload);
+    let Some(reason) = schema_check::mismatch(&decoded) else {
+        // mismatch only passes a packet that decoded as a known type
+        let name = decoded.ok().and_then(|p| p.packet_type().variant_name());
+        return (Outcome::Decoded, name.unwrap_or_default().to_string());
+    };
+    match schema_check::upconvert(payload) {
+        Ok(upconverted) => (Outcome::Decoded, upconverted.packet_type),
+        // a packet, just not of a type this build knows
+        Err(_) if decoded.is_ok() => (Outcome::UnknownType, reason),
+        Err(e) => (Outcome::Malformed, format!("{reason}, and not a legacy packet: {e}")),
+    }
+}
+
+pub fn check(vector: &TestVector) -> VectorResult {
+    let (actual, detail) = match hex::decode(vector.frame.trim()) {
+        Ok(frame) => decode(vector.crc, &frame),
+        Err(e) => {
+            return VectorResult {
+                name: vector.name.clone(),
+                passed: false,
+                expected: vector.expect,
+                actual: Outcome::Malformed,
+                detail: format!("frame isn't valid hex: {e}"),
+            }
+        }
+    };
+    let type_ok = match (&vector.packet_type, actual) {
+        (Some(expected), Outcome::Decoded) => *expected == detail,
+        _ => true,
+    };
+    VectorResult {
+        name: vector.name.clone(),
+        passed: actual == vector.expect && type_ok,
+        expected: vector.expect,
+        actual,
+        detail,
+    }
+}
+
+pub fn run(vectors: &[TestVector]) -> ConformanceReport {
+    let vectors: Vec<VectorResult> = vectors.iter().map(check).collect();
+    ConformanceReport { passed: vectors.iter().all(|v| v.passed), vectors }
+}
+
+/// The vectors the flight software shares with us, a JSON array of TestVector
+pub fn load(path: &Path) -> Result<Vec<TestVector>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    serde_json::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// The built in vectors in the shared format, for the flight software's tests
+pub fn write(path: &Path) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(&builtin()).map_err(|e| e.to_string())?;
+    std::fs::write(path, text).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+// Reference frames, kept as the bytes that go over the air rather than built by our own
+// encoder and bindings, which would move the expectations along with any change to
+// them. They were encoded once with a bare flatbuffers builder, fields laid out the way
+// flatc's create() does: a PayloadControlPacket (union member 6) with throttle 0.5 and
+// rotation -0.25, KV0R header, CRC-16 little endian after the payload.
+const BUILTIN: &[(&str, &str, CrcMode, &str, Outcome, Option<&str>)] = &[
+    (
+        "valid",
+        "PayloadControlPacket, throttle 0.5, rotation -0.25, CRC-16",
+        CrcMode::Crc16Ccitt,
+        "4b5630522e0c00000008000c000700080008000000000000060c00000008000c0004000800080000000000003f000080be47d4",
+        Outcome::Decoded,
+        Some("PayloadControlPacket"),
+    ),
+    (
+        "valid_no_crc",
+        "the same packet on a link without a CRC",
+        CrcMode::None,
+        "4b5630522c0c00000008000c000700080008000000000000060c00000008000c0004000800080000000000003f000080be",
+        Outcome::Decoded,
+        Some("PayloadControlPacket"),
+    ),
+    (
+        "crc_corrupt",
+        "valid with bit 4 of payload byte 22 flipped after the CRC was computed",
+        CrcMode::Crc16Ccitt,
+        "4b5630522e0c00000008000c000700080008000000000000060c00100008000c0004000800080000000000003f000080be47d4",
+        Outcome::CrcFailed,
+        None,
+    ),
+    (
+        "truncated",
+        "valid with its last 4 bytes never arriving, the header still says the full length",
+        CrcMode::Crc16Ccitt,
+        "4b5630522e0c00000008000c000700080008000000000000060c00000008000c0004000800080000000000003f0000",
+        Outcome::Incomplete,
+        None,
+    ),
+    (
+        "truncated_payload",
+        "valid header and CRC around the first 22 bytes of the packet",
+        CrcMode::Crc16Ccitt,
+        "4b563052180c00000008000c000700080008000000000000060c0011a2",
+        Outcome::Malformed,
+        None,
+    ),
+    (
+        "unknown_type",
+        "packet type 250 around the same PayloadControlPacket table",
+        CrcMode::Crc16Ccitt,
+        "4b5630522e0c00000008000c000700080008000000000000fa0c00000008000c0004000800080000000000003f000080be2842",
+        Outcome::UnknownType,
+        None,
+    ),
+];
+
+pub fn builtin() -> Vec<TestVector> {
+    BUILTIN
+        .iter()
+        .map(|&(name, description, crc, frame, expect, packet_type)| TestVector {
+            name: name.into(),
+            description: description.into(),
+            crc,
+            frame: frame.into(),
+            expect,
+            packet_type: packet_type.map(str::to_string),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // what the reference frames carry
+    const TEST_THROTTLE: f32 = 0.5;
+    const TEST_ROTATION: f32 = -0.25;
+    // not a PacketUnion member, and not likely to become one
+    const UNKNOWN_PACKET_TYPE: u8 = 250;
+
+    fn builtin_vector(name: &str) -> TestVector {
+        builtin().into_iter().find(|v| v.name == name).unwrap_or_else(|| panic!("no built in vector {name}"))
+    }
+
+    // the packet inside a vector's frame, as the decoder would hand it on
+    fn payload_of(vector: &TestVector) -> Vec<u8> {
+        let mut accumulator = hex::decode(&vector.frame).unwrap();
+        let frame = next_frame(&mut accumulator).unwrap();
+        frame_body(crc_check::check(vector.crc, &frame).unwrap()).to_vec()
+    }
+
+    #[test]
+    fn builtin_vectors_have_the_expected_outcomes() {
+        let expected = [
+            ("valid", Outcome::Decoded),
+            ("valid_no_crc", Outcome::Decoded),
+            ("crc_corrupt", Outcome::CrcFailed),
+            ("truncated", Outcome::Incomplete),
+            ("truncated_payload", Outcome::Malformed),
+            ("unknown_type", Outcome::UnknownType),
+        ];
+        assert_eq!(builtin().len(), expected.len());
+        for (name, outcome) in expected {
+            let vector = builtin_vector(name);
+            assert_eq!(vector.expect, outcome, "{name}");
+            let result = check(&vector);
+            assert_eq!(result.actual, outcome, "{name}: {}", result.detail);
+            assert!(result.passed, "{name}: {}", result.detail);
+        }
+        assert!(run(&builtin()).passed);
+    }
+
+    #[test]
+    fn valid_vectors_decode_to_the_test_packet() {
+        for name in ["valid", "valid_no_crc"] {
+            let vector = builtin_vector(name);
+            let result = check(&vector);
+            assert_eq!(result.detail, "PayloadControlPacket", "{name}");
+            assert_eq!(vector.packet_type.as_deref(), Some("PayloadControlPacket"));
+
+            let payload = payload_of(&vector);
+            let packet = hprc::root_as_packet(&payload).unwrap();
+            let control = packet.packet_as_payload_control_packet().unwrap();
+            assert_eq!(control.throttle(), TEST_THROTTLE, "{name}");
+            assert_eq!(control.rotation(), TEST_ROTATION, "{name}");
+        }
+    }
+
+    #[test]
+    fn unknown_type_keeps_its_packet_type_number() {
+        let vector = builtin_vector("unknown_type");
+        let packet_type = hprc::root_as_packet(&payload_of(&vector)).unwrap().packet_type();
+        assert_eq!(packet_type.0, UNKNOWN_PACKET_TYPE);
+        assert_eq!(check(&vector).detail, format!("packet type {UNKNOWN_PACKET_TYPE}"));
+    }
+
+    #[test]
+    fn a_wrong_packet_type_fails_the_vector() {
+        let mut vector = builtin_vector("valid");
+        vector.packet_type = Some("RocketTelemetryPacket".into());
+        let result = check(&vector);
+        assert_eq!(result.actual, Outcome::Decoded);
+        assert!(!result.passed);
+    }
+}
@@ -0,0 +1,362 @@
+// Two-person rule for hazardous uplinks (arming, remote start, anything that can light a pyro)
+//
+// A hazardous command doesn't go out when it's sent. It becomes a pending uplink that a
+// second operator has to approve before the timeout, and only then goes to the radio.
+// Roles come from the window a call arrives on, never from the frontend: the operator
+// requests from the main window and the RSO confirms from the console, so the approval
+// has to come from a different window than the request. Which commands count as
+// hazardous is the RSO's call too, from the console, or the operator could empty the
+// list and send ArmFlight straight out. Every step (requested, approved, rejected,
+// expired, cancelled, refused, sent, config changed) is appended to the uplink audit log.
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::middleware::config_file::append_json_line;
+use crate::middleware::lock_timing::TimedMutex;
+
+pub const UPLINK_CONFIRMATION_EVENT: &str = "uplink_confirmation";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HazardConfig {
+    // command numbers that need a second operator
+    pub commands: Vec<u8>,
+    // how long the second operator has to approve
+    pub timeout_ms: u64,
+}
+
+impl Default for HazardConfig {
+    fn default() -> Self {
+        // ArmFlight and RemoteStartOn
+        Self { commands: vec![0, 3], timeout_ms: 30_000 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmState {
+    Pending,
+    Approved,
+    Rejected,
+    Expired,
+    // withdrawn by whoever asked for it
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingUplink {
+    pub id: u32,
+    pub command: u8,
+    pub requested_by: String,
+    pub requested_from: String,
+    pub requested_at: i64,
+    pub expires_at: i64,
+    pub state: ConfirmState,
+    pub decided_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UplinkAction {
+    Requested,
+    Approved,
+    // the requester tried to approve their own command
+    Refused,
+    Rejected,
+    Expired,
+    Cancelled,
+    Sent,
+    SendFailed,
+    // the hazard list or timeout changed, the new config in detail
+    ConfigChanged,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UplinkAuditRecord {
+    pub timestamp: i64,
+    // None for a config change, which isn't about any one uplink
+    pub id: Option<u32>,
+    pub command: Option<u8>,
+    pub action: UplinkAction,
+    pub operator_role: String,
+    pub window: String,
+    pub detail: Option<String>,
+}
+
+// shared by the radio handle clones, so every window sees the same pending list
+pub type SharedConfirmations = Arc<TimedMutex<UplinkConfirmations>>;
+
+pub struct UplinkConfirmations {
+    config: HazardConfig,
+    pending: Vec<PendingUplink>,
+    next_id: u32,
+    audit: Vec<UplinkAuditRecord>,
+    audit_path: PathBuf,
+}
+
+// which role each window speaks for, any other window can't take part
+const WINDOW_ROLES: &[(&str, &str)] = &[("main", "operator"), ("console", "rso")];
+// the only role that can change the hazard config
+const CONFIG_ROLE: &str = "rso";
+
+fn role(window: &str) -> Result<String, String> {
+    WINDOW_ROLES
+        .iter()
+        .find(|(label, _)| *label == window)
+        .map(|(_, role)| role.to_string())
+        .ok_or_else(|| format!("Window '{window}' has no operator role for hazardous commands"))
+}
+
+impl UplinkConfirmations {
+    pub fn new(audit_path: PathBuf) -> Self {
+        Self {
+            config: HazardConfig::default(),
+            pending: Vec::new(),
+            next_id: 0,
+            audit: Vec::new(),
+            audit_path,
+        }
+    }
+
+    pub fn shared(audit_path: PathBuf) -> SharedConfirmations {
+        Arc::new(TimedMutex::new("radio.confirmations", Self::new(audit_path)))
+    }
+
+    pub fn config(&self) -> HazardConfig {
+        self.config.clone()
+    }
+
+    pub fn set_config(&mut self, config: HazardConfig, window: &str) -> Result<(), String> {
+        let changed_by = role(window)?;
+        if changed_by != CONFIG_ROLE {
+            return Err("The hazardous command list can only be changed from the RSO console".into());
+        }
+        if config.timeout_ms == 0 {
+            return Err("Confirmation timeout must be positive".into());
+        }
+        let detail = format!("commands {:?}, timeout {} ms", config.commands, config.timeout_ms);
+        self.config = config;
+        self.record(chrono::Utc::now().timestamp_millis(), None, UplinkAction::ConfigChanged, &changed_by, window, Some(detail));
+        Ok(())
+    }
+
+    pub fn is_hazardous(&self, command: u8) -> bool {
+        self.config.commands.contains(&command)
+    }
+
+    fn audit(&mut self, uplink: &PendingUplink, action: UplinkAction, operator_role: &str, window: &str, detail: Option<String>) {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.audit_at(now, uplink, action, operator_role, window, detail);
+    }
+
+    fn audit_at(&mut self, timestamp: i64, uplink: &PendingUplink, action: UplinkAction, operator_role: &str, window: &str, detail: Option<String>) {
+        self.record(timestamp, Some(uplink), action, operator_role, window, detail);
+    }
+
+    fn record(&mut self, timestamp: i64, uplink: Option<&PendingUplink>, action: UplinkAction, operator_role: &str, window: &str, detail: Option<String>) {
+        let record = UplinkAuditRecord {
+            timestamp,
+            id: uplink.map(|u| u.id),
+            command: uplink.map(|u| u.command),
+            action,
+            operator_role: operator_role.to_string(),
+            window: window.to_string(),
+            detail,
+        };
+        if let Err(e) = append_json_line(&self.audit_path, &record) {
+            eprintln!("[uplink_confirm] Failed to write audit record: {e}");
+        }
+        self.audit.push(record);
+    }
+
+    /// Mark anything past its deadline as expired, returning what just expired
+    pub fn expire(&mut self) -> Vec<PendingUplink> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut expired = Vec::new();
+        for uplink in self.pending.iter_mut() {
+            if uplink.state == ConfirmState::Pending && now >= uplink.expires_at {
+                uplink.state = ConfirmState::Expired;
+                expired.push(uplink.clone());
+            }
+        }
+        // noticed late or not, it expired at its deadline
+        for uplink in &expired {
+            self.audit_at(uplink.expires_at, uplink, UplinkAction::Expired, "", "", None);
+        }
+        expired
+    }
+
+    pub fn request(&mut self, command: u8, window: &str) -> Result<PendingUplink, String> {
+        let requested_by = role(window)?;
+        self.expire();
+        // decided ones stay until the next request so the frontend can show the outcome
+        self.pending.retain(|u| u.state == ConfirmState::Pending);
+
+        self.next_id = self.next_id.wrapping_add(1);
+        let now = chrono::Utc::now().timestamp_millis();
+        let uplink = PendingUplink {
+            id: self.next_id,
+            command,
+            requested_by: requested_by.clone(),
+            requested_from: window.to_string(),
+            requested_at: now,
+            expires_at: now + self.config.timeout_ms as i64,
+            state: ConfirmState::Pending,
+            decided_by: None,
+        };
+        self.audit(&uplink, UplinkAction::Requested, &requested_by, window, None);
+        self.pending.push(uplink.clone());
+        Ok(uplink)
+    }
+
+    fn decide(&mut self, id: u32, window: &str) -> Result<(usize, String), String> {
+        let decided_by = role(window)?;
+        self.expire();
+        let index = self
+            .pending
+            .iter()
+            .position(|u| u.id == id)
+            .ok_or_else(|| format!("No pending uplink {id}"))?;
+        match self.pending[index].state {
+            ConfirmState::Pending => Ok((index, decided_by)),
+            state => Err(format!("Uplink {id} is already {}", format!("{state:?}").to_lowercase())),
+        }
+    }
+
+    /// Approve a pending uplink, which the caller then sends. The approval has to come
+    /// from a different window than the request.
+    pub fn approve(&mut self, id: u32, window: &str) -> Result<PendingUplink, String> {
+        let (index, decided_by) = self.decide(id, window)?;
+        if window == self.pending[index].requested_from {
+            let uplink = self.pending[index].clone();
+            self.audit(&uplink, UplinkAction::Refused, &decided_by, window, None);
+            return Err("A hazardous command has to be approved by a second operator".into());
+        }
+        let uplink = &mut self.pending[index];
+        uplink.state = ConfirmState::Approved;
+        uplink.decided_by = Some(decided_by.clone());
+        let uplink = uplink.clone();
+        self.audit(&uplink, UplinkAction::Approved, &decided_by, window, None);
+        Ok(uplink)
+    }
+
+    // the requester rejecting their own command is withdrawing it
+    pub fn reject(&mut self, id: u32, window: &str) -> Result<PendingUplink, String> {
+        let (index, decided_by) = self.decide(id, window)?;
+        let uplink = &mut self.pending[index];
+        let (state, action) = if window == uplink.requested_from {
+            (ConfirmState::Cancelled, UplinkAction::Cancelled)
+        } else {
+            (ConfirmState::Rejected, UplinkAction::Rejected)
+        };
+        uplink.state = state;
+        uplink.decided_by = Some(decided_by.clone());
+        let uplink = uplink.clone();
+        self.audit(&uplink, action, &decided_by, window, None);
+        Ok(uplink)
+    }
+
+    pub fn sent(&mut self, uplink: &PendingUplink, result: &Result<(), String>, window: &str) {
+        let role = uplink.decided_by.clone().unwrap_or_default();
+        match result {
+            Ok(()) => self.audit(uplink, UplinkAction::Sent, &role, window, None),
+            Err(e) => self.audit(uplink, UplinkAction::SendFailed, &role, window, Some(e.clone())),
+        }
+    }
+
+    pub fn pending(&mut self) -> Vec<PendingUplink> {
+        self.expire();
+        self.pending.clone()
+    }
+
+    pub fn audit_log(&self) -> Vec<UplinkAuditRecord> {
+        self.audit.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARM_FLIGHT: u8 = 0;
+
+    fn confirmations(name: &str) -> UplinkConfirmations {
+        let path = std::env::temp_dir().join(format!("uplink_confirm_{name}_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        UplinkConfirmations::new(path)
+    }
+
+    fn actions(confirmations: &UplinkConfirmations) -> Vec<String> {
+        confirmations.audit_log().iter().map(|r| format!("{:?}", r.action)).collect()
+    }
+
+    #[test]
+    fn the_requesting_window_cannot_approve() {
+        let mut confirmations = confirmations("self_approval");
+        let uplink = confirmations.request(ARM_FLIGHT, "main").unwrap();
+        assert!(confirmations.approve(uplink.id, "main").is_err());
+        assert_eq!(confirmations.pending()[0].state, ConfirmState::Pending);
+
+        let approved = confirmations.approve(uplink.id, "console").unwrap();
+        assert_eq!(approved.state, ConfirmState::Approved);
+        assert_eq!(approved.decided_by.as_deref(), Some("rso"));
+        assert_eq!(actions(&confirmations), ["Requested", "Refused", "Approved"]);
+    }
+
+    #[test]
+    fn windows_without_a_role_take_no_part() {
+        let mut confirmations = confirmations("no_role");
+        assert!(confirmations.request(ARM_FLIGHT, "rocket-dashboard").is_err());
+        let uplink = confirmations.request(ARM_FLIGHT, "main").unwrap();
+        assert!(confirmations.approve(uplink.id, "rocket-dashboard").is_err());
+        assert_eq!(actions(&confirmations), ["Requested"]);
+    }
+
+    #[test]
+    fn an_unapproved_uplink_expires_and_cannot_be_approved_after() {
+        let mut confirmations = confirmations("expiry");
+        confirmations.set_config(HazardConfig { commands: vec![ARM_FLIGHT], timeout_ms: 1 }, "console").unwrap();
+        let uplink = confirmations.request(ARM_FLIGHT, "main").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+
+        let expired = confirmations.expire();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].state, ConfirmState::Expired);
+        // only reported the once
+        assert!(confirmations.expire().is_empty());
+        assert!(confirmations.approve(uplink.id, "console").is_err());
+
+        let record = confirmations.audit_log().into_iter().find(|r| matches!(r.action, UplinkAction::Expired)).unwrap();
+        assert_eq!(record.timestamp, uplink.expires_at);
+    }
+
+    #[test]
+    fn the_requester_cancels_and_the_other_window_rejects() {
+        let mut confirmations = confirmations("cancel_reject");
+        let cancelled = confirmations.request(ARM_FLIGHT, "main").unwrap();
+        let rejected = confirmations.request(ARM_FLIGHT, "main").unwrap();
+
+        assert_eq!(confirmations.reject(cancelled.id, "main").unwrap().state, ConfirmState::Cancelled);
+        assert_eq!(confirmations.reject(rejected.id, "console").unwrap().state, ConfirmState::Rejected);
+        // decided either way, so neither can be approved now
+        assert!(confirmations.approve(cancelled.id, "console").is_err());
+        assert!(confirmations.approve(rejected.id, "console").is_err());
+        assert_eq!(actions(&confirmations), ["Requested", "Requested", "Cancelled", "Rejected"]);
+    }
+
+    #[test]
+    fn only_the_rso_console_changes_the_hazard_list() {
+        let mut confirmations = confirmations("config");
+        let empty = HazardConfig { commands: Vec::new(), timeout_ms: 30_000 };
+        assert!(confirmations.set_config(empty.clone(), "main").is_err());
+        assert!(confirmations.is_hazardous(ARM_FLIGHT));
+
+        confirmations.set_config(empty, "console").unwrap();
+        assert!(!confirmations.is_hazardous(ARM_FLIGHT));
+        let record = &confirmations.audit_log()[0];
+        assert!(matches!(record.action, UplinkAction::ConfigChanged));
+        assert_eq!((record.id, record.command), (None, None));
+        assert_eq!(record.operator_role, "rso");
+    }
+}
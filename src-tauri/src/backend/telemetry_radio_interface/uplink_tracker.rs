@@ -0,0 +1,126 @@
+// Delivery tracking for uplinked commands
+//
+// Every command the radio writes out is tracked from there: it's acked once a vehicle
+// packet stamped after the send reports it as last_command_received, and timed out if
+// none does within ACK_TIMEOUT_MS. Each change goes out as UPLINK_STATUS_EVENT. The
+// vehicle only reports the last command it got, so the same command sent twice is
+// acked by the first one's echo; nothing better can be done without an ack packet.
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::middleware::lock_timing::TimedMutex;
+
+pub const UPLINK_STATUS_EVENT: &str = "uplink_status";
+// same as a macro step's default
+const ACK_TIMEOUT_MS: i64 = 3000;
+// finished uplinks kept for the UI
+const HISTORY: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryState {
+    Sent,
+    Acked,
+    TimedOut,
+    // never made it onto the wire
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackedUplink {
+    // the command_number it went out with
+    pub id: u16,
+    pub command: u8,
+    pub source: &'static str,
+    pub sent_at: i64,
+    pub state: DeliveryState,
+    pub acked_at: Option<i64>,
+    // which store's packet acked it
+    pub acked_by: Option<String>,
+    pub error: Option<String>,
+}
+
+pub type SharedUplinks = Arc<TimedMutex<UplinkTracker>>;
+
+#[derive(Default)]
+pub struct UplinkTracker {
+    uplinks: VecDeque<TrackedUplink>,
+}
+
+impl UplinkTracker {
+    pub fn shared() -> SharedUplinks {
+        Arc::new(TimedMutex::new("radio.uplinks", Self::default()))
+    }
+
+    fn push(&mut self, uplink: TrackedUplink) -> TrackedUplink {
+        if self.uplinks.len() >= HISTORY {
+            // drop the oldest finished one, never one still waiting
+            if let Some(i) = self.uplinks.iter().position(|u| u.state != DeliveryState::Sent) {
+                self.uplinks.remove(i);
+            }
+        }
+        self.uplinks.push_back(uplink.clone());
+        uplink
+    }
+
+    pub fn sent(&mut self, source: &'static str, id: u16, command: u8, timestamp: i64) -> TrackedUplink {
+        self.push(TrackedUplink {
+            id,
+            command,
+            source,
+            sent_at: timestamp,
+            state: DeliveryState::Sent,
+            acked_at: None,
+            acked_by: None,
+            error: None,
+        })
+    }
+
+    pub fn failed(&mut self, source: &'static str, id: u16, command: u8, timestamp: i64, error: String) -> TrackedUplink {
+        self.push(TrackedUplink {
+            id,
+            command,
+            source,
+            sent_at: timestamp,
+            state: DeliveryState::Failed,
+            acked_at: None,
+            acked_by: None,
+            error: Some(error),
+        })
+    }
+
+    /// A vehicle packet from `store` at `timestamp` reporting `last_command`. Acks the
+    /// oldest matching uplink sent before it, returning it.
+    pub fn on_report(&mut self, store: &str, last_command: u8, timestamp: i64) -> Option<TrackedUplink> {
+        let uplink = self
+            .uplinks
+            .iter_mut()
+            .find(|u| u.state == DeliveryState::Sent && u.command == last_command && u.sent_at <= timestamp)?;
+        uplink.state = DeliveryState::Acked;
+        uplink.acked_at = Some(timestamp);
+        uplink.acked_by = Some(store.to_string());
+        Some(uplink.clone())
+    }
+
+    /// Anything unacked past its timeout, returning what just timed out
+    pub fn expire(&mut self, now: i64) -> Vec<TrackedUplink> {
+        let mut expired = Vec::new();
+        for uplink in self.uplinks.iter_mut() {
+            if uplink.state == DeliveryState::Sent && now - uplink.sent_at >= ACK_TIMEOUT_MS {
+                uplink.state = DeliveryState::TimedOut;
+                expired.push(uplink.clone());
+            }
+        }
+        expired
+    }
+
+    pub fn waiting(&self) -> bool {
+        self.uplinks.iter().any(|u| u.state == DeliveryState::Sent)
+    }
+
+    // newest first
+    pub fn recent(&self) -> Vec<TrackedUplink> {
+        self.uplinks.iter().rev().cloned().collect()
+    }
+}
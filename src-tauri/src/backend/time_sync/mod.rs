@@ -0,0 +1,289 @@
+// Checks the OS clock against a reference, so timestamps from every ground machine line up
+//
+// Two references:
+//   Ntp   one SNTP query to the configured server per check
+//   Gpsd  the PPS reports gpsd makes from a local GPS, for the field with no internet
+// Neither of these sets the clock (that's chrony's job), we only measure. Past the
+// threshold we raise an alert. Every measurement lands in the "ground_station" store
+// as clock_offset_ms and in the session manifest.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::{watch, Mutex};
+use tokio::time::{interval, timeout, Duration, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::middleware::alerts::AlertSeverity;
+use crate::middleware::session_manifest::TimeSyncRecord;
+use crate::middleware::telemetry_stores::TelemetryData;
+use crate::middleware::Middleware;
+
+const STORE: &str = "ground_station";
+const DRIFT_ALERT: &str = "time.drift";
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+// seconds from the NTP epoch (1900) to the unix epoch
+const NTP_UNIX_OFFSET: f64 = 2_208_988_800.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum TimeSource {
+    #[default]
+    Ntp,
+    Gpsd,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeSyncConfig {
+    pub enabled: bool,
+    pub source: TimeSource,
+    pub ntp_server: String,
+    pub gpsd_addr: String,
+    // warn past this much offset either way
+    pub threshold_ms: f64,
+    pub interval_secs: u64,
+}
+
+impl Default for TimeSyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            source: TimeSource::Ntp,
+            ntp_server: "pool.ntp.org:123".into(),
+            gpsd_addr: "127.0.0.1:2947".into(),
+            threshold_ms: 50.0,
+            interval_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TimeSyncStatus {
+    // reference minus our clock, positive means we're behind
+    pub offset_ms: Option<f64>,
+    pub round_trip_ms: Option<f64>,
+    pub last_checked: Option<i64>,
+    pub within_threshold: bool,
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct TimeSyncHandle {
+    config_tx: Arc<watch::Sender<TimeSyncConfig>>,
+    status_tx: Arc<watch::Sender<TimeSyncStatus>>,
+}
+
+impl TimeSyncHandle {
+    pub fn set_config(&self, config: TimeSyncConfig) -> Result<(), String> {
+        if config.threshold_ms <= 0.0 {
+            return Err("Drift threshold must be positive".into());
+        }
+        if config.interval_secs == 0 {
+            return Err("Check interval must be at least 1 second".into());
+        }
+        self.config_tx.send_replace(config);
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> TimeSyncConfig {
+        self.config_tx.borrow().clone()
+    }
+
+    pub fn get_status(&self) -> TimeSyncStatus {
+        self.status_tx.borrow().clone()
+    }
+}
+
+// ── Constructor ───────────────────────────────────────────────────────────────
+
+pub fn new(middleware: Arc<Mutex<Middleware>>) -> (TimeSync, TimeSyncHandle) {
+    let config_tx = Arc::new(watch::Sender::new(TimeSyncConfig::default()));
+    let status_tx = Arc::new(watch::Sender::new(TimeSyncStatus::default()));
+    let monitor = TimeSync {
+        middleware,
+        config_rx: config_tx.subscribe(),
+        status_tx: status_tx.clone(),
+    };
+    (monitor, TimeSyncHandle { config_tx, status_tx })
+}
+
+// ── Actor ─────────────────────────────────────────────────────────────────────
+
+pub struct TimeSync {
+    middleware: Arc<Mutex<Middleware>>,
+    config_rx: watch::Receiver<TimeSyncConfig>,
+    status_tx: Arc<watch::Sender<TimeSyncStatus>>,
+}
+
+struct Measurement {
+    offset_ms: f64,
+    round_trip_ms: Option<f64>,
+}
+
+impl TimeSync {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
+        loop {
+            let config = self.config_rx.borrow_and_update().clone();
+            if !config.enabled {
+                tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    _ = self.config_rx.changed() => continue,
+                }
+            }
+
+            let mut check = interval(Duration::from_secs(config.interval_secs));
+            check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    _ = self.config_rx.changed() => break,
+                    _ = check.tick() => self.check(&config).await,
+                }
+            }
+        }
+    }
+
+    async fn check(&mut self, config: &TimeSyncConfig) {
+        let (label, result) = match config.source {
+            TimeSource::Ntp => (format!("ntp {}", config.ntp_server), query_ntp(&config.ntp_server).await),
+            TimeSource::Gpsd => (format!("gpsd {}", config.gpsd_addr), query_gpsd_pps(&config.gpsd_addr).await),
+        };
+        let measurement = match result {
+            Ok(m) => m,
+            Err(e) => {
+                if self.status_tx.borrow().last_error.is_none() {
+                    eprintln!("[time_sync] {label}: {e}");
+                }
+                self.status_tx.send_modify(|s| s.last_error = Some(e));
+                return;
+            }
+        };
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let within = measurement.offset_ms.abs() <= config.threshold_ms;
+        self.status_tx.send_modify(|s| {
+            s.offset_ms = Some(measurement.offset_ms);
+            s.round_trip_ms = measurement.round_trip_ms;
+            s.last_checked = Some(now);
+            s.within_threshold = within;
+            s.last_error = None;
+        });
+
+        let mut middleware = self.middleware.lock().await;
+        let _ = middleware.push_data(
+            STORE,
+            "clock_offset_ms",
+            TelemetryData::new().with_value(measurement.offset_ms),
+        );
+        middleware.record_time_sync(TimeSyncRecord {
+            source: label.clone(),
+            offset_ms: measurement.offset_ms,
+            checked_at: now,
+        });
+        if within {
+            middleware.clear_alert(DRIFT_ALERT);
+        } else {
+            middleware.raise_alert(
+                DRIFT_ALERT,
+                AlertSeverity::Warning,
+                format!(
+                    "System clock is {:+.1}ms off {label} (threshold {:.0}ms)",
+                    measurement.offset_ms, config.threshold_ms
+                ),
+            );
+        }
+    }
+}
+
+// ── References ────────────────────────────────────────────────────────────────
+
+fn unix_now() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64()
+}
+
+// NTP 64 bit timestamp (32.32 fixed point since 1900) -> unix seconds
+fn ntp_to_unix(bytes: &[u8]) -> f64 {
+    let seconds = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64;
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as f64 / 4_294_967_296.0;
+    seconds + fraction - NTP_UNIX_OFFSET
+}
+
+// one SNTP (RFC 4330) exchange, offset = ((t1 - t0) + (t2 - t3)) / 2
+async fn query_ntp(server: &str) -> Result<Measurement, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(|e| e.to_string())?;
+    socket.connect(server).await.map_err(|e| e.to_string())?;
+
+    let mut request = [0u8; 48];
+    // LI 0, version 4, mode 3 (client)
+    request[0] = 0x23;
+    let t0 = unix_now();
+    socket.send(&request).await.map_err(|e| e.to_string())?;
+
+    let mut response = [0u8; 48];
+    let received = timeout(QUERY_TIMEOUT, socket.recv(&mut response))
+        .await
+        .map_err(|_| "no reply".to_string())?
+        .map_err(|e| e.to_string())?;
+    let t3 = unix_now();
+    if received < 48 {
+        return Err(format!("short reply ({received} bytes)"));
+    }
+    // stratum 0 is a kiss-of-death, the server wants us to go away
+    if response[1] == 0 {
+        return Err("server refused the request".into());
+    }
+
+    let t1 = ntp_to_unix(&response[32..40]);
+    let t2 = ntp_to_unix(&response[40..48]);
+    Ok(Measurement {
+        offset_ms: ((t1 - t0) + (t2 - t3)) / 2.0 * 1000.0,
+        round_trip_ms: Some(((t3 - t0) - (t2 - t1)) * 1000.0),
+    })
+}
+
+// waits for the next PPS report from gpsd, which carries the system clock time
+// at the pulse next to the GPS time it marks
+async fn query_gpsd_pps(addr: &str) -> Result<Measurement, String> {
+    let stream = timeout(QUERY_TIMEOUT, TcpStream::connect(addr))
+        .await
+        .map_err(|_| "connect timed out".to_string())?
+        .map_err(|e| e.to_string())?;
+    let (reader, mut writer) = stream.into_split();
+    writer
+        .write_all(b"?WATCH={\"enable\":true,\"json\":true,\"pps\":true};\n")
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut lines = BufReader::new(reader).lines();
+    // PPS comes once a second, give it a couple of chances
+    let deadline = tokio::time::Instant::now() + QUERY_TIMEOUT;
+    loop {
+        let line = tokio::time::timeout_at(deadline, lines.next_line())
+            .await
+            .map_err(|_| "no PPS report from gpsd, is the GPS locked with PPS wired up?".to_string())?
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "gpsd closed the connection".to_string())?;
+
+        let Ok(report) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        if report["class"] != "PPS" {
+            continue;
+        }
+        let number = |key: &str| report[key].as_f64();
+        let (Some(real_sec), Some(real_nsec), Some(clock_sec), Some(clock_nsec)) = (
+            number("real_sec"),
+            number("real_nsec"),
+            number("clock_sec"),
+            number("clock_nsec"),
+        ) else {
+            continue;
+        };
+        return Ok(Measurement {
+            offset_ms: (real_sec - clock_sec) * 1000.0 + (real_nsec - clock_nsec) / 1_000_000.0,
+            round_trip_ms: None,
+        });
+    }
+}
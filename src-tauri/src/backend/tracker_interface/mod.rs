@@ -1 +1,311 @@
-// Wrapper for serial_interface that handles the specifics of talking with our robotic antenna tracker
\ No newline at end of file
+// Antenna tracker + PTZ camera mount
+//
+// For now this integrates manual jog rates into commanded pointing angles and
+// publishes them to the "tracker" store; the hardware driver consumes those.
+// It also checks the rocket is still inside the antenna's beam, aiming at the GPS fix
+// while gps_trustworthy says it's good and extrapolating from the last good fixes when
+// it isn't (a fix with two satellites can be kilometres out).
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex};
+use tokio::time::{interval, Duration};
+use tokio_util::sync::CancellationToken;
+
+use crate::middleware::{alerts::AlertSeverity, config_file, gps_quality::TRUSTWORTHY_FIELD, telemetry_stores::TelemetryData, Middleware};
+
+const STORE_NAME: &str = "tracker";
+const ROCKET_STORE: &str = "rocket";
+const TICK_MS: u64 = 20;
+// how often the boresight check runs, in ticks
+const ADVISORY_TICKS: u32 = 10;
+const OFF_BORESIGHT_ALERT: &str = "tracker.off_boresight";
+// past this without a trustworthy fix, extrapolating is guessing
+const MAX_EXTRAPOLATION_MS: i64 = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AntennaPattern {
+    pub name: String,
+    // full half-power (-3 dB) beamwidth
+    pub beamwidth_deg: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrackerConfig {
+    // where the tracker sits, degrees / meters MSL
+    pub station_lat: f64,
+    pub station_lon: f64,
+    pub station_alt: f64,
+    #[serde(default)]
+    pub antenna: Option<AntennaPattern>,
+}
+
+
+// all rates are in deg/s, zoom is a unitless rate in [-1, 1]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct JogRates {
+    pub azimuth: f32,
+    pub elevation: f32,
+    pub pan: f32,
+    pub tilt: f32,
+    pub zoom: f32,
+}
+
+#[derive(Clone)]
+pub struct TrackerHandle {
+    jog_tx: watch::Sender<JogRates>,
+    config_tx: Arc<watch::Sender<TrackerConfig>>,
+}
+
+impl TrackerHandle {
+    // latest value wins, the tracker samples it every tick
+    pub fn jog(&self, rates: JogRates) -> Result<(), String> {
+        self.jog_tx.send(rates).map_err(|e| e.to_string())
+    }
+
+    pub fn set_config(&self, config: TrackerConfig) -> Result<(), String> {
+        if let Some(antenna) = &config.antenna {
+            if !(antenna.beamwidth_deg > 0.0 && antenna.beamwidth_deg <= 360.0) {
+                return Err(format!("Beamwidth must be in (0, 360], got {}", antenna.beamwidth_deg));
+            }
+        }
+        self.config_tx.send_replace(config);
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> TrackerConfig {
+        self.config_tx.borrow().clone()
+    }
+
+    pub fn load_antenna_pattern(&self, path: &Path) -> Result<AntennaPattern, String> {
+        let pattern: AntennaPattern = config_file::read_config_file(path)?;
+        let mut config = self.get_config();
+        config.antenna = Some(pattern.clone());
+        self.set_config(config)?;
+        Ok(pattern)
+    }
+}
+
+pub fn new(middleware: Arc<Mutex<Middleware>>) -> (TrackerInterface, TrackerHandle) {
+    let (jog_tx, jog_rx) = watch::channel(JogRates::default());
+    let config_tx = Arc::new(watch::Sender::new(TrackerConfig::default()));
+    (
+        TrackerInterface {
+            middleware,
+            jog_rx,
+            config_rx: config_tx.subscribe(),
+            pointing: Pointing::default(),
+            track: GpsTrack::default(),
+        },
+        TrackerHandle { jog_tx, config_tx },
+    )
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Pointing {
+    azimuth: f32,
+    elevation: f32,
+    pan: f32,
+    tilt: f32,
+    zoom: f32,
+}
+
+pub struct TrackerInterface {
+    middleware: Arc<Mutex<Middleware>>,
+    jog_rx: watch::Receiver<JogRates>,
+    config_rx: watch::Receiver<TrackerConfig>,
+    pointing: Pointing,
+    track: GpsTrack,
+}
+
+// (lat, lon, alt) and when
+#[derive(Debug, Clone, Copy)]
+struct TargetFix {
+    position: (f64, f64, f64),
+    timestamp: i64,
+}
+
+// the last trustworthy fix and the rate the one before it gives, per second
+#[derive(Debug, Clone, Default)]
+struct GpsTrack {
+    last: Option<TargetFix>,
+    rate: (f64, f64, f64),
+}
+
+impl GpsTrack {
+    fn update(&mut self, fix: TargetFix) {
+        if let Some(last) = self.last {
+            let dt = (fix.timestamp - last.timestamp) as f64 / 1000.0;
+            if dt <= 0.0 {
+                return;
+            }
+            let (p, q) = (fix.position, last.position);
+            self.rate = ((p.0 - q.0) / dt, (p.1 - q.1) / dt, (p.2 - q.2) / dt);
+        }
+        self.last = Some(fix);
+    }
+
+    fn extrapolate(&self, now: i64) -> Option<(f64, f64, f64)> {
+        let last = self.last?;
+        let elapsed = now - last.timestamp;
+        if elapsed > MAX_EXTRAPOLATION_MS {
+            return None;
+        }
+        let dt = elapsed.max(0) as f64 / 1000.0;
+        let (p, r) = (last.position, self.rate);
+        Some((p.0 + r.0 * dt, p.1 + r.1 * dt, p.2 + r.2 * dt))
+    }
+}
+
+impl TrackerInterface {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
+        let mut tick = interval(Duration::from_millis(TICK_MS));
+        let dt = TICK_MS as f32 / 1000.0;
+        let mut ticks_to_advisory = ADVISORY_TICKS;
+
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = tick.tick() => {}
+            }
+
+            ticks_to_advisory -= 1;
+            if ticks_to_advisory == 0 {
+                ticks_to_advisory = ADVISORY_TICKS;
+                self.check_boresight().await;
+            }
+
+            let rates = *self.jog_rx.borrow();
+            if rates == JogRates::default() {
+                continue; // nothing moving, don't spam the store
+            }
+
+            let p = &mut self.pointing;
+            p.azimuth = (p.azimuth + rates.azimuth * dt).rem_euclid(360.0);
+            p.elevation = (p.elevation + rates.elevation * dt).clamp(0.0, 90.0);
+            p.pan = (p.pan + rates.pan * dt).rem_euclid(360.0);
+            p.tilt = (p.tilt + rates.tilt * dt).clamp(-90.0, 90.0);
+            p.zoom = (p.zoom + rates.zoom * dt).clamp(0.0, 1.0);
+
+            let timestamp = chrono::Utc::now().timestamp_millis();
+            let mut mw = self.middleware.lock().await;
+            for (field, value) in [
+                ("commanded_azimuth", p.azimuth),
+                ("commanded_elevation", p.elevation),
+                ("ptz_pan", p.pan),
+                ("ptz_tilt", p.tilt),
+                ("ptz_zoom", p.zoom),
+            ] {
+                let _ = mw.push_data(
+                    STORE_NAME,
+                    field,
+                    TelemetryData::new().with_timestamp(timestamp).with_value(value as f64),
+                );
+            }
+        }
+    }
+}
+
+impl TrackerInterface {
+    // warn when the rocket has drifted outside the half-power beam
+    async fn check_boresight(&mut self) {
+        let config = self.config_rx.borrow().clone();
+        let Some(antenna) = config.antenna else {
+            return;
+        };
+
+        let mut mw = self.middleware.lock().await;
+        let latest = |field: &str| mw.get_last(ROCKET_STORE, field).ok().flatten();
+        let fix = match (latest("lat"), latest("lon"), latest("alt")) {
+            (Some(lat), Some(lon), Some(alt)) => Some(TargetFix {
+                position: (lat.value.as_f64(), lon.value.as_f64(), alt.value.as_f64()),
+                timestamp: lat.timestamp,
+            }),
+            _ => None,
+        };
+        // firmware without the inputs for a score gets its fixes believed, as before
+        let trustworthy = latest(TRUSTWORTHY_FIELD).is_none_or(|d| d.value.as_f64() > 0.5);
+
+        let (target, extrapolated) = match fix {
+            Some(fix) if trustworthy => {
+                self.track.update(fix);
+                (fix.position, false)
+            }
+            _ => match self.track.extrapolate(chrono::Utc::now().timestamp_millis()) {
+                Some(position) => (position, true),
+                None => return, // no GPS fix yet, or none good for too long
+            },
+        };
+
+        let (target_az, target_el) = geo::look_angles(
+            (config.station_lat, config.station_lon, config.station_alt),
+            target,
+        );
+        let offset = geo::angular_separation(
+            (self.pointing.azimuth as f64, self.pointing.elevation as f64),
+            (target_az, target_el),
+        );
+
+        let _ = mw.push_data(STORE_NAME, "boresight_offset", TelemetryData::new().with_value(offset));
+        let _ = mw.push_data(STORE_NAME, "target_azimuth", TelemetryData::new().with_value(target_az));
+        let _ = mw.push_data(STORE_NAME, "target_elevation", TelemetryData::new().with_value(target_el));
+        let _ = mw.push_data(STORE_NAME, "target_extrapolated", TelemetryData::new().with_value(extrapolated));
+
+        let half_power = antenna.beamwidth_deg / 2.0;
+        if offset > half_power {
+            mw.raise_alert(
+                OFF_BORESIGHT_ALERT,
+                AlertSeverity::Warning,
+                format!(
+                    "Rocket is {:.1} deg off boresight, outside the {:.1} deg half-power beam of {}",
+                    offset, half_power, antenna.name
+                ),
+            );
+        } else {
+            mw.clear_alert(OFF_BORESIGHT_ALERT);
+        }
+    }
+}
+
+// ── Geometry ──────────────────────────────────────────────────────────────────
+
+mod geo {
+    const WGS84_A: f64 = 6_378_137.0;
+    const WGS84_E2: f64 = 6.694_379_990_14e-3;
+
+    fn to_ecef(lat_deg: f64, lon_deg: f64, alt: f64) -> [f64; 3] {
+        let (lat, lon) = (lat_deg.to_radians(), lon_deg.to_radians());
+        let n = WGS84_A / (1.0 - WGS84_E2 * lat.sin().powi(2)).sqrt();
+        [
+            (n + alt) * lat.cos() * lon.cos(),
+            (n + alt) * lat.cos() * lon.sin(),
+            (n * (1.0 - WGS84_E2) + alt) * lat.sin(),
+        ]
+    }
+
+    /// Azimuth (deg from north, clockwise) and elevation (deg above horizon)
+    /// from `station` to `target`, both (lat, lon, alt)
+    pub fn look_angles(station: (f64, f64, f64), target: (f64, f64, f64)) -> (f64, f64) {
+        let s = to_ecef(station.0, station.1, station.2);
+        let t = to_ecef(target.0, target.1, target.2);
+        let d = [t[0] - s[0], t[1] - s[1], t[2] - s[2]];
+
+        let (lat, lon) = (station.0.to_radians(), station.1.to_radians());
+        let east = -lon.sin() * d[0] + lon.cos() * d[1];
+        let north = -lat.sin() * lon.cos() * d[0] - lat.sin() * lon.sin() * d[1] + lat.cos() * d[2];
+        let up = lat.cos() * lon.cos() * d[0] + lat.cos() * lon.sin() * d[1] + lat.sin() * d[2];
+
+        let az = east.atan2(north).to_degrees().rem_euclid(360.0);
+        let el = up.atan2((east * east + north * north).sqrt()).to_degrees();
+        (az, el)
+    }
+
+    /// Great-circle angle between two (az, el) directions, degrees
+    pub fn angular_separation(a: (f64, f64), b: (f64, f64)) -> f64 {
+        let (az1, el1) = (a.0.to_radians(), a.1.to_radians());
+        let (az2, el2) = (b.0.to_radians(), b.1.to_radians());
+        let cos = el1.sin() * el2.sin() + el1.cos() * el2.cos() * (az1 - az2).cos();
+        cos.clamp(-1.0, 1.0).acos().to_degrees()
+    }
+}
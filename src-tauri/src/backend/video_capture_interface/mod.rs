@@ -14,6 +14,8 @@ use tokio_util::sync::CancellationToken;
 
 use crate::middleware::{Middleware, video_streams::VideoFrame};
 
+pub mod test_pattern;
+
 // ── Constants ─────────────────────────────────────────────────────────────────
 
 const PREFERRED_WIDTH: u32 = 1920;
@@ -90,7 +92,7 @@ pub fn new(
 // ── CameraInput ───────────────────────────────────────────────────────────────
 
 impl CameraInput {
-    pub async fn run(mut self, shutdown: CancellationToken) {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
         let mut pending: Option<String> = None;
 
         loop {
@@ -0,0 +1,242 @@
+// Synthetic video source, for working on the video panels without a camera
+//
+// Renders a moving test pattern into a named stream like any camera would, with the
+// frame counter and the frame's timestamp burned into the top left corner so dropped
+// or stale frames are obvious on screen and in recordings. Idle until it's enabled.
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::middleware::{video_streams::VideoFrame, Middleware};
+
+const MAX_WIDTH: u32 = 3840;
+const MAX_HEIGHT: u32 = 2160;
+const MAX_FPS: u32 = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pattern {
+    // scrolling colour bars
+    #[default]
+    Bars,
+    // checkerboard drifting diagonally
+    Checker,
+    // hue ramp
+    Gradient,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestPatternConfig {
+    pub enabled: bool,
+    pub stream: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    #[serde(default)]
+    pub pattern: Pattern,
+}
+
+impl Default for TestPatternConfig {
+    fn default() -> Self {
+        Self { enabled: false, stream: "test_pattern".into(), width: 640, height: 480, fps: 30, pattern: Pattern::Bars }
+    }
+}
+
+impl TestPatternConfig {
+    fn validate(&self) -> Result<(), String> {
+        if self.stream.trim().is_empty() {
+            return Err("The test pattern needs a stream name".into());
+        }
+        if !(16..=MAX_WIDTH).contains(&self.width) || !(16..=MAX_HEIGHT).contains(&self.height) {
+            return Err(format!("Resolution must be between 16x16 and {MAX_WIDTH}x{MAX_HEIGHT}"));
+        }
+        if !(1..=MAX_FPS).contains(&self.fps) {
+            return Err(format!("Frame rate must be between 1 and {MAX_FPS}"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct TestPatternHandle {
+    config_tx: Arc<watch::Sender<TestPatternConfig>>,
+}
+
+impl TestPatternHandle {
+    pub fn set_config(&self, config: TestPatternConfig) -> Result<(), String> {
+        config.validate()?;
+        self.config_tx.send_replace(config);
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> TestPatternConfig {
+        self.config_tx.borrow().clone()
+    }
+}
+
+// ── Constructor ───────────────────────────────────────────────────────────────
+
+pub fn new(middleware: Arc<Mutex<Middleware>>) -> (TestPatternSource, TestPatternHandle) {
+    let config_tx = Arc::new(watch::Sender::new(TestPatternConfig::default()));
+    let source = TestPatternSource { middleware, config_rx: config_tx.subscribe() };
+    (source, TestPatternHandle { config_tx })
+}
+
+// ── Actor ─────────────────────────────────────────────────────────────────────
+
+pub struct TestPatternSource {
+    middleware: Arc<Mutex<Middleware>>,
+    config_rx: watch::Receiver<TestPatternConfig>,
+}
+
+impl TestPatternSource {
+    pub async fn run(&mut self, shutdown: CancellationToken) {
+        loop {
+            let config = self.config_rx.borrow_and_update().clone();
+            if !config.enabled {
+                tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    _ = self.config_rx.changed() => continue,
+                }
+            }
+
+            println!(
+                "[test_pattern] {:?} into '{}' at {}x{} @ {}fps",
+                config.pattern, config.stream, config.width, config.height, config.fps
+            );
+            let mut tick = interval(Duration::from_secs_f64(1.0 / config.fps as f64));
+            tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            let mut frame_number: u64 = 0;
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    _ = self.config_rx.changed() => break,
+                    _ = tick.tick() => {}
+                }
+                let timestamp = chrono::Utc::now().timestamp_millis();
+                let frame = Arc::new(render(&config, frame_number, timestamp));
+                if let Err(e) = self.middleware.lock().await.process_video_frame(&config.stream, frame) {
+                    eprintln!("[test_pattern] process_video_frame error: {e}");
+                }
+                frame_number += 1;
+            }
+        }
+    }
+}
+
+// ── Rendering ─────────────────────────────────────────────────────────────────
+
+const BARS: [[u8; 3]; 8] = [
+    [235, 235, 235],
+    [235, 235, 16],
+    [16, 235, 235],
+    [16, 235, 16],
+    [235, 16, 235],
+    [235, 16, 16],
+    [16, 16, 235],
+    [16, 16, 16],
+];
+
+fn render(config: &TestPatternConfig, frame_number: u64, timestamp: i64) -> VideoFrame {
+    let (width, height) = (config.width as usize, config.height as usize);
+    // two pixels a frame, so motion is visible at any frame rate
+    let shift = (frame_number * 2) as usize;
+    let mut data = vec![0u8; width * height * 3];
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = match config.pattern {
+                Pattern::Bars => BARS[((x + shift) % width) * BARS.len() / width],
+                Pattern::Checker => {
+                    let square = (width.min(height) / 8).max(1);
+                    if ((x + shift) / square + (y + shift) / square) % 2 == 0 {
+                        [235, 235, 235]
+                    } else {
+                        [16, 16, 16]
+                    }
+                }
+                Pattern::Gradient => hue(((x + shift) % width) as f64 / width as f64, y as f64 / height as f64),
+            };
+            let i = (y * width + x) * 3;
+            data[i..i + 3].copy_from_slice(&pixel);
+        }
+    }
+
+    let time = Local
+        .timestamp_millis_opt(timestamp)
+        .single()
+        .map(|t| t.format("%H:%M:%S%.3f").to_string())
+        .unwrap_or_default();
+    let scale = (height / 60).max(2);
+    burn_in(&mut data, width, height, &format!("{frame_number:06}"), scale, scale);
+    burn_in(&mut data, width, height, &time, scale, scale * 8);
+
+    VideoFrame { timestamp, data, width: config.width, height: config.height }
+}
+
+// full saturation hue ramp, darker toward the bottom
+fn hue(h: f64, v: f64) -> [u8; 3] {
+    let value = 1.0 - 0.6 * v;
+    let sector = h * 6.0;
+    let f = sector.fract();
+    let (r, g, b) = match sector as u32 {
+        0 => (1.0, f, 0.0),
+        1 => (1.0 - f, 1.0, 0.0),
+        2 => (0.0, 1.0, f),
+        3 => (0.0, 1.0 - f, 1.0),
+        4 => (f, 0.0, 1.0),
+        _ => (1.0, 0.0, 1.0 - f),
+    };
+    [(r * value * 255.0) as u8, (g * value * 255.0) as u8, (b * value * 255.0) as u8]
+}
+
+// 3x5 glyphs, one row per byte, high bit on the left
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        _ => [0; 5],
+    }
+}
+
+// white text on a black box at (scale, top), each glyph pixel scale x scale
+fn burn_in(data: &mut [u8], width: usize, height: usize, text: &str, scale: usize, top: usize) {
+    let left = scale;
+    let box_width = (text.chars().count() * 4 + 1) * scale;
+    let box_height = 7 * scale;
+    for y in top..(top + box_height).min(height) {
+        for x in left..(left + box_width).min(width) {
+            let i = (y * width + x) * 3;
+            data[i..i + 3].copy_from_slice(&[0, 0, 0]);
+        }
+    }
+    for (n, c) in text.chars().enumerate() {
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                let x0 = left + (n * 4 + 1 + col) * scale;
+                let y0 = top + (row + 1) * scale;
+                for y in y0..(y0 + scale).min(height) {
+                    for x in x0..(x0 + scale).min(width) {
+                        let i = (y * width + x) * 3;
+                        data[i..i + 3].copy_from_slice(&[255, 255, 255]);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,9 @@
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 
-use crate::backend::{self, video_capture_interface};
+use crate::backend::{self, serial_interface::SerialDevice};
+#[cfg(feature = "video")]
+use crate::backend::video_capture_interface;
 
 pub struct ShutdownState {
     pub shutdown: tokio_util::sync::CancellationToken,
@@ -20,12 +23,32 @@ pub enum PlaybackState {
     Done,
 }
 
+// serial devices only, cameras are picked by device index through their own handles
 pub struct HardwarePorts {
     pub telemetry_radio_port_tx: tokio::sync::mpsc::Sender<String>,
-    pub live_video_port_tx: tokio::sync::mpsc::Sender<String>,
-    pub tracking_video_port_tx: tokio::sync::mpsc::Sender<String>,
-    pub tracker_port_tx: tokio::sync::mpsc::Sender<String>,
-    pub pointing_stick_port_tx: tokio::sync::mpsc::Sender<String>,
+    pub telemetry_radio_backup_port_tx: tokio::sync::mpsc::Sender<String>,
+    pub control_surface_port_tx: tokio::sync::mpsc::Sender<String>,
+    // last port chosen for each device
+    pub selected: DashMap<SerialDevice, String>,
+}
+
+impl HardwarePorts {
+    pub async fn select(&self, device: SerialDevice, port: String) -> Result<(), String> {
+        let tx = match device {
+            SerialDevice::TelemetryRadio => &self.telemetry_radio_port_tx,
+            SerialDevice::TelemetryRadioBackup => &self.telemetry_radio_backup_port_tx,
+            SerialDevice::ControlSurface => &self.control_surface_port_tx,
+        };
+        tx.send(port.clone()).await.map_err(|e| e.to_string())?;
+        // one device per port, whoever had it before loses it
+        self.selected.retain(|d, p| *d == device || *p != port);
+        self.selected.insert(device, port);
+        Ok(())
+    }
+
+    pub fn assigned_device(&self, port: &str) -> Option<SerialDevice> {
+        self.selected.iter().find(|e| e.value() == port).map(|e| *e.key())
+    }
 }
 
 pub struct RemoteControlChannels {
@@ -33,5 +56,9 @@ pub struct RemoteControlChannels {
     pub payload_control_tx: tokio::sync::mpsc::Sender<(f32,f32)>,
 }
 
+#[cfg(feature = "video")]
 pub struct LiveVideoHandle(pub video_capture_interface::CameraHandle);
-pub struct TrackingCameraHandle(pub video_capture_interface::CameraHandle);
\ No newline at end of file
+#[cfg(feature = "video")]
+pub struct TrackingCameraHandle(pub video_capture_interface::CameraHandle);
+// the second telemetry radio, the primary's handle is managed as TelemetryRadioHandle
+pub struct BackupRadioHandle(pub backend::telemetry_radio_interface::TelemetryRadioHandle);
\ No newline at end of file
@@ -1,36 +1,186 @@
 use crate::{
-    backend::telemetry_radio_interface::{TelemetryRadioHandle, hprc}, 
-    channels::{LiveVideoHandle, TrackingCameraHandle}, 
-    middleware::{Middleware, TelemetryDataFrontend, VideoFrameFrontend},
+    backend::telemetry_radio_interface::{AuthConfig, AuthConfigSummary, CaptureStatus, CrcConfig, LinkFraming, LinkStats, SharedDedup, TelemetryRadioHandle}, 
+    channels as Channels, 
+    middleware::{ConsistentSnapshot, Middleware, TelemetryDataFrontend, VideoFrameFrontend},
+    middleware::alerts::{AlertAuditRecord, AlertFrontend},
+    middleware::mission_profile::{MissionProfile, SchemaDiff},
+    middleware::recovery::RecoveryBundle,
+    middleware::packet_log::InspectedFrame,
+    middleware::packet_stats::PacketStreamStats,
+    middleware::packet_recorder::PacketLogStatus,
+    middleware::data_audit::DataAuditRecord,
+    middleware::session_manifest::{SessionManifest, StationLocation},
+    middleware::session_template::{Checklist, ChecklistItem, PreparedSession},
+    middleware::flight_state::{FlightState, FlightStatus, FlightTransition},
+    middleware::landing::LandingPrediction,
+    middleware::gps_quality::GpsQualityConfig,
+    middleware::derived_fields::DerivedField,
+    middleware::formatting::FieldFormat,
+    middleware::field_metadata::FieldMetadata,
+    middleware::time_base::{TimeBase, TimeBaseConfig},
+    middleware::drops::{DropReport, DropSite},
+    middleware::load_shedding::{LoadSheddingConfig, LoadSheddingStatus},
+    middleware::lock_timing::{LockBudgetConfig, LockStats},
+    middleware::buffer_policy::BufferConfig,
+    middleware::csv_rotation::{RecordingSegment, RotationConfig},
+    middleware::recording_arm::{ArmConfig, ArmStatus},
+    middleware::recording_format::RecordingFormat,
+    middleware::recording_compression::RecordingCompression,
+    middleware::flush_policy::FlushPolicy,
+    middleware::recording_resume::ResumeSummary,
+    middleware::recording_library::{self, RecordingInfo},
+    middleware::session_naming::SessionPaths,
+    middleware::data_quality::QualityConfig,
+    middleware::telemetry_reader::TelemetryReader,
+    middleware::aggregate::{Aggregate, AggregateResult},
+    middleware::telemetry_search::{Predicate, SearchResult},
+    middleware::events::{EventTraceSummary, MAX_TRACE_MS},
+    middleware::journal::{self, JournalStatus, RebuildSummary},
+    middleware::store_snapshot::RestoreSummary,
+    middleware::launch_sessions::{LaunchSession, LaunchSessionSpec, LaunchSessionStatus},
+    middleware::schema_health::SchemaHealth,
+    middleware::change_events::{FieldChange, WatchedField},
+    middleware::sqlite_store::{self, SqlitePoint, SqliteStatus, SqliteStream},
+    backend::control_surface::{ButtonAction, ControlSurfaceHandle},
+    backend::relay::{RelayConfig, RelayHandle, RelayStatus},
+    backend::influx_sink::{InfluxConfig, InfluxSinkHandle, InfluxStatus},
+    backend::rebroadcast::{RebroadcastConfig, RebroadcastHandle, RebroadcastStatus},
+    backend::services::{ServiceInfo, ServiceRegistry},
+    backend::serial_interface::{self, SerialDevice, SerialPortInfo},
+    backend::power_monitor::{PowerConfig, PowerMonitorHandle, PowerStatus},
+    backend::time_sync::{TimeSyncConfig, TimeSyncHandle, TimeSyncStatus},
+    backend::countdown::{AutomationRecord, CountdownConfig, CountdownHandle, CountdownStatus, COUNTDOWN_EVENT},
+    backend::self_test::{self, SelfTestReport},
+    backend::preflight::{self, PreflightReport},
+    backend::telemetry_radio_interface::test_vectors::{self, ConformanceReport},
+};
+// the optional subsystems, see [features] in Cargo.toml
+#[cfg(feature = "sim")]
+use crate::backend::data_playback::{DataPlaybackHandle, LatencyModel, PlaybackStatus};
+#[cfg(feature = "uplink")]
+use crate::{
+    backend::telemetry_radio_interface::{HazardConfig, PendingUplink, TrackedUplink, UplinkAuditRecord, UPLINK_CONFIRMATION_EVENT, hprc},
+    backend::command_macros::{CommandMacro, CommandMacroHandle, MacroProgress},
+};
+#[cfg(feature = "maps")]
+use crate::middleware::elevation::TerrainPoint;
+#[cfg(feature = "video")]
+use crate::{
+    channels::{LiveVideoHandle, TrackingCameraHandle},
     backend::video_capture_interface::CameraHandle,
+    backend::video_capture_interface::test_pattern::{TestPatternConfig, TestPatternHandle},
 };
-use tauri::State;
+#[cfg(feature = "df")]
+use crate::{
+    backend::tracker_interface::{AntennaPattern, TrackerConfig, TrackerHandle},
+    backend::ros_bridge::{RosBridgeConfig, RosBridgeHandle, RosBridgeStatus},
+};
+#[cfg(all(feature = "uplink", feature = "df"))]
+use crate::backend::joystick_input::{JoystickConfig, JoystickHandle};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, State, Window};
+use tauri_plugin_opener::OpenerExt;
+use tokio::sync::Mutex;
 // use std::alloc::Global;
 // use serde::Serialize;
 // use std::collections::HashMap;
 // use crate::Channels;
 
+/* =========================================================
+   WINDOW PERMISSIONS
+   ========================================================= */
+
+// the livestream/dashboard/console windows are for looking, not touching
+const OPERATOR_WINDOW: &str = "main";
+
+fn require_operator_window(window: &Window) -> Result<(), String> {
+    if window.label() != OPERATOR_WINDOW {
+        return Err(format!(
+            "Window '{}' is not allowed to do that, use the main operator window",
+            window.label()
+        ));
+    }
+    Ok(())
+}
+
 /* =========================================================
    PLAYBACK CONTROL
    ========================================================= */
 
-// #[tauri::command]
-// pub async fn set_playback_state(
-//     playback_channel: State<'_, Channels::PlaybackControlChannel>,
-//     control: Channels::PlaybackState,
-// ) -> Result<(), String> {
-//     playback_channel
-//         .playback_tx
-//         .send(control)
-//         .map_err(|_| "Data Playback Backend not running".to_string())
-// }
+#[tauri::command]
+pub async fn set_playback_state(
+    window: Window,
+    playback_channel: State<'_, Channels::PlaybackControlChannel>,
+    control: Channels::PlaybackState,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    playback_channel
+        .playback_tx
+        .send(control)
+        .map_err(|_| "Data Playback Backend not running".to_string())
+}
+
+#[tauri::command]
+pub async fn get_playback_state(
+    playback_channel: State<'_, Channels::PlaybackControlChannel>,
+) -> Result<Channels::PlaybackState, String> {
+    Ok(playback_channel.playback_rx.borrow().clone())
+}
+
+#[cfg(feature = "sim")]
+#[tauri::command]
+pub async fn load_playback_folder(
+    playback: State<'_, DataPlaybackHandle>,
+    folder: String,
+) -> Result<(), String> {
+    playback.load_folder(folder.into()).await
+}
+
+#[cfg(feature = "sim")]
+#[tauri::command]
+pub async fn get_playback_status(
+    playback: State<'_, DataPlaybackHandle>,
+) -> Result<PlaybackStatus, String> {
+    Ok(playback.get_status())
+}
+
+// simulated link latency/jitter applied to each emitted row
+#[cfg(feature = "sim")]
+#[tauri::command]
+pub async fn set_playback_latency(
+    playback: State<'_, DataPlaybackHandle>,
+    model: LatencyModel,
+) -> Result<(), String> {
+    playback.set_latency(model)
+}
+
+#[cfg(feature = "sim")]
+#[tauri::command]
+pub async fn get_playback_latency(
+    playback: State<'_, DataPlaybackHandle>,
+) -> Result<LatencyModel, String> {
+    Ok(playback.get_latency())
+}
+
+// 1.0 plays the CSVs back at the rate they were recorded
+#[cfg(feature = "sim")]
+#[tauri::command]
+pub async fn set_playback_speed(
+    playback: State<'_, DataPlaybackHandle>,
+    speed: f64,
+) -> Result<(), String> {
+    playback.set_speed(speed)
+}
 
-// #[tauri::command]
-// pub async fn get_playback_state(
-//     playback_channel: State<'_, Channels::PlaybackControlChannel>,
-// ) -> Result<Channels::PlaybackState, String> {
-//     Ok(playback_channel.playback_rx.borrow().clone())
-// }
+#[cfg(feature = "sim")]
+#[tauri::command]
+pub async fn get_playback_speed(
+    playback: State<'_, DataPlaybackHandle>,
+) -> Result<f64, String> {
+    Ok(playback.get_speed())
+}
 
 /* =========================================================
    SERIAL/VIDEO PORT CHOOSING (WRITE + READ)
@@ -42,131 +192,1848 @@ pub async fn get_serial_port_names(
     Ok(TelemetryRadioHandle::available_ports())
 }
 
+#[tauri::command]
+pub async fn list_serial_ports(
+    ports: State<'_, Channels::HardwarePorts>,
+) -> Result<Vec<SerialPortInfo>, String> {
+    let mut list = serial_interface::list_ports()?;
+    for port in &mut list {
+        port.assigned_to = ports.assigned_device(&port.name);
+    }
+    Ok(list)
+}
+
+#[tauri::command]
+pub async fn select_serial_port(
+    window: Window,
+    ports: State<'_, Channels::HardwarePorts>,
+    device: SerialDevice,
+    port: String,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    ports.select(device, port).await
+}
+
 #[tauri::command]
 pub async fn set_telem_serial_port(
+    window: Window,
     telem_backend: State<'_, TelemetryRadioHandle>,
     port_name: String,
 ) -> Result<(), String> {
+    require_operator_window(&window)?;
     telem_backend.send_serial_port(port_name).await
 }
 
+#[tauri::command]
+pub async fn set_link_framing(
+    window: Window,
+    telem_backend: State<'_, TelemetryRadioHandle>,
+    framing: LinkFraming,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    telem_backend.set_framing(framing);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_link_framing(
+    telem_backend: State<'_, TelemetryRadioHandle>,
+) -> Result<LinkFraming, String> {
+    Ok(telem_backend.get_framing())
+}
+
+#[tauri::command]
+pub async fn set_crc_config(
+    window: Window,
+    telem_backend: State<'_, TelemetryRadioHandle>,
+    config: CrcConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    telem_backend.set_crc_config(config);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_crc_config(
+    telem_backend: State<'_, TelemetryRadioHandle>,
+) -> Result<CrcConfig, String> {
+    Ok(telem_backend.get_crc_config())
+}
+
+#[tauri::command]
+pub async fn set_packet_auth_config(
+    window: Window,
+    telem_backend: State<'_, TelemetryRadioHandle>,
+    config: AuthConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    telem_backend.set_auth_config(config)
+}
+
+#[tauri::command]
+pub async fn get_packet_auth_config(
+    window: Window,
+    telem_backend: State<'_, TelemetryRadioHandle>,
+) -> Result<AuthConfigSummary, String> {
+    require_operator_window(&window)?;
+    Ok(telem_backend.get_auth_config())
+}
+
+// hazardous commands come back as a pending uplink for a second operator to approve
+#[cfg(feature = "uplink")]
 #[tauri::command]
 pub async fn send_command(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
     telem_backend: State<'_, TelemetryRadioHandle>,
     cmd: u8,
-) -> Result<(), String> {
+) -> Result<Option<PendingUplink>, String> {
+    require_operator_window(&window)?;
     let cmd = hprc::Command(cmd);
-    telem_backend.send_command(cmd).await
+    let pending = telem_backend.request_command(cmd, window.label()).await?;
+    if let Some(uplink) = &pending {
+        middleware.lock().await.emit(UPLINK_CONFIRMATION_EVENT, uplink.clone());
+    }
+    Ok(pending)
+}
+
+// the RSO confirms from the console window, the role comes from the window not the caller
+#[cfg(feature = "uplink")]
+#[tauri::command]
+pub async fn approve_uplink_command(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    telem_backend: State<'_, TelemetryRadioHandle>,
+    id: u32,
+) -> Result<PendingUplink, String> {
+    let uplink = telem_backend.approve_command(id, window.label()).await?;
+    middleware.lock().await.emit(UPLINK_CONFIRMATION_EVENT, uplink.clone());
+    Ok(uplink)
+}
+
+#[cfg(feature = "uplink")]
+#[tauri::command]
+pub async fn reject_uplink_command(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    telem_backend: State<'_, TelemetryRadioHandle>,
+    id: u32,
+) -> Result<PendingUplink, String> {
+    let uplink = telem_backend.reject_command(id, window.label())?;
+    middleware.lock().await.emit(UPLINK_CONFIRMATION_EVENT, uplink.clone());
+    Ok(uplink)
+}
+
+#[cfg(feature = "uplink")]
+#[tauri::command]
+pub async fn get_pending_uplink_commands(
+    telem_backend: State<'_, TelemetryRadioHandle>,
+) -> Result<Vec<PendingUplink>, String> {
+    Ok(telem_backend.pending_commands())
+}
+
+#[cfg(feature = "uplink")]
+#[tauri::command]
+pub async fn get_uplink_audit_log(
+    telem_backend: State<'_, TelemetryRadioHandle>,
+) -> Result<Vec<UplinkAuditRecord>, String> {
+    Ok(telem_backend.uplink_audit_log())
+}
+
+// delivery of every recent command, newest first
+#[cfg(feature = "uplink")]
+#[tauri::command]
+pub async fn get_uplink_status(
+    telem_backend: State<'_, TelemetryRadioHandle>,
+) -> Result<Vec<TrackedUplink>, String> {
+    Ok(telem_backend.uplink_status())
+}
+
+#[cfg(feature = "uplink")]
+#[tauri::command]
+pub async fn set_hazard_config(
+    window: Window,
+    telem_backend: State<'_, TelemetryRadioHandle>,
+    config: HazardConfig,
+) -> Result<(), String> {
+    // from the RSO console only, checked against the window's role
+    telem_backend.set_hazard_config(config, window.label())
+}
+
+#[cfg(feature = "uplink")]
+#[tauri::command]
+pub async fn get_hazard_config(
+    telem_backend: State<'_, TelemetryRadioHandle>,
+) -> Result<HazardConfig, String> {
+    Ok(telem_backend.get_hazard_config())
+}
+
+#[tauri::command]
+pub async fn start_serial_capture(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    telem_backend: State<'_, TelemetryRadioHandle>,
+    path: String,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    let encryption = middleware.lock().await.session_encryption();
+    telem_backend.start_capture(Path::new(&path), &encryption)
+}
+
+#[tauri::command]
+pub async fn stop_serial_capture(
+    window: Window,
+    telem_backend: State<'_, TelemetryRadioHandle>,
+) -> Result<Option<CaptureStatus>, String> {
+    require_operator_window(&window)?;
+    Ok(telem_backend.stop_capture())
+}
+
+// rate, gaps and losses for each radio, also sent as link_stats every link check
+#[tauri::command]
+pub async fn get_link_stats(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<PacketStreamStats>, String> {
+    Ok(middleware.lock().await.get_link_stats())
+}
+
+#[tauri::command]
+pub async fn get_radio_link_stats(
+    dedup: State<'_, SharedDedup>,
+) -> Result<Vec<LinkStats>, String> {
+    Ok(dedup.lock().stats())
+}
+
+#[tauri::command]
+pub async fn set_raw_capture(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    telem_backend: State<'_, TelemetryRadioHandle>,
+    enabled: bool,
+) -> Result<Option<CaptureStatus>, String> {
+    require_operator_window(&window)?;
+    let stopped = telem_backend.set_raw_capture(enabled);
+    // don't wait for the next connect to start capturing
+    if enabled && telem_backend.capture_status().is_none() {
+        let (path, encryption) = {
+            let middleware = middleware.lock().await;
+            (middleware.raw_capture_path("telemetry_radio"), middleware.session_encryption())
+        };
+        telem_backend.start_capture(&path, &encryption)?;
+    }
+    Ok(stopped)
+}
+
+#[tauri::command]
+pub async fn get_raw_capture(
+    telem_backend: State<'_, TelemetryRadioHandle>,
+) -> Result<bool, String> {
+    Ok(telem_backend.get_raw_capture())
+}
+
+// every decoded frame also goes to a .frames log while recording, replayable as is
+#[tauri::command]
+pub async fn set_packet_logging(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    enabled: bool,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.set_packet_logging(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_packet_log_status(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<PacketLogStatus, String> {
+    Ok(middleware.lock().await.get_packet_log_status())
+}
+
+// every store change to a journal_<time>.jsonl that can rebuild the stores later
+#[tauri::command]
+pub async fn set_journal_enabled(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    enabled: bool,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.set_journal_enabled(enabled)
+}
+
+#[tauri::command]
+pub async fn get_journal_status(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<JournalStatus, String> {
+    Ok(middleware.lock().await.get_journal_status())
+}
+
+// what the stores held as of entry until_seq, or the end of the journal
+#[tauri::command]
+pub fn rebuild_from_journal(path: String, until_seq: Option<u64>) -> Result<RebuildSummary, String> {
+    Ok(journal::rebuild(Path::new(&path), until_seq)?.summary())
+}
+
+// the buffers from the last session's store_snapshot.json, or the snapshot at path
+#[tauri::command]
+pub async fn restore_snapshot(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    path: Option<String>,
+) -> Result<RestoreSummary, String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.restore_snapshot(path.as_deref().map(Path::new))
+}
+
+// every point into the session's telemetry.sqlite as it arrives
+#[tauri::command]
+pub async fn set_sqlite_enabled(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    enabled: bool,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.set_sqlite_enabled(enabled)
+}
+
+#[tauri::command]
+pub async fn get_sqlite_status(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<SqliteStatus, String> {
+    Ok(middleware.lock().await.get_sqlite_status())
+}
+
+// any session's database, including one from before a restart
+#[tauri::command]
+pub fn list_sqlite_streams(path: String) -> Result<Vec<SqliteStream>, String> {
+    sqlite_store::streams(Path::new(&path))
+}
+
+#[tauri::command]
+pub fn query_sqlite(
+    path: String,
+    store_name: String,
+    field: Option<String>,
+    from: Option<i64>,
+    to: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<SqlitePoint>, String> {
+    sqlite_store::query(Path::new(&path), &store_name, field.as_deref(), from, to, limit)
+}
+
+#[tauri::command]
+pub async fn set_session_encryption(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    enabled: bool,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.set_session_encryption(enabled)
+}
+
+#[tauri::command]
+pub async fn get_session_encryption(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<bool, String> {
+    Ok(middleware.lock().await.get_session_encryption())
+}
+
+#[tauri::command]
+pub async fn get_serial_capture_status(
+    telem_backend: State<'_, TelemetryRadioHandle>,
+) -> Result<Option<CaptureStatus>, String> {
+    Ok(telem_backend.capture_status())
+}
+
+#[tauri::command]
+pub async fn replay_serial_capture(
+    window: Window,
+    telem_backend: State<'_, TelemetryRadioHandle>,
+    path: String,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    telem_backend.replay_capture(path.into()).await
+}
+
+/* =========================================================
+   EVENT TRACE
+   ========================================================= */
+
+// every event sent to the windows for `duration_ms`, for tests checking the event volume
+#[tauri::command]
+pub async fn record_event_trace(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    duration_ms: u64,
+) -> Result<EventTraceSummary, String> {
+    if duration_ms == 0 || duration_ms > MAX_TRACE_MS {
+        return Err(format!("A trace has to run between 1 and {MAX_TRACE_MS} ms"));
+    }
+    middleware.lock().await.start_event_trace()?;
+    tokio::time::sleep(std::time::Duration::from_millis(duration_ms)).await;
+    middleware.lock().await.stop_event_trace()
 }
 
 /* =========================================================
    TELEMETRY (READ ONLY + DTO)
    ========================================================= */
 
+// the reads the panels poll go through the reader, not the middleware lock (see telemetry_reader)
 #[tauri::command]
 pub async fn get_telemetry(
-    middleware: State<'_, Middleware>,
+    telemetry: State<'_, TelemetryReader>,
     store_name: String,
     field_name: String,
     count: Option<usize>,
+    formatted: Option<bool>,
 ) -> Result<Vec<TelemetryDataFrontend>, String> {
-    let data = match count {
-        Some(n) => middleware.get_last_n(&store_name, &field_name, n)?
-            .unwrap_or_default(),
-        None => middleware.get_all(&store_name, &field_name)?,
-    };
-
-    Ok(data
-        .into_iter()
-        .map(|d| TelemetryDataFrontend {
-            timestamp: d.timestamp,
-            value: d.value.to_string(),
-        })
-        .collect())
+    telemetry.get(&store_name, &field_name, count, formatted.unwrap_or(false))
 }
 
 #[tauri::command]
 pub async fn get_latest_telemetry(
-    middleware: State<'_, Middleware>,
+    telemetry: State<'_, TelemetryReader>,
     store_name: String,
     field_name: String,
+    formatted: Option<bool>,
 ) -> Result<Option<TelemetryDataFrontend>, String> {
-    let data = middleware.get_last(&store_name, &field_name)?;
+    telemetry.latest(&store_name, &field_name, formatted.unwrap_or(false))
+}
+
+// e.g. "max altitude" or "mean battery voltage over the last 60 s", agg is min, max,
+// mean, std, sum or count
+#[tauri::command]
+pub async fn get_aggregate(
+    telemetry: State<'_, TelemetryReader>,
+    store_name: String,
+    field_name: String,
+    window_ms: Option<i64>,
+    agg: String,
+) -> Result<AggregateResult, String> {
+    telemetry.aggregate(&store_name, &field_name, window_ms, Aggregate::parse(&agg)?)
+}
 
-    Ok(data.map(|d| TelemetryDataFrontend {
-        timestamp: d.timestamp,
-        value: d.value.to_string(),
-    }))
+// debrief queries: every interval where the predicate held, e.g. {"op": "gt", "value": 1524}
+#[tauri::command]
+pub async fn find_telemetry(
+    telemetry: State<'_, TelemetryReader>,
+    store_name: String,
+    field_name: String,
+    predicate: Predicate,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> Result<SearchResult, String> {
+    telemetry.find(&store_name, &field_name, predicate, from, to)
+}
+
+// for panels showing several fields together, so they all come from the same instant
+#[tauri::command]
+pub async fn get_latest_consistent(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    keys: Vec<String>,
+    formatted: Option<bool>,
+) -> Result<ConsistentSnapshot, String> {
+    middleware.lock().await.get_latest_consistent(&keys, formatted.unwrap_or(false))
 }
 
 #[tauri::command]
 pub async fn get_telemetry_store_names(
-    middleware: State<'_, Middleware>,
+    telemetry: State<'_, TelemetryReader>,
 ) -> Result<Vec<String>, String> {
-    Ok(middleware.get_store_names())
+    Ok(telemetry.store_names())
 }
 
-/* =========================================================
-   VIDEO
-   ========================================================= */
+#[tauri::command]
+pub async fn export_telemetry(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    store_name: String,
+    format: String,
+    path: String,
+) -> Result<String, String> {
+    let path = middleware
+        .lock()
+        .await
+        .export_store(&store_name, &format, Path::new(&path))?;
+    Ok(path.display().to_string())
+}
 
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+pub struct TimeRange {
+    pub start: i64,
+    pub end: i64,
+}
+
+// straight from memory, no recording needed. ND-JSON unless `pretty`
 #[tauri::command]
-pub async fn get_video_stream_names(
-    middleware: State<'_, Middleware>,
+pub async fn export_packets_json(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    key: String,
+    time_range: Option<TimeRange>,
+    path: String,
+    pretty: Option<bool>,
+) -> Result<String, String> {
+    if let Some(range) = time_range.filter(|r| r.start > r.end) {
+        return Err(format!("Time range starts at {} after it ends at {}", range.start, range.end));
+    }
+    let format = if pretty.unwrap_or(false) { "json" } else { "ndjson" };
+    let path = middleware.lock().await.export_store_range(
+        &key,
+        format,
+        time_range.map(|r| (r.start, r.end)),
+        Path::new(&path),
+    )?;
+    Ok(path.display().to_string())
+}
+
+#[tauri::command]
+pub async fn get_export_formats(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
 ) -> Result<Vec<String>, String> {
-    Ok(middleware.get_video_keys())
+    Ok(middleware.lock().await.get_export_formats())
 }
 
 #[tauri::command]
-pub async fn get_latest_video_frame(
-    middleware: State<'_, Middleware>,
-    stream_name: String,
-) -> Result<Option<VideoFrameFrontend>, String> {
-    Ok(middleware.get_latest_video_frame(&stream_name))
+pub async fn set_field_format(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    key: String,
+    format: Option<FieldFormat>,
+) -> Result<(), String> {
+    middleware.lock().await.set_field_format(&key, format);
+    Ok(())
 }
 
 #[tauri::command]
-pub fn list_video_devices() -> Vec<String> {
-    CameraHandle::available_devices()
+pub async fn get_field_formats(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<BTreeMap<String, FieldFormat>, String> {
+    Ok(middleware.lock().await.get_field_formats())
 }
 
 #[tauri::command]
-pub async fn set_front_camera_device(
-    camera_handle: tauri::State<'_, LiveVideoHandle>,
-    device: String,
+pub async fn get_field_metadata(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<BTreeMap<String, FieldMetadata>, String> {
+    Ok(middleware.lock().await.get_field_metadata())
+}
+
+// key is "store.field" or a bare field name, None removes it
+#[tauri::command]
+pub async fn set_field_metadata(
+    window: tauri::Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    key: String,
+    metadata: Option<FieldMetadata>,
 ) -> Result<(), String> {
-    camera_handle.0.set_device(device).await
+    require_operator_window(&window)?;
+    middleware.lock().await.set_field_metadata(&key, metadata)
 }
 
 #[tauri::command]
-pub async fn set_payload_camera_device(
-    camera_handle: tauri::State<'_, TrackingCameraHandle>,
-    device: String,
+pub async fn set_time_base(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    base: TimeBase,
 ) -> Result<(), String> {
-    camera_handle.0.set_device(device).await
+    middleware.lock().await.set_time_base(base);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_mission_t0(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    timestamp: Option<i64>,
+) -> Result<(), String> {
+    middleware.lock().await.set_mission_t0(timestamp);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_time_base(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<TimeBaseConfig, String> {
+    Ok(middleware.lock().await.get_time_base())
+}
+
+// field_changed events for every field matching store/field ("*" for anything)
+#[tauri::command]
+pub async fn watch_field_changes(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    store: String,
+    field: String,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.watch_field_changes(WatchedField { store, field })
+}
+
+#[tauri::command]
+pub async fn unwatch_field_changes(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    store: String,
+    field: String,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.unwatch_field_changes(&WatchedField { store, field });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_watched_fields(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<WatchedField>, String> {
+    Ok(middleware.lock().await.get_watched_fields())
+}
+
+// the last few changes, for an event log opened after they happened
+#[tauri::command]
+pub async fn get_recent_changes(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<FieldChange>, String> {
+    Ok(middleware.lock().await.get_recent_changes())
 }
 
 /* =========================================================
-   GLOBAL RECORDING CONTROL
+   TERRAIN
    ========================================================= */
 
+#[cfg(feature = "maps")]
 #[tauri::command]
-pub async fn start_recording_all(
-    middleware: State<'_, Middleware>,
+pub async fn set_dem_directory(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    path: String,
 ) -> Result<(), String> {
-    middleware.start_recording_all()
+    middleware.lock().await.set_dem_directory(path.into());
+    Ok(())
 }
 
+#[cfg(feature = "maps")]
 #[tauri::command]
-pub async fn stop_recording_all(
-    middleware: State<'_, Middleware>,
-) -> Result<(), String> {
-    middleware.stop_recording_all()
+pub async fn get_terrain_elevation(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    lat: f64,
+    lon: f64,
+) -> Result<Option<f64>, String> {
+    Ok(middleware.lock().await.get_terrain_elevation(lat, lon))
 }
 
+#[cfg(feature = "maps")]
 #[tauri::command]
-pub async fn get_recording_status(
-    middleware: State<'_, Middleware>,
-) -> Result<bool, String> {
-    Ok(middleware.get_recording_status())
-}
\ No newline at end of file
+pub async fn get_height_above_terrain(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    store_name: String,
+) -> Result<Option<TerrainPoint>, String> {
+    middleware.lock().await.get_height_above_terrain(&store_name)
+}
+
+#[cfg(feature = "maps")]
+#[tauri::command]
+pub async fn get_track_terrain(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    store_name: String,
+    count: Option<usize>,
+) -> Result<Vec<TerrainPoint>, String> {
+    middleware.lock().await.get_track_terrain(&store_name, count)
+}
+
+#[tauri::command]
+pub async fn get_recovery_bundle(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    store_name: Option<String>,
+) -> Result<RecoveryBundle, String> {
+    let store_name = store_name.unwrap_or_else(|| "rocket".to_string());
+    middleware.lock().await.get_recovery_bundle(&store_name)
+}
+
+/* =========================================================
+   VIDEO
+   ========================================================= */
+
+#[tauri::command]
+pub async fn get_video_stream_names(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<String>, String> {
+    Ok(middleware.lock().await.get_video_keys())
+}
+
+#[tauri::command]
+pub async fn get_latest_video_frame(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    stream_name: String,
+) -> Result<Option<VideoFrameFrontend>, String> {
+    Ok(middleware.lock().await.get_latest_video_frame(&stream_name))
+}
+
+#[cfg(feature = "video")]
+#[tauri::command]
+pub fn list_video_devices() -> Vec<String> {
+    CameraHandle::available_devices()
+}
+
+#[cfg(feature = "video")]
+#[tauri::command]
+pub async fn set_front_camera_device(
+    camera_handle: tauri::State<'_, LiveVideoHandle>,
+    device: String,
+) -> Result<(), String> {
+    camera_handle.0.set_device(device).await
+}
+
+#[cfg(feature = "video")]
+#[tauri::command]
+pub async fn set_payload_camera_device(
+    camera_handle: tauri::State<'_, TrackingCameraHandle>,
+    device: String,
+) -> Result<(), String> {
+    camera_handle.0.set_device(device).await
+}
+
+#[tauri::command]
+pub async fn set_video_timelapse(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    stream_name: String,
+    speedup: Option<f64>,
+) -> Result<(), String> {
+    middleware.lock().await.set_video_timelapse(&stream_name, speedup)
+}
+
+// synthetic video into a named stream, for demos and testing panels without a camera
+#[cfg(feature = "video")]
+#[tauri::command]
+pub async fn set_test_pattern_config(
+    test_pattern: State<'_, TestPatternHandle>,
+    config: TestPatternConfig,
+) -> Result<(), String> {
+    test_pattern.set_config(config)
+}
+
+#[cfg(feature = "video")]
+#[tauri::command]
+pub async fn get_test_pattern_config(
+    test_pattern: State<'_, TestPatternHandle>,
+) -> Result<TestPatternConfig, String> {
+    Ok(test_pattern.get_config())
+}
+
+/* =========================================================
+   GLOBAL RECORDING CONTROL
+   ========================================================= */
+
+// format is "csv" or "jsonl", compression "none" or "gzip" and flush e.g.
+// {"every_rows": 100, "every_ms": 500}, any left out is whatever was used last
+#[tauri::command]
+pub async fn start_recording_all(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    format: Option<RecordingFormat>,
+    compression: Option<RecordingCompression>,
+    flush: Option<FlushPolicy>,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    let mut middleware = middleware.lock().await;
+    if let Some(flush) = flush {
+        middleware.set_flush_policy(flush)?;
+    }
+    if let Some(format) = format {
+        middleware.set_recording_format(format);
+    }
+    if let Some(compression) = compression {
+        middleware.set_recording_compression(compression)?;
+    }
+    middleware.start_recording_all()
+}
+
+#[tauri::command]
+pub async fn stop_recording_all(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.stop_recording_all()
+}
+
+#[tauri::command]
+pub async fn get_recording_status(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<bool, String> {
+    Ok(middleware.lock().await.get_recording_status())
+}
+
+#[tauri::command]
+pub async fn get_recording_format(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<RecordingFormat, String> {
+    Ok(middleware.lock().await.get_recording_format())
+}
+
+// after a restart, carry on the earlier session's CSVs instead of starting new ones.
+// session is that session's folder, left out it's the newest one
+#[tauri::command]
+pub async fn resume_recording_all(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    session: Option<String>,
+) -> Result<ResumeSummary, String> {
+    require_operator_window(&window)?;
+    let session = session.map(PathBuf::from);
+    middleware.lock().await.resume_recording_all(session.as_deref())
+}
+
+// the flight library, dir left out is the station's folder. Reading each CSV's
+// timestamps can take a while, so it's off the async threads.
+#[tauri::command]
+pub async fn list_recordings(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    dir: Option<String>,
+) -> Result<Vec<RecordingInfo>, String> {
+    let (station_dir, current) = {
+        let middleware = middleware.lock().await;
+        let current = middleware.session_dir();
+        (current.parent().map(Path::to_path_buf).unwrap_or_else(|| current.clone()), current)
+    };
+    let dir = dir.map(PathBuf::from).unwrap_or(station_dir);
+    tokio::task::spawn_blocking(move || recording_library::list(&dir, &current))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// the whole session folder, returns the bytes freed
+#[tauri::command]
+pub async fn delete_recording(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    path: String,
+) -> Result<u64, String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.delete_recording(Path::new(&path))
+}
+
+// shows the folder in the OS file manager
+#[tauri::command]
+pub async fn open_recording_folder(
+    app: AppHandle,
+    path: String,
+) -> Result<(), String> {
+    recording_library::check(Path::new(&path))?;
+    app.opener().open_path(path, None::<&str>).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_recording_compression(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<RecordingCompression, String> {
+    Ok(middleware.lock().await.get_recording_compression())
+}
+
+#[tauri::command]
+pub async fn get_flush_policy(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<FlushPolicy, String> {
+    Ok(middleware.lock().await.get_flush_policy())
+}
+
+// recording waits for liftoff, then starts with the seconds before it
+#[tauri::command]
+pub async fn arm_recording(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    config: ArmConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.arm_recording(config)
+}
+
+#[tauri::command]
+pub async fn disarm_recording(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.disarm_recording();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_recording_arm(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<ArmStatus, String> {
+    Ok(middleware.lock().await.get_recording_arm())
+}
+
+#[tauri::command]
+pub async fn clear_all_telemetry(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    operator_role: String,
+    force: Option<bool>,
+) -> Result<DataAuditRecord, String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.clear_all_telemetry(force.unwrap_or(false), &operator_role)
+}
+
+#[tauri::command]
+pub async fn get_data_audit_log(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<DataAuditRecord>, String> {
+    Ok(middleware.lock().await.get_data_audit_log())
+}
+
+#[tauri::command]
+pub async fn get_black_box_files(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<String>, String> {
+    Ok(middleware
+        .lock()
+        .await
+        .get_black_box_files()
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect())
+}
+
+#[tauri::command]
+pub async fn get_drop_report(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    relay: State<'_, RelayHandle>,
+) -> Result<DropReport, String> {
+    // the relay counts its own, it doesn't know when so last_drop is left at 0
+    let relay_overflow = DropSite {
+        site: "relay.overflow".to_string(),
+        dropped: relay.get_status().overflow_dropped,
+        last_drop: 0,
+    };
+    Ok(middleware.lock().await.get_drop_report(vec![relay_overflow]))
+}
+
+#[tauri::command]
+pub async fn get_load_shedding_status(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<LoadSheddingStatus, String> {
+    Ok(middleware.lock().await.load_shedding_status())
+}
+
+#[tauri::command]
+pub async fn get_stream_priorities(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<LoadSheddingConfig, String> {
+    Ok(middleware.lock().await.stream_priorities())
+}
+
+#[tauri::command]
+pub async fn set_stream_priorities(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    config: LoadSheddingConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.set_stream_priorities(config)
+}
+
+// how much of each stream stays in memory, see buffer_policy
+#[tauri::command]
+pub async fn get_buffer_config(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<BufferConfig, String> {
+    Ok(middleware.lock().await.get_buffer_config())
+}
+
+#[tauri::command]
+pub async fn set_buffer_config(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    config: BufferConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.set_buffer_config(config)
+}
+
+// when the CSVs roll over to a new segment, e.g. {"max_mb": 500, "max_minutes": 30}
+#[tauri::command]
+pub async fn get_recording_rotation(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<RotationConfig, String> {
+    Ok(middleware.lock().await.get_recording_rotation())
+}
+
+#[tauri::command]
+pub async fn set_recording_rotation(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    config: RotationConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.set_recording_rotation(config)
+}
+
+#[tauri::command]
+pub async fn get_recording_segments(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    store_name: String,
+) -> Result<Vec<RecordingSegment>, String> {
+    middleware.lock().await.get_recording_segments(&store_name)
+}
+
+// the ranges and rates points are flagged against, see data_quality
+#[tauri::command]
+pub async fn get_quality_config(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<QualityConfig, String> {
+    Ok(middleware.lock().await.get_quality_config())
+}
+
+#[tauri::command]
+pub async fn set_quality_config(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    config: QualityConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.set_quality_config(config)
+}
+
+// how long each shared sync lock is held, see lock_timing
+#[tauri::command]
+pub async fn get_lock_stats(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<LockStats>, String> {
+    Ok(middleware.lock().await.get_lock_stats())
+}
+
+#[tauri::command]
+pub async fn set_lock_budget(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    config: LockBudgetConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.set_lock_budget(config)
+}
+
+#[tauri::command]
+pub async fn get_lock_budget(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<LockBudgetConfig, String> {
+    Ok(middleware.lock().await.get_lock_budget())
+}
+
+/* =========================================================
+   LAUNCH SESSIONS
+   ========================================================= */
+
+// binds spec.sources (e.g. "telemetry_radio_backup") to a flight with its own stores
+#[tauri::command]
+pub async fn start_launch_session(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    spec: LaunchSessionSpec,
+) -> Result<LaunchSession, String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.start_launch_session(spec)
+}
+
+#[tauri::command]
+pub async fn end_launch_session(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    id: String,
+) -> Result<LaunchSession, String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.end_launch_session(&id)
+}
+
+#[tauri::command]
+pub async fn list_active_sessions(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<LaunchSessionStatus>, String> {
+    Ok(middleware.lock().await.list_active_sessions())
+}
+
+#[tauri::command]
+pub async fn start_session_recording(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    id: String,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.start_session_recording(&id)
+}
+
+#[tauri::command]
+pub async fn stop_session_recording(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    id: String,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.stop_session_recording(&id)
+}
+
+#[tauri::command]
+pub async fn get_session_alerts(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    id: String,
+) -> Result<Vec<AlertFrontend>, String> {
+    middleware.lock().await.get_session_alerts(&id)
+}
+
+/* =========================================================
+   ALERTS
+   ========================================================= */
+
+#[tauri::command]
+pub async fn get_alerts(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<AlertFrontend>, String> {
+    Ok(middleware.lock().await.get_alerts())
+}
+
+#[tauri::command]
+pub async fn get_shelved_alerts(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<AlertFrontend>, String> {
+    Ok(middleware.lock().await.get_shelved_alerts())
+}
+
+#[tauri::command]
+pub async fn acknowledge_alert(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    key: String,
+    operator_role: String,
+) -> Result<(), String> {
+    middleware.lock().await.acknowledge_alert(&key, &operator_role)
+}
+
+#[tauri::command]
+pub async fn shelve_alert(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    key: String,
+    duration_ms: i64,
+    operator_role: String,
+) -> Result<(), String> {
+    middleware.lock().await.shelve_alert(&key, duration_ms, &operator_role)
+}
+
+#[tauri::command]
+pub async fn unshelve_alert(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    key: String,
+    operator_role: String,
+) -> Result<(), String> {
+    middleware.lock().await.unshelve_alert(&key, &operator_role)
+}
+
+#[tauri::command]
+pub async fn get_alert_audit_log(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<AlertAuditRecord>, String> {
+    Ok(middleware.lock().await.get_alert_audit_log())
+}
+
+#[tauri::command]
+pub async fn import_alert_rules(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    path: String,
+) -> Result<String, String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.import_alert_rules(Path::new(&path))
+}
+
+#[tauri::command]
+pub async fn export_alert_rules(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    rule_set: String,
+    path: String,
+) -> Result<(), String> {
+    middleware.lock().await.export_alert_rules(&rule_set, Path::new(&path))
+}
+
+#[tauri::command]
+pub async fn list_alert_rule_sets(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<String>, String> {
+    Ok(middleware.lock().await.list_alert_rule_sets())
+}
+
+#[tauri::command]
+pub async fn get_active_alert_rule_set(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Option<String>, String> {
+    Ok(middleware.lock().await.get_active_alert_rule_set())
+}
+
+#[tauri::command]
+pub async fn activate_alert_rule_set(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    rule_set: Option<String>,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.activate_alert_rule_set(rule_set.as_deref())
+}
+
+/* =========================================================
+   MISSION PROFILE
+   ========================================================= */
+
+#[tauri::command]
+pub async fn load_mission_profile(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    path: String,
+) -> Result<MissionProfile, String> {
+    middleware.lock().await.load_mission_profile(Path::new(&path))
+}
+
+#[tauri::command]
+pub async fn get_mission_profile(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Option<MissionProfile>, String> {
+    Ok(middleware.lock().await.get_mission_profile())
+}
+
+#[tauri::command]
+pub async fn get_schema_diff(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    store_name: String,
+) -> Result<Option<SchemaDiff>, String> {
+    middleware.lock().await.check_schema(&store_name)
+}
+
+// the fields every packet into store_name must carry, None stops checking it
+#[tauri::command]
+pub async fn set_expected_schema(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    store_name: String,
+    fields: Option<Vec<String>>,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.set_expected_schema(&store_name, fields)
+}
+
+#[tauri::command]
+pub async fn get_expected_schemas(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<BTreeMap<String, Vec<String>>, String> {
+    Ok(middleware.lock().await.get_expected_schemas())
+}
+
+#[tauri::command]
+pub async fn get_schema_health(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<SchemaHealth>, String> {
+    Ok(middleware.lock().await.get_schema_health())
+}
+
+/* =========================================================
+   CONTROL SURFACE (BUTTON BOX)
+   ========================================================= */
+
+#[tauri::command]
+pub async fn set_control_surface_port(
+    control_surface: State<'_, ControlSurfaceHandle>,
+    port_name: String,
+) -> Result<(), String> {
+    control_surface.set_port(port_name).await
+}
+
+#[tauri::command]
+pub async fn assign_control_button(
+    control_surface: State<'_, ControlSurfaceHandle>,
+    button: u8,
+    action: Option<ButtonAction>,
+) -> Result<(), String> {
+    control_surface.assign(button, action);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_control_button_assignments(
+    control_surface: State<'_, ControlSurfaceHandle>,
+) -> Result<Vec<(u8, ButtonAction)>, String> {
+    Ok(control_surface.assignments())
+}
+
+/* =========================================================
+   JOYSTICK
+   ========================================================= */
+
+#[cfg(all(feature = "uplink", feature = "df"))]
+#[tauri::command]
+pub async fn set_joystick_config(
+    joystick: State<'_, JoystickHandle>,
+    config: JoystickConfig,
+) -> Result<(), String> {
+    joystick.set_config(config)
+}
+
+#[cfg(all(feature = "uplink", feature = "df"))]
+#[tauri::command]
+pub async fn get_joystick_config(
+    joystick: State<'_, JoystickHandle>,
+) -> Result<JoystickConfig, String> {
+    Ok(joystick.get_config())
+}
+
+/* =========================================================
+   TRACKER
+   ========================================================= */
+
+#[cfg(feature = "df")]
+#[tauri::command]
+pub async fn set_tracker_config(
+    tracker: State<'_, TrackerHandle>,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    config: TrackerConfig,
+) -> Result<(), String> {
+    let station = StationLocation { lat: config.station_lat, lon: config.station_lon, alt: config.station_alt };
+    tracker.set_config(config)?;
+    // the tracker's position is the station's, session.json keeps it
+    middleware.lock().await.set_station_location(station);
+    Ok(())
+}
+
+#[cfg(feature = "df")]
+#[tauri::command]
+pub async fn get_tracker_config(
+    tracker: State<'_, TrackerHandle>,
+) -> Result<TrackerConfig, String> {
+    Ok(tracker.get_config())
+}
+
+// when a fix counts as good enough to point at, rather than extrapolate past
+#[tauri::command]
+pub async fn set_gps_quality_config(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    config: GpsQualityConfig,
+) -> Result<(), String> {
+    middleware.lock().await.set_gps_quality_config(config)
+}
+
+#[tauri::command]
+pub async fn get_gps_quality_config(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<GpsQualityConfig, String> {
+    Ok(middleware.lock().await.get_gps_quality_config())
+}
+
+// replaces every computed field, a mission profile load replaces them again
+#[tauri::command]
+pub async fn set_derived_fields(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    fields: Vec<DerivedField>,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.set_derived_fields(fields)
+}
+
+#[tauri::command]
+pub async fn get_derived_fields(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<DerivedField>, String> {
+    Ok(middleware.lock().await.get_derived_fields())
+}
+
+#[cfg(feature = "df")]
+#[tauri::command]
+pub async fn load_antenna_pattern(
+    tracker: State<'_, TrackerHandle>,
+    path: String,
+) -> Result<AntennaPattern, String> {
+    tracker.load_antenna_pattern(Path::new(&path))
+}
+
+/* =========================================================
+   RELAY
+   ========================================================= */
+
+#[tauri::command]
+pub async fn set_relay_config(
+    relay: State<'_, RelayHandle>,
+    config: RelayConfig,
+) -> Result<(), String> {
+    relay.set_config(config)
+}
+
+#[tauri::command]
+pub async fn get_relay_config(
+    relay: State<'_, RelayHandle>,
+) -> Result<RelayConfig, String> {
+    Ok(relay.get_config())
+}
+
+#[tauri::command]
+pub async fn get_relay_status(
+    relay: State<'_, RelayHandle>,
+) -> Result<RelayStatus, String> {
+    Ok(relay.get_status())
+}
+
+/* =========================================================
+   INFLUXDB SINK
+   ========================================================= */
+
+#[tauri::command]
+pub async fn set_influx_config(
+    window: Window,
+    influx: State<'_, InfluxSinkHandle>,
+    config: InfluxConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    influx.set_config(config)
+}
+
+#[tauri::command]
+pub async fn get_influx_config(
+    influx: State<'_, InfluxSinkHandle>,
+) -> Result<InfluxConfig, String> {
+    Ok(influx.get_config())
+}
+
+#[tauri::command]
+pub async fn get_influx_status(
+    influx: State<'_, InfluxSinkHandle>,
+) -> Result<InfluxStatus, String> {
+    Ok(influx.get_status())
+}
+
+/* =========================================================
+   LAN REBROADCAST
+   ========================================================= */
+
+#[tauri::command]
+pub async fn set_rebroadcast_config(
+    window: Window,
+    rebroadcast: State<'_, RebroadcastHandle>,
+    config: RebroadcastConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    rebroadcast.set_config(config)
+}
+
+#[tauri::command]
+pub async fn get_rebroadcast_config(
+    rebroadcast: State<'_, RebroadcastHandle>,
+) -> Result<RebroadcastConfig, String> {
+    Ok(rebroadcast.get_config())
+}
+
+#[tauri::command]
+pub async fn get_rebroadcast_status(
+    rebroadcast: State<'_, RebroadcastHandle>,
+) -> Result<RebroadcastStatus, String> {
+    Ok(rebroadcast.get_status())
+}
+
+/* =========================================================
+   ROS 2 BRIDGE
+   ========================================================= */
+
+#[cfg(feature = "df")]
+#[tauri::command]
+pub async fn set_ros_bridge_config(
+    window: Window,
+    ros: State<'_, RosBridgeHandle>,
+    config: RosBridgeConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    ros.set_config(config)
+}
+
+#[cfg(feature = "df")]
+#[tauri::command]
+pub async fn get_ros_bridge_config(
+    ros: State<'_, RosBridgeHandle>,
+) -> Result<RosBridgeConfig, String> {
+    Ok(ros.get_config())
+}
+
+#[cfg(feature = "df")]
+#[tauri::command]
+pub async fn get_ros_bridge_status(
+    ros: State<'_, RosBridgeHandle>,
+) -> Result<RosBridgeStatus, String> {
+    Ok(ros.get_status())
+}
+
+/* =========================================================
+   SERVICES
+   ========================================================= */
+
+#[tauri::command]
+pub async fn list_services(
+    services: State<'_, ServiceRegistry>,
+) -> Result<Vec<ServiceInfo>, String> {
+    Ok(services.list())
+}
+
+#[tauri::command]
+pub async fn start_service(
+    window: Window,
+    services: State<'_, ServiceRegistry>,
+    name: String,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    services.start(&name)
+}
+
+#[tauri::command]
+pub async fn stop_service(
+    window: Window,
+    services: State<'_, ServiceRegistry>,
+    name: String,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    services.stop(&name)
+}
+
+/* =========================================================
+   POWER
+   ========================================================= */
+
+#[tauri::command]
+pub async fn set_power_config(
+    window: Window,
+    power: State<'_, PowerMonitorHandle>,
+    config: PowerConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    power.set_config(config)
+}
+
+#[tauri::command]
+pub async fn get_power_config(
+    power: State<'_, PowerMonitorHandle>,
+) -> Result<PowerConfig, String> {
+    Ok(power.get_config())
+}
+
+#[tauri::command]
+pub async fn get_power_status(
+    power: State<'_, PowerMonitorHandle>,
+) -> Result<PowerStatus, String> {
+    Ok(power.get_status())
+}
+
+/* =========================================================
+   FLIGHT STATE
+   ========================================================= */
+
+#[tauri::command]
+pub async fn get_flight_status(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<FlightStatus, String> {
+    Ok(middleware.lock().await.get_flight_status())
+}
+
+#[tauri::command]
+pub async fn force_flight_state(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    state: FlightState,
+    hold: Option<bool>,
+) -> Result<FlightTransition, String> {
+    require_operator_window(&window)?;
+    Ok(middleware.lock().await.force_flight_state(state, hold.unwrap_or(false)))
+}
+
+#[tauri::command]
+pub async fn get_landing_predictions(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<LandingPrediction>, String> {
+    Ok(middleware.lock().await.get_landing_predictions())
+}
+
+/* =========================================================
+   COMMAND MACROS
+   ========================================================= */
+
+#[cfg(feature = "uplink")]
+#[tauri::command]
+pub async fn list_command_macros(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<CommandMacro>, String> {
+    Ok(middleware
+        .lock()
+        .await
+        .get_mission_profile()
+        .map(|p| p.command_macros)
+        .unwrap_or_default())
+}
+
+// `confirm` has to repeat the macro's name for macros that ask for it
+#[cfg(feature = "uplink")]
+#[tauri::command]
+pub async fn run_command_macro(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    macros: State<'_, CommandMacroHandle>,
+    name: String,
+    confirm: Option<String>,
+) -> Result<MacroProgress, String> {
+    require_operator_window(&window)?;
+    let command_macro = middleware
+        .lock()
+        .await
+        .get_mission_profile()
+        .and_then(|p| p.command_macros.into_iter().find(|m| m.name == name))
+        .ok_or_else(|| format!("No command macro named '{name}' in the mission profile"))?;
+    if command_macro.confirm && confirm.as_deref() != Some(name.as_str()) {
+        return Err(format!("Type '{name}' to confirm running this macro"));
+    }
+    macros.start(command_macro)
+}
+
+#[cfg(feature = "uplink")]
+#[tauri::command]
+pub async fn cancel_command_macro(
+    window: Window,
+    macros: State<'_, CommandMacroHandle>,
+) -> Result<Option<MacroProgress>, String> {
+    require_operator_window(&window)?;
+    Ok(macros.cancel())
+}
+
+#[cfg(feature = "uplink")]
+#[tauri::command]
+pub async fn get_command_macro_status(
+    macros: State<'_, CommandMacroHandle>,
+) -> Result<Option<MacroProgress>, String> {
+    Ok(macros.status())
+}
+
+/* =========================================================
+   COUNTDOWN AUTOMATIONS
+   ========================================================= */
+
+// sets the mission T-0 too, None is T-0 right now
+#[tauri::command]
+pub async fn start_countdown(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    countdown: State<'_, CountdownHandle>,
+    t0: Option<i64>,
+) -> Result<CountdownStatus, String> {
+    require_operator_window(&window)?;
+    let t0 = t0.unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+    middleware.lock().await.set_mission_t0(Some(t0));
+    Ok(countdown.start(t0))
+}
+
+#[tauri::command]
+pub async fn stop_countdown(
+    window: Window,
+    countdown: State<'_, CountdownHandle>,
+) -> Result<CountdownStatus, String> {
+    require_operator_window(&window)?;
+    Ok(countdown.stop())
+}
+
+#[tauri::command]
+pub async fn cancel_countdown_automation(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    countdown: State<'_, CountdownHandle>,
+    id: String,
+    operator_role: String,
+) -> Result<AutomationRecord, String> {
+    require_operator_window(&window)?;
+    let record = countdown.cancel(&id, &operator_role)?;
+    middleware.lock().await.emit(COUNTDOWN_EVENT, record.clone());
+    Ok(record)
+}
+
+#[tauri::command]
+pub async fn restore_countdown_automation(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    countdown: State<'_, CountdownHandle>,
+    id: String,
+    operator_role: String,
+) -> Result<AutomationRecord, String> {
+    require_operator_window(&window)?;
+    let record = countdown.restore(&id, &operator_role)?;
+    middleware.lock().await.emit(COUNTDOWN_EVENT, record.clone());
+    Ok(record)
+}
+
+#[tauri::command]
+pub async fn set_countdown_config(
+    window: Window,
+    countdown: State<'_, CountdownHandle>,
+    config: CountdownConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    countdown.set_config(config)
+}
+
+#[tauri::command]
+pub async fn get_countdown_config(
+    countdown: State<'_, CountdownHandle>,
+) -> Result<CountdownConfig, String> {
+    Ok(countdown.get_config())
+}
+
+#[tauri::command]
+pub async fn get_countdown_status(
+    countdown: State<'_, CountdownHandle>,
+) -> Result<CountdownStatus, String> {
+    Ok(countdown.status())
+}
+
+#[tauri::command]
+pub async fn get_countdown_log(
+    countdown: State<'_, CountdownHandle>,
+) -> Result<Vec<AutomationRecord>, String> {
+    Ok(countdown.log())
+}
+
+/* =========================================================
+   TIME SYNC
+   ========================================================= */
+
+#[tauri::command]
+pub async fn set_time_sync_config(
+    window: Window,
+    time_sync: State<'_, TimeSyncHandle>,
+    config: TimeSyncConfig,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    time_sync.set_config(config)
+}
+
+#[tauri::command]
+pub async fn get_time_sync_config(
+    time_sync: State<'_, TimeSyncHandle>,
+) -> Result<TimeSyncConfig, String> {
+    Ok(time_sync.get_config())
+}
+
+#[tauri::command]
+pub async fn get_time_sync_status(
+    time_sync: State<'_, TimeSyncHandle>,
+) -> Result<TimeSyncStatus, String> {
+    Ok(time_sync.get_status())
+}
+
+#[tauri::command]
+pub async fn get_session_manifest(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<SessionManifest, String> {
+    Ok(middleware.lock().await.get_session_manifest())
+}
+
+// where this session's files are going, named by the backend (see session_naming)
+#[tauri::command]
+pub async fn get_session_paths(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    telem_backend: State<'_, TelemetryRadioHandle>,
+) -> Result<SessionPaths, String> {
+    let mut paths = middleware.lock().await.session_paths();
+    paths.raw = telem_backend.capture_status().map(|status| PathBuf::from(status.path));
+    Ok(paths)
+}
+
+// who's running the station, goes in session.json
+#[tauri::command]
+pub async fn set_session_operator(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    name: String,
+) -> Result<(), String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.set_session_operator(&name);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_session_templates(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<String>, String> {
+    Ok(middleware.lock().await.list_session_templates())
+}
+
+#[tauri::command]
+pub async fn create_session_from_template(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    template: String,
+) -> Result<PreparedSession, String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.create_session_from_template(&template)
+}
+
+#[tauri::command]
+pub async fn get_checklist(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Option<Checklist>, String> {
+    Ok(middleware.lock().await.get_checklist())
+}
+
+#[tauri::command]
+pub async fn set_checklist_item(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    id: String,
+    done: bool,
+    operator_role: String,
+) -> Result<ChecklistItem, String> {
+    require_operator_window(&window)?;
+    middleware.lock().await.set_checklist_item(&id, done, &operator_role)
+}
+
+/* =========================================================
+   SELF TEST
+   ========================================================= */
+
+#[tauri::command]
+pub async fn run_self_test(
+    window: Window,
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<SelfTestReport, String> {
+    require_operator_window(&window)?;
+    Ok(self_test::run_self_test(&middleware).await)
+}
+
+// serial permissions, drivers, cameras, ffmpeg, disk and clock, before leaving for the site
+#[tauri::command]
+pub async fn run_preflight_check(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    time_sync: State<'_, TimeSyncHandle>,
+) -> Result<PreflightReport, String> {
+    Ok(preflight::run_preflight_check(&middleware, &time_sync).await)
+}
+
+// the built in vectors, plus the flight software's if a file is given
+#[tauri::command]
+pub fn run_protocol_conformance(path: Option<String>) -> Result<ConformanceReport, String> {
+    let mut vectors = test_vectors::builtin();
+    if let Some(path) = path {
+        vectors.extend(test_vectors::load(Path::new(&path))?);
+    }
+    Ok(test_vectors::run(&vectors))
+}
+
+#[tauri::command]
+pub fn write_protocol_test_vectors(path: String) -> Result<(), String> {
+    test_vectors::write(Path::new(&path))
+}
+
+/* =========================================================
+   PACKET INSPECTOR
+   ========================================================= */
+
+#[tauri::command]
+pub async fn inspect_last_packets(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+    source: String,
+    n: usize,
+) -> Result<Vec<InspectedFrame>, String> {
+    middleware.lock().await.inspect_last_packets(&source, n)
+}
+
+#[tauri::command]
+pub async fn get_packet_sources(
+    middleware: State<'_, Arc<Mutex<Middleware>>>,
+) -> Result<Vec<String>, String> {
+    Ok(middleware.lock().await.get_packet_sources())
+}
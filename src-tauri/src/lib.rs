@@ -1,140 +1,234 @@
 // Main Tauri Application
 
-use tauri::{Manager, RunEvent, WebviewWindowBuilder, WindowEvent};
+use tauri::{Emitter, Manager, RunEvent, WebviewWindowBuilder, WindowEvent};
 use tokio::sync::{Mutex, mpsc};
 use tokio_util::sync::CancellationToken;
 use std::sync::{Arc};
-use std::fs;
 
 use std::path::{PathBuf as PathBuf};
-// use tauri::path::PathResolver as PathResolver;
 use chrono::Local;
 
-// import our middleware
-mod middleware;
+// import our middleware, it lives in the core crate with the rest of the non-Tauri code
+use groundstation_core::middleware;
 use crate::backend::telemetry_radio_interface::hprc::Command;
-use crate::middleware::Middleware;
+use crate::middleware::{session_naming, Middleware};
 
 // our channels for misc IPC
 mod channels; 
-use crate::channels::{self as Channels, LiveVideoHandle, PlaybackState, TrackingCameraHandle}; 
+use crate::channels::{self as Channels, BackupRadioHandle, PlaybackState}; 
+#[cfg(feature = "video")]
+use crate::channels::{LiveVideoHandle, TrackingCameraHandle};
 
 mod commands;
 
 mod backend;
 use crate::backend::{ 
-    // data_playback, 
+    countdown,
     telemetry_radio_interface,
-    // tracker_interface,
-    video_capture_interface,
-    joystick_input,
+    control_surface,
+    relay,
+    power_monitor,
+    resource_monitor,
+    influx_sink,
+    rebroadcast,
+    time_sync,
+    store_snapshot,
+    services::bootstrap::Bootstrap,
 };
-
-// commands for tauri to call from frontend
-// mod commands;
-
+// the optional subsystems, see [features] in Cargo.toml
+#[cfg(feature = "sim")]
+use crate::backend::data_playback;
+#[cfg(feature = "uplink")]
+use crate::backend::command_macros;
+#[cfg(feature = "video")]
+use crate::backend::video_capture_interface;
+#[cfg(feature = "df")]
+use crate::backend::{tracker_interface, ros_bridge};
+#[cfg(all(feature = "uplink", feature = "df"))]
+use crate::backend::joystick_input;
+
+// <Documents>/Ground-Station/<date>_flight-NN, see session_naming
 fn create_data_dir(app: &tauri::App) -> PathBuf {
     let docs_path = app.path().document_dir().unwrap_or(".".into());
-    let base_path = docs_path
-    .join("Ground-Station".to_string())
-    .join(Local::now().format("%Y-%m-%d_%H-%M-%S").to_string());
-    
-    let _ = fs::create_dir_all(&base_path).map_err(|e| format!("Failed to create directory: {e}"));
+    let station_path = docs_path.join("Ground-Station".to_string());
 
-    base_path
+    session_naming::create_session_dir(&station_path).unwrap_or_else(|e| {
+        eprintln!("[session] {e}");
+        station_path.join(Local::now().format("%Y-%m-%d_%H-%M-%S").to_string())
+    })
 }
 
 fn setup_backend(app: &tauri::App) -> tauri::Result<()> {
     
     let app_handle = app.handle();
-    let main_window = app.get_webview_window("main").unwrap();
 
     // init middleware
-    let middleware = Arc::new(Mutex::new(Middleware::new(create_data_dir(app))));
-
-    // give it to tauri data store so things can access it
-    app_handle.manage(middleware.clone());
+    let data_dir = create_data_dir(app);
+    let mut middleware = Middleware::new(data_dir.clone());
+    middleware.set_build_info(&app.package_info().version.to_string(), option_env!("TELEMETRY_SCHEMA_HASH"));
+    let event_handle = app_handle.clone();
+    middleware.attach_events(Box::new(move |event, payload| {
+        event_handle.emit(event, payload).map_err(|e| e.to_string())
+    }));
+    // the vehicles' CSVs get their columns from the packet types, not whatever arrives first
+    for (store, fields) in telemetry_radio_interface::store_schemas() {
+        middleware.register_store_schema(store, fields);
+    }
+    if let Err(e) = middleware.register_field_metadata(telemetry_radio_interface::field_metadata()) {
+        eprintln!("[middleware] field metadata not registered: {e}");
+    }
+    // the polled reads skip the middleware lock
+    app_handle.manage(middleware.telemetry_reader());
+    let snapshot_writer = middleware.snapshot_writer();
 
     // create an app shutdown signal
     let shutdown = CancellationToken::new();
-    let shutdown_rx = shutdown.child_token();
+    // gives the middleware to tauri, and every backend below is built and spawned through it
+    let mut boot = Bootstrap::new(app_handle, Arc::new(Mutex::new(middleware)), data_dir, &shutdown);
     
     // create a channel for communication to control data playback
     let(playback_tx, playback_rx) = tokio::sync::watch::channel::<PlaybackState>(PlaybackState::NoData);
-
-    // create a channel to communicate hardware ports
-    // let(telemetry_radio_port_tx, telemetry_radio_port_rx) = tokio::sync::mpsc::channel::<String>(8);
-    // let(live_video_port_tx, live_video_port_rx) = mpsc::channel::<String>(8);
-    // let(tracking_video_port_tx, tracking_video_port_rx) = tokio::sync::mpsc::channel::<String>(8);
-    // let(tracker_port_tx, tracker_port_rx) = tokio::sync::mpsc::channel::<String>(8);
-    // let(pointing_stick_port_tx, pointing_stick_port_rx) = tokio::sync::mpsc::channel::<String>(8);
+    #[cfg(feature = "sim")]
+    let data_playback_rx = playback_rx.clone();
 
     let(remote_control_tx, remote_control_rx) = tokio::sync::mpsc::channel::<Command>(8);
     let(payload_control_tx, payload_control_rx) = tokio::sync::mpsc::channel::<(f32, f32)>(8);
 
 
     // give all our comms channels to tauri so we can access them in the frontend commands
-    app_handle.manage(Channels::ShutdownState { shutdown });
-    app_handle.manage(Channels::PlaybackControlChannel { playback_tx, playback_rx });
-    // app_handle.manage(Channels::HardwarePorts { telemetry_radio_port_tx, live_video_port_tx, tracking_video_port_tx, tracker_port_tx, pointing_stick_port_tx });
-    app_handle.manage(Channels::RemoteControlChannels {remote_control_tx, payload_control_tx});
+    boot.manage(Channels::ShutdownState { shutdown });
+    boot.manage(Channels::PlaybackControlChannel { playback_tx, playback_rx });
+    boot.manage(Channels::RemoteControlChannels {remote_control_tx, payload_control_tx});
+
+
+    // create our backend modules, each one after the ones it takes a handle from
+
+    let (relay, relay_handle) = relay::new();
+    boot.spawn("relay", relay);
+
+    // both radios share one deduplicator, so a packet heard by both is only handled once
+    let radio_dedup = telemetry_radio_interface::PacketDedup::shared();
+    boot.manage(radio_dedup.clone());
+    // pending hazardous commands waiting on a second operator
+    let uplink_confirmations = telemetry_radio_interface::UplinkConfirmations::shared(boot.session_path("uplink_audit.jsonl"));
+    // sent commands waiting on an ack, which either radio may hear
+    let uplink_tracker = telemetry_radio_interface::UplinkTracker::shared();
+
+    let (telem_radio, telem_radio_handle, telem_payload_control_handle) = telemetry_radio_interface::new(
+        boot.middleware(),
+        relay_handle.clone(),
+        telemetry_radio_interface::RadioLink::Primary,
+        radio_dedup.clone(),
+        uplink_confirmations.clone(),
+        uplink_tracker.clone(),
+    );
+    boot.spawn("telemetry_radio", telem_radio);
+    let telemetry_radio_port_tx = telem_radio_handle.port_tx.clone();
+
+    // packet logs play back through the primary radio's decoder
+    #[cfg(feature = "sim")]
+    {
+        let (data_playback, data_playback_handle) = data_playback::new(
+            boot.middleware(),
+            data_playback_rx,
+            telem_radio_handle.clone(),
+        );
+        boot.spawn_managed("data_playback", data_playback, data_playback_handle);
+    }
+
+    #[cfg(feature = "uplink")]
+    boot.manage(command_macros::new(boot.middleware(), telem_radio_handle.clone(), boot.shutdown()));
+    boot.manage(telem_radio_handle);
+
+    // backup radio, idle until it's given a port. Uplink commands only go out on the primary.
+    let (telem_radio_backup, telem_radio_backup_handle, _) = telemetry_radio_interface::new(
+        boot.middleware(),
+        relay_handle.clone(),
+        telemetry_radio_interface::RadioLink::Backup,
+        radio_dedup,
+        uplink_confirmations,
+        uplink_tracker,
+    );
+    boot.spawn("telemetry_radio_backup", telem_radio_backup);
+    let telemetry_radio_backup_port_tx = telem_radio_backup_handle.port_tx.clone();
+    boot.manage(BackupRadioHandle(telem_radio_backup_handle));
+    boot.manage(relay_handle.clone());
+    
 
+    #[cfg(feature = "video")]
+    {
+        let (live_video_cam, live_video_cam_handle) = video_capture_interface::new("live_vide", boot.middleware());
+        boot.spawn_managed("live_video", live_video_cam, LiveVideoHandle(live_video_cam_handle));
 
-    // create our backend modules
+        let (tracking_cam, tracking_cam_handle) = video_capture_interface::new("tracking", boot.middleware());
+        boot.spawn_managed("tracking_camera", tracking_cam, TrackingCameraHandle(tracking_cam_handle));
 
-    // let data_playback = data_playback::new(middleware.clone(), playback_rx.clone());
-    // tauri::async_runtime::spawn(async move {
-        // data_playback.run(shutdown_rx.clone()).await;
-    // });
+        // a camera stand-in for the video panels, idle until it's enabled
+        let (test_pattern, test_pattern_handle) = video_capture_interface::test_pattern::new(boot.middleware());
+        boot.spawn_managed("test_pattern", test_pattern, test_pattern_handle);
+    }
 
-    let telem_shutdown_rx = shutdown_rx.clone();
-    let (telem_radio, telem_radio_handle, telem_payload_control_handle) 
-        = telemetry_radio_interface::new(middleware.clone());
-    tauri::async_runtime::spawn(async move {
-        telem_radio.run(telem_shutdown_rx).await;
-    });
-    app_handle.manage(telem_radio_handle);
-    
 
-    let live_video_shutdown = shutdown_rx.clone();
-    let (live_video_cam, live_video_cam_handle) = video_capture_interface::new("live_vide", middleware.clone());
-    tauri::async_runtime::spawn(async move {
-        live_video_cam.run(live_video_shutdown).await;
-    });
-    app_handle.manage(LiveVideoHandle(live_video_cam_handle));
+    #[cfg(feature = "df")]
+    {
+        let (tracker, tracker_handle) = tracker_interface::new(boot.middleware());
+        boot.spawn("tracker", tracker);
 
-    let tracking_cam_shutdown = shutdown_rx.clone();
-    let (tracking_cam, tracking_cam_handle) = video_capture_interface::new("tracking", middleware.clone());
-    tauri::async_runtime::spawn(async move {
-        tracking_cam.run(tracking_cam_shutdown).await;
+        #[cfg(feature = "uplink")]
+        {
+            let (joystick, joystick_handle) = joystick_input::new(
+                telem_payload_control_handle.clone(),
+                tracker_handle.clone(),
+                boot.middleware(),
+            );
+            boot.spawn_managed("joystick", joystick, joystick_handle);
+        }
+
+        let (ros_bridge, ros_bridge_handle) = ros_bridge::new(boot.middleware(), tracker_handle.clone());
+        boot.spawn_managed("ros_bridge", ros_bridge, ros_bridge_handle);
+        boot.manage(tracker_handle);
+    }
+    // nothing else steers the payload
+    #[cfg(not(all(feature = "uplink", feature = "df")))]
+    drop(telem_payload_control_handle);
+
+    let (control_surface, control_surface_handle) = control_surface::new(boot.middleware());
+    boot.spawn("control_surface", control_surface);
+    // one place the frontend can send any serial device to any port
+    boot.manage(Channels::HardwarePorts {
+        telemetry_radio_port_tx,
+        telemetry_radio_backup_port_tx,
+        control_surface_port_tx: control_surface_handle.port_sender(),
+        selected: dashmap::DashMap::new(),
     });
-    app_handle.manage(TrackingCameraHandle(tracking_cam_handle));
+    boot.manage(control_surface_handle);
 
+    let (power_monitor, power_monitor_handle) = power_monitor::new(boot.middleware());
+    boot.spawn_managed("power_monitor", power_monitor, power_monitor_handle);
 
-    // let telem_shutdown_rx2 = shutdown_rx.clone();
-    // let (telem_radio2, telem_radio_handle2) 
-    //     = telemetry_radio_interface::new(middleware.clone());
-    // tauri::async_runtime::spawn(async move {
-    //     telem_radio2.run(telem_shutdown_rx2).await;
-    // });
+    let (influx_sink, influx_sink_handle) = influx_sink::new(boot.middleware());
+    boot.spawn_managed("influx_sink", influx_sink, influx_sink_handle);
 
-    let joystick_shutdown = shutdown_rx.clone();
-    let (joystick, joystick_handle) = joystick_input::new(
-        telem_payload_control_handle.clone(),
-        middleware.clone(),
-    );
-    tauri::async_runtime::spawn(async move {
-        joystick.run(joystick_shutdown).await;
-    });
-    app_handle.manage(joystick_handle);
-    
+    let (rebroadcast, rebroadcast_handle) = rebroadcast::new(boot.middleware());
+    boot.spawn_managed("rebroadcast", rebroadcast, rebroadcast_handle);
 
+    let (time_sync, time_sync_handle) = time_sync::new(boot.middleware());
+    boot.spawn_managed("time_sync", time_sync, time_sync_handle);
 
-    // let tracker_interface = tracker_interface::new(middleware.clone());
-    // tauri::async_runtime::spawn(async move {
-    //     tracker_interface.run(shutdown_rx.clone()).await;
-    // });
+    let (countdown, countdown_handle) = countdown::new(
+        boot.middleware(),
+        boot.station_path("countdown.toml"),
+        boot.session_path("countdown_log.jsonl"),
+    );
+    boot.spawn_managed("countdown", countdown, countdown_handle);
 
+    let resource_monitor = resource_monitor::new(boot.middleware(), relay_handle);
+    boot.spawn("resource_monitor", resource_monitor);
+
+    // the buffers on disk every few seconds, restore_snapshot brings them back after a crash
+    boot.spawn("store_snapshot", store_snapshot::new(snapshot_writer));
+    boot.finish();
+    
 
 
     // create secondary windows
@@ -180,20 +274,235 @@ pub fn run() {
         .setup(|app| Ok(setup_backend(app)?))
 
         .invoke_handler(tauri::generate_handler![
+            commands::set_playback_state,
+            commands::get_playback_state,
+            #[cfg(feature = "sim")]
+            commands::load_playback_folder,
+            #[cfg(feature = "sim")]
+            commands::get_playback_status,
+            #[cfg(feature = "sim")]
+            commands::set_playback_latency,
+            #[cfg(feature = "sim")]
+            commands::get_playback_latency,
+            #[cfg(feature = "sim")]
+            commands::set_playback_speed,
+            #[cfg(feature = "sim")]
+            commands::get_playback_speed,
             commands::get_serial_port_names,
+            commands::list_serial_ports,
+            commands::select_serial_port,
             commands::set_telem_serial_port,
+            commands::set_link_framing,
+            commands::get_link_framing,
+            commands::set_crc_config,
+            commands::get_crc_config,
+            commands::set_packet_auth_config,
+            commands::get_packet_auth_config,
+            #[cfg(feature = "uplink")]
             commands::send_command,
+            #[cfg(feature = "uplink")]
+            commands::approve_uplink_command,
+            #[cfg(feature = "uplink")]
+            commands::reject_uplink_command,
+            #[cfg(feature = "uplink")]
+            commands::get_pending_uplink_commands,
+            #[cfg(feature = "uplink")]
+            commands::get_uplink_audit_log,
+            #[cfg(feature = "uplink")]
+            commands::get_uplink_status,
+            #[cfg(feature = "uplink")]
+            commands::set_hazard_config,
+            #[cfg(feature = "uplink")]
+            commands::get_hazard_config,
+            commands::start_serial_capture,
+            commands::stop_serial_capture,
+            commands::get_radio_link_stats,
+            commands::get_link_stats,
+            #[cfg(feature = "uplink")]
+            commands::list_command_macros,
+            #[cfg(feature = "uplink")]
+            commands::run_command_macro,
+            #[cfg(feature = "uplink")]
+            commands::cancel_command_macro,
+            #[cfg(feature = "uplink")]
+            commands::get_command_macro_status,
+            commands::start_countdown,
+            commands::stop_countdown,
+            commands::cancel_countdown_automation,
+            commands::restore_countdown_automation,
+            commands::set_countdown_config,
+            commands::get_countdown_config,
+            commands::get_countdown_status,
+            commands::get_countdown_log,
+            commands::set_raw_capture,
+            commands::get_raw_capture,
+            commands::set_packet_logging,
+            commands::get_packet_log_status,
+            commands::set_journal_enabled,
+            commands::get_journal_status,
+            commands::rebuild_from_journal,
+            commands::restore_snapshot,
+            commands::set_sqlite_enabled,
+            commands::get_sqlite_status,
+            commands::list_sqlite_streams,
+            commands::query_sqlite,
+            commands::get_serial_capture_status,
+            commands::set_session_encryption,
+            commands::get_session_encryption,
+            commands::replay_serial_capture,
             commands::get_telemetry,
             commands::get_latest_telemetry,
+            commands::get_aggregate,
+            commands::find_telemetry,
+            commands::get_latest_consistent,
             commands::get_telemetry_store_names,
+            commands::record_event_trace,
+            commands::export_telemetry,
+            commands::export_packets_json,
+            commands::get_export_formats,
+            commands::set_field_format,
+            commands::get_field_formats,
+            commands::get_field_metadata,
+            commands::set_field_metadata,
+            commands::set_time_base,
+            commands::set_mission_t0,
+            commands::get_time_base,
+            #[cfg(feature = "maps")]
+            commands::set_dem_directory,
+            #[cfg(feature = "maps")]
+            commands::get_terrain_elevation,
+            #[cfg(feature = "maps")]
+            commands::get_height_above_terrain,
+            #[cfg(feature = "maps")]
+            commands::get_track_terrain,
+            commands::get_recovery_bundle,
             commands::get_video_stream_names,
             commands::get_latest_video_frame,
+            #[cfg(feature = "video")]
             commands::list_video_devices,
+            #[cfg(feature = "video")]
             commands::set_front_camera_device,
+            #[cfg(feature = "video")]
             commands::set_payload_camera_device,
+            commands::set_video_timelapse,
+            #[cfg(feature = "video")]
+            commands::set_test_pattern_config,
+            #[cfg(feature = "video")]
+            commands::get_test_pattern_config,
             commands::start_recording_all,
             commands::stop_recording_all,
+            commands::start_launch_session,
+            commands::end_launch_session,
+            commands::list_active_sessions,
+            commands::start_session_recording,
+            commands::stop_session_recording,
+            commands::get_session_alerts,
             commands::get_recording_status,
+            commands::get_recording_format,
+            commands::get_recording_compression,
+            commands::list_recordings,
+            commands::delete_recording,
+            commands::open_recording_folder,
+            commands::get_flush_policy,
+            commands::resume_recording_all,
+            commands::arm_recording,
+            commands::disarm_recording,
+            commands::get_recording_arm,
+            commands::clear_all_telemetry,
+            commands::get_data_audit_log,
+            commands::get_black_box_files,
+            commands::get_drop_report,
+            commands::get_load_shedding_status,
+            commands::get_stream_priorities,
+            commands::set_stream_priorities,
+            commands::get_buffer_config,
+            commands::set_buffer_config,
+            commands::get_recording_rotation,
+            commands::set_recording_rotation,
+            commands::get_recording_segments,
+            commands::get_quality_config,
+            commands::set_quality_config,
+            commands::get_lock_stats,
+            commands::set_lock_budget,
+            commands::get_lock_budget,
+            commands::get_alerts,
+            commands::get_shelved_alerts,
+            commands::acknowledge_alert,
+            commands::shelve_alert,
+            commands::unshelve_alert,
+            commands::get_alert_audit_log,
+            commands::import_alert_rules,
+            commands::export_alert_rules,
+            commands::list_alert_rule_sets,
+            commands::get_active_alert_rule_set,
+            commands::activate_alert_rule_set,
+            commands::load_mission_profile,
+            commands::get_mission_profile,
+            commands::get_schema_diff,
+            commands::set_expected_schema,
+            commands::get_expected_schemas,
+            commands::get_schema_health,
+            commands::watch_field_changes,
+            commands::unwatch_field_changes,
+            commands::get_watched_fields,
+            commands::get_recent_changes,
+            commands::set_control_surface_port,
+            commands::assign_control_button,
+            commands::get_control_button_assignments,
+            #[cfg(all(feature = "uplink", feature = "df"))]
+            commands::set_joystick_config,
+            #[cfg(all(feature = "uplink", feature = "df"))]
+            commands::get_joystick_config,
+            #[cfg(feature = "df")]
+            commands::set_tracker_config,
+            #[cfg(feature = "df")]
+            commands::get_tracker_config,
+            commands::set_gps_quality_config,
+            commands::get_gps_quality_config,
+            commands::set_derived_fields,
+            commands::get_derived_fields,
+            #[cfg(feature = "df")]
+            commands::load_antenna_pattern,
+            commands::set_relay_config,
+            commands::get_relay_config,
+            commands::get_relay_status,
+            commands::set_influx_config,
+            commands::get_influx_config,
+            commands::get_influx_status,
+            commands::set_rebroadcast_config,
+            commands::get_rebroadcast_config,
+            commands::get_rebroadcast_status,
+            #[cfg(feature = "df")]
+            commands::set_ros_bridge_config,
+            #[cfg(feature = "df")]
+            commands::get_ros_bridge_config,
+            #[cfg(feature = "df")]
+            commands::get_ros_bridge_status,
+            commands::list_services,
+            commands::start_service,
+            commands::stop_service,
+            commands::set_power_config,
+            commands::get_power_config,
+            commands::get_power_status,
+            commands::get_flight_status,
+            commands::force_flight_state,
+            commands::get_landing_predictions,
+            commands::set_time_sync_config,
+            commands::get_time_sync_config,
+            commands::get_time_sync_status,
+            commands::get_session_manifest,
+            commands::set_session_operator,
+            commands::get_session_paths,
+            commands::list_session_templates,
+            commands::create_session_from_template,
+            commands::get_checklist,
+            commands::set_checklist_item,
+            commands::run_self_test,
+            commands::run_preflight_check,
+            commands::run_protocol_conformance,
+            commands::write_protocol_test_vectors,
+            commands::inspect_last_packets,
+            commands::get_packet_sources,
         ])
         .build(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -212,8 +521,8 @@ pub fn run() {
                 app_handle.state::<Channels::ShutdownState>().shutdown.cancel();
 
                 // call explicit cleanup on middleware to close file handles
-                let middleware = app_handle.state::<Arc<Middleware>>();
-                middleware.shutdown();
+                let middleware = app_handle.state::<Arc<Mutex<Middleware>>>();
+                tauri::async_runtime::block_on(middleware.lock()).shutdown();
                 
                 api.prevent_close();
 